@@ -0,0 +1,159 @@
+//! Scan throughput and bind latency baselines
+//!
+//! Builds a synthetic database of `ENTITIES` entities and twice as many edges,
+//! then measures cold bind (open plus schema discovery), full entity and edge
+//! scans, a `where_json` scan matching a tenth of the entities, and the
+//! key-only count floor. A second database of `LARGE_ENTITIES` entities with
+//! `LARGE_PROPERTIES` long string properties each compares serial decoding
+//! with `decode_threads`. Run with `cargo bench --features bench`.
+
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use manifoldb_core::encoding::Encoder;
+use manifoldb_core::types::{Edge, EdgeId, EdgeType, Entity, EntityId, Label, Value};
+use manifoldb_storage::backends::RedbEngine;
+use manifoldb_storage::{StorageEngine, Transaction};
+
+use duckdb_manifold::bench;
+
+/// Entities in the fixture; each has two outgoing edges
+const ENTITIES: u64 = 50_000;
+
+/// Distinct `city` values, so `where_json` on one keeps a tenth
+const CITIES: u64 = 10;
+
+/// Entities in the large-entity fixture
+const LARGE_ENTITIES: u64 = 10_000;
+
+/// String properties per large entity, each `LARGE_VALUE_BYTES` long
+const LARGE_PROPERTIES: usize = 40;
+const LARGE_VALUE_BYTES: usize = 200;
+
+/// Decoding threads for the parallel decode benchmark
+const DECODE_THREADS: usize = 4;
+
+/// Write the fixture, replacing any left by an earlier run
+fn build_fixture(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let _ = std::fs::remove_file(path);
+    let engine = RedbEngine::open(path)?;
+    let mut tx = engine.begin_write()?;
+
+    for id in 1..=ENTITIES {
+        let mut properties = HashMap::new();
+        properties.insert("name".to_string(), Value::String(format!("person-{}", id)));
+        properties.insert("age".to_string(), Value::Int((id % 90) as i64));
+        properties.insert("score".to_string(), Value::Float(id as f64 / 7.0));
+        properties.insert("city".to_string(), Value::String(format!("city-{}", id % CITIES)));
+        let entity = Entity {
+            id: EntityId::from(id),
+            labels: vec![Label::new("Person")],
+            properties,
+            vectors: HashMap::new(),
+        };
+        tx.put("nodes", &id.to_be_bytes(), &entity.encode()?)?;
+    }
+
+    for id in 1..=ENTITIES * 2 {
+        let source = (id - 1) / 2 + 1;
+        let target = (source * 7919) % ENTITIES + 1;
+        let mut properties = HashMap::new();
+        properties.insert("weight".to_string(), Value::Float((id % 100) as f64 / 100.0));
+        let edge = Edge {
+            id: EdgeId::from(id),
+            source: EntityId::from(source),
+            target: EntityId::from(target),
+            edge_type: EdgeType::new("KNOWS"),
+            properties,
+        };
+        tx.put("edges", &id.to_be_bytes(), &edge.encode()?)?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Write the large-entity fixture, replacing any left by an earlier run
+fn build_large_fixture(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let _ = std::fs::remove_file(path);
+    let engine = RedbEngine::open(path)?;
+    let mut tx = engine.begin_write()?;
+
+    for id in 1..=LARGE_ENTITIES {
+        let properties = (0..LARGE_PROPERTIES)
+            .map(|i| {
+                let text = format!("{}-{}-", id, i).repeat(LARGE_VALUE_BYTES / 8);
+                (format!("field_{:02}", i), Value::String(text))
+            })
+            .collect();
+        let entity = Entity {
+            id: EntityId::from(id),
+            labels: vec![Label::new("Document")],
+            properties,
+            vectors: HashMap::new(),
+        };
+        tx.put("nodes", &id.to_be_bytes(), &entity.encode()?)?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+fn scan_benchmarks(c: &mut Criterion) {
+    let path = std::env::temp_dir().join(format!("manifold_bench_{}.redb", std::process::id()));
+    let db_path = path.to_str().expect("temp path is UTF-8").to_string();
+    build_fixture(&db_path).expect("fixture builds");
+
+    c.bench_function("cold_bind_entities", |b| {
+        b.iter(|| bench::cold_bind_entities(&db_path).unwrap())
+    });
+
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(20);
+
+    group.throughput(Throughput::Elements(ENTITIES));
+    group.bench_function("entities_full", |b| {
+        b.iter(|| assert_eq!(bench::scan_entities(&db_path, None, 1).unwrap(), ENTITIES as usize))
+    });
+    group.bench_function("entities_where_json", |b| {
+        let filter = Some(r#"$.city = "city-3""#);
+        b.iter(|| {
+            let rows = bench::scan_entities(&db_path, filter, 1).unwrap();
+            assert_eq!(rows, (ENTITIES / CITIES) as usize)
+        })
+    });
+    group.bench_function("entities_count_keys", |b| {
+        b.iter(|| assert_eq!(bench::count_entity_keys(&db_path).unwrap(), ENTITIES as usize))
+    });
+
+    group.throughput(Throughput::Elements(ENTITIES * 2));
+    group.bench_function("edges_full", |b| {
+        b.iter(|| assert_eq!(bench::scan_edges(&db_path).unwrap(), ENTITIES as usize * 2))
+    });
+
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+
+    let path =
+        std::env::temp_dir().join(format!("manifold_bench_large_{}.redb", std::process::id()));
+    let db_path = path.to_str().expect("temp path is UTF-8").to_string();
+    build_large_fixture(&db_path).expect("large fixture builds");
+
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(LARGE_ENTITIES));
+    for threads in [1, DECODE_THREADS] {
+        group.bench_function(format!("large_entities_threads_{}", threads), |b| {
+            b.iter(|| {
+                let rows = bench::scan_entities(&db_path, None, threads).unwrap();
+                assert_eq!(rows, LARGE_ENTITIES as usize)
+            })
+        });
+    }
+
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(benches, scan_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Entry points for the `benches/` suite
+//!
+//! The scanners' storage and decode work, driven the way a query drives it but
+//! without DuckDB: the extension's DuckDB calls need a running DuckDB to load
+//! into, which a benchmark binary doesn't have. What's measured is everything
+//! up to filling output vectors - opening, schema discovery, cursor reads,
+//! decoding and pushed-down filters. Not part of the extension's API.
+//! Built only with the `bench` feature.
+
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+use std::error::Error;
+
+use crate::scanner::edges::{EdgeScanOptions, ManifoldEdgesInitData};
+use crate::scanner::entities::{
+    discover_entity_schema, EntityScanOptions, ManifoldEntitiesInitData,
+};
+use crate::scanner::json_path::JsonPathFilter;
+use crate::scanner::{drop_cached_engine, get_cached_engine, BATCH_SIZE, SCHEMA_SAMPLE_SIZE};
+
+/// Bind `manifold_entities` on a database not yet open: open the file and
+/// discover the schema, returning the number of columns
+pub fn cold_bind_entities(db_path: &str) -> Result<usize, Box<dyn Error>> {
+    drop_cached_engine(db_path)?;
+    let engine = get_cached_engine(db_path)?;
+    let options = EntityScanOptions {
+        include_labels: true,
+        schema_sample: SCHEMA_SAMPLE_SIZE,
+        ..Default::default()
+    };
+    let (columns, _) = discover_entity_schema(&engine, &options)?;
+    Ok(columns.len())
+}
+
+/// Scan every entity in batches, optionally with a `where_json` filter, on
+/// `decode_threads` decoding threads, returning the number of rows the scan
+/// would emit
+pub fn scan_entities(
+    db_path: &str,
+    where_json: Option<&str>,
+    decode_threads: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let engine = get_cached_engine(db_path)?;
+    let options = EntityScanOptions {
+        where_json: where_json.map(JsonPathFilter::parse).transpose()?,
+        decode_threads,
+        ..Default::default()
+    };
+
+    let init_data = ManifoldEntitiesInitData::new();
+    let mut rows = 0;
+    loop {
+        let batch = init_data.next_batch(db_path, &engine, &options, BATCH_SIZE)?;
+        if batch.is_empty() {
+            return Ok(rows);
+        }
+        rows += batch.len();
+    }
+}
+
+/// Scan every edge in batches, returning the number of rows
+pub fn scan_edges(db_path: &str) -> Result<usize, Box<dyn Error>> {
+    let engine = get_cached_engine(db_path)?;
+    let options = EdgeScanOptions::default();
+
+    let init_data = ManifoldEdgesInitData::new();
+    let mut rows = 0;
+    loop {
+        let batch = init_data.next_batch(db_path, &engine, options.safe_mode, BATCH_SIZE)?;
+        if batch.is_empty() {
+            return Ok(rows);
+        }
+        rows += batch.len();
+    }
+}
+
+/// Count the nodes table's keys without decoding any values
+///
+/// `count(*)` over `manifold_entities` still runs the full scan, as there is
+/// no count pushdown; this is the storage floor such a path could reach.
+pub fn count_entity_keys(db_path: &str) -> Result<usize, Box<dyn Error>> {
+    let engine = get_cached_engine(db_path)?;
+    let tx = engine.begin_read()?;
+    let Ok(mut cursor) = tx.cursor("nodes") else {
+        return Ok(0);
+    };
+
+    let mut count = 0;
+    let mut entry = cursor.seek_first()?;
+    while entry.is_some() {
+        count += 1;
+        entry = cursor.next()?;
+    }
+    Ok(count)
+}
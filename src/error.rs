@@ -3,10 +3,23 @@
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(dead_code)] // Some variants are reserved for scanners not yet implemented
 pub enum ManifoldScannerError {
     #[error("Failed to open database at path: {path}")]
     DatabaseOpenError { path: String, source: Box<dyn std::error::Error + Send + Sync> },
 
+    #[error("Database file not found: {0}")]
+    DatabaseNotFound(String),
+
+    #[error("Cannot access database at path {path}: {reason}")]
+    DatabaseAccessDenied { path: String, reason: String },
+
+    #[error("Failed to open database at path {path}: {reason}")]
+    DatabaseUnreadable { path: String, reason: String },
+
+    #[error("Database file {path} is truncated ({size} of {expected} bytes); incomplete copy?")]
+    DatabaseTruncated { path: String, size: u64, expected: u64 },
+
     #[error("Failed to read entity: {0}")]
     EntityReadError(String),
 
@@ -22,6 +35,12 @@ pub enum ManifoldScannerError {
     #[error("Storage error: {0}")]
     StorageError(String),
 
+    #[error("Cannot write export file {path}: {reason}")]
+    ExportWriteError { path: String, reason: String },
+
+    #[error("Value {value} of {column} in record {id} doesn't fit {column_type}")]
+    CoercionError { id: u64, column: String, column_type: String, value: String },
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 }
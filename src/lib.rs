@@ -18,38 +18,280 @@ extern crate duckdb;
 extern crate duckdb_loadable_macros;
 extern crate libduckdb_sys;
 
-mod error;
-mod scanner;
-mod schema;
+pub(crate) mod error;
+pub(crate) mod scanner;
+pub(crate) mod schema;
 
-use duckdb::{ffi, Connection, Result};
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+
+use duckdb::{ffi, vscalar::VScalar, vtab::VTab, Connection, Result};
 use duckdb_loadable_macros::duckdb_entrypoint_c_api;
 use std::error::Error;
 
 // Re-export scanner implementations
-pub use scanner::entities::ManifoldEntitiesVTab;
-pub use scanner::edges::ManifoldEdgesVTab;
+pub use scanner::entities::{
+    ManifoldEntitiesByLabelsVTab, ManifoldEntitiesOrderedVTab, ManifoldEntitiesVTab,
+};
+pub use scanner::edges::{ManifoldEdgesOfTypeVTab, ManifoldEdgesVTab};
+pub use scanner::refresh::ManifoldRefreshScalar;
+pub use scanner::warmup::ManifoldWarmupScalar;
+pub use scanner::vector_search::{ManifoldVectorSearchBatchVTab, ManifoldVectorSearchVTab};
+pub use scanner::edge_weights::ManifoldEdgeWeightsVTab;
+pub use scanner::id_bounds::ManifoldIdBoundsVTab;
+pub use scanner::dangling_edges::ManifoldDanglingEdgesVTab;
+pub use scanner::collections::ManifoldCollectionsVTab;
+pub use scanner::triangles::ManifoldTriangleCountVTab;
+pub use scanner::traverse::ManifoldTraverseVTab;
+pub use scanner::reach_count::ManifoldReachCountVTab;
+pub use scanner::gather::ManifoldGatherVTab;
+pub use scanner::neighbors_aggregate::ManifoldNeighborsAggregateVTab;
+pub use scanner::feature_matrix::ManifoldFeatureMatrixVTab;
+pub use scanner::find_by_prop::ManifoldFindByPropVTab;
+pub use scanner::changes::ManifoldChangesVTab;
+pub use scanner::new_entities::ManifoldNewEntitiesVTab;
+pub use scanner::export::ManifoldExportVTab;
+pub use scanner::export_jsonl::ManifoldExportJsonlVTab;
+pub use scanner::label_bits::ManifoldLabelBitsVTab;
+pub use scanner::stats::ManifoldLastScanStatsVTab;
+pub use scanner::storage_stats::ManifoldStorageStatsVTab;
+pub use scanner::pushdown::ManifoldLastPushdownVTab;
+pub use scanner::config::ManifoldConfigVTab;
+pub use scanner::verify::ManifoldVerifyVTab;
+pub use scanner::encode_vector::ManifoldEncodeVectorScalar;
 
 const EXTENSION_NAME: &str = env!("CARGO_PKG_NAME");
 
+/// Registers the extension's functions, collecting failures rather than
+/// panicking across the C API
+///
+/// Loading the extension a second time in one process (under another path,
+/// say) registers every function again. DuckDB's registration calls report
+/// failure without a reason, so a failed name is taken to be a repeat only
+/// when DuckDB lists a function of that name with this extension's signature
+/// (a table function with the same number of parameters, or a scalar with the
+/// same number of overloads), and the existing definition is kept.
+/// Any other failure, including a clash with another extension's function, is
+/// remembered, the remaining functions still register, and the entrypoint
+/// returns one error naming them all.
+struct Registrar<'a> {
+    con: &'a Connection,
+    failed: Vec<String>,
+}
+
+/// The shape of a function being registered, to recognize an earlier copy
+enum Signature {
+    /// A table function taking this many positional and named parameters
+    Table { parameters: usize },
+    /// A scalar function with this many overloads
+    Scalar { overloads: usize },
+}
+
+impl Signature {
+    fn kind(&self) -> &'static str {
+        match self {
+            Signature::Table { .. } => "table",
+            Signature::Scalar { .. } => "scalar",
+        }
+    }
+}
+
+impl<'a> Registrar<'a> {
+    fn new(con: &'a Connection) -> Self {
+        Self {
+            con,
+            failed: Vec::new(),
+        }
+    }
+
+    /// Register a table function
+    fn table<T: VTab>(&mut self, name: &str) {
+        let result = self.con.register_table_function::<T>(name);
+        let parameters = T::parameters().map_or(0, |p| p.len())
+            + T::named_parameters().map_or(0, |p| p.len());
+        self.check(name, Signature::Table { parameters }, result);
+    }
+
+    /// Register a scalar function
+    fn scalar<S: VScalar>(&mut self, name: &str)
+    where
+        S::State: Default,
+    {
+        let result = self.con.register_scalar_function::<S>(name);
+        let overloads = S::signatures().len();
+        self.check(name, Signature::Scalar { overloads }, result);
+    }
+
+    fn check(&mut self, name: &str, signature: Signature, result: Result<()>) {
+        let Err(e) = result else {
+            return;
+        };
+        if !self.is_registered(name, &signature) {
+            self.failed.push(format!("{} function {} ({})", signature.kind(), name, e));
+        }
+    }
+
+    /// Whether DuckDB already has this function, as this extension defines it
+    fn is_registered(&self, name: &str, signature: &Signature) -> bool {
+        let (sql, expected) = match *signature {
+            Signature::Table { parameters } => (
+                "SELECT count(*) > 0 FROM duckdb_functions() \
+                 WHERE function_name = $1 AND function_type = 'table' \
+                 AND len(parameters) = $2",
+                parameters,
+            ),
+            Signature::Scalar { overloads } => (
+                "SELECT count(*) = $2 FROM duckdb_functions() \
+                 WHERE function_name = $1 AND function_type = 'scalar'",
+                overloads,
+            ),
+        };
+        self.con
+            .query_row(sql, duckdb::params![name, expected as i64], |row| row.get::<_, bool>(0))
+            .unwrap_or(false)
+    }
+
+    /// Ok if everything registered or was already present
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+        Err(format!("{}: failed to register {}", EXTENSION_NAME, self.failed.join(", ")).into())
+    }
+}
+
 /// Extension entrypoint - registers all table functions with DuckDB
+///
+/// # Safety
+/// Called by DuckDB through the C API when the extension is loaded.
 #[duckdb_entrypoint_c_api()]
 pub unsafe fn extension_entrypoint(con: Connection) -> Result<(), Box<dyn Error>> {
+    let mut registrar = Registrar::new(&con);
+
     // Register entity scanner
     // Usage: SELECT * FROM manifold_entities('/path/to/db')
-    con.register_table_function::<ManifoldEntitiesVTab>("manifold_entities")
-        .expect("Failed to register manifold_entities table function");
+    registrar.table::<ManifoldEntitiesVTab>("manifold_entities");
+
+    // Register multi-label entity scanner
+    // Usage: SELECT * FROM manifold_entities_by_labels('/path/to/db', ['A', 'B'], 'and')
+    registrar.table::<ManifoldEntitiesByLabelsVTab>("manifold_entities_by_labels");
+
+    // Register id-list entity lookup
+    // Usage: SELECT * FROM manifold_entities_ordered('/path/to/db', '[3, 1, 2]')
+    registrar.table::<ManifoldEntitiesOrderedVTab>("manifold_entities_ordered");
 
     // Register edge scanner
     // Usage: SELECT * FROM manifold_edges('/path/to/db')
-    con.register_table_function::<ManifoldEdgesVTab>("manifold_edges")
-        .expect("Failed to register manifold_edges table function");
+    registrar.table::<ManifoldEdgesVTab>("manifold_edges");
+
+    // Register single-type edge scanner
+    // Usage: SELECT * FROM manifold_edges_of_type('/path/to/db', edge_type)
+    registrar.table::<ManifoldEdgesOfTypeVTab>("manifold_edges_of_type");
 
-    // TODO: Register graph traversal function
+    // Register graph traversal
     // Usage: SELECT * FROM manifold_traverse('/path/to/db', start_id, edge_type, depth)
+    registrar.table::<ManifoldTraverseVTab>("manifold_traverse");
+
+    // Register reachable set sizes
+    // Usage: SELECT * FROM manifold_reach_count('/path/to/db', depth, edge_type)
+    registrar.table::<ManifoldReachCountVTab>("manifold_reach_count");
+
+    // Register neighbour feature gathering
+    // Usage: SELECT * FROM manifold_gather('/path/to/db', seeds, direction, edge_type, props)
+    registrar.table::<ManifoldGatherVTab>("manifold_gather");
+
+    // Register neighbour aggregates
+    // Usage: SELECT * FROM manifold_neighbors_aggregate('/path/to/db', seeds, 'out', 'KNOWS', 'score', 'mean')
+    registrar.table::<ManifoldNeighborsAggregateVTab>("manifold_neighbors_aggregate");
 
-    // TODO: Register vector search function
+    // Register feature matrix lookup
+    // Usage: SELECT * FROM manifold_feature_matrix('/path/to/db', ids, props)
+    registrar.table::<ManifoldFeatureMatrixVTab>("manifold_feature_matrix");
+
+    // Register property equality lookup
+    // Usage: SELECT * FROM manifold_find_by_prop('/path/to/db', 'nodes', 'email', 'a@b.c')
+    registrar.table::<ManifoldFindByPropVTab>("manifold_find_by_prop");
+
+    // Register vector search
     // Usage: SELECT * FROM manifold_vector_search('/path/to/db', collection, query_vector, k)
+    registrar.table::<ManifoldVectorSearchVTab>("manifold_vector_search");
+
+    // Register batch vector search
+    // Usage: SELECT * FROM manifold_vector_search_batch('/path/to/db', collection, query_vectors, k)
+    registrar.table::<ManifoldVectorSearchBatchVTab>("manifold_vector_search_batch");
+
+    // Register edge weight aggregation
+    // Usage: SELECT * FROM manifold_edge_weights('/path/to/db', weight_prop)
+    registrar.table::<ManifoldEdgeWeightsVTab>("manifold_edge_weights");
+
+    // Register id bounds lookup
+    // Usage: SELECT * FROM manifold_id_bounds('/path/to/db', 'nodes')
+    registrar.table::<ManifoldIdBoundsVTab>("manifold_id_bounds");
+
+    // Register per-table storage sizes
+    // Usage: SELECT * FROM manifold_storage_stats('/path/to/db')
+    registrar.table::<ManifoldStorageStatsVTab>("manifold_storage_stats");
+
+    // Register dangling edge check
+    // Usage: SELECT * FROM manifold_dangling_edges('/path/to/db')
+    registrar.table::<ManifoldDanglingEdgesVTab>("manifold_dangling_edges");
+
+    // Register decode audit
+    // Usage: SELECT * FROM manifold_verify('/path/to/db', 'nodes')
+    registrar.table::<ManifoldVerifyVTab>("manifold_verify");
+
+    // Register vector collection catalog
+    // Usage: SELECT * FROM manifold_collections('/path/to/db')
+    registrar.table::<ManifoldCollectionsVTab>("manifold_collections");
+
+    // Register triangle count
+    // Usage: SELECT * FROM manifold_triangle_count('/path/to/db', 'KNOWS')
+    registrar.table::<ManifoldTriangleCountVTab>("manifold_triangle_count");
+
+    // Register combined node and edge stream
+    // Usage: SELECT * FROM manifold_changes('/path/to/db')
+    registrar.table::<ManifoldChangesVTab>("manifold_changes");
+
+    // Register graph export
+    // Usage: SELECT document FROM manifold_export('/path/to/db', 'graphml')
+    registrar.table::<ManifoldExportVTab>("manifold_export");
+
+    // Register JSON Lines export
+    // Usage: SELECT * FROM manifold_export_jsonl('/path/to/db', 'nodes', '/path/to/out.jsonl')
+    registrar.table::<ManifoldExportJsonlVTab>("manifold_export_jsonl");
+
+    // Register label bit mapping
+    // Usage: SELECT * FROM manifold_label_bits('/path/to/db')
+    registrar.table::<ManifoldLabelBitsVTab>("manifold_label_bits");
+
+    // Register snapshot additions
+    // Usage: SELECT * FROM manifold_new_entities('/path/to/base', '/path/to/current')
+    registrar.table::<ManifoldNewEntitiesVTab>("manifold_new_entities");
+
+    // Register last scan counters
+    // Usage: SELECT * FROM manifold_last_scan_stats()
+    registrar.table::<ManifoldLastScanStatsVTab>("manifold_last_scan_stats");
+
+    // Register pushdown diagnostics for the most recent scan
+    // Usage: SELECT * FROM manifold_last_pushdown()
+    registrar.table::<ManifoldLastPushdownVTab>("manifold_last_pushdown");
+
+    // Register configuration introspection
+    // Usage: SELECT * FROM manifold_config()
+    registrar.table::<ManifoldConfigVTab>("manifold_config");
+
+    // Register cached database reopen
+    // Usage: SELECT manifold_refresh('/path/to/db')
+    registrar.scalar::<ManifoldRefreshScalar>("manifold_refresh");
+
+    // Register database warm-up
+    // Usage: SELECT manifold_warmup('/path/to/db')
+    registrar.scalar::<ManifoldWarmupScalar>("manifold_warmup");
+
+    // Register vector encoding
+    // Usage: SELECT manifold_encode_vector([0.1, 0.2, 0.3])
+    registrar.scalar::<ManifoldEncodeVectorScalar>("manifold_encode_vector");
 
-    Ok(())
+    registrar.finish()
 }
@@ -0,0 +1,382 @@
+//! Combined node and edge stream for ManifoldDB
+//!
+//! Implements a table function returning every entity and edge as one stream,
+//! interleaved by id, for replaying a database into a client-side graph.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_changes('/path/to/database.redb');
+//! ```
+//!
+//! ## Columns
+//! - `kind` - 'node' or 'edge'
+//! - `id` - Entity or edge id
+//! - `labels` - Entity labels as LIST(VARCHAR); NULL for edges
+//! - `source`, `target`, `edge_type` - As in `manifold_edges`; NULL for nodes
+//! - `properties` - All properties as a JSON object with sorted keys
+//!
+//! ## Order
+//! The nodes and edges tables are read with one cursor each and merged by
+//! storage key, which is the id for every well-formed record. When a node and
+//! an edge share an id the node comes first. The order is deterministic for an
+//! unchanged database.
+//!
+//! Rows are in id order, not dependency order: an edge precedes its endpoints
+//! when it has a lower id than one of them. Databases that give nodes and
+//! edges ids from one counter, creating edges after their endpoints, replay
+//! with every node before its edges. Otherwise buffer edges whose endpoints
+//! haven't arrived yet.
+//!
+//! ## Strategy
+//! Like the other scans, the stream is read in batches from one read
+//! transaction, continuing each cursor after the last key it returned, so
+//! memory use doesn't grow with the database. Records that fail to decode are
+//! skipped.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    ffi::CString,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, Entity};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, Transaction};
+
+use super::engine::ManifoldEngine;
+use super::values::properties_to_json;
+use super::{
+    chunk_batch_size, first_after, get_cached_engine, insert_str, read_scan_batch, string_param,
+    ScanSnapshot,
+};
+
+/// One row of the stream
+#[derive(Debug)]
+pub enum Change {
+    Node(Entity),
+    Edge(Edge),
+}
+
+/// Where the merge has got to: the last key consumed from each table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergePosition {
+    pub node_key: Option<Vec<u8>>,
+    pub edge_key: Option<Vec<u8>>,
+}
+
+/// Bind data for the change stream
+#[repr(C)]
+pub struct ManifoldChangesBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+}
+
+/// Init data for the change stream - holds scan state
+#[repr(C)]
+pub struct ManifoldChangesInitData {
+    /// Flag indicating the stream is exhausted
+    pub done: AtomicBool,
+    /// Continuation markers for both cursors
+    pub position: Mutex<MergePosition>,
+    /// Read transaction held across batches; None before the first batch and
+    /// after the scan completes
+    pub snapshot: Mutex<Option<ScanSnapshot>>,
+}
+
+impl ManifoldChangesInitData {
+    /// Read the next batch of the stream, releasing the snapshot once it is exhausted
+    fn next_batch(
+        &self,
+        db_path: &str,
+        engine: &ManifoldEngine,
+        batch_size: usize,
+    ) -> Result<Vec<Change>, Box<dyn Error>> {
+        if self.done.load(Ordering::Relaxed) {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let position = self.position.lock().unwrap().clone();
+        let last_key = position.node_key.as_deref().max(position.edge_key.as_deref());
+
+        let batch = read_scan_batch(&self.done, db_path, last_key, || {
+            let tx = match &mut *snapshot {
+                Some(snapshot) => snapshot.tx(),
+                None => snapshot.insert(ScanSnapshot::begin(engine)?).tx(),
+            };
+            scan_change_batch(tx, &position, batch_size)
+        });
+        let (changes, next_position) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                // The scan is over; don't keep the snapshot pinned
+                *snapshot = None;
+                return Err(e.into());
+            }
+        };
+
+        if changes.is_empty() {
+            // Both tables are exhausted - stop pinning the snapshot
+            self.done.store(true, Ordering::Relaxed);
+            *snapshot = None;
+            return Ok(changes);
+        }
+
+        *self.position.lock().unwrap() = next_position;
+        Ok(changes)
+    }
+}
+
+/// Change stream VTab implementation
+pub struct ManifoldChangesVTab;
+
+impl VTab for ManifoldChangesVTab {
+    type InitData = ManifoldChangesInitData;
+    type BindData = ManifoldChangesBindData;
+
+    /// Bind phase: set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        let varchar = || LogicalTypeHandle::from(LogicalTypeId::Varchar);
+        bind.add_result_column("kind", varchar());
+        bind.add_result_column("id", varchar());
+        bind.add_result_column("labels", LogicalTypeHandle::list(&varchar()));
+        bind.add_result_column("source", varchar());
+        bind.add_result_column("target", varchar());
+        bind.add_result_column("edge_type", varchar());
+        bind.add_result_column("properties", varchar());
+
+        Ok(ManifoldChangesBindData { db_path })
+    }
+
+    /// Init phase: the snapshot is taken with the first batch
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldChangesInitData {
+            done: AtomicBool::new(false),
+            position: Mutex::new(MergePosition::default()),
+            snapshot: Mutex::new(None),
+        })
+    }
+
+    /// Func phase: emit the next batch of the stream
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_changes".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+        ])
+    }
+}
+
+impl ManifoldChangesVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let changes = init_data.next_batch(&bind_data.db_path, &engine, chunk_batch_size(output))?;
+        populate_change_output(&changes, output)?;
+        output.set_len(changes.len());
+
+        Ok(())
+    }
+}
+
+/// Read up to `batch_size` records after `after`, merging both tables by key
+///
+/// Returns the records and the position to continue from. An empty batch
+/// means both tables are exhausted: undecodable records are skipped without
+/// ending the batch early.
+fn scan_change_batch(
+    tx: &RedbTransaction,
+    after: &MergePosition,
+    batch_size: usize,
+) -> Result<(Vec<Change>, MergePosition), Box<dyn Error>> {
+    let mut changes = Vec::with_capacity(batch_size);
+    let mut position = after.clone();
+
+    // A missing table is an empty one
+    let mut nodes = tx.cursor("nodes").ok();
+    let mut edges = tx.cursor("edges").ok();
+    let mut node = match &mut nodes {
+        Some(cursor) => first_after(cursor, after.node_key.as_deref())?,
+        None => None,
+    };
+    let mut edge = match &mut edges {
+        Some(cursor) => first_after(cursor, after.edge_key.as_deref())?,
+        None => None,
+    };
+
+    while changes.len() < batch_size {
+        // Nodes win ties so an edge never precedes a node with its id
+        let take_node = match (&node, &edge) {
+            (Some((node_key, _)), Some((edge_key, _))) => node_key <= edge_key,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+
+        if take_node {
+            let (key, value) = node.take().expect("a node entry was peeked");
+            if let Ok(entity) = Entity::decode(&value) {
+                changes.push(Change::Node(entity));
+            }
+            position.node_key = Some(key);
+            node = nodes.as_mut().expect("nodes were read").next()?;
+        } else {
+            let (key, value) = edge.take().expect("an edge entry was peeked");
+            if let Ok(edge) = Edge::decode(&value) {
+                changes.push(Change::Edge(edge));
+            }
+            position.edge_key = Some(key);
+            edge = edges.as_mut().expect("edges were read").next()?;
+        }
+    }
+
+    Ok((changes, position))
+}
+
+/// Populate DuckDB output chunk with stream rows
+fn populate_change_output(
+    changes: &[Change],
+    output: &mut DataChunkHandle,
+) -> Result<(), Box<dyn Error>> {
+    let kinds = output.flat_vector(0);
+    let ids = output.flat_vector(1);
+    let mut sources = output.flat_vector(3);
+    let mut targets = output.flat_vector(4);
+    let mut edge_types = output.flat_vector(5);
+    let properties = output.flat_vector(6);
+
+    let mut labels = output.list_vector(2);
+    let label_count = changes
+        .iter()
+        .map(|change| match change {
+            Change::Node(entity) => entity.labels.len(),
+            Change::Edge(_) => 0,
+        })
+        .sum();
+    let label_values = labels.child(label_count);
+    let mut label_offset = 0;
+
+    for (row_idx, change) in changes.iter().enumerate() {
+        match change {
+            Change::Node(entity) => {
+                kinds.insert(row_idx, "node");
+                ids.insert(row_idx, CString::new(entity.id.as_u64().to_string())?);
+                for (i, label) in entity.labels.iter().enumerate() {
+                    insert_str(&label_values, label_offset + i, label.as_str());
+                }
+                labels.set_entry(row_idx, label_offset, entity.labels.len());
+                label_offset += entity.labels.len();
+                sources.set_null(row_idx);
+                targets.set_null(row_idx);
+                edge_types.set_null(row_idx);
+                properties.insert(row_idx, CString::new(properties_to_json(&entity.properties))?);
+            }
+            Change::Edge(edge) => {
+                kinds.insert(row_idx, "edge");
+                ids.insert(row_idx, CString::new(edge.id.as_u64().to_string())?);
+                labels.set_null(row_idx);
+                sources.insert(row_idx, CString::new(edge.source.as_u64().to_string())?);
+                targets.insert(row_idx, CString::new(edge.target.as_u64().to_string())?);
+                insert_str(&edge_types, row_idx, edge.edge_type.as_str());
+                properties.insert(row_idx, CString::new(properties_to_json(&edge.properties))?);
+            }
+        }
+    }
+    labels.set_len(label_offset);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId};
+    use manifoldb_storage::StorageEngine;
+
+    #[test]
+    fn test_scan_change_batch_merges_by_id() {
+        let db = TempDb::new("changes");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in [1u64, 2, 5, 6] {
+            let entity = Entity::new(EntityId::from(id));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        for (id, source, target) in [(2u64, 1u64, 2u64), (3, 2, 5), (7, 5, 6)] {
+            let edge = Edge::new(
+                EdgeId::new(id),
+                EntityId::from(source),
+                EntityId::from(target),
+                EdgeType::new("LINKS"),
+            );
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.put("edges", &4u64.to_be_bytes(), b"not an edge").unwrap();
+        tx.commit().unwrap();
+
+        let describe = |change: &Change| match change {
+            Change::Node(entity) => ("node", entity.id.as_u64()),
+            Change::Edge(edge) => ("edge", edge.id.as_u64()),
+        };
+
+        // Batches of 3 continue each cursor where it stopped
+        let tx = engine.begin_read().unwrap();
+        let mut position = MergePosition::default();
+        let mut rows = Vec::new();
+        loop {
+            let (changes, next) = scan_change_batch(&tx, &position, 3).unwrap();
+            if changes.is_empty() {
+                break;
+            }
+            rows.extend(changes.iter().map(describe));
+            position = next;
+        }
+        assert_eq!(
+            rows,
+            vec![
+                ("node", 1),
+                ("node", 2),
+                ("edge", 2),
+                ("edge", 3),
+                ("node", 5),
+                ("node", 6),
+                ("edge", 7),
+            ]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Vector collection catalog for ManifoldDB
+//!
+//! Implements a table function listing the collections `manifold_vector_search`
+//! can query, with the dimension a query vector must have.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_collections('/path/to/database.redb');
+//! ```
+//!
+//! ## Columns
+//! - `collection_name` - Entity property holding `Value::Vector` embeddings
+//! - `dimension` - Length of the stored vectors; NULL if they disagree, in
+//!   which case searches only match vectors of the query's length
+//! - `metric` - Metric of the collection's HNSW index (see below)
+//! - `count` - Number of entities with a vector in the collection
+//! - `indexed` - Whether Manifold has registered an HNSW index for it
+//!
+//! ## Strategy
+//! Collections are found by reading every entity once, as a brute-force
+//! search would, plus the names in the HNSW registry (an indexed collection
+//! with no stored vectors is listed with a count of 0). The registry's index
+//! configuration is written by the Manifold vector index crate, which this
+//! extension does not link, so `metric` can't be read from it and is NULL;
+//! brute-force search accepts any metric.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use super::vector_search::HNSW_REGISTRY_TABLE;
+use super::{chunk_batch_size, get_cached_engine, string_param};
+
+/// One vector collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionInfo {
+    pub name: String,
+    /// None when stored vectors have different lengths (or there are none)
+    pub dimension: Option<usize>,
+    pub count: u64,
+    pub indexed: bool,
+}
+
+/// Accumulates vector lengths and counts per collection
+#[derive(Default)]
+struct CatalogBuilder {
+    /// Collection -> (dimension seen so far, whether lengths disagreed, count)
+    stats: BTreeMap<String, (Option<usize>, bool, u64)>,
+}
+
+impl CatalogBuilder {
+    /// Record an entity's vector properties
+    fn observe(&mut self, entity: &Entity) {
+        for (name, value) in &entity.properties {
+            if let Value::Vector(vector) = value {
+                let (dimension, mixed, count) =
+                    self.stats.entry(name.clone()).or_insert((None, false, 0));
+                match dimension {
+                    None if *count == 0 => *dimension = Some(vector.len()),
+                    Some(d) if *d != vector.len() => *mixed = true,
+                    _ => {}
+                }
+                *count += 1;
+            }
+        }
+    }
+
+    /// Record a collection with a registered index
+    fn observe_index(&mut self, name: String) {
+        self.stats.entry(name).or_insert((None, false, 0));
+    }
+
+    /// Collections sorted by name
+    fn into_collections(self, tx: &RedbTransaction) -> Result<Vec<CollectionInfo>, Box<dyn Error>> {
+        self.stats
+            .into_iter()
+            .map(|(name, (dimension, mixed, count))| {
+                let indexed = match tx.get(HNSW_REGISTRY_TABLE, name.as_bytes()) {
+                    Ok(entry) => entry.is_some(),
+                    // Registry table doesn't exist - no indexes at all
+                    Err(_) => false,
+                };
+                Ok(CollectionInfo {
+                    name,
+                    dimension: if mixed { None } else { dimension },
+                    count,
+                    indexed,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Bind data for the collection catalog
+#[repr(C)]
+pub struct ManifoldCollectionsBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+}
+
+/// Init data for the collection catalog - holds the collections
+#[repr(C)]
+pub struct ManifoldCollectionsInitData {
+    /// Collections sorted by name
+    pub collections: Vec<CollectionInfo>,
+    /// Number of collections already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Collection catalog VTab implementation
+pub struct ManifoldCollectionsVTab;
+
+impl VTab for ManifoldCollectionsVTab {
+    type InitData = ManifoldCollectionsInitData;
+    type BindData = ManifoldCollectionsBindData;
+
+    /// Bind phase: set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+
+        bind.add_result_column("collection_name", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("dimension", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("metric", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("count", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("indexed", LogicalTypeHandle::from(LogicalTypeId::Boolean));
+
+        Ok(ManifoldCollectionsBindData { db_path })
+    }
+
+    /// Init phase: build the catalog
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldCollectionsBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        Ok(ManifoldCollectionsInitData {
+            collections: find_collections(&tx)?,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the collections
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_collections".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+        ])
+    }
+}
+
+impl ManifoldCollectionsVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.collections.len());
+        let batch = &init_data.collections[start..end];
+
+        let names = output.flat_vector(0);
+        for (row_idx, collection) in batch.iter().enumerate() {
+            names.insert(row_idx, CString::new(collection.name.as_str())?);
+        }
+
+        let mut dimensions = output.flat_vector(1);
+        for (row_idx, collection) in batch.iter().enumerate() {
+            match collection.dimension {
+                Some(d) => dimensions.as_mut_slice::<i64>()[row_idx] = d as i64,
+                None => dimensions.set_null(row_idx),
+            }
+        }
+
+        let mut metrics = output.flat_vector(2);
+        for row_idx in 0..batch.len() {
+            metrics.set_null(row_idx);
+        }
+
+        let mut counts = output.flat_vector(3);
+        for (row_idx, collection) in batch.iter().enumerate() {
+            counts.as_mut_slice::<i64>()[row_idx] = collection.count as i64;
+        }
+
+        let mut indexed = output.flat_vector(4);
+        for (row_idx, collection) in batch.iter().enumerate() {
+            indexed.as_mut_slice::<bool>()[row_idx] = collection.indexed;
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Read every entity and the HNSW registry, listing vector collections
+fn find_collections(tx: &RedbTransaction) -> Result<Vec<CollectionInfo>, Box<dyn Error>> {
+    let mut builder = CatalogBuilder::default();
+
+    if let Ok(mut cursor) = tx.cursor("nodes") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            if let Ok(entity) = Entity::decode(&value) {
+                builder.observe(&entity);
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    if let Ok(mut cursor) = tx.cursor(HNSW_REGISTRY_TABLE) {
+        let mut entry = cursor.seek_first()?;
+        while let Some((key, _value)) = entry {
+            builder.observe_index(String::from_utf8_lossy(&key).into_owned());
+            entry = cursor.next()?;
+        }
+    }
+
+    builder.into_collections(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::EntityId;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_find_collections() {
+        let db = TempDb::new("collections");
+        let engine = db.open();
+
+        let vectors = [
+            ("embedding", vec![0.1f32, 0.2, 0.3]),
+            ("embedding", vec![0.3, 0.2, 0.1]),
+            ("thumb", vec![1.0, 0.0]),
+            ("thumb", vec![1.0, 0.0, 0.0]),
+        ];
+        let mut tx = engine.begin_write().unwrap();
+        for (id, (name, vector)) in vectors.into_iter().enumerate() {
+            let mut properties = HashMap::new();
+            properties.insert(name.to_string(), Value::Vector(vector));
+            properties.insert("title".to_string(), Value::String("x".to_string()));
+            let entity = Entity {
+                id: EntityId::from(id as u64),
+                labels: Vec::new(),
+                properties,
+                vectors: HashMap::new(),
+            };
+            tx.put("nodes", &(id as u64).to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.put(HNSW_REGISTRY_TABLE, b"captions", b"config").unwrap();
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let collections = find_collections(&tx).unwrap();
+        let summary: Vec<(&str, Option<usize>, u64, bool)> = collections
+            .iter()
+            .map(|c| (c.name.as_str(), c.dimension, c.count, c.indexed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("captions", None, 0, true),
+                ("embedding", Some(3), 2, false),
+                ("thumb", None, 2, false),
+            ]
+        );
+    }
+}
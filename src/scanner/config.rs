@@ -0,0 +1,232 @@
+//! Extension configuration
+//!
+//! Implements `manifold_config()`, a table function listing the settings in
+//! force for this process - defaults after environment overrides, cache
+//! occupancy and which optional scan features are available - so odd or slow
+//! behaviour can be diagnosed without asking how the extension was set up.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_config();
+//! SELECT value FROM manifold_config() WHERE name = 'batch_size';
+//! ```
+//!
+//! ## Columns
+//! - `name` - Setting name
+//! - `value` - Current value as text; NULL when unset
+//! - `description` - What it controls and where it comes from
+//!
+//! ## Scope
+//! Values are read when the query is bound. Like the default path and scan
+//! statistics they are process-wide, shared by every connection.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::entities::MAX_DECODE_THREADS;
+use super::{
+    batch_size, cached_engine_count, chunk_batch_size, insert_str, open_snapshots,
+    schema_sample_size,
+};
+
+/// One row of `manifold_config()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub name: &'static str,
+    pub value: Option<String>,
+    pub description: &'static str,
+}
+
+impl ConfigEntry {
+    fn new(name: &'static str, value: Option<String>, description: &'static str) -> Self {
+        Self {
+            name,
+            value,
+            description,
+        }
+    }
+}
+
+/// The settings currently in force, in display order
+pub fn config_entries() -> Vec<ConfigEntry> {
+    vec![
+        ConfigEntry::new(
+            "version",
+            Some(env!("CARGO_PKG_VERSION").to_string()),
+            "Extension version",
+        ),
+        ConfigEntry::new(
+            "batch_size",
+            Some(batch_size().to_string()),
+            "Rows per output batch (MANIFOLD_BATCH_SIZE, default 1024, capped by the chunk size)",
+        ),
+        ConfigEntry::new(
+            "schema_sample",
+            Some(schema_sample_size().to_string()),
+            "Records sampled from each end and the middle for schema discovery \
+             (MANIFOLD_SCHEMA_SAMPLE, default 100; per query with schema_sample)",
+        ),
+        ConfigEntry::new(
+            "engine_cache_capacity",
+            Some("unbounded".to_string()),
+            "Databases the engine cache may hold; entries stay open until manifold_refresh \
+             or process exit",
+        ),
+        ConfigEntry::new(
+            "engine_cache_entries",
+            Some(cached_engine_count().to_string()),
+            "Databases currently open in the engine cache",
+        ),
+        ConfigEntry::new(
+            "open_snapshots",
+            Some(open_snapshots().to_string()),
+            "Scans currently holding a read transaction",
+        ),
+        ConfigEntry::new(
+            "projection_pushdown",
+            Some("true".to_string()),
+            "manifold_entities builds only the columns a query selects",
+        ),
+        ConfigEntry::new(
+            "filter_pushdown",
+            Some("false".to_string()),
+            "WHERE clauses are applied by DuckDB after the scan; push filters down with \
+             where_json, has_prop or a label filter",
+        ),
+        ConfigEntry::new(
+            "parallel_scan",
+            Some("false".to_string()),
+            "Each scan runs on one DuckDB thread",
+        ),
+        ConfigEntry::new(
+            "decode_threads",
+            Some("1".to_string()),
+            "Threads decoding each entity batch (per query with decode_threads)",
+        ),
+        ConfigEntry::new(
+            "max_decode_threads",
+            Some(MAX_DECODE_THREADS.to_string()),
+            "Largest decode_threads a query may ask for; at most one per core is used",
+        ),
+    ]
+}
+
+/// Bind data for config - the entries, read at bind
+#[repr(C)]
+pub struct ManifoldConfigBindData {
+    pub entries: Vec<ConfigEntry>,
+}
+
+/// Init data for config - tracks the next entry to emit
+#[repr(C)]
+pub struct ManifoldConfigInitData {
+    pub next: AtomicUsize,
+}
+
+/// Config VTab implementation
+pub struct ManifoldConfigVTab;
+
+impl VTab for ManifoldConfigVTab {
+    type InitData = ManifoldConfigInitData;
+    type BindData = ManifoldConfigBindData;
+
+    /// Bind phase: take a copy of the settings, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        bind.add_result_column("name", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("value", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("description", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+
+        let entries = config_entries();
+        bind.set_cardinality(entries.len() as u64, true);
+
+        Ok(ManifoldConfigBindData { entries })
+    }
+
+    /// Init phase: start at the first entry
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldConfigInitData {
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the entries
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_config".into()),
+        }
+    }
+
+    /// No parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        None
+    }
+}
+
+impl ManifoldConfigVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+
+        let batch_size = chunk_batch_size(output);
+        let start = init_data.next.load(Ordering::Relaxed);
+        let end = (start + batch_size).min(bind_data.entries.len());
+        init_data.next.store(end, Ordering::Relaxed);
+
+        let name_vector = output.flat_vector(0);
+        let mut value_vector = output.flat_vector(1);
+        let description_vector = output.flat_vector(2);
+        for (row_idx, entry) in bind_data.entries[start..end].iter().enumerate() {
+            insert_str(&name_vector, row_idx, entry.name);
+            match &entry.value {
+                Some(value) => insert_str(&value_vector, row_idx, value),
+                None => value_vector.set_null(row_idx),
+            }
+            insert_str(&description_vector, row_idx, entry.description);
+        }
+
+        output.set_len(end - start);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_entries() {
+        let entries = config_entries();
+        let value = |name: &str| {
+            entries.iter().find(|e| e.name == name).unwrap().value.clone()
+        };
+
+        assert_eq!(value("batch_size"), Some(batch_size().to_string()));
+        assert_eq!(value("schema_sample"), Some(schema_sample_size().to_string()));
+        assert_eq!(value("version").as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert!(value("engine_cache_entries").unwrap().parse::<usize>().is_ok());
+        assert_eq!(value("projection_pushdown").as_deref(), Some("true"));
+
+        let mut names: Vec<&str> = entries.iter().map(|e| e.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), entries.len());
+    }
+}
@@ -0,0 +1,245 @@
+//! Dangling edge check for ManifoldDB
+//!
+//! Implements a table function that returns edges whose source or target id
+//! has no entity in the nodes table - a referential-integrity check for use
+//! after bulk imports.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_dangling_edges('/path/to/database.redb');
+//! SELECT missing, COUNT(*) FROM manifold_dangling_edges('/path/to/database.redb') GROUP BY missing;
+//! ```
+//!
+//! ## Columns
+//! - `id`, `source`, `target`, `edge_type` - As in `manifold_edges`
+//! - `missing` - Which endpoint has no entity: 'source', 'target' or 'both'
+//!
+//! ## Strategy
+//! Edges are read once through a cursor and each endpoint is checked with a
+//! point lookup on the nodes table, all within one read transaction. Memory
+//! use is proportional to the number of dangling edges, not the graph size.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::Edge;
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use super::{chunk_batch_size, get_cached_engine, insert_str, string_param};
+
+/// Which endpoint of an edge has no entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingEndpoint {
+    Source,
+    Target,
+    Both,
+}
+
+impl MissingEndpoint {
+    /// Classify an edge from whether each endpoint exists
+    fn from_exists(source_exists: bool, target_exists: bool) -> Option<Self> {
+        match (source_exists, target_exists) {
+            (true, true) => None,
+            (false, true) => Some(MissingEndpoint::Source),
+            (true, false) => Some(MissingEndpoint::Target),
+            (false, false) => Some(MissingEndpoint::Both),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MissingEndpoint::Source => "source",
+            MissingEndpoint::Target => "target",
+            MissingEndpoint::Both => "both",
+        }
+    }
+}
+
+/// An edge with at least one missing endpoint
+#[derive(Debug)]
+pub struct DanglingEdge {
+    pub edge: Edge,
+    pub missing: MissingEndpoint,
+}
+
+/// Bind data for the dangling edge check
+#[repr(C)]
+pub struct ManifoldDanglingEdgesBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+}
+
+/// Init data for the dangling edge check - holds the dangling edges
+#[repr(C)]
+pub struct ManifoldDanglingEdgesInitData {
+    /// Dangling edges in key order
+    pub edges: Vec<DanglingEdge>,
+    /// Number of edges already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Dangling edge check VTab implementation
+pub struct ManifoldDanglingEdgesVTab;
+
+impl VTab for ManifoldDanglingEdgesVTab {
+    type InitData = ManifoldDanglingEdgesInitData;
+    type BindData = ManifoldDanglingEdgesBindData;
+
+    /// Bind phase: set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+
+        for name in ["id", "source", "target", "edge_type", "missing"] {
+            bind.add_result_column(name, LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        }
+
+        Ok(ManifoldDanglingEdgesBindData { db_path })
+    }
+
+    /// Init phase: find every dangling edge
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldDanglingEdgesBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        Ok(ManifoldDanglingEdgesInitData {
+            edges: find_dangling_edges(&tx)?,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the dangling edges
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_dangling_edges".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+        ])
+    }
+}
+
+impl ManifoldDanglingEdgesVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.edges.len());
+        let batch = &init_data.edges[start..end];
+
+        let ids = output.flat_vector(0);
+        let sources = output.flat_vector(1);
+        let targets = output.flat_vector(2);
+        let edge_types = output.flat_vector(3);
+        let missing = output.flat_vector(4);
+        for (row_idx, dangling) in batch.iter().enumerate() {
+            let edge = &dangling.edge;
+            ids.insert(row_idx, CString::new(edge.id.as_u64().to_string())?);
+            sources.insert(row_idx, CString::new(edge.source.as_u64().to_string())?);
+            targets.insert(row_idx, CString::new(edge.target.as_u64().to_string())?);
+            insert_str(&edge_types, row_idx, edge.edge_type.as_str());
+            missing.insert(row_idx, dangling.missing.as_str());
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Scan all edges, keeping those with an endpoint missing from the nodes table
+fn find_dangling_edges(tx: &RedbTransaction) -> Result<Vec<DanglingEdge>, Box<dyn Error>> {
+    let mut dangling = Vec::new();
+
+    let Ok(mut cursor) = tx.cursor("edges") else {
+        // No edges table - nothing can dangle
+        return Ok(dangling);
+    };
+
+    let entity_exists = |id: u64| -> Result<bool, Box<dyn Error>> {
+        match tx.get("nodes", &id.to_be_bytes()) {
+            Ok(entry) => Ok(entry.is_some()),
+            // Nodes table doesn't exist - no entities at all
+            Err(_) => Ok(false),
+        }
+    };
+
+    let mut entry = cursor.seek_first()?;
+    while let Some((_key, value)) = entry {
+        if let Ok(edge) = Edge::decode(&value) {
+            let source_exists = entity_exists(edge.source.as_u64())?;
+            let target_exists = entity_exists(edge.target.as_u64())?;
+            if let Some(missing) = MissingEndpoint::from_exists(source_exists, target_exists) {
+                dangling.push(DanglingEdge { edge, missing });
+            }
+        }
+        entry = cursor.next()?;
+    }
+
+    Ok(dangling)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_find_dangling_edges() {
+        let db = TempDb::new("dangling");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in [1u64, 2] {
+            tx.put("nodes", &id.to_be_bytes(), b"").unwrap();
+        }
+        for (id, source, target) in [(10u64, 1u64, 2u64), (11, 1, 9), (12, 8, 2), (13, 8, 9)] {
+            let edge = Edge {
+                id: EdgeId::from(id),
+                source: EntityId::from(source),
+                target: EntityId::from(target),
+                edge_type: EdgeType::new("LINKS"),
+                properties: HashMap::new(),
+            };
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let found: Vec<(u64, &str)> = find_dangling_edges(&tx)
+            .unwrap()
+            .iter()
+            .map(|d| (d.edge.id.as_u64(), d.missing.as_str()))
+            .collect();
+        assert_eq!(found, vec![(11, "target"), (12, "source"), (13, "both")]);
+    }
+}
@@ -0,0 +1,333 @@
+//! Fixed-precision property columns
+//!
+//! Manifold has no decimal value type, so monetary values are stored as
+//! strings or integers. The `decimal_props` parameter names property columns
+//! to expose as DuckDB `DECIMAL(width, scale)` instead of VARCHAR, so sums and
+//! averages over them don't pick up float rounding errors.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT sum(prop_amount) FROM manifold_entities('/path/to/database.redb',
+//!     decimal_props={'prop_amount': '18,2'});
+//! ```
+//!
+//! ## Conversion
+//! - `Value::String` - a plain decimal (`-12.345`); extra fractional digits
+//!   are rounded half away from zero, as DuckDB's cast does
+//! - `Value::Int` - the integer, at the column's scale
+//! - `Value::Float` - its shortest round-trip decimal, then as a string
+//!
+//! Anything else, text that isn't a decimal, or a value with more integer
+//! digits than the width allows doesn't fit the column.
+//!
+//! ## Coercion Policy
+//! `coercion_policy` decides what happens to a value that doesn't fit:
+//! - `'null'` (default) - the cell is NULL
+//! - `'error'` - the query fails, naming the record id, column and value
+//! - `'stringify'` - the column is VARCHAR instead: values that fit are
+//!   written as decimal text at the column's scale (`'12.50'`), the rest as
+//!   their usual VARCHAR rendering
+//!
+//! Under `'null'` and `'stringify'` each such value is counted in
+//! `manifold_last_scan_stats().coercion_failures`. A null property value is
+//! NULL under every policy and isn't counted.
+
+use duckdb::{
+    core::{DataChunkHandle, FlatVector},
+    vtab::BindInfo,
+};
+use manifoldb_core::types::Value;
+use std::collections::HashMap;
+
+use crate::error::ManifoldScannerError;
+use crate::schema::{ColumnType, DiscoveredColumn};
+use super::values::value_to_duckdb_string;
+use super::{insert_str, named_string_map};
+
+/// Widest DECIMAL DuckDB supports
+const MAX_DECIMAL_WIDTH: u8 = 38;
+
+/// What to do with a value that doesn't fit its declared column type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoercionPolicy {
+    /// Fail the query
+    Error,
+    /// Write NULL
+    #[default]
+    Null,
+    /// Expose the column as VARCHAR and write the value's own text
+    Stringify,
+}
+
+impl CoercionPolicy {
+    /// Parse a `coercion_policy` value
+    pub fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        match text {
+            "error" => Ok(Self::Error),
+            "null" => Ok(Self::Null),
+            "stringify" => Ok(Self::Stringify),
+            other => Err(ManifoldScannerError::InvalidParameter(format!(
+                "coercion_policy must be 'error', 'null' or 'stringify', got '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Read `coercion_policy`, defaulting to 'null'
+pub fn named_coercion_policy(bind: &BindInfo) -> Result<CoercionPolicy, ManifoldScannerError> {
+    match bind.get_named_parameter("coercion_policy") {
+        Some(v) => CoercionPolicy::parse(&v.to_string()),
+        None => Ok(CoercionPolicy::default()),
+    }
+}
+
+/// Read `decimal_props`, mapping column names to DECIMAL column types
+pub fn named_decimal_props(
+    bind: &BindInfo,
+    name: &str,
+) -> Result<HashMap<String, ColumnType>, ManifoldScannerError> {
+    let Some(pairs) = named_string_map(bind, name)? else {
+        return Ok(HashMap::new());
+    };
+
+    pairs
+        .into_iter()
+        .map(|(column, text)| {
+            let column_type = parse_decimal_type(&text).ok_or_else(|| {
+                ManifoldScannerError::InvalidParameter(format!(
+                    "{} for '{}' must be 'width,scale' or 'DECIMAL(width,scale)' with width \
+                     1-{} and scale at most width, got '{}'",
+                    name, column, MAX_DECIMAL_WIDTH, text
+                ))
+            })?;
+            Ok((column, column_type))
+        })
+        .collect()
+}
+
+/// Parse `w,s` or `DECIMAL(w,s)` into a DECIMAL column type
+fn parse_decimal_type(text: &str) -> Option<ColumnType> {
+    let text = text.trim();
+    let spec = match text.get(..8) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("decimal(") => text[8..].strip_suffix(')')?,
+        _ => text,
+    };
+    let (width, scale) = spec.split_once(',')?;
+    let width: u8 = width.trim().parse().ok()?;
+    let scale: u8 = scale.trim().parse().ok()?;
+
+    (1..=MAX_DECIMAL_WIDTH).contains(&width).then_some(())?;
+    (scale <= width).then_some(ColumnType::Decimal { width, scale })
+}
+
+/// Retype the named property columns as DECIMAL, or as decimal text under
+/// `CoercionPolicy::Stringify`
+///
+/// Fails if a name isn't a property column of the scan.
+pub fn apply_decimal_props(
+    columns: &mut [DiscoveredColumn],
+    decimal_props: &HashMap<String, ColumnType>,
+    policy: CoercionPolicy,
+) -> Result<(), ManifoldScannerError> {
+    for (column_name, &column_type) in decimal_props {
+        let column = columns
+            .iter_mut()
+            .find(|col| col.name == *column_name && col.property.is_some())
+            .ok_or_else(|| {
+                ManifoldScannerError::InvalidParameter(format!(
+                    "decimal_props names '{}', which is not a property column",
+                    column_name
+                ))
+            })?;
+        column.column_type = match (column_type, policy) {
+            (ColumnType::Decimal { width, scale }, CoercionPolicy::Stringify) => {
+                ColumnType::DecimalText { width, scale }
+            }
+            _ => column_type,
+        };
+        column.nullable = true;
+        column.fields.clear();
+    }
+    Ok(())
+}
+
+/// Write a value into a DECIMAL column, or NULL if it can't be represented
+///
+/// Returns whether the value fit.
+pub fn populate_decimal(
+    col_idx: usize,
+    row_idx: usize,
+    value: &Value,
+    width: u8,
+    scale: u8,
+    output: &mut DataChunkHandle,
+) -> bool {
+    let mut vector = output.flat_vector(col_idx);
+    let unscaled = decimal_value(value, width, scale);
+    match unscaled {
+        Some(unscaled) => write_decimal(&mut vector, row_idx, width, unscaled),
+        None => vector.set_null(row_idx),
+    }
+    unscaled.is_some()
+}
+
+/// Write a value into a decimal text column: its decimal text if it fits,
+/// its usual VARCHAR rendering otherwise
+///
+/// Returns whether the value fit.
+pub fn populate_decimal_text(
+    vector: &FlatVector,
+    row_idx: usize,
+    value: &Value,
+    (width, scale): (u8, u8),
+    float_precision: Option<usize>,
+) -> bool {
+    match decimal_value(value, width, scale) {
+        Some(unscaled) => {
+            insert_str(vector, row_idx, &decimal_text(unscaled, scale));
+            true
+        }
+        None => {
+            let text = value_to_duckdb_string(value, float_precision);
+            insert_str(vector, row_idx, &text.replace('\0', ""));
+            false
+        }
+    }
+}
+
+/// Render an unscaled value with `scale` fractional digits
+fn decimal_text(unscaled: i128, scale: u8) -> String {
+    let digits = format!("{:0>width$}", unscaled.unsigned_abs(), width = usize::from(scale) + 1);
+    let (int_part, frac_part) = digits.split_at(digits.len() - usize::from(scale));
+    let sign = if unscaled < 0 { "-" } else { "" };
+    match frac_part {
+        "" => format!("{}{}", sign, int_part),
+        _ => format!("{}{}.{}", sign, int_part, frac_part),
+    }
+}
+
+/// The value as an unscaled integer at `scale`, if it fits `width` digits
+fn decimal_value(value: &Value, width: u8, scale: u8) -> Option<i128> {
+    let unscaled = match value {
+        Value::Int(i) => i128::from(*i).checked_mul(10i128.pow(scale.into()))?,
+        Value::String(s) => parse_decimal(s, scale)?,
+        Value::Float(f) if f.is_finite() => parse_decimal(&f.to_string(), scale)?,
+        _ => return None,
+    };
+
+    (unscaled.unsigned_abs() < 10u128.pow(width.into())).then_some(unscaled)
+}
+
+/// Parse a plain decimal string to an unscaled integer, rounding extra
+/// fractional digits half away from zero
+fn parse_decimal(text: &str, scale: u8) -> Option<i128> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    let no_digits = int_part.is_empty() && frac_part.is_empty();
+    if no_digits || !all_digits(int_part) || !all_digits(frac_part) {
+        return None;
+    }
+
+    let scale = usize::from(scale);
+    let mut unscaled: i128 = 0;
+    let kept = frac_part.bytes().chain(std::iter::repeat(b'0')).take(scale);
+    for b in int_part.bytes().chain(kept) {
+        unscaled = unscaled.checked_mul(10)?.checked_add(i128::from(b - b'0'))?;
+    }
+    if frac_part.as_bytes().get(scale).is_some_and(|&b| b >= b'5') {
+        unscaled = unscaled.checked_add(1)?;
+    }
+
+    Some(if negative { -unscaled } else { unscaled })
+}
+
+/// Store an unscaled value in the physical type DuckDB uses for the width
+fn write_decimal(vector: &mut FlatVector, row_idx: usize, width: u8, unscaled: i128) {
+    // The value already fits `width` digits, so the narrowing casts are exact
+    match width {
+        1..=4 => vector.as_mut_slice::<i16>()[row_idx] = unscaled as i16,
+        5..=9 => vector.as_mut_slice::<i32>()[row_idx] = unscaled as i32,
+        10..=18 => vector.as_mut_slice::<i64>()[row_idx] = unscaled as i64,
+        // HUGEINT: low 64 bits, then the signed high 64 bits
+        _ => {
+            let halves = [unscaled as u64, (unscaled >> 64) as u64];
+            vector.as_mut_slice::<[u64; 2]>()[row_idx] = halves;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal_type() {
+        let decimal = |width, scale| Some(ColumnType::Decimal { width, scale });
+        assert_eq!(parse_decimal_type("18,2"), decimal(18, 2));
+        assert_eq!(parse_decimal_type(" DECIMAL(38, 10) "), decimal(38, 10));
+        assert_eq!(parse_decimal_type("decimal(4,4)"), decimal(4, 4));
+        assert_eq!(parse_decimal_type("0,0"), None);
+        assert_eq!(parse_decimal_type("39,2"), None);
+        assert_eq!(parse_decimal_type("4,5"), None);
+        assert_eq!(parse_decimal_type("18"), None);
+        assert_eq!(parse_decimal_type("DECIMAL(18,2"), None);
+    }
+
+    #[test]
+    fn test_decimal_value() {
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(decimal_value(&string("12.34"), 18, 2), Some(1234));
+        assert_eq!(decimal_value(&string("-0.5"), 18, 2), Some(-50));
+        assert_eq!(decimal_value(&string(".125"), 18, 2), Some(13));
+        assert_eq!(decimal_value(&string("-0.125"), 18, 2), Some(-13));
+        assert_eq!(decimal_value(&string("+7."), 18, 0), Some(7));
+        assert_eq!(decimal_value(&Value::Int(-42), 18, 2), Some(-4200));
+        assert_eq!(decimal_value(&Value::Float(0.1), 18, 3), Some(100));
+
+        // Too many integer digits for the width
+        assert_eq!(decimal_value(&string("100.00"), 4, 2), None);
+        assert_eq!(decimal_value(&string("99.995"), 4, 2), None);
+        assert_eq!(decimal_value(&Value::Int(i64::MAX), 38, 30), None);
+
+        // Not a plain decimal
+        for text in ["", ".", "1e3", "12.3.4", "abc", "--1"] {
+            assert_eq!(decimal_value(&string(text), 18, 2), None, "{:?}", text);
+        }
+        assert_eq!(decimal_value(&Value::Bool(true), 18, 2), None);
+        assert_eq!(decimal_value(&Value::Float(f64::NAN), 18, 2), None);
+    }
+
+    #[test]
+    fn test_coercion_policy() {
+        assert_eq!(CoercionPolicy::parse("error").unwrap(), CoercionPolicy::Error);
+        assert_eq!(CoercionPolicy::parse("stringify").unwrap(), CoercionPolicy::Stringify);
+        assert!(CoercionPolicy::parse("NULL").is_err());
+
+        assert_eq!(decimal_text(1250, 2), "12.50");
+        assert_eq!(decimal_text(-5, 2), "-0.05");
+        assert_eq!(decimal_text(0, 3), "0.000");
+        assert_eq!(decimal_text(-42, 0), "-42");
+
+        // Stringify retypes the column as VARCHAR but keeps the declared precision
+        let mut columns = vec![DiscoveredColumn {
+            name: "prop_amount".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: Some("amount".to_string()),
+        }];
+        let decimal_props = HashMap::from([(
+            "prop_amount".to_string(),
+            ColumnType::Decimal { width: 18, scale: 2 },
+        )]);
+        apply_decimal_props(&mut columns, &decimal_props, CoercionPolicy::Stringify).unwrap();
+        assert_eq!(columns[0].column_type, ColumnType::DecimalText { width: 18, scale: 2 });
+    }
+}
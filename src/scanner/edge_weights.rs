@@ -0,0 +1,332 @@
+//! Edge weight aggregation for ManifoldDB
+//!
+//! Implements a table function that collapses a multigraph into one row per
+//! (source, target) pair, summing a numeric edge property across all edges
+//! between the pair.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_edge_weights('/path/to/database.redb', 'cost');
+//! SELECT * FROM manifold_edge_weights('/path/to/database.redb', 'cost', missing_weight='one');
+//! ```
+//!
+//! ## Missing Weights
+//! An edge whose weight property is absent or not numeric is handled by
+//! `missing_weight`:
+//! - `'skip'` (default): the edge contributes nothing; a pair whose edges are
+//!   all skipped is omitted
+//! - `'one'`: the edge counts as weight 1, so unweighted edges count as hops
+//!
+//! Edges are streamed once through a cursor and accumulated in a map keyed by
+//! (source, target); rows are returned sorted by source, then target.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::Edge;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::engine::ManifoldEngine;
+use super::values::value_to_f64;
+use super::{chunk_batch_size, get_cached_engine, string_param};
+
+/// How edges without a numeric weight are aggregated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingWeight {
+    /// Leave the edge out of the sum
+    Skip,
+    /// Count the edge as weight 1
+    One,
+}
+
+impl MissingWeight {
+    /// Parse the `missing_weight` named parameter
+    pub fn parse(name: &str) -> Result<Self, ManifoldScannerError> {
+        match name.to_ascii_lowercase().as_str() {
+            "skip" => Ok(MissingWeight::Skip),
+            "one" => Ok(MissingWeight::One),
+            other => Err(ManifoldScannerError::InvalidParameter(format!(
+                "unknown missing_weight '{}', expected 'skip' or 'one'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Summed weight of all edges from `source` to `target`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairWeight {
+    pub source: u64,
+    pub target: u64,
+    pub weight: f64,
+}
+
+/// Accumulates edge weights per (source, target) pair
+pub struct WeightAggregator<'a> {
+    weight_prop: &'a str,
+    missing: MissingWeight,
+    sums: BTreeMap<(u64, u64), f64>,
+}
+
+impl<'a> WeightAggregator<'a> {
+    pub fn new(weight_prop: &'a str, missing: MissingWeight) -> Self {
+        Self {
+            weight_prop,
+            missing,
+            sums: BTreeMap::new(),
+        }
+    }
+
+    /// Add one edge's weight to its pair
+    pub fn observe(&mut self, edge: &Edge) {
+        let weight = match edge.properties.get(self.weight_prop).and_then(value_to_f64) {
+            Some(weight) => weight,
+            None => match self.missing {
+                MissingWeight::Skip => return,
+                MissingWeight::One => 1.0,
+            },
+        };
+
+        *self
+            .sums
+            .entry((edge.source.as_u64(), edge.target.as_u64()))
+            .or_insert(0.0) += weight;
+    }
+
+    /// Pair weights sorted by source, then target
+    pub fn into_sorted_vec(self) -> Vec<PairWeight> {
+        self.sums
+            .into_iter()
+            .map(|((source, target), weight)| PairWeight {
+                source,
+                target,
+                weight,
+            })
+            .collect()
+    }
+}
+
+/// Bind data for edge weight aggregation
+#[repr(C)]
+pub struct ManifoldEdgeWeightsBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Edge property holding the weight
+    pub weight_prop: String,
+    /// Handling of edges without a numeric weight
+    pub missing: MissingWeight,
+}
+
+/// Init data for edge weight aggregation - holds the aggregated pairs
+#[repr(C)]
+pub struct ManifoldEdgeWeightsInitData {
+    /// Pair weights sorted by source, then target
+    pub pairs: Vec<PairWeight>,
+    /// Number of pairs already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Edge weight aggregation VTab implementation
+pub struct ManifoldEdgeWeightsVTab;
+
+impl VTab for ManifoldEdgeWeightsVTab {
+    type InitData = ManifoldEdgeWeightsInitData;
+    type BindData = ManifoldEdgeWeightsBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let weight_prop = string_param(bind, 1, "weight_prop")?;
+
+        let missing = match bind.get_named_parameter("missing_weight") {
+            Some(v) => MissingWeight::parse(&v.to_string())?,
+            None => MissingWeight::Skip,
+        };
+
+        bind.add_result_column("source", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("target", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("weight", LogicalTypeHandle::from(LogicalTypeId::Double));
+
+        Ok(ManifoldEdgeWeightsBindData {
+            db_path,
+            weight_prop,
+            missing,
+        })
+    }
+
+    /// Init phase: aggregate all edges
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldEdgeWeightsBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+
+        let pairs = aggregate_edge_weights(&engine, &bind_data.weight_prop, bind_data.missing)?;
+
+        Ok(ManifoldEdgeWeightsInitData {
+            pairs,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the aggregated pairs
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_edge_weights".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // weight_prop
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![(
+            "missing_weight".to_string(),
+            LogicalTypeHandle::from(LogicalTypeId::Varchar),
+        )])
+    }
+}
+
+impl ManifoldEdgeWeightsVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.pairs.len());
+        let batch = &init_data.pairs[start..end];
+
+        let mut weights = output.flat_vector(2);
+        for (row_idx, pair) in batch.iter().enumerate() {
+            weights.as_mut_slice::<f64>()[row_idx] = pair.weight;
+        }
+
+        let sources = output.flat_vector(0);
+        let targets = output.flat_vector(1);
+        for (row_idx, pair) in batch.iter().enumerate() {
+            sources.insert(row_idx, CString::new(pair.source.to_string())?);
+            targets.insert(row_idx, CString::new(pair.target.to_string())?);
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Stream every edge once, summing weights per (source, target) pair
+fn aggregate_edge_weights(
+    engine: &ManifoldEngine,
+    weight_prop: &str,
+    missing: MissingWeight,
+) -> Result<Vec<PairWeight>, Box<dyn Error>> {
+    let tx = engine.begin_read()?;
+    let mut aggregator = WeightAggregator::new(weight_prop, missing);
+
+    if let Ok(mut cursor) = tx.cursor("edges") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            if let Ok(edge) = Edge::decode(&value) {
+                aggregator.observe(&edge);
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    Ok(aggregator.into_sorted_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId, Value};
+    use std::collections::HashMap;
+
+    fn edge(id: u64, source: u64, target: u64, cost: Option<Value>) -> Edge {
+        let mut properties = HashMap::new();
+        if let Some(cost) = cost {
+            properties.insert("cost".to_string(), cost);
+        }
+        Edge {
+            id: EdgeId::from(id),
+            source: EntityId::from(source),
+            target: EntityId::from(target),
+            edge_type: EdgeType::new("ROAD"),
+            properties,
+        }
+    }
+
+    fn aggregate(missing: MissingWeight) -> Vec<PairWeight> {
+        let edges = [
+            edge(1, 2, 3, Some(Value::Float(1.5))),
+            edge(2, 1, 2, Some(Value::Int(4))),
+            edge(3, 2, 3, Some(Value::Int(2))),
+            edge(4, 2, 3, None),
+            edge(5, 3, 1, Some(Value::String("n/a".to_string()))),
+        ];
+
+        let mut aggregator = WeightAggregator::new("cost", missing);
+        for edge in &edges {
+            aggregator.observe(edge);
+        }
+        aggregator.into_sorted_vec()
+    }
+
+    #[test]
+    fn test_aggregate_skip_missing() {
+        let pairs = aggregate(MissingWeight::Skip);
+        assert_eq!(
+            pairs,
+            vec![
+                PairWeight { source: 1, target: 2, weight: 4.0 },
+                PairWeight { source: 2, target: 3, weight: 3.5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_missing_as_one() {
+        let pairs = aggregate(MissingWeight::One);
+        assert_eq!(
+            pairs,
+            vec![
+                PairWeight { source: 1, target: 2, weight: 4.0 },
+                PairWeight { source: 2, target: 3, weight: 4.5 },
+                PairWeight { source: 3, target: 1, weight: 1.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_weight() {
+        assert_eq!(MissingWeight::parse("SKIP").unwrap(), MissingWeight::Skip);
+        assert_eq!(MissingWeight::parse("one").unwrap(), MissingWeight::One);
+        assert!(MissingWeight::parse("zero").is_err());
+    }
+}
@@ -7,46 +7,355 @@
 //! SELECT * FROM manifold_edges('/path/to/database.redb');
 //! SELECT source, target, edge_type FROM manifold_edges('/path/to/database.redb')
 //!     WHERE edge_type = 'RESPONDS_TO';
+//! SELECT source, target, weight FROM manifold_edges('/path/to/database.redb', weight_prop='cost');
+//! SELECT source, target FROM manifold_edges_of_type('/path/to/database.redb', 'RESPONDS_TO');
 //! ```
 //!
+//! ## Edges of One Type
+//! `manifold_edges_of_type(db, edge_type)` returns the same columns and takes
+//! the same named parameters as `manifold_edges`, for edges of one type only.
+//! If the database has an `edge_type_index` entry for the type, the scan walks
+//! that index and looks up each edge by id; otherwise (no index, or nothing
+//! indexed under the type) it falls back to scanning all edges and filtering.
+//!
+//! With `case_insensitive=true` the type matches whatever its case, so
+//! `'knows'` also returns `KNOWS` and `Knows` edges. Both sides are lowercased
+//! before comparing, which costs a string allocation per edge, and the type
+//! index can't be used: the scan always reads all edges and filters.
+//!
+//! ## Edge Weights
+//! When `weight_prop` is given, a `weight` (DOUBLE) column is added, parsed from
+//! that property. Edges without a numeric value for it get `weight_default`, or
+//! NULL if no default was given.
+//!
+//! ## Triples
+//! `triples=true` returns only `source` BIGINT, `target` BIGINT and `weight`
+//! DOUBLE, the typed edge list graph libraries ingest (and that exports to
+//! Arrow without conversion). The weight is read from `weight_prop` as usual,
+//! but falls back to `weight_default` and then to 1.0, so it is never NULL.
+//! No schema is sampled at bind and no property is rendered; each edge is
+//! still decoded whole, as the record format has no partial decode. Options
+//! that add columns, and `safe_mode`, can't be combined with it.
+//!
+//! ## Reciprocal Edges
+//! With `include_reciprocal=true`, an `is_reciprocal` (BOOLEAN) column is added:
+//! true when an edge of the same type runs from `target` back to `source`.
+//! Self-loops count as their own reverse. This costs an extra full pass over
+//! the edges table at init, and memory for one (source, target, type) entry
+//! per edge for the life of the scan.
+//!
+//! ## Undirected Edges
+//! With `undirected=true`, a `canonical_key` (VARCHAR) column is added, holding
+//! `min(source, target):max(source, target)` with ids compared as numbers, so
+//! `(A, B)` and `(B, A)` share a key. The key leaves out the edge type: group
+//! by `canonical_key, edge_type` to keep types apart. `dedupe_reciprocal=true`
+//! (which needs `undirected`) also drops every edge whose key and type were
+//! already returned by this scan, so each undirected edge appears once, as
+//! the first of its directions in id order. That costs memory for one entry
+//! per returned edge for the life of the scan.
+//!
+//! ## Composite Keys
+//! With `composite_key=true`, a `composite_key` (VARCHAR) column is added,
+//! holding `source:edge_type:target` (ids in decimal, the type as stored), so
+//! the key depends only on the relationship, not the edge id. Ids never
+//! contain `:`, so the first and last `:` split the key back into its parts
+//! even when the type contains one. Parallel edges of the same type share a
+//! key. In SQL it is `source || ':' || edge_type || ':' || target`.
+//!
+//! ## Row Numbers
+//! `with_row_number=true` adds a `row_number` BIGINT counting rows in output
+//! order from 0 across batches, like `rowid` on `manifold_entities`. The scan
+//! doesn't declare parallelism, so DuckDB runs it on one thread and numbers
+//! are gap-free; rows dropped by `dedupe_reciprocal` are not counted. It is
+//! not stored with the edge and changes when the data or filters do.
+//!
+//! ## Stable Order
+//! Rows come back in edge key (id) order: the scan doesn't declare
+//! parallelism, so DuckDB reads it on one thread, and `manifold_edges_of_type`
+//! walks its index in id order too. `stable_order=true` turns that into a
+//! checked guarantee for output that must compare equal across runs: each
+//! row's key is compared with the previous row's, and the scan fails rather
+//! than return an edge out of order. The check costs one key comparison per
+//! row and, as the scan is single-threaded anyway, gives up no parallelism.
+//! It covers the scan's own output; a query that joins or aggregates still
+//! needs `ORDER BY` to fix its final order.
+//!
+//! ## Endpoint Properties
+//! `source_props=['name']` adds a `source_prop_name` (VARCHAR) column holding
+//! the `name` property of the edge's source entity, and `target_props` does
+//! the same for the target, rendered like `prop_*` columns. The endpoints of
+//! each batch are looked up once each in the scan's snapshot, so an entity on
+//! many edges of a batch is decoded once. The column is NULL when the
+//! endpoint doesn't exist (or can't be decoded) or lacks the property.
+//!
+//! ## Null Token
+//! `null_string` works as for `manifold_entities`, and also covers the
+//! `source_prop_*` and `target_prop_*` columns.
+//!
+//! ## Safe Mode
+//! With `safe_mode=true`, records that fail to decode are returned as
+//! diagnostic rows (see `manifold_entities`): `id` from the storage key, the
+//! decode error in `_error`, NULL everywhere else.
+//!
+//! ## Storage Keys
+//! `expose_keys=true` adds a `_key` BLOB with each row's key in the `edges`
+//! table (see `manifold_entities`). `manifold_edges_of_type` reports the same
+//! `edges` key when it walks the type index, though it continues from the
+//! index key.
+//!
+//! ## Mixed Tables
+//! `table => 'graph', record_filter => 'edge'` reads edges from a table that
+//! holds nodes and edges together (see `manifold_entities`). The type index
+//! doesn't cover it, so `manifold_edges_of_type` filters a full scan, and
+//! `source_props` and `target_props`, which look endpoints up by id, are
+//! refused.
+//!
 //! ## Scanning Strategy
 //!
 //! This scanner uses cursor-based streaming to efficiently scan edges:
 //! - The storage engine is cached globally (opened once per path, reused)
 //! - No upfront ID collection - edges are scanned directly via cursor
 //! - Each batch continues from the last key seen, avoiding redundant work
+//! - One read transaction is held for the whole scan, so batches share a
+//!   snapshot; it is released as soon as the scan completes or is abandoned
 
 use duckdb::{
     core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
     vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
     ffi::CString,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
     },
 };
 
+use manifoldb_core::encoding::keys::encode_edge_type_index_prefix;
 use manifoldb_core::encoding::Decoder;
-use manifoldb_core::types::{Edge, Value};
-use manifoldb_storage::backends::RedbEngine;
+use manifoldb_core::types::{Edge, EdgeType, Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
 use manifoldb_storage::{Cursor, StorageEngine, Transaction};
 
-use crate::schema::{DiscoveredColumn, EdgeSchemaDiscovery};
-use super::{get_cached_engine, BATCH_SIZE, SCHEMA_SAMPLE_SIZE};
+use crate::error::ManifoldScannerError;
+use crate::schema::{
+    ColumnType, DiscoveredColumn, EdgeSchemaDiscovery, DEFAULT_PROP_PREFIX,
+};
+use super::{
+    add_json_debug_columns, batch_full, check_unique_columns, chunk_batch_size, first_after,
+    insert_str, key_to_id_string, named_bool, named_float_precision, named_schema_sample,
+    named_string_list, null_varchar, populate_error_row, populate_properties, populate_row_numbers,
+    property_index, read_scan_batch, sample_records, string_param, DiscoveredSchema,
+    PropertyFormat, QueryEngine, ScanBatch, ScanRecord, ScanSnapshot,
+};
+use super::decimal::{
+    apply_decimal_props, named_coercion_policy, named_decimal_props, CoercionPolicy,
+};
+use super::engine::ManifoldEngine;
+use super::pushdown::{
+    PushdownEntry, PushdownReport, APPLIED_DUCKDB, APPLIED_INDEX, APPLIED_SCAN,
+};
+use super::mixed_table::{record_bytes, scan_table, MixedTable, RecordKind};
+use super::stats::ScanStats;
+use super::values::{value_to_duckdb_string, value_to_f64};
+
+/// Named parameters accepted by the edge scanner
+#[derive(Debug, Clone, Default)]
+pub struct EdgeScanOptions {
+    /// Property to expose as the typed `weight` column
+    pub weight_prop: Option<String>,
+    /// Weight used when the property is absent or non-numeric
+    pub weight_default: Option<f64>,
+    /// Emit diagnostic rows for undecodable records
+    pub safe_mode: bool,
+    /// Add the `is_reciprocal` column
+    pub include_reciprocal: bool,
+    /// Fixed decimals for floats rendered as VARCHAR (None = round-trip)
+    pub float_precision: Option<usize>,
+    /// Add a `prop_x__json` column after each `prop_x`
+    pub debug_columns: bool,
+    /// Records sampled from each of the start, middle and end at bind
+    pub schema_sample: usize,
+    /// Add a `_key` column with each row's raw storage key
+    pub expose_keys: bool,
+    /// Add the `canonical_key` column
+    pub undirected: bool,
+    /// Add the `composite_key` column
+    pub composite_key: bool,
+    /// Add a `row_number` column numbering rows in output order
+    pub with_row_number: bool,
+    /// Return each (canonical key, type) once
+    pub dedupe_reciprocal: bool,
+    /// Source entity properties to add as `source_prop_*` columns
+    pub source_props: Vec<String>,
+    /// Target entity properties to add as `target_prop_*` columns
+    pub target_props: Vec<String>,
+    /// Property columns exposed as DECIMAL, by column name
+    pub decimal_props: HashMap<String, ColumnType>,
+    /// Open the database for this scan only, bypassing the engine cache
+    pub no_cache: bool,
+    /// Prefix of property column names (None = `prop_`)
+    pub prop_prefix: Option<String>,
+    /// Token for null and absent VARCHAR property values (None = SQL NULL)
+    pub null_string: Option<String>,
+    /// What to do with values that don't fit a `decimal_props` column
+    pub coercion_policy: CoercionPolicy,
+    /// Match the `manifold_edges_of_type` type ignoring case
+    pub case_insensitive: bool,
+    /// Fail rather than return rows out of key order
+    pub stable_order: bool,
+    /// Return only typed `source`, `target` and `weight` columns
+    pub triples: bool,
+    /// Table holding tagged nodes and edges, read instead of `edges`
+    pub mixed_table: Option<MixedTable>,
+}
+
+impl EdgeScanOptions {
+    /// Prefix of property column names
+    fn prop_prefix(&self) -> &str {
+        self.prop_prefix.as_deref().unwrap_or(DEFAULT_PROP_PREFIX)
+    }
+
+    /// Whether any endpoint properties were requested
+    fn wants_endpoints(&self) -> bool {
+        !self.source_props.is_empty() || !self.target_props.is_empty()
+    }
+
+    /// The table the scan reads
+    fn table(&self) -> &str {
+        scan_table(self.mixed_table.as_ref(), "edges")
+    }
+}
+
+impl EdgeScanOptions {
+    /// Read the named parameters from the bind info
+    fn from_bind(bind: &BindInfo) -> Result<Self, ManifoldScannerError> {
+        let weight_prop = bind.get_named_parameter("weight_prop").map(|v| v.to_string());
+
+        let weight_default = match bind.get_named_parameter("weight_default") {
+            Some(v) => {
+                let raw = v.to_string();
+                let parsed = raw.parse::<f64>().map_err(|_| {
+                    ManifoldScannerError::InvalidParameter(format!(
+                        "weight_default must be a number, got '{}'",
+                        raw
+                    ))
+                })?;
+                Some(parsed)
+            }
+            None => None,
+        };
+
+        let undirected = named_bool(bind, "undirected")?.unwrap_or(false);
+        let dedupe_reciprocal = named_bool(bind, "dedupe_reciprocal")?.unwrap_or(false);
+        if dedupe_reciprocal && !undirected {
+            return Err(ManifoldScannerError::InvalidParameter(
+                "dedupe_reciprocal requires undirected=true".to_string(),
+            ));
+        }
+
+        let options = Self {
+            weight_prop,
+            weight_default,
+            safe_mode: named_bool(bind, "safe_mode")?.unwrap_or(false),
+            include_reciprocal: named_bool(bind, "include_reciprocal")?.unwrap_or(false),
+            float_precision: named_float_precision(bind)?,
+            debug_columns: named_bool(bind, "debug_columns")?.unwrap_or(false),
+            schema_sample: named_schema_sample(bind)?,
+            expose_keys: named_bool(bind, "expose_keys")?.unwrap_or(false),
+            undirected,
+            composite_key: named_bool(bind, "composite_key")?.unwrap_or(false),
+            with_row_number: named_bool(bind, "with_row_number")?.unwrap_or(false),
+            dedupe_reciprocal,
+            source_props: named_endpoint_props(bind, "source_props")?,
+            target_props: named_endpoint_props(bind, "target_props")?,
+            decimal_props: named_decimal_props(bind, "decimal_props")?,
+            no_cache: named_bool(bind, "no_cache")?.unwrap_or(false),
+            prop_prefix: bind.get_named_parameter("prop_prefix").map(|v| v.to_string()),
+            null_string: bind.get_named_parameter("null_string").map(|v| v.to_string()),
+            coercion_policy: named_coercion_policy(bind)?,
+            case_insensitive: named_bool(bind, "case_insensitive")?.unwrap_or(false),
+            stable_order: named_bool(bind, "stable_order")?.unwrap_or(false),
+            triples: named_bool(bind, "triples")?.unwrap_or(false),
+            mixed_table: MixedTable::from_bind(bind, RecordKind::Edge)?,
+        };
+        options.check_triples()?;
+        if let Some(mixed) = &options.mixed_table {
+            // Endpoints are looked up by id in the nodes table
+            if !options.source_props.is_empty() {
+                return Err(mixed.refuse("source_props"));
+            }
+            if !options.target_props.is_empty() {
+                return Err(mixed.refuse("target_props"));
+            }
+        }
+        Ok(options)
+    }
+
+    /// Refuse options that add columns (or error rows) alongside `triples`
+    fn check_triples(&self) -> Result<(), ManifoldScannerError> {
+        if !self.triples {
+            return Ok(());
+        }
+        let conflicts = [
+            ("safe_mode", self.safe_mode),
+            ("include_reciprocal", self.include_reciprocal),
+            ("debug_columns", self.debug_columns),
+            ("expose_keys", self.expose_keys),
+            ("undirected", self.undirected),
+            ("composite_key", self.composite_key),
+            ("with_row_number", self.with_row_number),
+            ("source_props", !self.source_props.is_empty()),
+            ("target_props", !self.target_props.is_empty()),
+            ("decimal_props", !self.decimal_props.is_empty()),
+        ];
+        match conflicts.iter().find(|(_, set)| *set) {
+            Some((name, _)) => Err(ManifoldScannerError::InvalidParameter(format!(
+                "triples returns only source, target and weight, so it can't be combined with {}",
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Read a list of endpoint property names, dropping repeats
+fn named_endpoint_props(bind: &BindInfo, name: &str) -> Result<Vec<String>, ManifoldScannerError> {
+    let mut props = named_string_list(bind, name)?.unwrap_or_default();
+    let mut seen = HashSet::new();
+    props.retain(|prop| seen.insert(prop.clone()));
+    Ok(props)
+}
 
 /// Bind data for edge scanner - holds schema and database path
 #[repr(C)]
 pub struct ManifoldEdgesBindData {
     /// Path to the ManifoldDB database
     pub db_path: String,
+    /// Engine the scan reads through
+    pub engine: QueryEngine,
+    /// Options from named parameters
+    pub options: EdgeScanOptions,
+    /// Only scan edges of this type (`manifold_edges_of_type`)
+    pub edge_type: Option<String>,
     /// Discovered schema columns
     pub columns: Vec<DiscoveredColumn>,
     /// Map from column name to index for fast lookup
     pub column_index: HashMap<String, usize>,
+    /// Schema sample records that failed to decode, for the scan's stats
+    pub sample_failures: usize,
+}
+
+impl ManifoldEdgesBindData {
+    /// Name of the scanning function, for `manifold_last_scan_stats`
+    fn function(&self) -> &'static str {
+        match self.edge_type {
+            Some(_) => "manifold_edges_of_type",
+            None => "manifold_edges",
+        }
+    }
 }
 
 /// Init data for edge scanner - holds scan state
@@ -55,8 +364,249 @@ pub struct ManifoldEdgesInitData {
     /// Flag indicating scan is complete
     pub done: AtomicBool,
     /// Last key seen - used as continuation marker for cursor-based scanning
-    /// None means we haven't started yet, Some(key) means continue after this key
+    /// None means we haven't started yet, Some(key) means continue after this key.
+    /// Locked for the whole of `next_batch`, so concurrent func calls never
+    /// read the same marker
     pub last_key: Mutex<Option<Vec<u8>>>,
+    /// Read transaction held across batches; None before the first batch and
+    /// after the scan completes
+    pub snapshot: Mutex<Option<ScanSnapshot>>,
+    /// Every edge's key, when `include_reciprocal` is set
+    pub reciprocal: Option<EdgeKeySet>,
+    /// Type restriction, for `manifold_edges_of_type`
+    pub type_scan: Option<TypeScan>,
+    /// Table holding tagged nodes and edges, read instead of `edges`
+    pub mixed_table: Option<MixedTable>,
+    /// (low id, high id, type) of every edge returned, when `dedupe_reciprocal`
+    /// is set
+    pub returned_pairs: Mutex<HashSet<(u64, u64, String)>>,
+    /// Rows emitted so far - the first `row_number` of the next batch
+    pub rows_emitted: AtomicU64,
+    /// Key of the last row emitted, checked when `stable_order` is set
+    pub last_emitted: Mutex<Option<Vec<u8>>>,
+    /// Work counters for `manifold_last_scan_stats`
+    pub stats: ScanStats,
+}
+
+/// How a single-type scan finds its edges
+pub struct TypeScan {
+    /// The type, lowercased when `case_insensitive`
+    pub edge_type: EdgeType,
+    /// Walk the `edge_type_index` table rather than filtering a full scan
+    pub indexed: bool,
+    /// Compare lowercased types
+    pub case_insensitive: bool,
+}
+
+impl TypeScan {
+    /// Whether an edge is of the scanned type
+    fn matches(&self, edge: &Edge) -> bool {
+        if self.case_insensitive {
+            edge.edge_type.as_str().to_lowercase() == self.edge_type.as_str()
+        } else {
+            edge.edge_type == self.edge_type
+        }
+    }
+}
+
+impl ManifoldEdgesInitData {
+    pub(crate) fn new() -> Self {
+        Self {
+            done: AtomicBool::new(false),
+            last_key: Mutex::new(None),
+            snapshot: Mutex::new(None),
+            reciprocal: None,
+            type_scan: None,
+            mixed_table: None,
+            returned_pairs: Mutex::new(HashSet::new()),
+            rows_emitted: AtomicU64::new(0),
+            last_emitted: Mutex::new(None),
+            stats: ScanStats::default(),
+        }
+    }
+
+    /// Set up scan state for a query and publish its pushdown
+    fn start(bind_data: &ManifoldEdgesBindData) -> Result<Self, Box<dyn Error>> {
+        let init_data = Self::for_bind(bind_data)?;
+        init_data.pushdown_report(bind_data).publish();
+        Ok(init_data)
+    }
+
+    /// The filters the scan applies and the columns it fills, for
+    /// `manifold_last_pushdown`; edge scans don't take projections, so every
+    /// column is filled and DuckDB drops the unused ones
+    fn pushdown_report(&self, bind_data: &ManifoldEdgesBindData) -> PushdownReport {
+        let mut entries = Vec::new();
+        if let Some(mixed) = &self.mixed_table {
+            let kind = Some(mixed.kind.name().to_string());
+            entries.push(PushdownEntry::filter("record tag", "=", kind, APPLIED_SCAN));
+        }
+        if let Some(type_scan) = &self.type_scan {
+            let operator = if type_scan.case_insensitive { "= (any case)" } else { "=" };
+            let applied = if type_scan.indexed { APPLIED_INDEX } else { APPLIED_SCAN };
+            let edge_type = Some(type_scan.edge_type.as_str().to_string());
+            entries.push(PushdownEntry::filter("edge_type", operator, edge_type, applied));
+        }
+        if bind_data.options.dedupe_reciprocal {
+            let column = "source, target, edge_type";
+            entries.push(PushdownEntry::filter(column, "dedupe reciprocal", None, APPLIED_SCAN));
+        }
+        entries.extend(
+            bind_data
+                .columns
+                .iter()
+                .map(|column| PushdownEntry::projection(&column.name, APPLIED_DUCKDB)),
+        );
+
+        PushdownReport {
+            function: bind_data.function().to_string(),
+            db_path: bind_data.db_path.clone(),
+            entries,
+        }
+    }
+
+    /// Set up scan state, taking the scan's snapshot now if the reciprocal set
+    /// or the type index check needs it so they agree with the scanned rows
+    fn for_bind(bind_data: &ManifoldEdgesBindData) -> Result<Self, Box<dyn Error>> {
+        let mut init_data = Self::new();
+        init_data.stats.sample_failures(bind_data.sample_failures);
+        init_data.mixed_table = bind_data.options.mixed_table.clone();
+        if !bind_data.options.include_reciprocal && bind_data.edge_type.is_none() {
+            return Ok(init_data);
+        }
+
+        let engine = bind_data.engine.get()?;
+        let snapshot = ScanSnapshot::begin(&engine)?;
+
+        // Reciprocal lookups need every edge up front
+        if bind_data.options.include_reciprocal {
+            let mixed = init_data.mixed_table.as_ref();
+            init_data.reciprocal = Some(EdgeKeySet::build(snapshot.tx(), mixed)?);
+        }
+
+        if let Some(edge_type) = &bind_data.edge_type {
+            // The index is keyed by the exact type, so it can't match other
+            // cases, and covers only the edges table
+            let case_insensitive = bind_data.options.case_insensitive;
+            let type_scan = if case_insensitive {
+                let edge_type = EdgeType::new(edge_type.to_lowercase());
+                TypeScan { edge_type, indexed: false, case_insensitive }
+            } else {
+                let edge_type = EdgeType::new(edge_type.as_str());
+                let indexed = init_data.mixed_table.is_none()
+                    && has_type_index_entries(snapshot.tx(), &edge_type)?;
+                TypeScan { edge_type, indexed, case_insensitive }
+            };
+            init_data.type_scan = Some(type_scan);
+        }
+
+        *init_data.snapshot.get_mut().unwrap() = Some(snapshot);
+        Ok(init_data)
+    }
+
+    /// Read the next batch of the scan, releasing the snapshot once it is exhausted
+    pub(crate) fn next_batch(
+        &self,
+        db_path: &str,
+        engine: &ManifoldEngine,
+        safe_mode: bool,
+        batch_size: usize,
+    ) -> Result<Vec<ScanRecord<Edge>>, Box<dyn Error>> {
+        if self.done.load(Ordering::Relaxed) {
+            return Ok(Vec::new());
+        }
+
+        // Hold the continuation marker for the whole batch, so concurrent
+        // calls take turns, each continuing after the batch before it
+        let mut last_key = self.last_key.lock().unwrap();
+        if self.done.load(Ordering::Relaxed) {
+            // Another call finished the scan while this one waited
+            return Ok(Vec::new());
+        }
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let start_after_key = last_key.clone();
+
+        // Scan the next batch using cursor-based streaming
+        let batch = read_scan_batch(&self.done, db_path, start_after_key.as_deref(), || {
+            let tx = match &mut *snapshot {
+                Some(snapshot) => snapshot.tx(),
+                None => snapshot.insert(ScanSnapshot::begin(engine)?).tx(),
+            };
+            let after_key = start_after_key.as_deref();
+            let (size, stats, mixed) = (batch_size, &self.stats, self.mixed_table.as_ref());
+            match &self.type_scan {
+                None => scan_edge_batch(tx, mixed, after_key, size, safe_mode, stats),
+                Some(TypeScan { edge_type, indexed: true, .. }) => {
+                    scan_type_index_batch(tx, edge_type, after_key, size, safe_mode, stats)
+                }
+                Some(type_scan) => {
+                    scan_filtered_batch(tx, mixed, type_scan, after_key, size, safe_mode, stats)
+                }
+            }
+        });
+        let (edges, next_key) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                // The scan is over; don't keep the snapshot pinned
+                *snapshot = None;
+                return Err(e.into());
+            }
+        };
+
+        if edges.is_empty() {
+            // No more edges - we're done, stop pinning the snapshot
+            self.done.store(true, Ordering::Relaxed);
+            *snapshot = None;
+            return Ok(edges);
+        }
+
+        // Update the continuation marker for the next batch
+        *last_key = next_key;
+
+        Ok(edges)
+    }
+
+    /// Check that a batch continues the scan in strictly increasing key order
+    fn check_order(&self, edges: &[ScanRecord<Edge>]) -> Result<(), ManifoldScannerError> {
+        let mut last = self.last_emitted.lock().unwrap();
+        for record in edges {
+            let key = record.key();
+            if let Some(previous) = last.as_deref().filter(|previous| key <= *previous) {
+                return Err(ManifoldScannerError::EdgeReadError(format!(
+                    "stable_order: edge {} returned after {}",
+                    key_to_id_string(key),
+                    key_to_id_string(previous)
+                )));
+            }
+            *last = Some(key.to_vec());
+        }
+        Ok(())
+    }
+
+    /// Drop edges whose undirected pair and type were already returned
+    fn dedupe_reciprocal(&self, edges: &mut Vec<ScanRecord<Edge>>) {
+        let mut returned = self.returned_pairs.lock().unwrap();
+        let before = edges.len();
+        edges.retain(|record| match record {
+            ScanRecord::Decoded { record: edge, .. } => {
+                let (low, high) = canonical_pair(edge);
+                returned.insert((low, high, edge.edge_type.as_str().to_string()))
+            }
+            ScanRecord::Failed { .. } => true,
+        });
+        self.stats.filtered(before - edges.len());
+    }
+}
+
+/// An edge's endpoints, lower id first
+fn canonical_pair(edge: &Edge) -> (u64, u64) {
+    let (source, target) = (edge.source.as_u64(), edge.target.as_u64());
+    (source.min(target), source.max(target))
+}
+
+/// An edge's `source:edge_type:target` key
+fn composite_key(edge: &Edge) -> String {
+    format!("{}:{}:{}", edge.source.as_u64(), edge.edge_type.as_str(), edge.target.as_u64())
 }
 
 /// Edge scanner VTab implementation
@@ -68,34 +618,13 @@ impl VTab for ManifoldEdgesVTab {
 
     /// Bind phase: discover schema, set up columns
     fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
-        // Get database path from first parameter
-        let db_path = bind.get_parameter(0).to_string();
-
-        // Get cached engine (opens once, reused)
-        let engine = get_cached_engine(&db_path)?;
-
-        // Discover schema using the engine
-        let (columns, column_index) = discover_edge_schema(&engine)?;
-
-        // Register discovered columns with DuckDB
-        for col in &columns {
-            bind.add_result_column(&col.name, col.to_logical_type_handle());
-        }
-
-        Ok(ManifoldEdgesBindData {
-            db_path,
-            columns,
-            column_index,
-        })
+        bind_edge_scan(bind, None)
     }
 
     /// Init phase: prepare for scanning (no data loading - we use cursor streaming)
-    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
-        // No upfront data collection - we'll scan directly via cursor in func()
-        Ok(ManifoldEdgesInitData {
-            done: AtomicBool::new(false),
-            last_key: Mutex::new(None),
-        })
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldEdgesBindData>() };
+        ManifoldEdgesInitData::start(bind_data)
     }
 
     /// Func phase: produce output batches using cursor-based streaming
@@ -120,6 +649,106 @@ impl VTab for ManifoldEdgesVTab {
             LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
         ])
     }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "weight_prop".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "weight_default".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Double),
+            ),
+            (
+                "safe_mode".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "include_reciprocal".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "float_precision".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "debug_columns".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "schema_sample".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "expose_keys".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "undirected".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "dedupe_reciprocal".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "composite_key".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "with_row_number".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "source_props".to_string(),
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ),
+            (
+                "target_props".to_string(),
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ),
+            // A struct or map of column name -> 'width,scale'
+            ("decimal_props".to_string(), LogicalTypeHandle::from(LogicalTypeId::Any)),
+            (
+                "no_cache".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "prop_prefix".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "null_string".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "coercion_policy".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "case_insensitive".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "stable_order".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "triples".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "table".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "record_filter".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+        ])
+    }
 }
 
 impl ManifoldEdgesVTab {
@@ -127,115 +756,405 @@ impl ManifoldEdgesVTab {
         func: &TableFunctionInfo<Self>,
         output: &mut DataChunkHandle,
     ) -> Result<(), Box<dyn Error>> {
-        let init_data = func.get_init_data();
-        let bind_data = func.get_bind_data();
+        scan_edges(func, output)
+    }
+}
 
-        // Check if we're done
-        if init_data.done.load(Ordering::Relaxed) {
-            output.set_len(0);
-            return Ok(());
+/// Edges-of-one-type scanner VTab implementation
+pub struct ManifoldEdgesOfTypeVTab;
+
+impl VTab for ManifoldEdgesOfTypeVTab {
+    type InitData = ManifoldEdgesInitData;
+    type BindData = ManifoldEdgesBindData;
+
+    /// Bind phase: discover schema, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let edge_type = string_param(bind, 1, "edge_type")?;
+        bind_edge_scan(bind, Some(edge_type))
+    }
+
+    /// Init phase: choose between the type index and a filtered scan
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldEdgesBindData>() };
+        ManifoldEdgesInitData::start(bind_data)
+    }
+
+    /// Func phase: produce output batches of the requested type
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| scan_edges(func, output)));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_edges_of_type".into()),
         }
+    }
 
-        // Get the cached engine
-        let engine = get_cached_engine(&bind_data.db_path)?;
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // edge_type
+        ])
+    }
 
-        // Get the continuation key
-        let start_after_key = init_data.last_key.lock().unwrap().clone();
+    /// Define named parameters (same as manifold_edges)
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        ManifoldEdgesVTab::named_parameters()
+    }
+}
 
-        // Scan the next batch using cursor-based streaming
-        let (edges, next_key) = scan_edge_batch(&engine, start_after_key.as_deref(), BATCH_SIZE)?;
+/// Shared bind for the edge scanners: discover schema, set up columns
+fn bind_edge_scan(
+    bind: &BindInfo,
+    edge_type: Option<String>,
+) -> Result<ManifoldEdgesBindData, Box<dyn Error>> {
+    // Get database path from first parameter
+    let db_path = string_param(bind, 0, "db_path")?;
+    let options = EdgeScanOptions::from_bind(bind)?;
 
-        if edges.is_empty() {
-            // No more edges - we're done
-            init_data.done.store(true, Ordering::Relaxed);
-            output.set_len(0);
-            return Ok(());
+    // Get the cached engine (opens once, reused), or a private one with no_cache
+    let engine = QueryEngine::open(&db_path, options.no_cache)?;
+
+    // Discover schema using the engine
+    let ((columns, column_index), sample_failures) = if options.triples {
+        (triple_schema(), 0)
+    } else {
+        discover_edge_schema_with_failures(&*engine.get()?, &options)?
+    };
+
+    // Register discovered columns with DuckDB
+    for col in &columns {
+        bind.add_result_column(&col.name, col.to_logical_type_handle());
+    }
+
+    Ok(ManifoldEdgesBindData {
+        db_path,
+        engine,
+        options,
+        edge_type,
+        columns,
+        column_index,
+        sample_failures,
+    })
+}
+
+/// Shared func for the edge scanners: stream the next batch into the output
+fn scan_edges<V>(
+    func: &TableFunctionInfo<V>,
+    output: &mut DataChunkHandle,
+) -> Result<(), Box<dyn Error>>
+where
+    V: VTab<InitData = ManifoldEdgesInitData, BindData = ManifoldEdgesBindData>,
+{
+    let init_data = func.get_init_data();
+    let bind_data = func.get_bind_data();
+
+    // Check if we're done
+    if init_data.done.load(Ordering::Relaxed) {
+        output.set_len(0);
+        return Ok(());
+    }
+
+    // Get the engine (cached unless no_cache)
+    let engine = bind_data.engine.get()?;
+
+    let (safe_mode, size) = (bind_data.options.safe_mode, chunk_batch_size(output));
+    let mut edges = init_data.next_batch(&bind_data.db_path, &engine, safe_mode, size)?;
+    if bind_data.options.dedupe_reciprocal {
+        // An empty output ends the scan, so skip batches that were all duplicates
+        loop {
+            init_data.dedupe_reciprocal(&mut edges);
+            if !edges.is_empty() || init_data.done.load(Ordering::Relaxed) {
+                break;
+            }
+            edges = init_data.next_batch(&bind_data.db_path, &engine, safe_mode, size)?;
         }
+    }
+    if bind_data.options.stable_order {
+        init_data.check_order(&edges)?;
+    }
+    let batch_size = edges.len();
 
-        let batch_size = edges.len();
+    // A non-empty batch leaves the snapshot held, so endpoints match the edges
+    let endpoints = match &*init_data.snapshot.lock().unwrap() {
+        Some(snapshot) if bind_data.options.wants_endpoints() => {
+            Some(EndpointProperties::load(snapshot.tx(), &edges, &bind_data.options)?)
+        }
+        _ => None,
+    };
 
-        // Update the continuation marker for the next batch
-        *init_data.last_key.lock().unwrap() = next_key;
+    // Populate the output with edge data
+    if bind_data.options.triples {
+        populate_triples(&edges, &bind_data.options, output)?;
+    } else {
+        let coerced = populate_edge_output(
+            &edges,
+            &bind_data.columns,
+            &bind_data.column_index,
+            &bind_data.options,
+            init_data.reciprocal.as_ref(),
+            endpoints.as_ref(),
+            output,
+        )?;
+        init_data.stats.coerced(coerced);
+    }
+    if let Some(&col_idx) = bind_data.column_index.get("row_number") {
+        populate_row_numbers(&init_data.rows_emitted, &[col_idx], batch_size, output);
+    }
 
-        // Populate the output with edge data
-        populate_edge_output(&edges, &bind_data.column_index, output)?;
+    init_data.stats.finish_batch(bind_data.function(), &bind_data.db_path);
+    if init_data.done.load(Ordering::Relaxed) {
+        // Close a no_cache engine as soon as the scan is exhausted
+        bind_data.engine.release();
+    }
 
-        output.set_len(batch_size);
+    output.set_len(batch_size);
 
-        Ok(())
+    Ok(())
+}
+
+/// The (source, target, edge type) of every edge, for reciprocal lookups
+pub struct EdgeKeySet {
+    keys: HashSet<(u64, u64, String)>,
+}
+
+impl EdgeKeySet {
+    /// Read every edge in the transaction's snapshot, from `mixed` if given
+    fn build(tx: &RedbTransaction, mixed: Option<&MixedTable>) -> Result<Self, Box<dyn Error>> {
+        let mut set = Self {
+            keys: HashSet::new(),
+        };
+
+        if let Ok(mut cursor) = tx.cursor(scan_table(mixed, "edges")) {
+            let mut entry = cursor.seek_first()?;
+            while let Some((_key, value)) = entry {
+                if let Some(Ok(edge)) = record_bytes(mixed, &value).map(Edge::decode) {
+                    set.insert(&edge);
+                }
+                entry = cursor.next()?;
+            }
+        }
+
+        Ok(set)
+    }
+
+    fn insert(&mut self, edge: &Edge) {
+        self.keys.insert((
+            edge.source.as_u64(),
+            edge.target.as_u64(),
+            edge.edge_type.as_str().to_string(),
+        ));
+    }
+
+    /// Whether an edge of the same type runs from target back to source
+    pub fn has_reverse(&self, edge: &Edge) -> bool {
+        self.keys.contains(&(
+            edge.target.as_u64(),
+            edge.source.as_u64(),
+            edge.edge_type.as_str().to_string(),
+        ))
     }
 }
 
+/// The requested properties of one batch's endpoints, by entity id
+pub struct EndpointProperties {
+    entities: HashMap<u64, HashMap<String, Value>>,
+}
+
+impl EndpointProperties {
+    /// Look up each distinct endpoint of the batch once, keeping only the
+    /// requested properties; missing or undecodable entities are left out
+    fn load(
+        tx: &RedbTransaction,
+        edges: &[ScanRecord<Edge>],
+        options: &EdgeScanOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut wanted: HashMap<u64, Vec<&String>> = HashMap::new();
+        for record in edges {
+            if let ScanRecord::Decoded { record: edge, .. } = record {
+                let source = wanted.entry(edge.source.as_u64()).or_default();
+                source.extend(&options.source_props);
+                let target = wanted.entry(edge.target.as_u64()).or_default();
+                target.extend(&options.target_props);
+            }
+        }
+
+        let mut entities = HashMap::with_capacity(wanted.len());
+        for (id, props) in wanted {
+            let Some(value) = tx.get("nodes", &id.to_be_bytes())? else {
+                continue;
+            };
+            let Ok(mut entity) = Entity::decode(&value) else {
+                continue;
+            };
+            let kept = props
+                .into_iter()
+                .filter_map(|prop| entity.properties.remove_entry(prop))
+                .collect();
+            entities.insert(id, kept);
+        }
+
+        Ok(Self { entities })
+    }
+
+    /// The endpoint's value of the property, if it has one
+    fn get(&self, id: u64, prop: &str) -> Option<&Value> {
+        self.entities.get(&id)?.get(prop)
+    }
+}
+
+/// Name of the column holding an endpoint property
+fn endpoint_column_name(end: &str, prop: &str) -> String {
+    format!("{}_prop_{}", end, prop)
+}
+
 /// Discover edge schema by sampling the database
-fn discover_edge_schema(
-    engine: &Arc<RedbEngine>,
-) -> Result<(Vec<DiscoveredColumn>, HashMap<String, usize>), Box<dyn Error>> {
+pub fn discover_edge_schema(
+    engine: &ManifoldEngine,
+    options: &EdgeScanOptions,
+) -> Result<DiscoveredSchema, Box<dyn Error>> {
+    Ok(discover_edge_schema_with_failures(engine, options)?.0)
+}
+
+/// `discover_edge_schema`, also returning how many sampled records failed to
+/// decode
+pub fn discover_edge_schema_with_failures(
+    engine: &ManifoldEngine,
+    options: &EdgeScanOptions,
+) -> Result<(DiscoveredSchema, usize), Box<dyn Error>> {
     let tx = engine.begin_read()?;
 
     let mut discovery = EdgeSchemaDiscovery::new();
 
-    // Try to get a cursor on the edges table
-    match tx.cursor("edges") {
-        Ok(mut cursor) => {
-            if let Some((_key, value)) = cursor.seek_first()? {
-                if let Ok(edge) = Edge::decode(&value) {
-                    discovery.observe_edge(&edge.properties);
-                }
+    // Sample the start, middle and end of the edges table
+    let mixed = options.mixed_table.as_ref();
+    let sample = sample_records::<Edge>(&tx, options.table(), mixed, options.schema_sample)?;
+    for edge in &sample.records {
+        discovery.observe_edge(&edge.properties);
+    }
 
-                let mut count = 1;
-                while count < SCHEMA_SAMPLE_SIZE {
-                    match cursor.next()? {
-                        Some((_key, value)) => {
-                            if let Ok(edge) = Edge::decode(&value) {
-                                discovery.observe_edge(&edge.properties);
-                            }
-                            count += 1;
-                        }
-                        None => break,
-                    }
-                }
-            }
-        }
-        Err(_) => {
-            // Table doesn't exist yet - return base schema
+    // Weight column
+    let weight_column = options.weight_prop.as_ref().map(|_| DiscoveredColumn {
+        name: "weight".to_string(),
+        column_type: ColumnType::Double,
+        nullable: true,
+        fields: Vec::new(),
+        property: None,
+    });
+
+    let mut columns = discovery.finalize(options.prop_prefix());
+    apply_decimal_props(&mut columns, &options.decimal_props, options.coercion_policy)?;
+    if options.debug_columns {
+        add_json_debug_columns(&mut columns);
+    }
+    columns.extend(weight_column);
+
+    let endpoint_props = [("source", &options.source_props), ("target", &options.target_props)];
+    for (end, props) in endpoint_props {
+        for prop in props {
+            columns.push(DiscoveredColumn {
+                name: endpoint_column_name(end, prop),
+                column_type: ColumnType::Varchar,
+                nullable: true,
+                fields: Vec::new(),
+                property: None,
+            });
         }
     }
 
-    let columns = discovery.finalize();
+    if options.include_reciprocal {
+        columns.push(DiscoveredColumn {
+            name: "is_reciprocal".to_string(),
+            column_type: ColumnType::Boolean,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.undirected {
+        columns.push(DiscoveredColumn {
+            name: "canonical_key".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
 
+    if options.composite_key {
+        columns.push(DiscoveredColumn {
+            name: "composite_key".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.with_row_number {
+        columns.push(DiscoveredColumn {
+            name: "row_number".to_string(),
+            column_type: ColumnType::Bigint,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.expose_keys {
+        columns.push(DiscoveredColumn {
+            name: "_key".to_string(),
+            column_type: ColumnType::Blob,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.safe_mode {
+        columns.push(DiscoveredColumn {
+            name: "_error".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    check_unique_columns(&columns)?;
     let mut column_index = HashMap::new();
     for (i, col) in columns.iter().enumerate() {
         column_index.insert(col.name.clone(), i);
     }
 
-    Ok((columns, column_index))
+    Ok(((columns, column_index), sample.failed))
 }
 
-/// Scan a batch of edges using cursor-based streaming
+/// Scan a batch of edges using cursor-based streaming, from `mixed` if given
 ///
 /// Returns (edges, next_key) where next_key is the continuation marker
 /// for the next batch (the last key we read)
 fn scan_edge_batch(
-    engine: &Arc<RedbEngine>,
+    tx: &RedbTransaction,
+    mixed: Option<&MixedTable>,
     start_after_key: Option<&[u8]>,
     batch_size: usize,
-) -> Result<(Vec<Edge>, Option<Vec<u8>>), Box<dyn Error>> {
-    let tx = engine.begin_read()?;
+    safe_mode: bool,
+    stats: &ScanStats,
+) -> Result<ScanBatch<ScanRecord<Edge>>, Box<dyn Error>> {
     let mut edges = Vec::with_capacity(batch_size);
     let mut last_key: Option<Vec<u8>> = None;
 
-    match tx.cursor("edges") {
+    match tx.cursor(scan_table(mixed, "edges")) {
         Ok(mut cursor) => {
-            // Position cursor at the starting point
-            let first_entry = if let Some(after_key) = start_after_key {
-                // Seek to the key after our continuation marker
-                cursor.seek(after_key)?;
-                // Skip the key we already processed
-                cursor.next()?
-            } else {
-                // Start from the beginning
-                cursor.seek_first()?
-            };
+            // Position cursor after our continuation marker, or at the start
+            let first_entry = first_after(&mut cursor, start_after_key)?;
 
             // Process first entry if we have one
             let Some((key, value)) = first_entry else {
@@ -243,19 +1162,19 @@ fn scan_edge_batch(
                 return Ok((edges, last_key));
             };
 
-            if let Ok(edge) = Edge::decode(&value) {
-                last_key = Some(key.clone());
-                edges.push(edge);
+            if let Some(record) = stats.decode_in(mixed, &key, &value, safe_mode) {
+                edges.push(record);
             }
+            last_key = Some(key);
 
             // Continue reading until we have a full batch
             while edges.len() < batch_size {
                 match cursor.next()? {
                     Some((key, value)) => {
-                        if let Ok(edge) = Edge::decode(&value) {
-                            last_key = Some(key.clone());
-                            edges.push(edge);
+                        if let Some(record) = stats.decode_in(mixed, &key, &value, safe_mode) {
+                            edges.push(record);
                         }
+                        last_key = Some(key);
                     }
                     None => break,
                 }
@@ -269,13 +1188,201 @@ fn scan_edge_batch(
     Ok((edges, last_key))
 }
 
-/// Populate DuckDB output chunk with edge data
+/// Table holding `[0x06][edge_type_hash][edge_id]` keys, when maintained
+const EDGE_TYPE_INDEX: &str = "edge_type_index";
+
+/// Whether the edge type index has any entries for this type
+fn has_type_index_entries(
+    tx: &RedbTransaction,
+    edge_type: &EdgeType,
+) -> Result<bool, Box<dyn Error>> {
+    let Ok(mut cursor) = tx.cursor(EDGE_TYPE_INDEX) else {
+        return Ok(false);
+    };
+
+    let prefix = encode_edge_type_index_prefix(edge_type);
+    Ok(matches!(cursor.seek(&prefix)?, Some((key, _)) if key.starts_with(&prefix)))
+}
+
+/// Scan a batch of one type's edges through the edge type index
+///
+/// Each index entry is resolved with a point lookup on the edges table; the
+/// continuation key is the last index key read. Entries whose edge is missing
+/// or has another type (a hash collision) are skipped.
+fn scan_type_index_batch(
+    tx: &RedbTransaction,
+    edge_type: &EdgeType,
+    start_after_key: Option<&[u8]>,
+    batch_size: usize,
+    safe_mode: bool,
+    stats: &ScanStats,
+) -> Result<ScanBatch<ScanRecord<Edge>>, Box<dyn Error>> {
+    let mut edges = Vec::with_capacity(batch_size);
+    let mut last_key: Option<Vec<u8>> = None;
+
+    let Ok(mut cursor) = tx.cursor(EDGE_TYPE_INDEX) else {
+        return Ok((edges, last_key));
+    };
+
+    let prefix = encode_edge_type_index_prefix(edge_type);
+    let mut entry = match start_after_key {
+        Some(after_key) => first_after(&mut cursor, Some(after_key))?,
+        None => cursor.seek(&prefix)?,
+    };
+
+    let mut read = 0;
+    while !batch_full(edges.len(), read, batch_size) {
+        read += 1;
+        let Some((key, _value)) = entry else {
+            break;
+        };
+        if !key.starts_with(&prefix) {
+            break;
+        }
+
+        // The edge id is the trailing big-endian u64
+        let edge_key = &key[prefix.len()..];
+        if edge_key.len() == 8 {
+            stats.index_seek();
+            if let Some(value) = tx.get("edges", edge_key)? {
+                match stats.decode::<Edge>(edge_key, &value, safe_mode) {
+                    Some(ScanRecord::Decoded { record: edge, .. }) if edge.edge_type != *edge_type => {
+                        stats.filtered(1);
+                    }
+                    Some(record) => edges.push(record),
+                    None => {}
+                }
+            }
+        }
+
+        last_key = Some(key);
+        entry = cursor.next()?;
+    }
+
+    Ok((edges, last_key))
+}
+
+/// Scan a batch of one type's edges by filtering a full scan
+///
+/// Keeps reading until it has a non-empty batch or the table is exhausted, so
+/// an empty batch still means the scan is over. Undecodable records (safe mode)
+/// have no known type and are kept.
+fn scan_filtered_batch(
+    tx: &RedbTransaction,
+    mixed: Option<&MixedTable>,
+    type_scan: &TypeScan,
+    start_after_key: Option<&[u8]>,
+    batch_size: usize,
+    safe_mode: bool,
+    stats: &ScanStats,
+) -> Result<ScanBatch<ScanRecord<Edge>>, Box<dyn Error>> {
+    let mut after_key = start_after_key.map(<[u8]>::to_vec);
+    loop {
+        let (mut edges, next_key) =
+            scan_edge_batch(tx, mixed, after_key.as_deref(), batch_size, safe_mode, stats)?;
+        let scanned = edges.len();
+        edges.retain(|record| match record {
+            ScanRecord::Decoded { record: edge, .. } => type_scan.matches(edge),
+            ScanRecord::Failed { .. } => true,
+        });
+        stats.filtered(scanned - edges.len());
+
+        if !edges.is_empty() || next_key.is_none() {
+            return Ok((edges, next_key));
+        }
+        after_key = next_key;
+    }
+}
+
+/// The columns of `triples` output
+fn triple_schema() -> DiscoveredSchema {
+    let column = |name: &str, column_type| DiscoveredColumn {
+        name: name.to_string(),
+        column_type,
+        nullable: false,
+        fields: Vec::new(),
+        property: None,
+    };
+    let columns = vec![
+        column("source", ColumnType::Bigint),
+        column("target", ColumnType::Bigint),
+        column("weight", ColumnType::Double),
+    ];
+    let column_index = columns.iter().enumerate().map(|(i, c)| (c.name.clone(), i)).collect();
+    (columns, column_index)
+}
+
+/// An edge's `triples` weight: `weight_prop`, else `weight_default`, else 1.0
+fn triple_weight(edge: &Edge, options: &EdgeScanOptions) -> f64 {
+    options
+        .weight_prop
+        .as_ref()
+        .and_then(|p| edge.properties.get(p))
+        .and_then(value_to_f64)
+        .or(options.weight_default)
+        .unwrap_or(1.0)
+}
+
+/// An entity id as BIGINT, failing for ids past its range
+fn triple_id(id: u64) -> Result<i64, ManifoldScannerError> {
+    i64::try_from(id).map_err(|_| {
+        ManifoldScannerError::EdgeReadError(format!("entity id {} doesn't fit BIGINT", id))
+    })
+}
+
+/// Populate the `triples` columns: source, target and weight
+fn populate_triples(
+    edges: &[ScanRecord<Edge>],
+    options: &EdgeScanOptions,
+    output: &mut DataChunkHandle,
+) -> Result<(), ManifoldScannerError> {
+    let mut sources = output.flat_vector(0);
+    let mut targets = output.flat_vector(1);
+    let mut weights = output.flat_vector(2);
+    for (row_idx, record) in edges.iter().enumerate() {
+        // triples refuses safe_mode, so undecodable records were skipped
+        let ScanRecord::Decoded { record: edge, .. } = record else {
+            continue;
+        };
+        sources.as_mut_slice::<i64>()[row_idx] = triple_id(edge.source.as_u64())?;
+        targets.as_mut_slice::<i64>()[row_idx] = triple_id(edge.target.as_u64())?;
+        weights.as_mut_slice::<f64>()[row_idx] = triple_weight(edge, options);
+    }
+    Ok(())
+}
+
+/// Populate DuckDB output chunk with edge data, returning how many values
+/// didn't fit their `decimal_props` column
 fn populate_edge_output(
-    edges: &[Edge],
+    edges: &[ScanRecord<Edge>],
+    columns: &[DiscoveredColumn],
     column_index: &HashMap<String, usize>,
+    options: &EdgeScanOptions,
+    reciprocal: Option<&EdgeKeySet>,
+    endpoints: Option<&EndpointProperties>,
     output: &mut DataChunkHandle,
-) -> Result<(), Box<dyn Error>> {
-    for (row_idx, edge) in edges.iter().enumerate() {
+) -> Result<u64, Box<dyn Error>> {
+    let property_index = property_index(columns);
+    let mut coerced = 0;
+    for (row_idx, record) in edges.iter().enumerate() {
+        let edge = match record {
+            ScanRecord::Decoded { record: edge, .. } => edge,
+            ScanRecord::Failed { key, error } => {
+                populate_error_row(row_idx, key, error, column_index, output)?;
+                continue;
+            }
+        };
+
+        // Decoded rows have no error
+        if let Some(&col_idx) = column_index.get("_error") {
+            output.flat_vector(col_idx).set_null(row_idx);
+        }
+
+        // Populate the raw storage key
+        if let Some(&col_idx) = column_index.get("_key") {
+            output.flat_vector(col_idx).insert(row_idx, record.key());
+        }
+
         // Populate id column
         if let Some(&col_idx) = column_index.get("id") {
             let vector = output.flat_vector(col_idx);
@@ -300,38 +1407,416 @@ fn populate_edge_output(
         // Populate edge_type column
         if let Some(&col_idx) = column_index.get("edge_type") {
             let vector = output.flat_vector(col_idx);
-            let value = CString::new(edge.edge_type.as_str())?;
-            vector.insert(row_idx, value);
+            insert_str(&vector, row_idx, edge.edge_type.as_str());
         }
 
-        // Populate property columns
-        for (prop_name, prop_value) in &edge.properties {
-            let col_name = format!("prop_{}", prop_name);
-            if let Some(&col_idx) = column_index.get(&col_name) {
-                let vector = output.flat_vector(col_idx);
-                let value_str = value_to_duckdb_string(prop_value);
-                let value = CString::new(value_str)?;
-                vector.insert(row_idx, value);
+        // Populate weight column
+        if let Some(&col_idx) = column_index.get("weight") {
+            let mut vector = output.flat_vector(col_idx);
+            let weight = options
+                .weight_prop
+                .as_ref()
+                .and_then(|p| edge.properties.get(p))
+                .and_then(value_to_f64)
+                .or(options.weight_default);
+            match weight {
+                Some(w) => vector.as_mut_slice::<f64>()[row_idx] = w,
+                None => vector.set_null(row_idx),
             }
         }
+
+        // Populate endpoint property columns
+        if let Some(endpoints) = endpoints {
+            let ends = [
+                ("source", edge.source.as_u64(), &options.source_props),
+                ("target", edge.target.as_u64(), &options.target_props),
+            ];
+            for (end, id, props) in ends {
+                for prop in props {
+                    let Some(&col_idx) = column_index.get(&endpoint_column_name(end, prop)) else {
+                        continue;
+                    };
+                    let mut vector = output.flat_vector(col_idx);
+                    match endpoints.get(id, prop) {
+                        Some(Value::Null) | None => {
+                            null_varchar(&mut vector, row_idx, options.null_string.as_deref())
+                        }
+                        Some(value) => {
+                            let text = value_to_duckdb_string(value, options.float_precision);
+                            vector.insert(row_idx, CString::new(text.replace('\0', ""))?);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Populate is_reciprocal column
+        if let (Some(&col_idx), Some(reciprocal)) = (column_index.get("is_reciprocal"), reciprocal) {
+            let mut vector = output.flat_vector(col_idx);
+            vector.as_mut_slice::<bool>()[row_idx] = reciprocal.has_reverse(edge);
+        }
+
+        // Populate canonical_key column
+        if let Some(&col_idx) = column_index.get("canonical_key") {
+            let vector = output.flat_vector(col_idx);
+            let (low, high) = canonical_pair(edge);
+            vector.insert(row_idx, CString::new(format!("{}:{}", low, high))?);
+        }
+
+        // Populate composite_key column
+        if let Some(&col_idx) = column_index.get("composite_key") {
+            let vector = output.flat_vector(col_idx);
+            insert_str(&vector, row_idx, &composite_key(edge));
+        }
+
+        // Populate property columns
+        coerced += populate_properties(
+            row_idx,
+            &edge.properties,
+            columns,
+            column_index,
+            &property_index,
+            PropertyFormat {
+                float_precision: options.float_precision,
+                null_string: options.null_string.as_deref(),
+                coercion_policy: options.coercion_policy,
+                record_id: edge.id.as_u64(),
+                ..Default::default()
+            },
+            output,
+        )?;
     }
 
-    Ok(())
+    Ok(coerced)
 }
 
-/// Convert a Manifold Value to a string for DuckDB
-fn value_to_duckdb_string(value: &Value) -> String {
-    match value {
-        Value::Null => String::new(),
-        Value::Bool(b) => b.to_string(),
-        Value::Int(i) => i.to_string(),
-        Value::Float(f) => f.to_string(),
-        Value::String(s) => s.clone(),
-        // For complex types, use JSON
-        Value::Bytes(b) => serde_json::to_string(b).unwrap_or_else(|_| "[]".to_string()),
-        Value::Array(arr) => serde_json::to_string(arr).unwrap_or_else(|_| "[]".to_string()),
-        Value::Vector(v) => serde_json::to_string(v).unwrap_or_else(|_| "[]".to_string()),
-        Value::SparseVector(sv) => serde_json::to_string(sv).unwrap_or_else(|_| "{}".to_string()),
-        Value::MultiVector(mv) => serde_json::to_string(mv).unwrap_or_else(|_| "[]".to_string()),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId};
+
+    fn edge(source: u64, target: u64, edge_type: &str) -> Edge {
+        Edge {
+            id: EdgeId::from(source * 100 + target),
+            source: EntityId::from(source),
+            target: EntityId::from(target),
+            edge_type: EdgeType::new(edge_type),
+            properties: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_edge_key_set_reciprocal() {
+        let edges = [
+            edge(1, 2, "FOLLOWS"),
+            edge(2, 1, "FOLLOWS"),
+            edge(1, 3, "FOLLOWS"),
+            edge(3, 1, "BLOCKS"),
+            edge(4, 4, "FOLLOWS"),
+        ];
+        let mut set = EdgeKeySet {
+            keys: HashSet::new(),
+        };
+        for edge in &edges {
+            set.insert(edge);
+        }
+
+        let reciprocal: Vec<bool> = edges.iter().map(|e| set.has_reverse(e)).collect();
+        assert_eq!(reciprocal, vec![true, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_composite_key() {
+        assert_eq!(composite_key(&edge(12, 3, "KNOWS")), "12:KNOWS:3");
+        // Ids are digits, so a type holding ':' still splits at the first and last
+        let key = composite_key(&edge(1, 2, "A:B"));
+        assert_eq!(key, "1:A:B:2");
+        let (source, rest) = key.split_once(':').unwrap();
+        let (edge_type, target) = rest.rsplit_once(':').unwrap();
+        assert_eq!((source, edge_type, target), ("1", "A:B", "2"));
+        // The key ignores the edge id
+        let mut renumbered = edge(12, 3, "KNOWS");
+        renumbered.id = EdgeId::from(99);
+        assert_eq!(composite_key(&renumbered), "12:KNOWS:3");
+    }
+
+    #[test]
+    fn test_dedupe_reciprocal() {
+        let edges = [
+            edge(2, 1, "FOLLOWS"),
+            edge(1, 2, "FOLLOWS"),
+            edge(1, 2, "BLOCKS"),
+            edge(10, 9, "FOLLOWS"),
+        ];
+        assert_eq!(canonical_pair(&edges[0]), (1, 2));
+        assert_eq!(canonical_pair(&edges[3]), (9, 10));
+
+        let init_data = ManifoldEdgesInitData::new();
+        let record = |edge: &Edge| ScanRecord::Decoded {
+            key: edge.id.as_u64().to_be_bytes().to_vec(),
+            record: edge.clone(),
+        };
+        let mut first: Vec<_> = edges[..2].iter().map(record).collect();
+        init_data.dedupe_reciprocal(&mut first);
+        assert_eq!(first.len(), 1);
+
+        // Later batches drop pairs an earlier one returned; other types stay
+        let mut rest: Vec<_> = edges.iter().map(record).collect();
+        init_data.dedupe_reciprocal(&mut rest);
+        let kept: Vec<u64> = rest
+            .iter()
+            .filter_map(|r| match r {
+                ScanRecord::Decoded { record: edge, .. } => Some(edge.id.as_u64()),
+                ScanRecord::Failed { .. } => None,
+            })
+            .collect();
+        assert_eq!(kept, vec![102, 1009]);
+    }
+
+    #[test]
+    fn test_triples() {
+        let mut heavy = edge(1, 2, "ROAD");
+        heavy.properties.insert("cost".to_string(), Value::Float(2.5));
+        let light = edge(2, 3, "ROAD");
+
+        // weight_prop, then weight_default, then 1.0
+        let mut options = EdgeScanOptions {
+            triples: true,
+            weight_prop: Some("cost".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(triple_weight(&heavy, &options), 2.5);
+        assert_eq!(triple_weight(&light, &options), 1.0);
+        options.weight_default = Some(0.5);
+        assert_eq!(triple_weight(&light, &options), 0.5);
+
+        let (columns, _) = triple_schema();
+        let types: Vec<_> = columns.iter().map(|c| (c.name.as_str(), c.column_type)).collect();
+        assert_eq!(
+            types,
+            [
+                ("source", ColumnType::Bigint),
+                ("target", ColumnType::Bigint),
+                ("weight", ColumnType::Double)
+            ]
+        );
+        assert_eq!(triple_id(42).unwrap(), 42);
+        assert!(triple_id(u64::MAX).is_err());
+
+        // Options that add columns are refused
+        assert!(options.check_triples().is_ok());
+        options.composite_key = true;
+        let err = options.check_triples().unwrap_err();
+        assert!(err.to_string().contains("composite_key"));
+    }
+
+    #[test]
+    fn test_check_order() {
+        let record = |id: u64| ScanRecord::Decoded {
+            key: id.to_be_bytes().to_vec(),
+            record: edge(1, 2, "KNOWS"),
+        };
+        let init_data = ManifoldEdgesInitData::new();
+        init_data.check_order(&[record(1), record(2), record(256)]).unwrap();
+        // Order carries across batches, compared as numbers
+        init_data.check_order(&[record(300)]).unwrap();
+        let err = init_data.check_order(&[record(301), record(7)]).unwrap_err();
+        assert!(err.to_string().contains("edge 7 returned after 301"));
+        // A repeated key is out of order too
+        let init_data = ManifoldEdgesInitData::new();
+        assert!(init_data.check_order(&[record(5), record(5)]).is_err());
+    }
+    #[test]
+    fn test_type_scans() {
+        use manifoldb_core::encoding::keys::encode_edge_type_index_key;
+        use manifoldb_core::encoding::Encoder;
+
+        let db = TempDb::new("edge_type");
+        let engine = db.open();
+
+        let edges = [edge(1, 2, "FOLLOWS"), edge(1, 3, "BLOCKS"), edge(2, 3, "FOLLOWS")];
+        let mut tx = engine.begin_write().unwrap();
+        for edge in &edges {
+            tx.put("edges", &edge.id.as_u64().to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        // Only FOLLOWS is indexed
+        for edge in edges.iter().filter(|e| e.edge_type.as_str() == "FOLLOWS") {
+            let key = encode_edge_type_index_key(&edge.edge_type, edge.id);
+            tx.put(EDGE_TYPE_INDEX, &key, b"").unwrap();
+        }
+        tx.commit().unwrap();
+
+        let ids = |batch: ScanBatch<ScanRecord<Edge>>| -> Vec<u64> {
+            batch
+                .0
+                .iter()
+                .filter_map(|r| match r {
+                    ScanRecord::Decoded { record: edge, .. } => Some(edge.id.as_u64()),
+                    ScanRecord::Failed { .. } => None,
+                })
+                .collect()
+        };
+
+        let tx = engine.begin_read().unwrap();
+        let follows = EdgeType::new("FOLLOWS");
+        let blocks = EdgeType::new("BLOCKS");
+        assert!(has_type_index_entries(&tx, &follows).unwrap());
+        assert!(!has_type_index_entries(&tx, &blocks).unwrap());
+
+        // Index walk, resumed after the first entry
+        let stats = ScanStats::default();
+        let first = scan_type_index_batch(&tx, &follows, None, 1, false, &stats).unwrap();
+        let resume = first.1.clone().unwrap();
+        assert_eq!(ids(first), vec![102]);
+        let rest = scan_type_index_batch(&tx, &follows, Some(&resume), 10, false, &stats).unwrap();
+        assert_eq!(ids(rest), vec![203]);
+        assert_eq!(stats.index_seeks.load(Ordering::Relaxed), 2);
+
+        // Filtered scan skips batches with no matching edges
+        let filter = |edge_type: &str, case_insensitive| TypeScan {
+            edge_type: EdgeType::new(edge_type),
+            indexed: false,
+            case_insensitive,
+        };
+        let stats = ScanStats::default();
+        let scan = |type_scan: TypeScan, size| {
+            scan_filtered_batch(&tx, None, &type_scan, None, size, false, &stats)
+        };
+        assert_eq!(ids(scan(filter("BLOCKS", false), 1).unwrap()), vec![103]);
+        assert_eq!(stats.rows_scanned.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.rows_filtered.load(Ordering::Relaxed), 1);
+        assert_eq!(ids(scan(filter("FOLLOWS", false), 10).unwrap()), vec![102, 203]);
+
+        // Case-insensitive scans compare against the lowercased type
+        assert!(ids(scan(filter("follows", false), 10).unwrap()).is_empty());
+        assert_eq!(ids(scan(filter("follows", true), 10).unwrap()), vec![102, 203]);
+    }
+
+    #[test]
+    fn test_long_edge_type() {
+        use manifoldb_core::encoding::keys::encode_edge_type_index_key;
+        use manifoldb_core::encoding::Encoder;
+
+        let db = TempDb::new("long_type");
+        let engine = db.open();
+
+        // 100KB, with multi-byte characters and an embedded NUL
+        let long_type = format!("{}\0{}", "é".repeat(25_000), "RELATES_".repeat(6_250));
+        assert_eq!(long_type.len(), 100_001);
+        let long = edge(1, 2, &long_type);
+        let mut tx = engine.begin_write().unwrap();
+        tx.put("edges", &long.id.as_u64().to_be_bytes(), &long.encode().unwrap()).unwrap();
+        let key = encode_edge_type_index_key(&long.edge_type, long.id);
+        tx.put(EDGE_TYPE_INDEX, &key, b"").unwrap();
+        tx.commit().unwrap();
+
+        let edge_types = |batch: ScanBatch<ScanRecord<Edge>>| -> Vec<String> {
+            batch
+                .0
+                .into_iter()
+                .filter_map(|r| match r {
+                    ScanRecord::Decoded { record: edge, .. } => {
+                        Some(edge.edge_type.as_str().to_string())
+                    }
+                    ScanRecord::Failed { .. } => None,
+                })
+                .collect()
+        };
+
+        let tx = engine.begin_read().unwrap();
+        let stats = ScanStats::default();
+        let full = scan_edge_batch(&tx, None, None, 10, false, &stats).unwrap();
+        assert_eq!(edge_types(full), vec![long_type.clone()]);
+        let indexed = scan_type_index_batch(&tx, &long.edge_type, None, 10, false, &stats);
+        assert_eq!(edge_types(indexed.unwrap()), vec![long_type]);
+    }
+
+    #[test]
+    fn test_endpoint_properties() {
+        use manifoldb_core::encoding::Encoder;
+
+        let db = TempDb::new("endpoints");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for (id, name, age) in [(1u64, "Ada", 36i64), (2, "Alan", 41)] {
+            let entity = Entity::new(EntityId::from(id))
+                .with_property("name", name)
+                .with_property("age", age);
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.put("nodes", &3u64.to_be_bytes(), &[0xff]).unwrap();
+        tx.commit().unwrap();
+
+        let edges: Vec<ScanRecord<Edge>> =
+            [edge(1, 2, "KNOWS"), edge(2, 3, "KNOWS"), edge(4, 1, "KNOWS")]
+                .into_iter()
+                .map(|record| ScanRecord::Decoded { key: Vec::new(), record })
+                .collect();
+        let options = EdgeScanOptions {
+            source_props: vec!["name".to_string()],
+            target_props: vec!["age".to_string()],
+            ..Default::default()
+        };
+
+        let tx = engine.begin_read().unwrap();
+        let endpoints = EndpointProperties::load(&tx, &edges, &options).unwrap();
+        assert_eq!(endpoints.get(1, "name"), Some(&Value::String("Ada".to_string())));
+        assert_eq!(endpoints.get(2, "name"), Some(&Value::String("Alan".to_string())));
+        assert_eq!(endpoints.get(1, "age"), Some(&Value::Int(36)));
+        assert_eq!(endpoints.get(2, "age"), Some(&Value::Int(41)));
+        // Only requested properties are kept; missing and undecodable endpoints have none
+        assert!(endpoints.get(1, "missing").is_none());
+        assert!(endpoints.get(3, "age").is_none());
+        assert!(endpoints.get(4, "name").is_none());
+    }
+
+    #[test]
+    fn test_mixed_table_edges() {
+        use crate::scanner::mixed_table::{EDGE_TAG, NODE_TAG};
+        use manifoldb_core::encoding::Encoder;
+        use manifoldb_storage::backends::RedbEngine;
+
+        let engine = ManifoldEngine::from(RedbEngine::in_memory().unwrap());
+        let mut tx = engine.begin_write().unwrap();
+        for (key, edge) in [("e1", edge(1, 2, "FOLLOWS")), ("e2", edge(2, 1, "FOLLOWS"))] {
+            let value = [vec![EDGE_TAG], edge.encode().unwrap()].concat();
+            tx.put("graph", key.as_bytes(), &value).unwrap();
+        }
+        let node = Entity::new(EntityId::from(1)).encode().unwrap();
+        tx.put("graph", b"n1", &[vec![NODE_TAG], node].concat()).unwrap();
+        tx.commit().unwrap();
+
+        let mixed = MixedTable {
+            table: "graph".to_string(),
+            kind: RecordKind::Edge,
+        };
+        let tx = engine.begin_read().unwrap();
+        let stats = ScanStats::default();
+        let (edges, _) = scan_edge_batch(&tx, Some(&mixed), None, 10, false, &stats).unwrap();
+        let pairs: Vec<(u64, u64)> = edges
+            .iter()
+            .filter_map(|record| match record {
+                ScanRecord::Decoded { record, .. } => {
+                    Some((record.source.as_u64(), record.target.as_u64()))
+                }
+                ScanRecord::Failed { .. } => None,
+            })
+            .collect();
+        assert_eq!(pairs, vec![(1, 2), (2, 1)]);
+        assert_eq!(stats.rows_filtered.load(Ordering::Relaxed), 1);
+
+        let set = EdgeKeySet::build(&tx, Some(&mixed)).unwrap();
+        assert_eq!(set.keys.len(), 2);
+        assert!(set.has_reverse(&edge(1, 2, "FOLLOWS")));
+
+        let options = EdgeScanOptions {
+            mixed_table: Some(mixed),
+            schema_sample: 10,
+            ..EdgeScanOptions::default()
+        };
+        let (_, failures) = discover_edge_schema_with_failures(&engine, &options).unwrap();
+        assert_eq!(failures, 0);
     }
 }
@@ -0,0 +1,130 @@
+//! Encoding vectors for ManifoldDB
+//!
+//! Implements `manifold_encode_vector(arr)`, a scalar function that turns a
+//! DuckDB `DOUBLE[]` into the bytes ManifoldDB stores for a `Value::Vector`
+//! property, so vectors written back through ManifoldDB's API carry its own
+//! encoding rather than JSON.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT id, manifold_encode_vector(embedding) AS vector
+//! FROM embeddings;
+//! ```
+//!
+//! ## Encoding
+//! The bytes are `manifoldb_core`'s encoding of the value: a tag byte, the
+//! dimension as a big-endian u32, then each element as a little-endian f32,
+//! so the blob is `5 + 4 * dimension` bytes. Elements are narrowed from
+//! DOUBLE to FLOAT, as Manifold stores them.
+//!
+//! A NULL array gives NULL. A NULL element, an element outside the FLOAT
+//! range, or an empty array fails the query with the element's position and
+//! the array's dimension, since any of them would store a vector that doesn't
+//! match the input.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use manifoldb_core::encoding::Encoder;
+use manifoldb_core::types::Value;
+use std::error::Error;
+
+use crate::error::ManifoldScannerError;
+
+/// Encode an array's elements (None for NULL) as a Manifold vector value
+pub fn encode_vector(elements: &[Option<f64>]) -> Result<Vec<u8>, ManifoldScannerError> {
+    let dimension = elements.len();
+    if dimension == 0 {
+        return Err(ManifoldScannerError::InvalidParameter(
+            "manifold_encode_vector needs at least one element".to_string(),
+        ));
+    }
+
+    let mut vector = Vec::with_capacity(dimension);
+    for (position, element) in elements.iter().enumerate() {
+        let invalid = |reason: String| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "manifold_encode_vector: element {} of a {}-dimension vector {}",
+                position + 1,
+                dimension,
+                reason
+            ))
+        };
+        let Some(element) = *element else {
+            return Err(invalid("is NULL".to_string()));
+        };
+        let narrowed = element as f32;
+        if narrowed.is_infinite() && element.is_finite() {
+            return Err(invalid(format!("({}) is outside the FLOAT range", element)));
+        }
+        vector.push(narrowed);
+    }
+
+    Value::Vector(vector)
+        .encode()
+        .map_err(|e| ManifoldScannerError::InvalidParameter(e.to_string()))
+}
+
+/// `manifold_encode_vector` scalar implementation
+pub struct ManifoldEncodeVectorScalar;
+
+impl VScalar for ManifoldEncodeVectorScalar {
+    type State = ();
+
+    /// Encode each non-NULL array
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        // The list's own validity is read through a flat view of the same vector
+        let arrays = input.flat_vector(0);
+        let lists = input.list_vector(0);
+        let child = lists.child(lists.len());
+        let values = child.as_slice_with_len::<f64>(lists.len());
+        let mut output = output.flat_vector();
+
+        for row_idx in 0..input.len() {
+            if arrays.row_is_null(row_idx as u64) {
+                output.set_null(row_idx);
+                continue;
+            }
+
+            let (offset, length) = lists.get_entry(row_idx);
+            let elements: Vec<Option<f64>> = (offset..offset + length)
+                .map(|idx| (!child.row_is_null(idx as u64)).then(|| values[idx]))
+                .collect();
+            output.insert(row_idx, encode_vector(&elements)?.as_slice());
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::exact(
+            vec![LogicalTypeHandle::list(&LogicalTypeId::Double.into())],
+            LogicalTypeId::Blob.into(),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manifoldb_core::encoding::Decoder;
+
+    #[test]
+    fn test_encode_vector() {
+        let bytes = encode_vector(&[Some(1.0), Some(-0.5), Some(0.25)]).unwrap();
+        assert_eq!(bytes.len(), 5 + 4 * 3);
+        assert_eq!(Value::decode(&bytes).unwrap(), Value::Vector(vec![1.0, -0.5, 0.25]));
+
+        let error = encode_vector(&[Some(1.0), None, Some(2.0)]).unwrap_err().to_string();
+        assert!(error.contains("element 2 of a 3-dimension vector is NULL"), "{}", error);
+        let error = encode_vector(&[Some(1e300)]).unwrap_err().to_string();
+        assert!(error.contains("outside the FLOAT range"), "{}", error);
+        assert!(encode_vector(&[]).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Opening ManifoldDB files
+//!
+//! `RedbEngine::open` always opens read-write and creates the file if it is
+//! missing, which fails on read-only mounts (archived snapshots, network
+//! shares) and silently creates empty databases for mistyped paths. Scanners
+//! only read, so files are opened here instead:
+//! - a missing or unreadable file is reported as such, without creating it
+//! - a writable file is opened read-write as before, so redb can repair it
+//!   after an unclean shutdown
+//! - a read-only file, or one on a read-only filesystem, is opened with redb's
+//!   read-only database, which takes no write lock and writes nothing
+//!
+//! ## Backups and Replicas
+//! A backup or replica snapshot is an ordinary redb file and opens like any
+//! other. A copy that stopped part way must not: redb initializes an empty
+//! file as a new database (a scan would return no rows rather than fail) and
+//! panics on a file shorter than the layout its header records. So before
+//! redb sees the file, its header is checked here:
+//! - a file shorter than the header, or than the regions the header
+//!   describes, fails with `DatabaseTruncated`
+//! - a file without redb's magic number, or with pages of a size this redb
+//!   can't open, fails as unreadable
+//!
+//! A panic inside redb on some other corruption is caught and reported as an
+//! unreadable file too, rather than poisoning the engine cache.
+//!
+//! A read-only open fails while another process has the file open for
+//! writing; redb's file lock makes that an error rather than a torn read.
+//!
+//! ## Empty Databases
+//! A database created but never written to is a complete redb file with no
+//! tables. It passes the header check and opens either way; reads of a
+//! missing table find nothing, so scanners bind with only their fixed
+//! columns and return no rows.
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use manifoldb_storage::backends::{RedbEngine, RedbTransaction};
+use manifoldb_storage::{StorageEngine, StorageError};
+use redb::{ReadOnlyDatabase, ReadableDatabase};
+
+use crate::error::ManifoldScannerError;
+
+/// An open database, read-write or read-only
+pub enum ManifoldEngine {
+    /// Opened read-write through ManifoldDB's storage layer
+    ReadWrite(RedbEngine),
+    /// Opened with redb's read-only database
+    ReadOnly(ReadOnlyDatabase),
+}
+
+impl From<RedbEngine> for ManifoldEngine {
+    fn from(engine: RedbEngine) -> Self {
+        ManifoldEngine::ReadWrite(engine)
+    }
+}
+
+impl StorageEngine for ManifoldEngine {
+    type Transaction<'a> = RedbTransaction;
+
+    fn begin_read(&self) -> Result<Self::Transaction<'_>, StorageError> {
+        match self {
+            ManifoldEngine::ReadWrite(engine) => engine.begin_read(),
+            ManifoldEngine::ReadOnly(db) => db
+                .begin_read()
+                .map(RedbTransaction::new_read)
+                .map_err(|e| StorageError::Transaction(e.to_string())),
+        }
+    }
+
+    fn begin_write(&self) -> Result<Self::Transaction<'_>, StorageError> {
+        match self {
+            ManifoldEngine::ReadWrite(engine) => engine.begin_write(),
+            ManifoldEngine::ReadOnly(_) => Err(StorageError::ReadOnly),
+        }
+    }
+}
+
+/// Length of redb's file header: magic number, layout and two commit slots
+const REDB_HEADER_SIZE: usize = 320;
+
+/// The first bytes of every redb file
+const REDB_MAGIC: [u8; 9] = [b'r', b'e', b'd', b'b', 0x1A, 0x0A, 0xA9, 0x0D, 0x0A];
+
+/// The only page size redb opens outside its own tests
+const REDB_PAGE_SIZE: u32 = 4096;
+
+/// Little-endian u32 of the header at `offset`
+fn header_u32(header: &[u8], offset: usize) -> u64 {
+    let bytes: [u8; 4] = header[offset..offset + 4].try_into().unwrap();
+    u32::from_le_bytes(bytes).into()
+}
+
+/// Bytes the file must hold for the layout its header records: the header
+/// page, the full regions, and the trailing partial region if any
+fn layout_len(header: &[u8]) -> u64 {
+    let page_size = header_u32(header, 12);
+    let region_header_pages = header_u32(header, 16);
+    let region_data_pages = header_u32(header, 20);
+    let full_regions = header_u32(header, 24);
+    let trailing_pages = header_u32(header, 28);
+    let trailing = match trailing_pages {
+        0 => 0,
+        pages => region_header_pages + pages,
+    };
+    page_size * (1 + full_regions * (region_header_pages + region_data_pages) + trailing)
+}
+
+/// Check the file holds a complete redb database before redb opens it
+fn check_header(db_path: &str) -> Result<(), ManifoldScannerError> {
+    let unreadable = |reason: String| ManifoldScannerError::DatabaseUnreadable {
+        path: db_path.to_string(),
+        reason,
+    };
+    let truncated = |size: u64, expected: u64| ManifoldScannerError::DatabaseTruncated {
+        path: db_path.to_string(),
+        size,
+        expected,
+    };
+
+    let mut file = File::open(db_path).map_err(|e| unreadable(e.to_string()))?;
+    let size = file.metadata().map_err(|e| unreadable(e.to_string()))?.len();
+    if size < REDB_HEADER_SIZE as u64 {
+        return Err(truncated(size, REDB_HEADER_SIZE as u64));
+    }
+
+    let mut header = [0u8; REDB_HEADER_SIZE];
+    file.read_exact(&mut header).map_err(|e| unreadable(e.to_string()))?;
+    if header[..REDB_MAGIC.len()] != REDB_MAGIC {
+        return Err(unreadable("not a redb database file (no redb header)".to_string()));
+    }
+    let page_size = header_u32(&header, 12);
+    if page_size != u64::from(REDB_PAGE_SIZE) {
+        return Err(unreadable(format!(
+            "{}-byte pages, but only {}-byte pages can be opened",
+            page_size, REDB_PAGE_SIZE
+        )));
+    }
+
+    let expected = layout_len(&header);
+    if size < expected {
+        return Err(truncated(size, expected));
+    }
+    Ok(())
+}
+
+/// How a database file can be opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileAccess {
+    ReadWrite,
+    ReadOnly,
+}
+
+/// Check the file exists and is readable, and whether it is writable
+fn file_access(db_path: &str) -> Result<FileAccess, ManifoldScannerError> {
+    let denied = |reason: String| ManifoldScannerError::DatabaseAccessDenied {
+        path: db_path.to_string(),
+        reason,
+    };
+
+    if let Err(e) = OpenOptions::new().read(true).open(db_path) {
+        return Err(match e.kind() {
+            ErrorKind::NotFound => ManifoldScannerError::DatabaseNotFound(db_path.to_string()),
+            ErrorKind::PermissionDenied => denied("permission denied reading the file".to_string()),
+            _ => denied(e.to_string()),
+        });
+    }
+
+    match OpenOptions::new().read(true).write(true).open(db_path) {
+        Ok(_) => Ok(FileAccess::ReadWrite),
+        Err(e)
+            if matches!(e.kind(), ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem) =>
+        {
+            Ok(FileAccess::ReadOnly)
+        }
+        Err(e) => Err(denied(e.to_string())),
+    }
+}
+
+/// Open a database file, read-only if it can't be written
+pub fn open_engine(db_path: &str) -> Result<ManifoldEngine, ManifoldScannerError> {
+    let access = file_access(db_path)?;
+    check_header(db_path)?;
+
+    let open = || match access {
+        FileAccess::ReadWrite => RedbEngine::open(db_path)
+            .map(ManifoldEngine::ReadWrite)
+            .map_err(|e| e.to_string()),
+        FileAccess::ReadOnly => ReadOnlyDatabase::open(db_path)
+            .map(ManifoldEngine::ReadOnly)
+            .map_err(|e| format!("opening read-only: {}", e)),
+    };
+    let opened = catch_unwind(AssertUnwindSafe(open)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(format!("corrupt database file: {}", message))
+    });
+
+    opened.map_err(|reason| ManifoldScannerError::DatabaseUnreadable {
+        path: db_path.to_string(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_storage::Transaction;
+
+    #[test]
+    fn test_open_engine() {
+        let db = TempDb::new("open");
+        let db_path = db.db_path();
+
+        // A missing file is reported, not created
+        assert!(matches!(open_engine(db_path), Err(ManifoldScannerError::DatabaseNotFound(_))));
+        assert!(!db.path().exists());
+
+        let engine = db.open();
+        let mut tx = engine.begin_write().unwrap();
+        tx.put("nodes", &1u64.to_be_bytes(), b"entity").unwrap();
+        tx.commit().unwrap();
+        drop(engine);
+
+        // Without write permission the file is opened read-only (unless the
+        // tests run as root, who can write it regardless)
+        let mut permissions = std::fs::metadata(db.path()).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(db.path(), permissions).unwrap();
+        let engine = open_engine(db_path).unwrap();
+        if file_access(db_path).unwrap() == FileAccess::ReadOnly {
+            assert!(matches!(engine, ManifoldEngine::ReadOnly(_)));
+        }
+        drop(engine);
+
+        // Read-only engines read through the same transactions
+        let engine = ManifoldEngine::ReadOnly(ReadOnlyDatabase::open(db.path()).unwrap());
+        assert!(matches!(engine.begin_write(), Err(StorageError::ReadOnly)));
+        let tx = engine.begin_read().unwrap();
+        assert_eq!(tx.get("nodes", &1u64.to_be_bytes()).unwrap().as_deref(), Some(&b"entity"[..]));
+    }
+
+    #[test]
+    fn test_incomplete_file() {
+        let db = TempDb::new("cut");
+        let db_path = db.db_path();
+
+        // An empty file is refused, not initialized as a new database
+        std::fs::write(db.path(), b"").unwrap();
+        assert!(matches!(
+            open_engine(db_path),
+            Err(ManifoldScannerError::DatabaseTruncated { size: 0, expected: 320, .. })
+        ));
+        assert_eq!(std::fs::metadata(db.path()).unwrap().len(), 0);
+
+        std::fs::write(db.path(), vec![7u8; 4096]).unwrap();
+        let garbage = open_engine(db_path);
+        assert!(matches!(garbage, Err(ManifoldScannerError::DatabaseUnreadable { .. })));
+
+        std::fs::remove_file(db.path()).unwrap();
+        let engine = db.open();
+        let mut tx = engine.begin_write().unwrap();
+        tx.put("nodes", &1u64.to_be_bytes(), b"entity").unwrap();
+        tx.commit().unwrap();
+        drop(engine);
+        let full = std::fs::read(db.path()).unwrap();
+        assert!(check_header(db_path).is_ok());
+
+        // A copy that stopped part way is reported with the size it should have
+        std::fs::write(db.path(), &full[..full.len() / 2]).unwrap();
+        match open_engine(db_path) {
+            Err(ManifoldScannerError::DatabaseTruncated { size, expected, .. }) => {
+                assert_eq!((size, expected), (full.len() as u64 / 2, full.len() as u64));
+            }
+            _ => panic!("expected a truncated file"),
+        }
+    }
+}
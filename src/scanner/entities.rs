@@ -10,11 +10,203 @@
 //! ```
 //!
 //! ## Schema Discovery
-//! The schema is discovered dynamically by sampling entities at bind time.
+//! The schema is discovered dynamically by sampling entities at bind time:
+//! `schema_sample` (default 100) entities each from the start, middle and end
+//! of the id range, so properties added by newer entities are seen too.
 //! Columns are generated for:
 //! - `id` - Entity ID (VARCHAR)
-//! - `labels` - JSON array of labels (VARCHAR)
-//! - `prop_*` - Each discovered property gets a prefixed column
+//! - `labels` - Labels as LIST(VARCHAR); see Label Mode
+//! - `prop_*` - Each discovered property gets a prefixed column (VARCHAR, or
+//!   STRUCT for consistently-shaped nested objects)
+//! - `is_*` - One BOOLEAN indicator per label requested with `label_onehot`
+//! - `num_labels` - The entity's label count (INTEGER), with
+//!   `include_label_count`
+//! - `geometry` - A WKT point (VARCHAR), with `geometry_from`; see Geometry
+//!
+//! ## Label Mode
+//! `label_mode` picks the shape of the labels column:
+//! - `'list'` (default): `labels` as LIST(VARCHAR)
+//! - `'first'`: a scalar `label` VARCHAR holding the first label (NULL if none)
+//! - `'json'`: `labels` as a JSON array string (the legacy format)
+//! - `'mask'`: a `labels_mask` UINTEGER with bit `i` set for the `i`th label
+//!   of the catalog - every distinct label in the database, sorted, read in
+//!   full at bind and capped at 32. `manifold_label_bits` returns the same
+//!   catalog; bits shift whenever a label is added or removed.
+//!
+//! `include_labels=false` drops the labels column altogether, skipping its
+//! per-row serialization when labels aren't needed; `label_onehot` columns
+//! and `include_label_count` still work without it.
+//!
+//! ## Geometry
+//! `geometry_from='lat,lon'` names a latitude and a longitude property and
+//! adds a `geometry` VARCHAR holding the entity's location as a WKT point,
+//! `POINT (<lon> <lat>)` - longitude first, as WKT orders x before y - for
+//! DuckDB's spatial extension (`ST_GeomFromText(geometry)`). Both properties
+//! must hold numbers (integers or floats); if either is missing, null, a
+//! string or not finite, `geometry` is NULL. Coordinates are written as
+//! given, without range checks or reprojection. The property columns
+//! themselves are still returned.
+//!
+//! ## Properties as JSON
+//! `properties_as_json=true` replaces the `prop_*` columns with a single
+//! `properties` VARCHAR holding the whole property map as a JSON object. Keys
+//! are sorted, so the string is canonical: repeated scans of an unchanged
+//! entity produce identical JSON, suitable for hashing and diffing.
+//!
+//! ## Debug Columns
+//! `debug_columns=true` adds a `prop_x__json` VARCHAR after each `prop_x`,
+//! holding the value's JSON form beside its usual string form, to check how
+//! values convert (for example float formatting) during migrations.
+//!
+//! ## Value Types
+//! `include_types=true` adds a `prop_x_type` VARCHAR after each `prop_x`
+//! naming the `Value` variant the cell came from ('int', 'float', 'string',
+//! 'bool', 'array', ...; NULL when the entity lacks the property), so values
+//! in a column that fell back to VARCHAR for mixed types can be told apart.
+//! Binding fails if a property `x_type` would collide with the companion of
+//! a property `x`.
+//!
+//! ## Row Numbers
+//! `include_rowid=true` adds a `rowid` BIGINT counting rows in output order
+//! from 0, dense and unique within the query - a join key for temporary
+//! tables when the sparse entity ids are inconvenient. It is not stored: the
+//! same entity can get a different rowid in another query (after writes, or
+//! with a different `order_by`). Each batch reserves its range from a shared
+//! counter, so the numbering stays global however batches are produced.
+//! `with_row_number=true` adds the same numbering as `row_number` (the name
+//! `manifold_edges` uses), after `rowid` when both are asked for. The scan
+//! doesn't declare parallelism, so DuckDB runs it on one thread and the
+//! numbers run from 0 without gaps.
+//!
+//! ## Projection
+//! Projection pushdown is on: only the columns a query uses are filled. A
+//! query using none, like `SELECT COUNT(*)`, writes no cells at all - entities
+//! are still read and decoded (filters need them), but labels and properties
+//! aren't serialized, so the count costs little more than the decode.
+//!
+//! ## JSON-Path Filters
+//! `where_json='$.address.city = "NYC"'` keeps only entities whose nested
+//! property value satisfies the predicate, tested as each record is decoded,
+//! so filtered-out entities never reach DuckDB. Paths walk into pair-array
+//! objects and JSON strings; `=` and `!=` are supported (see `json_path`).
+//! Safe-mode error rows are always returned.
+//!
+//! ## Property Presence
+//! `has_prop='email'` keeps only entities whose property map has the key,
+//! including those where it is present with a null value - which
+//! `prop_email IS NOT NULL` can't tell apart from absent.
+//!
+//! ## JSON Extraction
+//! `json_extract={'prop_meta': '$.region'}` fills `prop_meta` with the value
+//! at `$.region` inside the property rather than the whole value, so large
+//! JSON blobs are never handed to DuckDB. The column becomes a nullable
+//! VARCHAR rendered as `->>` would (strings unquoted); unresolved paths give
+//! NULL.
+//!
+//! ## Decimal Columns
+//! `decimal_props={'prop_price': '18,2'}` exposes `prop_price` as
+//! `DECIMAL(18,2)` rather than VARCHAR, for money kept as strings or integers
+//! (see `decimal`). A column can't be in both `json_extract` and
+//! `decimal_props`. `coercion_policy` ('null', 'error' or 'stringify')
+//! decides what happens to values that don't fit.
+//!
+//! ## Timestamp Columns
+//! `timestamp_props=['created', 'updated']` exposes those properties'
+//! columns as `TIMESTAMP` rather than VARCHAR, parsing ISO-8601 / RFC 3339
+//! strings as rows are written; values with an offset are converted to UTC
+//! and anything unparseable is NULL (see `timestamp`). Entries are property
+//! names, not column names, and a property can't also be in `json_extract`
+//! or `decimal_props`.
+//!
+//! ## Boolean Strings
+//! `coerce_bool=true` reads boolean-like strings (`'true'`, `'0'`, `'yes'`,
+//! ...; see `value_to_bool`) as booleans. A property column becomes BOOLEAN
+//! when every non-null sampled value is a boolean or such a string, and
+//! BOOLEAN fields of STRUCT columns accept the strings too. A value with no
+//! boolean reading, in records the sample didn't see, is NULL.
+//!
+//! ## Neighbour Aggregates
+//! `neighbor_agg='count,avg:age'` adds a column per aggregate over each
+//! entity's one-hop neighbours (see `neighbors`). The adjacency is read at
+//! init, so this is the one option that loads data before the first batch.
+//!
+//! ## Label Filters
+//! `manifold_entities_by_labels('/db', ['Person', 'Employee'], 'and')` returns
+//! the entities that have every listed label, and with `'or'` those with at
+//! least one (`'all'` and `'any'` are accepted too, in any case). An empty
+//! list returns every entity. Labels aren't indexed, so the filter runs on
+//! each decoded entity during the scan, alongside `has_prop` and `where_json`;
+//! every other option works as for `manifold_entities`.
+//!
+//! ## Id Lists
+//! `manifold_entities_ordered('/db', '[42, 7, 19]')` returns the listed
+//! entities in list order (duplicates repeated), each fetched with a point
+//! lookup in one snapshot, to hydrate a ranking computed elsewhere. Ids may
+//! also be JSON strings, as in the `id` column. Ids without an entity are
+//! skipped unless `include_missing=true`, which gives them a row with only
+//! `id` set. Filters still apply; `order_by` can't be used.
+//!
+//! ## Change Capture
+//! `since_version=N` is accepted but always fails at bind: redb keeps no
+//! per-record write version and Manifold's entity encoding has no timestamp
+//! or commit sequence, so there is nothing to compare N against. Returning
+//! every entity instead would look like a sync that worked. Use an id
+//! watermark for append-only tables, or diff two scans.
+//!
+//! ## Storage Keys
+//! `expose_keys=true` adds a `_key` BLOB holding the exact redb key each row
+//! was read from (the big-endian entity id, unless the table is damaged), to
+//! check continuation and id-range pushdown against the keys actually read.
+//! Unlike `id` it is not decoded from the entity, so it is also filled for
+//! safe-mode error rows.
+//!
+//! ## Mixed Tables
+//! `table => 'graph', record_filter => 'node'` reads nodes from a table that
+//! holds nodes and edges together, each value led by a tag byte (see
+//! `mixed_table`). Edges in it are skipped, both when sampling the schema
+//! and when scanning. Its keys aren't entity ids, so
+//! `manifold_entities_ordered` and `neighbor_agg` are refused with it.
+//!
+//! ## Float Precision
+//! `float_precision=N` renders float properties in VARCHAR columns with N
+//! (at most 17) decimals instead of the default shortest round-trip form. Rounded strings
+//! no longer cast back to the stored value, so use it for display only.
+//!
+//! ## Null Token
+//! `null_string='\N'` writes that token in VARCHAR property columns (and
+//! `json_extract` columns) wherever they would otherwise be NULL - the
+//! property is absent, extracts to null, or holds a null value - for tools
+//! such as Postgres `COPY` that expect a literal. Other columns keep SQL NULL.
+//!
+//! ## Parallel Decoding
+//! `decode_threads=N` (1 to 64, default 1) decodes each batch of a plain scan
+//! in N slices on a worker pool shared by all scans, with one thread per core
+//! started once per process; N is capped at the core count, since further
+//! slices would only queue. Records are still read from the cursor serially and
+//! rows come out in the same order, in the same batches, as a serial scan;
+//! only the decode fans out. It pays off for large entities on a machine with
+//! idle cores. Without spare cores it is slower, since a round of raw records
+//! is held before decoding and handed between threads. `order_by` and
+//! `manifold_entities_ordered` decode serially.
+//!
+//! ## Value Size Limit
+//! `max_value_bytes=4096` cuts any VARCHAR property value (or `json_extract`
+//! result) longer than 4096 bytes back to that length, at a character
+//! boundary, and appends `...[truncated]`, so a few giant values can't
+//! inflate batch memory. Truncation is lossy and off by default. Values are
+//! still decoded whole; only what is written to the batch is bounded.
+//!
+//! ## Safe Mode
+//! With `safe_mode=true`, records that fail to decode are returned as
+//! diagnostic rows instead of being skipped: `id` is taken from the storage key,
+//! an extra `_error` column holds the decode error, and every other column is NULL.
+//!
+//! ## Ordered Scans
+//! With `order_by='<property>'`, rows are returned sorted by that property
+//! (NULL/missing first, ties broken by storage key). The full table is buffered
+//! through an external merge sort: once buffered records exceed
+//! `sort_memory_limit` bytes (default 64 MiB) they are spilled as sorted runs to
+//! the temp directory and merged on output, so memory stays bounded.
 //!
 //! ## Scanning Strategy
 //!
@@ -22,38 +214,489 @@
 //! - The storage engine is cached globally (opened once per path, reused)
 //! - No upfront ID collection - entities are scanned directly via cursor
 //! - Each batch continues from the last key seen, avoiding redundant work
+//! - One read transaction is held for the whole scan, so batches share a
+//!   snapshot; it is released as soon as the scan completes or is abandoned
+//! - DuckDB calls `func` from one thread at a time, as the scan never raises
+//!   its thread count, but batching doesn't rely on it: each batch holds the
+//!   continuation marker while it reads, so concurrent calls take turns and
+//!   never return the same rows twice
 
 use duckdb::{
     core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
     vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
 };
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     error::Error,
     ffi::CString,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
     },
 };
 
+use manifoldb_core::encoding::sortable::encode_sortable;
 use manifoldb_core::encoding::Decoder;
-use manifoldb_core::types::{Entity, Value};
-use manifoldb_storage::backends::RedbEngine;
+use manifoldb_core::types::{Entity, Label, Value};
+use manifoldb_storage::backends::RedbTransaction;
 use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+use rayon::prelude::*;
+
+use crate::error::ManifoldScannerError;
+use crate::schema::{
+    ColumnType, DiscoveredColumn, SchemaDiscovery, DEFAULT_PROP_PREFIX, EXTRA_PROPS_COLUMN,
+};
+use super::{
+    add_json_debug_columns, add_type_columns, batch_full, check_unique_columns, chunk_batch_size,
+    first_after, insert_str, named_bool, named_float_precision, named_i64, named_schema_sample,
+    named_string_list, named_string_map, populate_error_row, string_list_param, string_param,
+    populate_properties, populate_row_numbers, projected_index, property_index, read_scan_batch,
+    sample_records, DiscoveredSchema, PropertyFormat, QueryEngine, ScanBatch,
+    ScanRecord, ScanSnapshot,
+};
+use super::decimal::{
+    apply_decimal_props, named_coercion_policy, named_decimal_props, CoercionPolicy,
+};
+use super::engine::ManifoldEngine;
+use super::json_path::{JsonPath, JsonPathFilter};
+use super::mixed_table::{record_bytes, scan_table, MixedTable, RecordKind};
+use super::neighbors::{NeighborAgg, NeighborAggregator, NeighborDirection, NeighborOptions};
+use super::pushdown::{PushdownEntry, PushdownReport, APPLIED_INDEX, APPLIED_SCAN};
+use super::stats::ScanStats;
+use super::timestamp::{apply_timestamp_props, named_timestamp_props};
+use super::values::{properties_to_json, value_to_bool};
+use super::sort::{ExternalSorter, SortedStream, DEFAULT_SORT_MEMORY_LIMIT};
+
+/// Shape of the labels column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LabelMode {
+    /// `labels` LIST(VARCHAR)
+    #[default]
+    List,
+    /// `label` VARCHAR holding the first label
+    First,
+    /// `labels` VARCHAR holding a JSON array
+    Json,
+    /// `labels_mask` UINTEGER with one bit per label in the catalog
+    Mask,
+}
+
+impl LabelMode {
+    /// Parse the `label_mode` named parameter
+    pub fn parse(name: &str) -> Result<Self, ManifoldScannerError> {
+        match name.to_ascii_lowercase().as_str() {
+            "list" => Ok(LabelMode::List),
+            "first" => Ok(LabelMode::First),
+            "json" => Ok(LabelMode::Json),
+            "mask" => Ok(LabelMode::Mask),
+            other => Err(ManifoldScannerError::InvalidParameter(format!(
+                "unknown label_mode '{}', expected 'list', 'first', 'json' or 'mask'",
+                other
+            ))),
+        }
+    }
+}
+
+/// How `manifold_entities_by_labels` combines its labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelMatch {
+    /// Entities with every label
+    All,
+    /// Entities with at least one label
+    Any,
+}
+
+impl LabelMatch {
+    /// Parse the mode argument of `manifold_entities_by_labels`
+    pub fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        match text.to_ascii_lowercase().as_str() {
+            "and" | "all" => Ok(LabelMatch::All),
+            "or" | "any" => Ok(LabelMatch::Any),
+            _ => Err(ManifoldScannerError::InvalidParameter(format!(
+                "label match mode must be 'and' or 'or', got '{}'",
+                text
+            ))),
+        }
+    }
+}
+
+/// Label restriction, for `manifold_entities_by_labels`
+#[derive(Debug, Clone)]
+pub struct LabelFilter {
+    pub labels: Vec<String>,
+    pub mode: LabelMatch,
+}
+
+impl LabelFilter {
+    /// Whether the entity's labels satisfy the filter; an empty label list
+    /// keeps every entity in either mode
+    fn matches(&self, entity: &Entity) -> bool {
+        let has = |label: &String| entity.labels.iter().any(|l| l.as_str() == label);
+        match self.mode {
+            LabelMatch::All => self.labels.iter().all(has),
+            LabelMatch::Any => self.labels.is_empty() || self.labels.iter().any(has),
+        }
+    }
+}
+
+/// The coordinate properties of the `geometry` column
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeometryFrom {
+    /// Latitude property (the point's y)
+    pub lat: String,
+    /// Longitude property (the point's x)
+    pub lon: String,
+}
+
+impl GeometryFrom {
+    /// Parse `geometry_from`: two property names, latitude first
+    pub fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        let names: Vec<&str> = text.split(',').map(str::trim).collect();
+        match names[..] {
+            [lat, lon] if !lat.is_empty() && !lon.is_empty() => Ok(Self {
+                lat: lat.to_string(),
+                lon: lon.to_string(),
+            }),
+            _ => Err(ManifoldScannerError::InvalidParameter(format!(
+                "geometry_from must be 'lat_prop,lon_prop', got '{}'",
+                text
+            ))),
+        }
+    }
+
+    /// The entity's location as WKT, or None unless both coordinates are
+    /// finite numbers
+    fn wkt_point(&self, properties: &HashMap<String, Value>) -> Option<String> {
+        let coordinate = |prop: &String| match properties.get(prop)? {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(f) if f.is_finite() => Some(*f),
+            _ => None,
+        };
+        let (lat, lon) = (coordinate(&self.lat)?, coordinate(&self.lon)?);
+        Some(format!("POINT ({} {})", lon, lat))
+    }
+}
+
+/// Named parameters accepted by the entity scanner
+#[derive(Debug, Clone, Default)]
+pub struct EntityScanOptions {
+    /// Emit diagnostic rows for undecodable records
+    pub safe_mode: bool,
+    /// Property to sort the output by
+    pub order_by: Option<String>,
+    /// Bytes buffered in memory before an ordered scan spills to disk
+    pub sort_memory_limit: usize,
+    /// Labels to materialize as `is_<label>` BOOLEAN columns
+    pub label_onehot: Vec<String>,
+    /// Add a `num_labels` column counting each entity's labels
+    pub include_label_count: bool,
+    /// Coordinate properties of a `geometry` WKT point column
+    pub geometry_from: Option<GeometryFrom>,
+    /// Shape of the labels column
+    pub label_mode: LabelMode,
+    /// Emit the labels column at all
+    pub include_labels: bool,
+    /// Fixed decimals for floats rendered as VARCHAR (None = round-trip)
+    pub float_precision: Option<usize>,
+    /// Emit all properties as one `properties` JSON column instead of `prop_*`
+    pub properties_as_json: bool,
+    /// Add a `prop_x__json` column after each `prop_x`
+    pub debug_columns: bool,
+    /// Records sampled from each of the start, middle and end at bind
+    pub schema_sample: usize,
+    /// Add a `rowid` column numbering rows in output order
+    pub include_rowid: bool,
+    /// Add a `row_number` column, numbered like `rowid`
+    pub with_row_number: bool,
+    /// Add a `_key` column with each row's raw storage key
+    pub expose_keys: bool,
+    /// Only return entities matching this JSON-path predicate
+    pub where_json: Option<JsonPathFilter>,
+    /// Only return entities that have this property, whatever its value
+    pub has_prop: Option<String>,
+    /// Only return entities matching these labels, for
+    /// `manifold_entities_by_labels`
+    pub label_filter: Option<LabelFilter>,
+    /// Add a `prop_x_type` column after each `prop_x`
+    pub include_types: bool,
+    /// Columns that hold one path of their property instead of all of it
+    pub json_extract: HashMap<String, JsonPath>,
+    /// Property columns exposed as DECIMAL, by column name
+    pub decimal_props: HashMap<String, ColumnType>,
+    /// Properties exposed as TIMESTAMP, by property name
+    pub timestamp_props: Vec<String>,
+    /// One-hop neighbour aggregates (`neighbor_agg`)
+    pub neighbors: NeighborOptions,
+    /// Open the database for this scan only, bypassing the engine cache
+    pub no_cache: bool,
+    /// Prefix of property column names (None = `prop_`)
+    pub prop_prefix: Option<String>,
+    /// Token for null and absent VARCHAR property values (None = SQL NULL)
+    pub null_string: Option<String>,
+    /// What to do with values that don't fit a `decimal_props` column
+    pub coercion_policy: CoercionPolicy,
+    /// Read boolean-like strings as booleans
+    pub coerce_bool: bool,
+    /// Truncate VARCHAR property values longer than this many bytes
+    pub max_value_bytes: Option<usize>,
+    /// Threads decoding each batch of a plain scan (0 or 1 = serial)
+    pub decode_threads: usize,
+    /// Table holding tagged nodes and edges, read instead of `nodes`
+    pub mixed_table: Option<MixedTable>,
+}
+
+impl EntityScanOptions {
+    /// Prefix of property column names
+    fn prop_prefix(&self) -> &str {
+        self.prop_prefix.as_deref().unwrap_or(DEFAULT_PROP_PREFIX)
+    }
+
+    /// The table the scan reads
+    fn table(&self) -> &str {
+        scan_table(self.mixed_table.as_ref(), "nodes")
+    }
+
+    /// Whether a scanned record passes `where_json` and `has_prop`; safe-mode
+    /// error rows can't be tested and are kept
+    fn keeps(&self, record: &ScanRecord<Entity>) -> bool {
+        let ScanRecord::Decoded { record: entity, .. } = record else {
+            return true;
+        };
+        let has_prop = match &self.has_prop {
+            Some(prop) => entity.properties.contains_key(prop),
+            None => true,
+        };
+        let labeled = self.label_filter.as_ref().is_none_or(|f| f.matches(entity));
+        has_prop
+            && labeled
+            && self.where_json.as_ref().is_none_or(|f| f.matches(&entity.properties))
+    }
 
-use crate::schema::{DiscoveredColumn, SchemaDiscovery};
-use super::{get_cached_engine, BATCH_SIZE, SCHEMA_SAMPLE_SIZE};
+    /// Read the named parameters from the bind info
+    fn from_bind(bind: &BindInfo) -> Result<Self, ManifoldScannerError> {
+        let sort_memory_limit = match named_i64(bind, "sort_memory_limit")? {
+            Some(limit) if limit > 0 => limit as usize,
+            Some(limit) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "sort_memory_limit must be positive, got {}",
+                    limit
+                )))
+            }
+            None => DEFAULT_SORT_MEMORY_LIMIT,
+        };
+        let max_value_bytes = match named_i64(bind, "max_value_bytes")? {
+            Some(limit) if limit > 0 => Some(limit as usize),
+            Some(limit) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "max_value_bytes must be positive, got {}",
+                    limit
+                )))
+            }
+            None => None,
+        };
+        let decode_threads = match named_i64(bind, "decode_threads")? {
+            Some(threads) if (1..=MAX_DECODE_THREADS as i64).contains(&threads) => {
+                threads as usize
+            }
+            Some(threads) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "decode_threads must be between 1 and {}, got {}",
+                    MAX_DECODE_THREADS, threads
+                )))
+            }
+            None => 1,
+        };
+
+        let label_onehot =
+            onehot_labels(named_string_list(bind, "label_onehot")?.unwrap_or_default())?;
+
+        if let Some(version) = named_i64(bind, "since_version")? {
+            return Err(since_version_unsupported(version));
+        }
+
+        let label_mode = match bind.get_named_parameter("label_mode") {
+            Some(v) => LabelMode::parse(&v.to_string())?,
+            None => LabelMode::default(),
+        };
+
+        let neighbors = NeighborOptions {
+            aggs: match bind.get_named_parameter("neighbor_agg") {
+                Some(v) => NeighborAgg::parse_list(&v.to_string())?,
+                None => Vec::new(),
+            },
+            edge_type: bind.get_named_parameter("neighbor_edge_type").map(|v| v.to_string()),
+            direction: match bind.get_named_parameter("neighbor_direction") {
+                Some(v) => NeighborDirection::parse(&v.to_string())?,
+                None => NeighborDirection::default(),
+            },
+        };
+        let neighbor_settings = neighbors.edge_type.is_some()
+            || bind.get_named_parameter("neighbor_direction").is_some();
+        if neighbors.aggs.is_empty() && neighbor_settings {
+            return Err(ManifoldScannerError::InvalidParameter(
+                "neighbor_edge_type and neighbor_direction need neighbor_agg".to_string(),
+            ));
+        }
+
+        let mixed_table = MixedTable::from_bind(bind, RecordKind::Node)?;
+        if let Some(mixed) = &mixed_table {
+            if !neighbors.aggs.is_empty() {
+                return Err(mixed.refuse("neighbor_agg"));
+            }
+        }
+
+        Ok(Self {
+            safe_mode: named_bool(bind, "safe_mode")?.unwrap_or(false),
+            order_by: bind.get_named_parameter("order_by").map(|v| v.to_string()),
+            sort_memory_limit,
+            label_onehot,
+            include_label_count: named_bool(bind, "include_label_count")?.unwrap_or(false),
+            geometry_from: bind
+                .get_named_parameter("geometry_from")
+                .map(|v| GeometryFrom::parse(&v.to_string()))
+                .transpose()?,
+            label_mode,
+            include_labels: named_bool(bind, "include_labels")?.unwrap_or(true),
+            float_precision: named_float_precision(bind)?,
+            properties_as_json: named_bool(bind, "properties_as_json")?.unwrap_or(false),
+            debug_columns: named_bool(bind, "debug_columns")?.unwrap_or(false),
+            schema_sample: named_schema_sample(bind)?,
+            include_rowid: named_bool(bind, "include_rowid")?.unwrap_or(false),
+            with_row_number: named_bool(bind, "with_row_number")?.unwrap_or(false),
+            expose_keys: named_bool(bind, "expose_keys")?.unwrap_or(false),
+            where_json: bind
+                .get_named_parameter("where_json")
+                .map(|v| JsonPathFilter::parse(&v.to_string()))
+                .transpose()?,
+            has_prop: bind.get_named_parameter("has_prop").map(|v| v.to_string()),
+            label_filter: None,
+            include_types: named_bool(bind, "include_types")?.unwrap_or(false),
+            json_extract: named_string_map(bind, "json_extract")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(column, path)| Ok((column, JsonPath::parse(&path)?)))
+                .collect::<Result<_, ManifoldScannerError>>()?,
+            decimal_props: named_decimal_props(bind, "decimal_props")?,
+            timestamp_props: named_timestamp_props(bind)?,
+            neighbors,
+            no_cache: named_bool(bind, "no_cache")?.unwrap_or(false),
+            prop_prefix: bind.get_named_parameter("prop_prefix").map(|v| v.to_string()),
+            null_string: bind.get_named_parameter("null_string").map(|v| v.to_string()),
+            coercion_policy: named_coercion_policy(bind)?,
+            coerce_bool: named_bool(bind, "coerce_bool")?.unwrap_or(false),
+            max_value_bytes,
+            decode_threads,
+            mixed_table,
+        })
+    }
+}
 
 /// Bind data for entity scanner - holds schema and database path
 #[repr(C)]
 pub struct ManifoldEntitiesBindData {
     /// Path to the ManifoldDB database
     pub db_path: String,
+    /// Engine the scan reads through
+    pub engine: QueryEngine,
+    /// Options from named parameters
+    pub options: EntityScanOptions,
     /// Discovered schema columns
     pub columns: Vec<DiscoveredColumn>,
     /// Map from column name to index for fast lookup
     pub column_index: HashMap<String, usize>,
+    /// Ids to look up in order, for `manifold_entities_ordered`
+    pub ordered_ids: Option<OrderedIds>,
+    /// Schema sample records that failed to decode, for the scan's stats
+    pub sample_failures: usize,
+}
+
+impl ManifoldEntitiesBindData {
+    /// Name of the scanning function, for `manifold_last_scan_stats`
+    fn function(&self) -> &'static str {
+        if self.ordered_ids.is_some() {
+            return "manifold_entities_ordered";
+        }
+        match self.options.label_filter {
+            Some(_) => "manifold_entities_by_labels",
+            None => "manifold_entities",
+        }
+    }
+
+    /// The filters the scan applies and the projected columns it fills, for
+    /// `manifold_last_pushdown`
+    fn pushdown_report(&self, projection: &HashMap<String, usize>) -> PushdownReport {
+        let options = &self.options;
+        let mut entries = Vec::new();
+        if let Some(mixed) = &options.mixed_table {
+            let kind = Some(mixed.kind.name().to_string());
+            entries.push(PushdownEntry::filter("record tag", "=", kind, APPLIED_SCAN));
+        }
+        if let Some(filter) = &options.label_filter {
+            let operator = match filter.mode {
+                LabelMatch::All => "contains all",
+                LabelMatch::Any => "contains any",
+            };
+            let labels =
+                serde_json::to_string(&filter.labels).unwrap_or_else(|_| "[]".to_string());
+            entries.push(PushdownEntry::filter("labels", operator, Some(labels), APPLIED_SCAN));
+        }
+        if let Some(ordered) = &self.ordered_ids {
+            let ids = format!("{} ids", ordered.ids.len());
+            entries.push(PushdownEntry::filter("id", "IN", Some(ids), APPLIED_INDEX));
+        }
+        if let Some(filter) = &options.where_json {
+            let (path, operator, literal) = filter.describe();
+            entries.push(PushdownEntry::filter(path, operator, Some(literal), APPLIED_SCAN));
+        }
+        if let Some(prop) = &options.has_prop {
+            entries.push(PushdownEntry::filter(prop.as_str(), "has key", None, APPLIED_SCAN));
+        }
+        entries.extend(
+            self.columns
+                .iter()
+                .filter(|column| projection.contains_key(&column.name))
+                .map(|column| PushdownEntry::projection(&column.name, APPLIED_SCAN)),
+        );
+
+        PushdownReport {
+            function: self.function().to_string(),
+            db_path: self.db_path.clone(),
+            entries,
+        }
+    }
+}
+
+/// The id list of `manifold_entities_ordered`
+pub struct OrderedIds {
+    /// Ids in output order, duplicates included
+    pub ids: Vec<u64>,
+    /// Emit an id-only row for ids with no entity
+    pub include_missing: bool,
+}
+
+/// Parse the `ids_json` argument: a JSON array of entity ids, as numbers or
+/// as strings like the `id` column's
+fn parse_ordered_ids(text: &str) -> Result<Vec<u64>, ManifoldScannerError> {
+    let invalid = || {
+        ManifoldScannerError::InvalidParameter(format!(
+            "ids_json must be a JSON array of entity ids, got '{}'",
+            text
+        ))
+    };
+    let serde_json::Value::Array(items) = serde_json::from_str(text).map_err(|_| invalid())?
+    else {
+        return Err(invalid());
+    };
+
+    items
+        .iter()
+        .map(|item| match item {
+            serde_json::Value::Number(n) => n.as_u64(),
+            serde_json::Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<u64>>>()
+        .ok_or_else(invalid)
 }
 
 /// Init data for entity scanner - holds scan state
@@ -62,8 +705,166 @@ pub struct ManifoldEntitiesInitData {
     /// Flag indicating scan is complete
     pub done: AtomicBool,
     /// Last key seen - used as continuation marker for cursor-based scanning
-    /// None means we haven't started yet, Some(key) means continue after this key
+    /// None means we haven't started yet, Some(key) means continue after this key.
+    /// Locked for the whole of `next_batch`, so concurrent func calls never
+    /// read the same marker
     pub last_key: Mutex<Option<Vec<u8>>>,
+    /// Merged output of an ordered scan, built on the first call to func()
+    pub sorted: Mutex<Option<SortedStream>>,
+    /// Read transaction held across batches; None before the first batch and
+    /// after the scan completes
+    pub snapshot: Mutex<Option<ScanSnapshot>>,
+    /// Rows emitted so far - the first `rowid` and `row_number` of the next batch
+    pub rows_emitted: AtomicU64,
+    /// Work counters for `manifold_last_scan_stats`
+    pub stats: ScanStats,
+    /// Source of the `neighbor_agg` columns, when requested
+    pub neighbors: Option<NeighborAggregator>,
+    /// Output position of each projected column; None writes every column in
+    /// bind order
+    pub projection: Option<HashMap<String, usize>>,
+    /// Position in the id list of `manifold_entities_ordered`
+    pub next_id: AtomicUsize,
+}
+
+impl ManifoldEntitiesInitData {
+    pub(crate) fn new() -> Self {
+        Self {
+            done: AtomicBool::new(false),
+            last_key: Mutex::new(None),
+            sorted: Mutex::new(None),
+            snapshot: Mutex::new(None),
+            rows_emitted: AtomicU64::new(0),
+            stats: ScanStats::default(),
+            neighbors: None,
+            projection: None,
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Set up scan state, reading the adjacency now if `neighbor_agg` needs it
+    fn for_bind(bind_data: &ManifoldEntitiesBindData) -> Result<Self, Box<dyn Error>> {
+        let mut init_data = Self::new();
+        init_data.stats.sample_failures(bind_data.sample_failures);
+        let neighbors = &bind_data.options.neighbors;
+        if !neighbors.aggs.is_empty() {
+            let engine = bind_data.engine.get()?;
+            init_data.neighbors = Some(NeighborAggregator::open(&engine, neighbors)?);
+        }
+        Ok(init_data)
+    }
+
+    /// Close a `no_cache` engine as soon as the scan is exhausted
+    fn release_engine(&self, bind_data: &ManifoldEntitiesBindData) {
+        if self.done.load(Ordering::Relaxed) {
+            bind_data.engine.release();
+        }
+    }
+
+    /// Fill the projected columns of a batch
+    ///
+    /// A query that needs no column at all, such as `SELECT COUNT(*)`, gets an
+    /// empty projection: then only the row count matters and no cell is
+    /// written, skipping label and property serialization entirely.
+    fn populate_batch(
+        &self,
+        entities: &[ScanRecord<Entity>],
+        bind_data: &ManifoldEntitiesBindData,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let column_index = self.projection.as_ref().unwrap_or(&bind_data.column_index);
+        if column_index.is_empty() {
+            return Ok(());
+        }
+
+        let options = &bind_data.options;
+        let coerced =
+            populate_entity_output(entities, &bind_data.columns, column_index, options, output)?;
+        self.stats.coerced(coerced);
+        self.populate_rowids(column_index, entities.len(), output);
+        self.populate_neighbors(entities, column_index, output)
+    }
+
+    /// Fill the `neighbor_agg` columns of a batch
+    fn populate_neighbors(
+        &self,
+        entities: &[ScanRecord<Entity>],
+        column_index: &HashMap<String, usize>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        match &self.neighbors {
+            Some(neighbors) => neighbors.populate(entities, column_index, output),
+            None => Ok(()),
+        }
+    }
+
+    /// Fill the `rowid` and `row_number` columns of a batch, reserving its
+    /// range of the count
+    fn populate_rowids(
+        &self,
+        column_index: &HashMap<String, usize>,
+        batch_size: usize,
+        output: &mut DataChunkHandle,
+    ) {
+        let col_indices: Vec<usize> = ["rowid", "row_number"]
+            .iter()
+            .filter_map(|name| column_index.get(*name).copied())
+            .collect();
+        populate_row_numbers(&self.rows_emitted, &col_indices, batch_size, output);
+    }
+
+    /// Read the next batch of the scan, releasing the snapshot once it is exhausted
+    pub(crate) fn next_batch(
+        &self,
+        db_path: &str,
+        engine: &ManifoldEngine,
+        options: &EntityScanOptions,
+        batch_size: usize,
+    ) -> Result<Vec<ScanRecord<Entity>>, Box<dyn Error>> {
+        if self.done.load(Ordering::Relaxed) {
+            return Ok(Vec::new());
+        }
+
+        // Hold the continuation marker for the whole batch, so concurrent
+        // calls take turns, each continuing after the batch before it
+        let mut last_key = self.last_key.lock().unwrap();
+        if self.done.load(Ordering::Relaxed) {
+            // Another call finished the scan while this one waited
+            return Ok(Vec::new());
+        }
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let start_after_key = last_key.clone();
+
+        // Scan the next batch using cursor-based streaming
+        let batch = read_scan_batch(&self.done, db_path, start_after_key.as_deref(), || {
+            let tx = match &mut *snapshot {
+                Some(snapshot) => snapshot.tx(),
+                None => snapshot.insert(ScanSnapshot::begin(engine)?).tx(),
+            };
+            let after_key = start_after_key.as_deref();
+            scan_entity_batch(tx, after_key, batch_size, options, &self.stats)
+        });
+        let (entities, next_key) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                // The scan is over; don't keep the snapshot pinned
+                *snapshot = None;
+                return Err(e.into());
+            }
+        };
+
+        if entities.is_empty() {
+            // No more entities - we're done, stop pinning the snapshot
+            self.done.store(true, Ordering::Relaxed);
+            *snapshot = None;
+            return Ok(entities);
+        }
+
+        // Update the continuation marker for the next batch
+        *last_key = next_key;
+
+        Ok(entities)
+    }
 }
 
 /// Entity scanner VTab implementation
@@ -75,65 +876,361 @@ impl VTab for ManifoldEntitiesVTab {
 
     /// Bind phase: discover schema, set up columns
     fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
-        // Get database path from first parameter
-        let db_path = bind.get_parameter(0).to_string();
+        bind_entity_scan(bind, None)
+    }
 
-        // Get cached engine (opens once, reused)
-        let engine = get_cached_engine(&db_path)?;
+    /// Init phase: prepare for scanning (entities are streamed via cursor in
+    /// func(); only `neighbor_agg` loads anything up front)
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldEntitiesBindData>() };
+        let mut init_data = ManifoldEntitiesInitData::for_bind(bind_data)?;
+        let column_ids = init.get_column_indices();
+        let projection = projected_index(&bind_data.columns, &column_ids);
+        bind_data.pushdown_report(&projection).publish();
+        init_data.projection = Some(projection);
+        Ok(init_data)
+    }
 
-        // Discover schema using the engine
-        let (columns, column_index) = discover_entity_schema(&engine)?;
+    /// Func phase: produce output batches using cursor-based streaming
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
 
-        // Register discovered columns with DuckDB
-        for col in &columns {
-            bind.add_result_column(&col.name, col.to_logical_type_handle());
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_entities".into()),
         }
+    }
 
-        Ok(ManifoldEntitiesBindData {
-            db_path,
-            columns,
-            column_index,
-        })
+    /// Only the columns a query uses are written (see `populate_batch`)
+    fn supports_pushdown() -> bool {
+        true
     }
 
-    /// Init phase: prepare for scanning (no data loading - we use cursor streaming)
-    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
-        // No upfront data collection - we'll scan directly via cursor in func()
-        Ok(ManifoldEntitiesInitData {
-            done: AtomicBool::new(false),
-            last_key: Mutex::new(None),
-        })
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+        ])
     }
 
-    /// Func phase: produce output batches using cursor-based streaming
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "safe_mode".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "order_by".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "sort_memory_limit".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "label_onehot".to_string(),
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ),
+            (
+                "include_label_count".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "geometry_from".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "label_mode".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "include_labels".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "float_precision".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "properties_as_json".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "debug_columns".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "schema_sample".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "include_rowid".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "with_row_number".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "expose_keys".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "where_json".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "has_prop".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "include_types".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            // Rejected at bind: there are no record versions to filter on
+            (
+                "since_version".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            // A struct or map of column name -> path; the field names vary
+            ("json_extract".to_string(), LogicalTypeHandle::from(LogicalTypeId::Any)),
+            // A struct or map of column name -> 'width,scale'
+            ("decimal_props".to_string(), LogicalTypeHandle::from(LogicalTypeId::Any)),
+            (
+                "timestamp_props".to_string(),
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ),
+            (
+                "neighbor_agg".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "neighbor_edge_type".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "neighbor_direction".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "no_cache".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "prop_prefix".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "null_string".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "coercion_policy".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "coerce_bool".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "max_value_bytes".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "decode_threads".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+            (
+                "table".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "record_filter".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+        ])
+    }
+}
+
+/// Entities-by-labels scanner VTab implementation
+pub struct ManifoldEntitiesByLabelsVTab;
+
+impl VTab for ManifoldEntitiesByLabelsVTab {
+    type InitData = ManifoldEntitiesInitData;
+    type BindData = ManifoldEntitiesBindData;
+
+    /// Bind phase: read the labels and mode, then as manifold_entities
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let labels = string_list_param(bind, 1, "labels")?;
+        let mode = LabelMatch::parse(&string_param(bind, 2, "mode")?)?;
+        bind_entity_scan(bind, Some(LabelFilter { labels, mode }))
+    }
+
+    /// Init phase: same as manifold_entities
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        ManifoldEntitiesVTab::init(init)
+    }
+
+    /// Func phase: produce batches of the entities matching the labels
     fn func(
         func: &TableFunctionInfo<Self>,
         output: &mut DataChunkHandle,
     ) -> Result<(), Box<dyn Error>> {
         // Wrap in catch_unwind to prevent panics from crossing FFI boundary
         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            Self::func_inner(func, output)
+            ManifoldEntitiesVTab::func_inner(func, output)
         }));
 
         match result {
             Ok(r) => r,
-            Err(_) => Err("Internal panic in manifold_entities".into()),
+            Err(_) => Err("Internal panic in manifold_entities_by_labels".into()),
         }
     }
 
+    /// Same projection pushdown as manifold_entities
+    fn supports_pushdown() -> bool {
+        true
+    }
+
     /// Define input parameters
     fn parameters() -> Option<Vec<LogicalTypeHandle>> {
         Some(vec![
             LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)), // labels
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // mode
         ])
     }
+
+    /// Define named parameters (same as manifold_entities)
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        ManifoldEntitiesVTab::named_parameters()
+    }
 }
 
-impl ManifoldEntitiesVTab {
-    fn func_inner(
+/// Shared bind for the entity scanners: discover schema, set up columns
+fn bind_entity_scan(
+    bind: &BindInfo,
+    label_filter: Option<LabelFilter>,
+) -> Result<ManifoldEntitiesBindData, Box<dyn Error>> {
+    // Get database path from first parameter
+    let db_path = string_param(bind, 0, "db_path")?;
+    let options = EntityScanOptions {
+        label_filter,
+        ..EntityScanOptions::from_bind(bind)?
+    };
+
+    // Get the cached engine (opens once, reused), or a private one with no_cache
+    let engine = QueryEngine::open(&db_path, options.no_cache)?;
+
+    // Discover schema using the engine
+    let ((columns, column_index), sample_failures) =
+        discover_entity_schema_with_failures(&*engine.get()?, &options)?;
+
+    // Register discovered columns with DuckDB
+    for col in &columns {
+        bind.add_result_column(&col.name, col.to_logical_type_handle());
+    }
+
+    Ok(ManifoldEntitiesBindData {
+        db_path,
+        engine,
+        options,
+        columns,
+        column_index,
+        ordered_ids: None,
+        sample_failures,
+    })
+}
+
+/// Entities-in-given-order scanner VTab implementation
+pub struct ManifoldEntitiesOrderedVTab;
+
+impl VTab for ManifoldEntitiesOrderedVTab {
+    type InitData = ManifoldEntitiesInitData;
+    type BindData = ManifoldEntitiesBindData;
+
+    /// Bind phase: read the id list, then as manifold_entities
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let ids = parse_ordered_ids(&string_param(bind, 1, "ids_json")?)?;
+        let include_missing = named_bool(bind, "include_missing")?.unwrap_or(false);
+        let mut bind_data = bind_entity_scan(bind, None)?;
+        if let Some(mixed) = &bind_data.options.mixed_table {
+            return Err(mixed.refuse("manifold_entities_ordered").into());
+        }
+        if bind_data.options.order_by.is_some() {
+            return Err(ManifoldScannerError::InvalidParameter(
+                "manifold_entities_ordered keeps the order of ids_json; order_by can't be used"
+                    .to_string(),
+            )
+            .into());
+        }
+        bind_data.ordered_ids = Some(OrderedIds {
+            ids,
+            include_missing,
+        });
+        Ok(bind_data)
+    }
+
+    /// Init phase: same as manifold_entities
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        ManifoldEntitiesVTab::init(init)
+    }
+
+    /// Func phase: produce batches of the listed entities, in list order
+    fn func(
         func: &TableFunctionInfo<Self>,
         output: &mut DataChunkHandle,
     ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ManifoldEntitiesVTab::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_entities_ordered".into()),
+        }
+    }
+
+    /// Same projection pushdown as manifold_entities
+    fn supports_pushdown() -> bool {
+        true
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // ids_json
+        ])
+    }
+
+    /// Define named parameters (manifold_entities' plus include_missing)
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        let mut params = ManifoldEntitiesVTab::named_parameters()?;
+        params.push((
+            "include_missing".to_string(),
+            LogicalTypeHandle::from(LogicalTypeId::Boolean),
+        ));
+        Some(params)
+    }
+}
+
+impl ManifoldEntitiesVTab {
+    fn func_inner<V>(
+        func: &TableFunctionInfo<V>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        V: VTab<InitData = ManifoldEntitiesInitData, BindData = ManifoldEntitiesBindData>,
+    {
         let init_data = func.get_init_data();
         let bind_data = func.get_bind_data();
 
@@ -143,82 +1240,554 @@ impl ManifoldEntitiesVTab {
             return Ok(());
         }
 
-        // Get the cached engine
-        let engine = get_cached_engine(&bind_data.db_path)?;
-
-        // Get the continuation key
-        let start_after_key = init_data.last_key.lock().unwrap().clone();
-
-        // Scan the next batch using cursor-based streaming
-        let (entities, next_key) = scan_entity_batch(&engine, start_after_key.as_deref(), BATCH_SIZE)?;
+        // Get the engine (cached unless no_cache)
+        let engine = bind_data.engine.get()?;
 
-        if entities.is_empty() {
-            // No more entities - we're done
-            init_data.done.store(true, Ordering::Relaxed);
-            output.set_len(0);
-            return Ok(());
+        if let Some(order_by) = &bind_data.options.order_by {
+            return Self::func_sorted(init_data, bind_data, &engine, order_by, output);
+        }
+        if let Some(ordered) = &bind_data.ordered_ids {
+            return Self::func_ordered(init_data, bind_data, &engine, ordered, output);
         }
 
+        let size = chunk_batch_size(output);
+        let entities = init_data.next_batch(&bind_data.db_path, &engine, &bind_data.options, size)?;
         let batch_size = entities.len();
 
-        // Update the continuation marker for the next batch
-        *init_data.last_key.lock().unwrap() = next_key;
-
         // Populate the output with entity data
-        populate_entity_output(&entities, &bind_data.column_index, output)?;
+        init_data.populate_batch(&entities, bind_data, output)?;
+        init_data.stats.finish_batch(bind_data.function(), &bind_data.db_path);
+        init_data.release_engine(bind_data);
 
         output.set_len(batch_size);
 
         Ok(())
     }
-}
 
-/// Discover entity schema by sampling the database
-fn discover_entity_schema(
-    engine: &Arc<RedbEngine>,
-) -> Result<(Vec<DiscoveredColumn>, HashMap<String, usize>), Box<dyn Error>> {
-    let tx = engine.begin_read()?;
-
-    // Sample entities to discover schema
-    let mut discovery = SchemaDiscovery::new();
+    /// Emit the next batch of `manifold_entities_ordered`, looking each id up
+    /// in the scan's snapshot
+    ///
+    /// A missing id (or a missing nodes table) is skipped, or with
+    /// `include_missing` written like a safe-mode error row: the id, NULL
+    /// elsewhere, and `entity not found` in `_error` if there is one.
+    fn func_ordered(
+        init_data: &ManifoldEntitiesInitData,
+        bind_data: &ManifoldEntitiesBindData,
+        engine: &ManifoldEngine,
+        ordered: &OrderedIds,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let options = &bind_data.options;
+        let mut snapshot = init_data.snapshot.lock().unwrap();
+        let tx = match &mut *snapshot {
+            Some(snapshot) => snapshot.tx(),
+            None => snapshot.insert(ScanSnapshot::begin(engine)?).tx(),
+        };
 
-    // Try to get a cursor on the nodes table
-    match tx.cursor("nodes") {
-        Ok(mut cursor) => {
-            // Iterate through first SCHEMA_SAMPLE_SIZE entities
-            if let Some((_key, value)) = cursor.seek_first()? {
-                if let Ok(entity) = Entity::decode(&value) {
-                    discovery.observe_entity(&entity.properties);
-                }
+        let size = chunk_batch_size(output);
+        let mut entities = Vec::with_capacity(size);
+        let mut read = 0;
+        while !batch_full(entities.len(), read, size) {
+            let Some(&id) = ordered.ids.get(init_data.next_id.load(Ordering::Relaxed)) else {
+                init_data.done.store(true, Ordering::Relaxed);
+                break;
+            };
+            init_data.next_id.fetch_add(1, Ordering::Relaxed);
+            read += 1;
 
-                let mut count = 1;
-                while count < SCHEMA_SAMPLE_SIZE {
-                    match cursor.next()? {
-                        Some((_key, value)) => {
-                            if let Ok(entity) = Entity::decode(&value) {
-                                discovery.observe_entity(&entity.properties);
-                            }
-                            count += 1;
+            let key = id.to_be_bytes();
+            init_data.stats.index_seek();
+            match tx.get("nodes", &key).unwrap_or(None) {
+                Some(value) => {
+                    if let Some(record) = init_data.stats.decode(&key, &value, options.safe_mode) {
+                        if options.keeps(&record) {
+                            entities.push(record);
+                        } else {
+                            init_data.stats.filtered(1);
                         }
-                        None => break,
                     }
                 }
+                None if ordered.include_missing => entities.push(ScanRecord::Failed {
+                    key: key.to_vec(),
+                    error: "entity not found".to_string(),
+                }),
+                None => {}
             }
         }
-        Err(_) => {
-            // Table doesn't exist yet - return base schema
+
+        if init_data.done.load(Ordering::Relaxed) {
+            // Every id has been looked up; stop pinning the snapshot
+            *snapshot = None;
+        }
+        drop(snapshot);
+
+        init_data.populate_batch(&entities, bind_data, output)?;
+        init_data.stats.finish_batch(bind_data.function(), &bind_data.db_path);
+        init_data.release_engine(bind_data);
+        output.set_len(entities.len());
+
+        Ok(())
+    }
+
+    /// Emit the next batch of an ordered scan, sorting the table on first call
+    fn func_sorted(
+        init_data: &ManifoldEntitiesInitData,
+        bind_data: &ManifoldEntitiesBindData,
+        engine: &ManifoldEngine,
+        order_by: &str,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let options = &bind_data.options;
+        let mut sorted = init_data.sorted.lock().unwrap();
+        if sorted.is_none() {
+            *sorted = Some(sort_entities(engine, order_by, options)?);
+        }
+        let stream = sorted.as_mut().expect("sorted stream was just built");
+
+        let size = chunk_batch_size(output);
+        let mut entities = Vec::with_capacity(size);
+        let mut read = 0;
+        while !batch_full(entities.len(), read, size) {
+            read += 1;
+            let Some(entry) = stream.next() else {
+                init_data.done.store(true, Ordering::Relaxed);
+                break;
+            };
+            let (sort_key, value) = entry?;
+            let key = storage_key(&sort_key);
+            let mixed = options.mixed_table.as_ref();
+            if let Some(record) = init_data.stats.decode_in(mixed, key, &value, options.safe_mode) {
+                if options.keeps(&record) {
+                    entities.push(record);
+                } else {
+                    init_data.stats.filtered(1);
+                }
+            }
+        }
+
+        if init_data.done.load(Ordering::Relaxed) {
+            // Release the stream (and any run files) as soon as it is drained
+            *sorted = None;
+        }
+
+        init_data.populate_batch(&entities, bind_data, output)?;
+        init_data.stats.finish_batch(bind_data.function(), &bind_data.db_path);
+        init_data.release_engine(bind_data);
+        output.set_len(entities.len());
+
+        Ok(())
+    }
+}
+
+/// Read every entity into an external sorter keyed by `order_by`
+///
+/// In a mixed table the other kind's records are sorted too, as nulls, and
+/// dropped when decoded, so the scan counts them like an unsorted one.
+fn sort_entities(
+    engine: &ManifoldEngine,
+    order_by: &str,
+    options: &EntityScanOptions,
+) -> Result<SortedStream, Box<dyn Error>> {
+    let tx = engine.begin_read()?;
+    let mut sorter = ExternalSorter::new(options.sort_memory_limit);
+
+    if let Ok(mut cursor) = tx.cursor(options.table()) {
+        let mut entry = cursor.seek_first()?;
+        while let Some((key, value)) = entry {
+            let property = record_bytes(options.mixed_table.as_ref(), &value)
+                .and_then(|record| Entity::decode(record).ok())
+                .and_then(|entity| entity.properties.get(order_by).cloned())
+                .unwrap_or(Value::Null);
+            sorter.push(sort_key(&property, &key), value)?;
+            entry = cursor.next()?;
+        }
+    }
+
+    Ok(sorter.finish()?)
+}
+
+/// Populate the labels column of one row according to the label mode
+fn populate_labels(
+    row_idx: usize,
+    entity: &Entity,
+    column_index: &HashMap<String, usize>,
+    label_mode: LabelMode,
+    label_bits: &HashMap<&str, u32>,
+    output: &mut DataChunkHandle,
+) -> Result<(), Box<dyn Error>> {
+    match label_mode {
+        LabelMode::List => {
+            if let Some(&col_idx) = column_index.get("labels") {
+                let mut list = output.list_vector(col_idx);
+                let offset = list.len();
+                let count = entity.labels.len();
+                let child = list.child(offset + count);
+                for (i, label) in entity.labels.iter().enumerate() {
+                    insert_str(&child, offset + i, label.as_str());
+                }
+                list.set_entry(row_idx, offset, count);
+                list.set_len(offset + count);
+            }
+        }
+        LabelMode::First => {
+            if let Some(&col_idx) = column_index.get("label") {
+                let mut vector = output.flat_vector(col_idx);
+                match entity.labels.first() {
+                    Some(label) => insert_str(&vector, row_idx, label.as_str()),
+                    None => vector.set_null(row_idx),
+                }
+            }
+        }
+        LabelMode::Json => {
+            if let Some(&col_idx) = column_index.get("labels") {
+                let vector = output.flat_vector(col_idx);
+                let labels: Vec<&str> = entity.labels.iter().map(|l| l.as_str()).collect();
+                let labels_json =
+                    serde_json::to_string(&labels).unwrap_or_else(|_| "[]".to_string());
+                vector.insert(row_idx, CString::new(labels_json)?);
+            }
+        }
+        LabelMode::Mask => {
+            if let Some(&col_idx) = column_index.get("labels_mask") {
+                let mut vector = output.flat_vector(col_idx);
+                vector.as_mut_slice::<u32>()[row_idx] = label_mask(&entity.labels, label_bits);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Most labels a `labels_mask` can hold, one per bit of a UINTEGER
+pub const MAX_MASK_LABELS: usize = 32;
+
+/// Most threads `decode_threads` may ask for
+pub const MAX_DECODE_THREADS: usize = 64;
+
+/// Every distinct label in the nodes table, sorted - bit `i` of a
+/// `labels_mask` stands for the `i`th
+///
+/// Reads the whole table, since a label missed by sampling would have no
+/// bit. Fails if there are more labels than a mask can hold.
+pub fn label_catalog(
+    tx: &RedbTransaction,
+    mixed: Option<&MixedTable>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut labels = BTreeSet::new();
+    if let Ok(mut cursor) = tx.cursor(scan_table(mixed, "nodes")) {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_, value)) = entry {
+            let record = record_bytes(mixed, &value);
+            if let Some(Ok(entity)) = record.map(Entity::decode) {
+                for label in &entity.labels {
+                    if !labels.contains(label.as_str()) {
+                        labels.insert(label.as_str().to_string());
+                    }
+                }
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    if labels.len() > MAX_MASK_LABELS {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "label_mode 'mask' holds at most {} labels, but the database has {}",
+            MAX_MASK_LABELS,
+            labels.len()
+        ))
+        .into());
+    }
+    Ok(labels.into_iter().collect())
+}
+
+/// Bit of each label in a `labels_mask` column's catalog
+fn label_bits(columns: &[DiscoveredColumn]) -> HashMap<&str, u32> {
+    let Some(column) = columns.iter().find(|col| col.name == "labels_mask") else {
+        return HashMap::new();
+    };
+    column.fields.iter().enumerate().map(|(bit, (label, _))| (label.as_str(), bit as u32)).collect()
+}
+
+/// An entity's labels as a mask; labels without a bit (added since bind) are
+/// left out
+fn label_mask(labels: &[Label], label_bits: &HashMap<&str, u32>) -> u32 {
+    labels
+        .iter()
+        .filter_map(|label| label_bits.get(label.as_str()))
+        .fold(0, |mask, bit| mask | 1 << bit)
+}
+
+/// Drop repeated `label_onehot` labels, failing on two that differ only by
+/// case: DuckDB column names are case-insensitive, so their `is_<label>`
+/// columns would collide
+fn onehot_labels(labels: Vec<String>) -> Result<Vec<String>, ManifoldScannerError> {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut unique = Vec::with_capacity(labels.len());
+    for label in labels {
+        match seen.get(&label.to_lowercase()) {
+            Some(existing) if *existing == label => {}
+            Some(existing) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "label_onehot labels '{}' and '{}' differ only by case; their is_ columns \
+                     would collide, as DuckDB column names are case-insensitive",
+                    existing, label
+                )))
+            }
+            None => {
+                seen.insert(label.to_lowercase(), label.clone());
+                unique.push(label);
+            }
+        }
+    }
+    Ok(unique)
+}
+
+/// Column name for a `label_onehot` indicator
+fn onehot_column_name(label: &str) -> String {
+    format!("is_{}", label)
+}
+
+/// Build the sort key for one record
+///
+/// The sortable encoding of the property (after all other values when not
+/// orderable, e.g. vectors), then the storage key as a tie-break, then the
+/// storage key length as a trailing u32 so the key can be recovered.
+fn sort_key(property: &Value, key: &[u8]) -> Vec<u8> {
+    let mut sort_key = encode_sortable(property).unwrap_or_else(|_| vec![0xff]);
+    sort_key.extend_from_slice(key);
+    sort_key.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    sort_key
+}
+
+/// Recover the storage key embedded at the end of a sort key
+fn storage_key(sort_key: &[u8]) -> &[u8] {
+    let (rest, len_bytes) = sort_key.split_at(sort_key.len() - 4);
+    let len = u32::from_be_bytes(len_bytes.try_into().expect("4-byte suffix")) as usize;
+    &rest[rest.len() - len..]
+}
+
+/// Note which properties have only boolean-like values in the sample
+fn observe_bool_like(bool_like: &mut HashMap<String, bool>, properties: &HashMap<String, Value>) {
+    for (name, value) in properties {
+        if !matches!(value, Value::Null) {
+            let all = bool_like.entry(name.clone()).or_insert(true);
+            *all = *all && value_to_bool(value).is_some();
+        }
+    }
+}
+
+/// Discover entity schema by sampling the database
+pub fn discover_entity_schema(
+    engine: &ManifoldEngine,
+    options: &EntityScanOptions,
+) -> Result<DiscoveredSchema, Box<dyn Error>> {
+    Ok(discover_entity_schema_with_failures(engine, options)?.0)
+}
+
+/// `discover_entity_schema`, also returning how many sampled records failed
+/// to decode
+pub fn discover_entity_schema_with_failures(
+    engine: &ManifoldEngine,
+    options: &EntityScanOptions,
+) -> Result<(DiscoveredSchema, usize), Box<dyn Error>> {
+    let tx = engine.begin_read()?;
+
+    // Sample entities to discover schema
+    let mut discovery = SchemaDiscovery::new();
+    // Property -> whether every sampled non-null value reads as a boolean
+    let mut bool_like: HashMap<String, bool> = HashMap::new();
+
+    // Sample the start, middle and end of the nodes table
+    let mixed = options.mixed_table.as_ref();
+    let sample = sample_records::<Entity>(&tx, options.table(), mixed, options.schema_sample)?;
+    for entity in &sample.records {
+        discovery.observe_entity(&entity.properties);
+        if options.coerce_bool {
+            observe_bool_like(&mut bool_like, &entity.properties);
         }
     }
 
     // Finalize schema
-    let columns = discovery.finalize();
+    let mut columns = discovery.finalize(options.prop_prefix());
+
+    for column in &mut columns {
+        let is_bool = |name: &String| bool_like.get(name).copied().unwrap_or(false);
+        let bool_property = column.property.as_ref().is_some_and(is_bool);
+        if column.column_type == ColumnType::Varchar && bool_property {
+            column.column_type = ColumnType::Boolean;
+        }
+    }
+
+    if !options.include_labels {
+        columns.retain(|col| col.name != "labels");
+    }
+
+    if let Some(labels) = columns.iter_mut().find(|col| col.name == "labels") {
+        match options.label_mode {
+            LabelMode::List => labels.column_type = ColumnType::VarcharList,
+            LabelMode::First => {
+                labels.name = "label".to_string();
+                labels.nullable = true;
+            }
+            LabelMode::Json => {}
+            LabelMode::Mask => {
+                labels.name = "labels_mask".to_string();
+                labels.column_type = ColumnType::UInteger;
+                labels.fields = label_catalog(&tx, mixed)?
+                    .into_iter()
+                    .map(|label| (label, ColumnType::Boolean))
+                    .collect();
+            }
+        }
+    }
+
+    if options.properties_as_json {
+        columns.retain(|col| col.property.is_none() && col.name != EXTRA_PROPS_COLUMN);
+        columns.push(DiscoveredColumn {
+            name: "properties".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
 
+    for column_name in options.json_extract.keys() {
+        let column = columns
+            .iter_mut()
+            .find(|col| col.name == *column_name && col.property.is_some())
+            .ok_or_else(|| {
+                ManifoldScannerError::InvalidParameter(format!(
+                    "json_extract names '{}', which is not a property column",
+                    column_name
+                ))
+            })?;
+        // Extracted values are strings, whatever the property's shape
+        column.column_type = ColumnType::Varchar;
+        column.nullable = true;
+        column.fields.clear();
+    }
+
+    if let Some(column_name) =
+        options.decimal_props.keys().find(|name| options.json_extract.contains_key(*name))
+    {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "'{}' can't be in both json_extract and decimal_props",
+            column_name
+        ))
+        .into());
+    }
+    apply_decimal_props(&mut columns, &options.decimal_props, options.coercion_policy)?;
+
+    if let Some(column) = columns.iter().find(|col| {
+        col.property.as_ref().is_some_and(|prop| options.timestamp_props.contains(prop))
+            && options.json_extract.contains_key(&col.name)
+    }) {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "'{}' can't be in both json_extract and timestamp_props",
+            column.name
+        ))
+        .into());
+    }
+    apply_timestamp_props(&mut columns, &options.timestamp_props)?;
+
+    if options.debug_columns {
+        add_json_debug_columns(&mut columns);
+    }
+
+    if options.include_types {
+        add_type_columns(&mut columns)?;
+    }
+
+    for label in &options.label_onehot {
+        columns.push(DiscoveredColumn {
+            name: onehot_column_name(label),
+            column_type: ColumnType::Boolean,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.include_label_count {
+        columns.push(DiscoveredColumn {
+            name: "num_labels".to_string(),
+            column_type: ColumnType::Integer,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.geometry_from.is_some() {
+        columns.push(DiscoveredColumn {
+            name: "geometry".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    columns.extend(options.neighbors.aggs.iter().map(NeighborAgg::column));
+
+    if options.expose_keys {
+        columns.push(DiscoveredColumn {
+            name: "_key".to_string(),
+            column_type: ColumnType::Blob,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.include_rowid {
+        columns.push(DiscoveredColumn {
+            name: "rowid".to_string(),
+            column_type: ColumnType::Bigint,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.with_row_number {
+        columns.push(DiscoveredColumn {
+            name: "row_number".to_string(),
+            column_type: ColumnType::Bigint,
+            nullable: false,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    if options.safe_mode {
+        columns.push(DiscoveredColumn {
+            name: "_error".to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    check_unique_columns(&columns)?;
     let mut column_index = HashMap::new();
     for (i, col) in columns.iter().enumerate() {
         column_index.insert(col.name.clone(), i);
     }
 
-    Ok((columns, column_index))
+    Ok(((columns, column_index), sample.failed))
+}
+
+/// The error for `since_version`, which the storage can't support
+fn since_version_unsupported(version: i64) -> ManifoldScannerError {
+    ManifoldScannerError::InvalidParameter(format!(
+        "since_version={} is not supported: the database records no per-entity write \
+         version or commit sequence, so changed entities can't be told apart from unchanged \
+         ones",
+        version
+    ))
 }
 
 /// Scan a batch of entities using cursor-based streaming
@@ -226,26 +1795,24 @@ fn discover_entity_schema(
 /// Returns (entities, next_key) where next_key is the continuation marker
 /// for the next batch (the last key we read)
 fn scan_entity_batch(
-    engine: &Arc<RedbEngine>,
+    tx: &RedbTransaction,
     start_after_key: Option<&[u8]>,
     batch_size: usize,
-) -> Result<(Vec<Entity>, Option<Vec<u8>>), Box<dyn Error>> {
-    let tx = engine.begin_read()?;
+    options: &EntityScanOptions,
+    stats: &ScanStats,
+) -> Result<ScanBatch<ScanRecord<Entity>>, Box<dyn Error>> {
+    if options.decode_threads > 1 {
+        return scan_entity_batch_parallel(tx, start_after_key, batch_size, options, stats);
+    }
+
+    let (safe_mode, mixed) = (options.safe_mode, options.mixed_table.as_ref());
     let mut entities = Vec::with_capacity(batch_size);
     let mut last_key: Option<Vec<u8>> = None;
 
-    match tx.cursor("nodes") {
+    match tx.cursor(options.table()) {
         Ok(mut cursor) => {
-            // Position cursor at the starting point
-            let first_entry = if let Some(after_key) = start_after_key {
-                // Seek to the key after our continuation marker
-                cursor.seek(after_key)?;
-                // Skip the key we already processed
-                cursor.next()?
-            } else {
-                // Start from the beginning
-                cursor.seek_first()?
-            };
+            // Position cursor after our continuation marker, or at the start
+            let first_entry = first_after(&mut cursor, start_after_key)?;
 
             // Process first entry if we have one
             let Some((key, value)) = first_entry else {
@@ -253,19 +1820,29 @@ fn scan_entity_batch(
                 return Ok((entities, last_key));
             };
 
-            if let Ok(entity) = Entity::decode(&value) {
-                last_key = Some(key.clone());
-                entities.push(entity);
+            if let Some(record) = stats.decode_in(mixed, &key, &value, safe_mode) {
+                if options.keeps(&record) {
+                    entities.push(record);
+                } else {
+                    stats.filtered(1);
+                }
             }
+            last_key = Some(key);
+            let mut read = 1;
 
-            // Continue reading until we have a full batch
-            while entities.len() < batch_size {
+            // Continue reading until we have a full batch or have read one
+            while !batch_full(entities.len(), read, batch_size) {
+                read += 1;
                 match cursor.next()? {
                     Some((key, value)) => {
-                        if let Ok(entity) = Entity::decode(&value) {
-                            last_key = Some(key.clone());
-                            entities.push(entity);
+                        if let Some(record) = stats.decode_in(mixed, &key, &value, safe_mode) {
+                            if options.keeps(&record) {
+                                entities.push(record);
+                            } else {
+                                stats.filtered(1);
+                            }
                         }
+                        last_key = Some(key);
                     }
                     None => break,
                 }
@@ -279,13 +1856,117 @@ fn scan_entity_batch(
     Ok((entities, last_key))
 }
 
-/// Populate DuckDB output chunk with entity data
+/// `scan_entity_batch` with `decode_threads` decoding in parallel
+///
+/// Records are read from the cursor serially, in rounds of as many as the
+/// batch still needs; each round is decoded across the threads and then
+/// filtered in cursor order. So the batch holds the same rows, in the same
+/// order, as a serial scan, and ends at the same continuation key.
+fn scan_entity_batch_parallel(
+    tx: &RedbTransaction,
+    start_after_key: Option<&[u8]>,
+    batch_size: usize,
+    options: &EntityScanOptions,
+    stats: &ScanStats,
+) -> Result<ScanBatch<ScanRecord<Entity>>, Box<dyn Error>> {
+    let mut entities = Vec::with_capacity(batch_size);
+    let mut last_key: Option<Vec<u8>> = None;
+    let Ok(mut cursor) = tx.cursor(options.table()) else {
+        // Table doesn't exist - return empty
+        return Ok((entities, last_key));
+    };
+
+    let mut next = first_after(&mut cursor, start_after_key)?;
+    let mut read = 0;
+    while let Some(first) = next.take() {
+        let wanted = batch_size - entities.len();
+        let mut raw = vec![first];
+        while raw.len() < wanted {
+            match cursor.next()? {
+                Some(entry) => raw.push(entry),
+                None => break,
+            }
+        }
+        read += raw.len();
+        last_key = raw.last().map(|(key, _)| key.clone());
+        let exhausted = raw.len() < wanted;
+
+        let decoded = decode_parallel(&raw, options, stats);
+        for record in decoded.into_iter().flatten() {
+            if options.keeps(&record) {
+                entities.push(record);
+            } else {
+                stats.filtered(1);
+            }
+        }
+
+        if exhausted || batch_full(entities.len(), read, batch_size) {
+            break;
+        }
+        next = cursor.next()?;
+    }
+
+    Ok((entities, last_key))
+}
+
+/// Decode raw (key, value) records in up to `threads` contiguous slices on
+/// rayon's global pool, returning the results in input order
+///
+/// The pool's workers live for the process, so a round pays for handing out
+/// slices rather than starting threads. A panic while decoding is resumed on
+/// the caller, where the scan's `catch_unwind` turns it into a query error.
+fn decode_parallel(
+    raw: &[(Vec<u8>, Vec<u8>)],
+    options: &EntityScanOptions,
+    stats: &ScanStats,
+) -> Vec<Option<ScanRecord<Entity>>> {
+    let (threads, safe_mode) = (options.decode_threads, options.safe_mode);
+    let mixed = options.mixed_table.as_ref();
+    let decode_slice = |slice: &[(Vec<u8>, Vec<u8>)]| -> Vec<Option<ScanRecord<Entity>>> {
+        slice.iter().map(|(key, value)| stats.decode_in(mixed, key, value, safe_mode)).collect()
+    };
+    // More slices than workers would only queue
+    let threads = threads.min(rayon::current_num_threads());
+    if threads <= 1 || raw.len() <= 1 {
+        return decode_slice(raw);
+    }
+
+    let per_thread = raw.len().div_ceil(threads);
+    let slices: Vec<_> = raw.par_chunks(per_thread).map(decode_slice).collect();
+    slices.into_iter().flatten().collect()
+}
+
+/// Populate DuckDB output chunk with entity data, returning how many values
+/// didn't fit their `decimal_props` column
 fn populate_entity_output(
-    entities: &[Entity],
+    entities: &[ScanRecord<Entity>],
+    columns: &[DiscoveredColumn],
     column_index: &HashMap<String, usize>,
+    options: &EntityScanOptions,
     output: &mut DataChunkHandle,
-) -> Result<(), Box<dyn Error>> {
-    for (row_idx, entity) in entities.iter().enumerate() {
+) -> Result<u64, Box<dyn Error>> {
+    let property_index = property_index(columns);
+    let label_bits = label_bits(columns);
+    let mut coerced = 0;
+    for (row_idx, record) in entities.iter().enumerate() {
+        let entity = match record {
+            ScanRecord::Decoded { record: entity, .. } => entity,
+            ScanRecord::Failed { key, error } => {
+                populate_error_row(row_idx, key, error, column_index, output)?;
+                continue;
+            }
+        };
+
+        // Decoded rows have no error
+        if let Some(&col_idx) = column_index.get("_error") {
+            output.flat_vector(col_idx).set_null(row_idx);
+        }
+
+        // Populate the raw storage key
+        if let Some(&col_idx) = column_index.get("_key") {
+            output.flat_vector(col_idx).insert(row_idx, record.key());
+        }
+
         // Populate id column
         if let Some(&col_idx) = column_index.get("id") {
             let vector = output.flat_vector(col_idx);
@@ -293,84 +1974,772 @@ fn populate_entity_output(
             vector.insert(row_idx, value);
         }
 
-        // Populate labels column (JSON array)
-        if let Some(&col_idx) = column_index.get("labels") {
+        // Populate labels column in the requested shape
+        if options.include_labels {
+            let label_mode = options.label_mode;
+            populate_labels(row_idx, entity, column_index, label_mode, &label_bits, output)?;
+        }
+
+        // Populate label indicator columns
+        for label in &options.label_onehot {
+            if let Some(&col_idx) = column_index.get(&onehot_column_name(label)) {
+                let mut vector = output.flat_vector(col_idx);
+                vector.as_mut_slice::<bool>()[row_idx] = entity.labels.iter().any(|l| l.as_str() == label);
+            }
+        }
+
+        // Populate the label count
+        if let Some(&col_idx) = column_index.get("num_labels") {
+            let mut vector = output.flat_vector(col_idx);
+            vector.as_mut_slice::<i32>()[row_idx] = entity.labels.len() as i32;
+        }
+
+        // Populate the WKT point
+        if let Some(geometry) = &options.geometry_from {
+            if let Some(&col_idx) = column_index.get("geometry") {
+                let mut vector = output.flat_vector(col_idx);
+                match geometry.wkt_point(&entity.properties) {
+                    Some(point) => insert_str(&vector, row_idx, &point),
+                    None => vector.set_null(row_idx),
+                }
+            }
+        }
+
+        // Populate the whole property map as canonical JSON
+        if let Some(&col_idx) = column_index.get("properties") {
             let vector = output.flat_vector(col_idx);
-            let labels: Vec<&str> = entity.labels.iter().map(|l| l.as_str()).collect();
-            let labels_json = serde_json::to_string(&labels).unwrap_or_else(|_| "[]".to_string());
-            let value = CString::new(labels_json)?;
-            vector.insert(row_idx, value);
+            vector.insert(row_idx, CString::new(properties_to_json(&entity.properties))?);
         }
 
         // Populate property columns
-        for (prop_name, prop_value) in &entity.properties {
-            let col_name = format!("prop_{}", prop_name);
-            if let Some(&col_idx) = column_index.get(&col_name) {
-                let vector = output.flat_vector(col_idx);
-                let value_str = value_to_duckdb_string(prop_value);
-                let value = CString::new(value_str)?;
-                vector.insert(row_idx, value);
+        coerced += populate_properties(
+            row_idx,
+            &entity.properties,
+            columns,
+            column_index,
+            &property_index,
+            PropertyFormat {
+                float_precision: options.float_precision,
+                include_types: options.include_types,
+                json_extract: (!options.json_extract.is_empty()).then_some(&options.json_extract),
+                null_string: options.null_string.as_deref(),
+                coercion_policy: options.coercion_policy,
+                record_id: entity.id.as_u64(),
+                coerce_bool: options.coerce_bool,
+                max_value_bytes: options.max_value_bytes,
+            },
+            output,
+        )?;
+    }
+
+    Ok(coerced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use crate::scanner::{sample_table, BATCH_SIZE, SCHEMA_SAMPLE_SIZE};
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EntityId, Label};
+
+    #[test]
+    fn test_scans_release_snapshots() {
+        let db = TempDb::new("snapshot");
+        let engine = ManifoldEngine::from(db.open());
+
+        // Enough entities for several batches
+        let options = EntityScanOptions::default();
+        let mut tx = engine.begin_write().unwrap();
+        for id in 0..(BATCH_SIZE as u64 * 2 + 10) {
+            let entity = Entity {
+                id: EntityId::from(id),
+                labels: vec![Label::new("Node")],
+                properties: HashMap::new(),
+                vectors: HashMap::new(),
+            };
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        for _ in 0..50 {
+            // Scan to completion
+            let init_data = ManifoldEntitiesInitData::new();
+            let mut rows = 0;
+            loop {
+                let batch = init_data.next_batch("test", &engine, &options, BATCH_SIZE).unwrap();
+                if batch.is_empty() {
+                    break;
+                }
+                rows += batch.len();
             }
+            assert_eq!(rows, BATCH_SIZE * 2 + 10);
+            assert!(init_data.snapshot.lock().unwrap().is_none());
+            drop(init_data);
+
+            // Abandon a scan after one batch, as LIMIT would
+            let init_data = ManifoldEntitiesInitData::new();
+            init_data.next_batch("test", &engine, &options, BATCH_SIZE).unwrap();
+            assert!(init_data.snapshot.lock().unwrap().is_some());
+            drop(init_data);
+        }
+
+        // Concurrent callers of one scan: every row comes back exactly once
+        for _ in 0..5 {
+            let init_data = ManifoldEntitiesInitData::new();
+            let keys = Mutex::new(Vec::new());
+            std::thread::scope(|scope| {
+                for _ in 0..8 {
+                    scope.spawn(|| loop {
+                        let batch = init_data.next_batch("test", &engine, &options, 64).unwrap();
+                        if batch.is_empty() {
+                            break;
+                        }
+                        let mut keys = keys.lock().unwrap();
+                        keys.extend(batch.iter().map(|record| record.key().to_vec()));
+                    });
+                }
+            });
+            let mut keys = keys.into_inner().unwrap();
+            keys.sort();
+            assert_eq!(keys.len(), BATCH_SIZE * 2 + 10);
+            assert!(keys.windows(2).all(|pair| pair[0] != pair[1]), "a row came back twice");
+            assert!(init_data.snapshot.lock().unwrap().is_none());
         }
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_parse_label_mode() {
+        assert_eq!(LabelMode::parse("LIST").unwrap(), LabelMode::List);
+        assert_eq!(LabelMode::parse("first").unwrap(), LabelMode::First);
+        assert_eq!(LabelMode::parse("json").unwrap(), LabelMode::Json);
+        assert_eq!(LabelMode::parse("mask").unwrap(), LabelMode::Mask);
+        assert!(LabelMode::parse("csv").is_err());
+    }
+
+    #[test]
+    fn test_onehot_labels() {
+        let labels = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        assert_eq!(
+            onehot_labels(labels(&["Person", "Company", "Person"])).unwrap(),
+            vec!["Person", "Company"]
+        );
 
-/// Convert a Manifold Value to a JSON string for DuckDB
-fn value_to_json_string(value: &Value) -> String {
-    match value {
-        Value::Null => "null".to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Int(i) => i.to_string(),
-        Value::Float(f) => f.to_string(),
-        Value::String(s) => serde_json::to_string(s).unwrap_or_else(|_| format!("\"{}\"", s)),
-        Value::Bytes(b) => serde_json::to_string(&base64_encode(b)).unwrap_or_else(|_| "\"\"".to_string()),
-        Value::Array(arr) => serde_json::to_string(arr).unwrap_or_else(|_| "[]".to_string()),
-        Value::Vector(v) => serde_json::to_string(v).unwrap_or_else(|_| "[]".to_string()),
-        Value::SparseVector(sv) => serde_json::to_string(sv).unwrap_or_else(|_| "{}".to_string()),
-        Value::MultiVector(mv) => serde_json::to_string(mv).unwrap_or_else(|_| "[]".to_string()),
+        let err = onehot_labels(labels(&["Person", "person"])).unwrap_err().to_string();
+        assert!(err.contains("'Person' and 'person'"), "{}", err);
     }
-}
 
-/// Simple base64 encoding for bytes
-fn base64_encode(bytes: &[u8]) -> String {
-    use std::fmt::Write;
-    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    #[test]
+    fn test_label_mask() {
+        let db = TempDb::new("label_mask");
+        let engine = ManifoldEngine::from(db.open());
+
+        // Only entity 150 has Robot, outside the runs sampling reads
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1..=400u64 {
+            let mut labels = vec![Label::new("Person")];
+            if id % 2 == 0 {
+                labels.push(Label::new("Admin"));
+            }
+            if id == 150 {
+                labels.push(Label::new("Robot"));
+            }
+            let entity = Entity { labels, ..Entity::new(EntityId::from(id)) };
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let options = EntityScanOptions {
+            label_mode: LabelMode::Mask,
+            include_labels: true,
+            schema_sample: 10,
+            ..Default::default()
+        };
+        let (columns, _) = discover_entity_schema(&engine, &options).unwrap();
+        let mask = columns.iter().find(|col| col.name == "labels_mask").unwrap();
+        assert_eq!(mask.column_type, ColumnType::UInteger);
+        let catalog: Vec<_> = mask.fields.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(catalog, ["Admin", "Person", "Robot"]);
 
-    let mut result = String::new();
-    for chunk in bytes.chunks(3) {
-        let b0 = chunk[0] as usize;
-        let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
-        let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
+        let bits = label_bits(&columns);
+        let labels = [Label::new("Robot"), Label::new("Admin"), Label::new("Ghost")];
+        assert_eq!(label_mask(&labels, &bits), 0b101);
+        assert_eq!(label_mask(&[], &bits), 0);
 
-        let _ = write!(result, "{}", ALPHABET[(b0 >> 2) & 0x3F] as char);
-        let _ = write!(result, "{}", ALPHABET[((b0 << 4) | (b1 >> 4)) & 0x3F] as char);
+        // One label more than a mask holds fails the catalog
+        let mut tx = engine.begin_write().unwrap();
+        for id in 0..MAX_MASK_LABELS as u64 {
+            let entity = Entity {
+                labels: vec![Label::new(format!("L{}", id))],
+                ..Entity::new(EntityId::from(1000 + id))
+            };
+            tx.put("nodes", &(1000 + id).to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+        let tx = engine.begin_read().unwrap();
+        let error = label_catalog(&tx, None).unwrap_err().to_string();
+        assert!(error.contains("at most 32 labels, but the database has 35"), "{}", error);
+    }
 
-        if chunk.len() > 1 {
-            let _ = write!(result, "{}", ALPHABET[((b1 << 2) | (b2 >> 6)) & 0x3F] as char);
-        } else {
-            result.push('=');
+    #[test]
+    fn test_geometry_from() {
+        let geometry = GeometryFrom::parse("lat, lon").unwrap();
+        assert_eq!(geometry.lat, "lat");
+        assert_eq!(geometry.lon, "lon");
+        for bad in ["lat", "lat,", "lat,lon,alt"] {
+            assert!(GeometryFrom::parse(bad).is_err(), "{}", bad);
         }
 
-        if chunk.len() > 2 {
-            let _ = write!(result, "{}", ALPHABET[b2 & 0x3F] as char);
-        } else {
-            result.push('=');
+        // Longitude first; integers count as coordinates
+        let berlin = Entity::new(EntityId::from(1u64))
+            .with_property("lat", Value::Float(52.52))
+            .with_property("lon", Value::Int(13));
+        let point = geometry.wkt_point(&berlin.properties);
+        assert_eq!(point.as_deref(), Some("POINT (13 52.52)"));
+
+        // Missing, null, string or non-finite coordinates give NULL
+        let no_lon = Entity::new(EntityId::from(2u64)).with_property("lat", Value::Float(1.0));
+        assert_eq!(geometry.wkt_point(&no_lon.properties), None);
+        for bad in [Value::Null, Value::String("52.5".to_string()), Value::Float(f64::NAN)] {
+            let entity = Entity::new(EntityId::from(3u64))
+                .with_property("lat", bad)
+                .with_property("lon", Value::Float(1.0));
+            assert_eq!(geometry.wkt_point(&entity.properties), None);
         }
     }
-    result
-}
 
-/// Convert a Manifold Value to the appropriate DuckDB string representation
-fn value_to_duckdb_string(value: &Value) -> String {
-    match value {
-        Value::Null => String::new(),
-        Value::Bool(b) => b.to_string(),
-        Value::Int(i) => i.to_string(),
-        Value::Float(f) => f.to_string(),
-        Value::String(s) => s.clone(),
-        // For complex types, use JSON
-        _ => value_to_json_string(value),
+    #[test]
+    fn test_exclude_labels() {
+        let db = TempDb::new("no_labels");
+        let engine = ManifoldEngine::from(db.open());
+
+        let mut properties = HashMap::new();
+        properties.insert("name".to_string(), Value::String("Ada".to_string()));
+        let entity = Entity {
+            id: EntityId::from(1),
+            labels: vec![Label::new("Person")],
+            properties,
+            vectors: HashMap::new(),
+        };
+        let mut tx = engine.begin_write().unwrap();
+        tx.put("nodes", &1u64.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        tx.commit().unwrap();
+
+        let options = EntityScanOptions {
+            include_labels: false,
+            label_onehot: vec!["Person".to_string()],
+            schema_sample: SCHEMA_SAMPLE_SIZE,
+            ..Default::default()
+        };
+        let (columns, column_index) = discover_entity_schema(&engine, &options).unwrap();
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "prop_name", "is_Person"]);
+        assert!(!column_index.contains_key("labels"));
+        assert_eq!(column_index["prop_name"], 1);
+
+        let options = EntityScanOptions { include_label_count: true, ..options };
+        let (columns, column_index) = discover_entity_schema(&engine, &options).unwrap();
+        assert_eq!(column_index["num_labels"], 3);
+        assert_eq!(columns[3].column_type, ColumnType::Integer);
+        assert!(!columns[3].nullable);
+    }
+
+    #[test]
+    fn test_rowid_column() {
+        let db = TempDb::new("rowid");
+        let engine = ManifoldEngine::from(db.open());
+
+        let entity = Entity {
+            id: EntityId::from(1),
+            labels: Vec::new(),
+            properties: HashMap::new(),
+            vectors: HashMap::new(),
+        };
+        let mut tx = engine.begin_write().unwrap();
+        tx.put("nodes", &1u64.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        tx.commit().unwrap();
+
+        let options = EntityScanOptions {
+            include_labels: false,
+            include_rowid: true,
+            safe_mode: true,
+            schema_sample: SCHEMA_SAMPLE_SIZE,
+            ..Default::default()
+        };
+        let (columns, _) = discover_entity_schema(&engine, &options).unwrap();
+        let rowid = columns.iter().find(|c| c.name == "rowid").unwrap();
+        assert_eq!(rowid.column_type, ColumnType::Bigint);
+        assert!(!rowid.nullable);
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "rowid", "_error"]);
+
+        let options = EntityScanOptions {
+            include_labels: false,
+            include_rowid: true,
+            with_row_number: true,
+            schema_sample: SCHEMA_SAMPLE_SIZE,
+            ..Default::default()
+        };
+        let (columns, _) = discover_entity_schema(&engine, &options).unwrap();
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "rowid", "row_number"]);
+    }
+
+    #[test]
+    fn test_coerce_bool_columns() {
+        let db = TempDb::new("bool");
+        let engine = ManifoldEngine::from(db.open());
+
+        let string = |s: &str| Value::String(s.to_string());
+        let rows = [
+            (string("true"), string("1"), Value::Bool(true)),
+            (string("0"), string("bob"), Value::Int(1)),
+            (Value::Bool(false), string("no"), Value::Null),
+        ];
+        let mut tx = engine.begin_write().unwrap();
+        for (i, (active, name, flag)) in rows.into_iter().enumerate() {
+            let id = i as u64 + 1;
+            let entity = Entity::new(EntityId::from(id))
+                .with_property("active", active)
+                .with_property("name", name)
+                .with_property("flag", flag);
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let column_types = |coerce_bool| {
+            let options = EntityScanOptions {
+                include_labels: false,
+                coerce_bool,
+                schema_sample: SCHEMA_SAMPLE_SIZE,
+                ..Default::default()
+            };
+            let (columns, _) = discover_entity_schema(&engine, &options).unwrap();
+            columns.into_iter().map(|c| (c.name, c.column_type)).collect::<Vec<_>>()
+        };
+        let varchar = |name: &str| (name.to_string(), ColumnType::Varchar);
+        assert_eq!(
+            column_types(true),
+            vec![
+                varchar("id"),
+                ("prop_active".to_string(), ColumnType::Boolean),
+                varchar("prop_flag"),
+                varchar("prop_name"),
+            ]
+        );
+        assert_eq!(
+            column_types(false),
+            vec![varchar("id"), varchar("prop_active"), varchar("prop_flag"), varchar("prop_name")]
+        );
+    }
+
+    #[test]
+    fn test_has_prop() {
+        let entity = |props: &[(&str, Value)]| ScanRecord::Decoded {
+            key: Vec::new(),
+            record: Entity {
+                id: EntityId::from(1),
+                labels: Vec::new(),
+                properties: props.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+                vectors: HashMap::new(),
+            },
+        };
+        let options = EntityScanOptions {
+            has_prop: Some("email".to_string()),
+            ..Default::default()
+        };
+
+        assert!(options.keeps(&entity(&[("email", Value::String("a@b.c".to_string()))])));
+        // Present but null still counts
+        assert!(options.keeps(&entity(&[("email", Value::Null)])));
+        assert!(!options.keeps(&entity(&[("name", Value::String("Ada".to_string()))])));
+        assert!(options.keeps(&ScanRecord::Failed {
+            key: Vec::new(),
+            error: "bad".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_label_filter() {
+        let entity = Entity::new(EntityId::from(1))
+            .with_label("Person")
+            .with_label("Employee");
+        let filter = |labels: &[&str], mode| LabelFilter {
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            mode,
+        };
+
+        assert!(filter(&["Person", "Employee"], LabelMatch::All).matches(&entity));
+        assert!(!filter(&["Person", "Company"], LabelMatch::All).matches(&entity));
+        assert!(filter(&["Person", "Company"], LabelMatch::Any).matches(&entity));
+        assert!(!filter(&["Company", "person"], LabelMatch::Any).matches(&entity));
+        // An empty list keeps everything in either mode
+        assert!(filter(&[], LabelMatch::All).matches(&entity));
+        assert!(filter(&[], LabelMatch::Any).matches(&entity));
+
+        assert_eq!(LabelMatch::parse("AND").unwrap(), LabelMatch::All);
+        assert_eq!(LabelMatch::parse("any").unwrap(), LabelMatch::Any);
+        assert!(LabelMatch::parse("xor").is_err());
+    }
+
+    #[test]
+    fn test_parse_ordered_ids() {
+        assert_eq!(parse_ordered_ids("[3, 1, 3]").unwrap(), vec![3, 1, 3]);
+        assert_eq!(parse_ordered_ids(r#"["12", 7]"#).unwrap(), vec![12, 7]);
+        assert_eq!(parse_ordered_ids("[]").unwrap(), Vec::<u64>::new());
+        assert!(parse_ordered_ids("[-1]").is_err());
+        assert!(parse_ordered_ids("[1.5]").is_err());
+        assert!(parse_ordered_ids(r#"["abc"]"#).is_err());
+        assert!(parse_ordered_ids("3").is_err());
+        assert!(parse_ordered_ids("[1,").is_err());
+    }
+
+    #[test]
+    fn test_expose_keys_column() {
+        let db = TempDb::new("keys");
+        let engine = ManifoldEngine::from(db.open());
+
+        let options = EntityScanOptions {
+            include_labels: false,
+            expose_keys: true,
+            include_rowid: true,
+            schema_sample: SCHEMA_SAMPLE_SIZE,
+            ..Default::default()
+        };
+        let (columns, _) = discover_entity_schema(&engine, &options).unwrap();
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "_key", "rowid"]);
+        assert_eq!(columns[1].column_type, ColumnType::Blob);
+    }
+
+    #[test]
+    fn test_parallel_decode_matches_serial() {
+        let db = TempDb::new("decode");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1u64..=40 {
+            let mut entity = Entity::new(EntityId::from(id));
+            if id % 3 == 0 {
+                entity = entity.with_property("email", Value::String(format!("{}@x", id)));
+            }
+            let value = match id % 7 {
+                0 => vec![0xff, 0x01],
+                _ => entity.encode().unwrap(),
+            };
+            tx.put("nodes", &id.to_be_bytes(), &value).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let scan = |decode_threads, has_prop: Option<&str>, batch_size| {
+            let options = EntityScanOptions {
+                decode_threads,
+                has_prop: has_prop.map(str::to_string),
+                safe_mode: true,
+                ..Default::default()
+            };
+            let stats = ScanStats::default();
+            let mut batches = Vec::new();
+            let mut after_key = None;
+            loop {
+                let (batch, next_key) =
+                    scan_entity_batch(&tx, after_key.as_deref(), batch_size, &options, &stats)
+                        .unwrap();
+                if batch.is_empty() {
+                    break;
+                }
+                let keys: Vec<Vec<u8>> = batch.iter().map(|r| r.key().to_vec()).collect();
+                batches.push((keys, next_key.clone()));
+                after_key = next_key;
+            }
+            (batches, stats.rows_scanned.into_inner(), stats.rows_filtered.into_inner())
+        };
+
+        for has_prop in [None, Some("email")] {
+            for batch_size in [1, 4, 7, BATCH_SIZE] {
+                let serial = scan(1, has_prop, batch_size);
+                assert_eq!(scan(4, has_prop, batch_size), serial, "batch size {}", batch_size);
+                assert_eq!(scan(64, has_prop, batch_size), serial, "batch size {}", batch_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_ids() {
+        let db = TempDb::new("sparse");
+        let engine = db.open();
+
+        // Gaps left by deleted entities
+        let present = [1u64, 5, 1_000_000];
+        let mut tx = engine.begin_write().unwrap();
+        for id in present {
+            let entity = Entity::new(EntityId::from(id));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let options = EntityScanOptions::default();
+        let stats = ScanStats::default();
+        let ids = |batch: &[ScanRecord<Entity>]| -> Vec<u64> {
+            batch
+                .iter()
+                .filter_map(|r| match r {
+                    ScanRecord::Decoded { record: entity, .. } => Some(entity.id.as_u64()),
+                    ScanRecord::Failed { .. } => None,
+                })
+                .collect()
+        };
+
+        // Full scans return exactly the present entities, whatever the batch size
+        for batch_size in [1, 2, BATCH_SIZE] {
+            let mut scanned = Vec::new();
+            let mut after_key = None;
+            loop {
+                let (batch, next_key) =
+                    scan_entity_batch(&tx, after_key.as_deref(), batch_size, &options, &stats)
+                        .unwrap();
+                if batch.is_empty() {
+                    break;
+                }
+                scanned.extend(ids(&batch));
+                after_key = next_key;
+            }
+            assert_eq!(scanned, present, "batch size {}", batch_size);
+        }
+
+        // Continuing after a deleted key lands on the next present one
+        for (after, expected) in [(2u64, 5u64), (6, 1_000_000), (999_999, 1_000_000)] {
+            let key = after.to_be_bytes();
+            let (batch, _) = scan_entity_batch(&tx, Some(&key), 1, &options, &stats).unwrap();
+            assert_eq!(ids(&batch), vec![expected]);
+        }
+        let key = 1_000_001u64.to_be_bytes();
+        assert!(scan_entity_batch(&tx, Some(&key), 1, &options, &stats).unwrap().0.is_empty());
+
+        // The sample's middle seek falls in the gap and takes the next present id
+        let sampled: Vec<u64> = sample_table(&tx, "nodes", 1)
+            .unwrap()
+            .iter()
+            .map(|value| Entity::decode(value).unwrap().id.as_u64())
+            .collect();
+        assert_eq!(sampled, vec![1, 1_000_000]);
+    }
+
+    #[test]
+    fn test_sort_key_order() {
+        let key = |id: u64| id.to_be_bytes();
+        let mut keys = [
+            sort_key(&Value::Vector(vec![1.0]), &key(1)),
+            sort_key(&Value::Int(10), &key(2)),
+            sort_key(&Value::Null, &key(3)),
+            sort_key(&Value::Int(-5), &key(4)),
+            sort_key(&Value::Int(10), &key(0)),
+        ];
+        keys.sort();
+
+        let ids: Vec<&[u8]> = keys.iter().map(|k| storage_key(k)).collect();
+        assert_eq!(ids, vec![&key(3)[..], &key(4)[..], &key(0)[..], &key(2)[..], &key(1)[..]]);
+    }
+
+    #[test]
+    fn test_pushdown_report() {
+        use crate::scanner::pushdown::{PushdownEntry, APPLIED_INDEX, APPLIED_SCAN};
+
+        let db = TempDb::new("pushdown");
+        drop(db.open());
+        let db_path = db.db_path().to_string();
+
+        let options = EntityScanOptions {
+            label_filter: Some(LabelFilter {
+                labels: vec!["Person".to_string()],
+                mode: LabelMatch::Any,
+            }),
+            where_json: Some(JsonPathFilter::parse(r#"$.address["zip code"][0] != 1"#).unwrap()),
+            has_prop: Some("email".to_string()),
+            ..EntityScanOptions::default()
+        };
+        let engine = QueryEngine::open(&db_path, true).unwrap();
+        let ((columns, column_index), _) =
+            discover_entity_schema_with_failures(&engine.get().unwrap(), &options).unwrap();
+        let mut bind_data = ManifoldEntitiesBindData {
+            db_path: db_path.clone(),
+            engine,
+            options,
+            columns,
+            column_index,
+            ordered_ids: None,
+            sample_failures: 0,
+        };
+
+        // Filters first, then only the projected columns
+        let projection = HashMap::from([("id".to_string(), 0)]);
+        let report = bind_data.pushdown_report(&projection);
+        assert_eq!(report.function, "manifold_entities_by_labels");
+        assert_eq!(report.db_path, db_path);
+        assert_eq!(
+            report.entries,
+            vec![
+                PushdownEntry::filter(
+                    "labels",
+                    "contains any",
+                    Some(r#"["Person"]"#.to_string()),
+                    APPLIED_SCAN
+                ),
+                PushdownEntry::filter(
+                    r#"$.address["zip code"][0]"#,
+                    "!=",
+                    Some("1".to_string()),
+                    APPLIED_SCAN
+                ),
+                PushdownEntry::filter("email", "has key", None, APPLIED_SCAN),
+                PushdownEntry::projection("id", APPLIED_SCAN),
+            ]
+        );
+
+        // An ordered scan seeks its ids; count(*) projects nothing
+        bind_data.options = EntityScanOptions::default();
+        bind_data.ordered_ids = Some(OrderedIds {
+            ids: vec![3, 1, 3],
+            include_missing: false,
+        });
+        let report = bind_data.pushdown_report(&HashMap::new());
+        assert_eq!(report.function, "manifold_entities_ordered");
+        assert_eq!(
+            report.entries,
+            vec![PushdownEntry::filter("id", "IN", Some("3 ids".to_string()), APPLIED_INDEX)]
+        );
+
+        drop(bind_data);
+    }
+
+    #[test]
+    fn test_mixed_table_scan() {
+        use crate::scanner::mixed_table::{EDGE_TAG, NODE_TAG};
+        use manifoldb_core::types::{Edge, EdgeId, EdgeType};
+
+        let db = TempDb::new("mixed");
+        let engine = ManifoldEngine::from(db.open());
+
+        // Nodes and edges interleaved in one table, plus a value of no known kind
+        let tagged = |tag: u8, record: Vec<u8>| [vec![tag], record].concat();
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1..=4u64 {
+            let entity = Entity {
+                id: EntityId::from(id),
+                labels: vec![Label::new(if id % 2 == 0 { "Even" } else { "Odd" })],
+                properties: HashMap::from([("rank".to_string(), Value::Int(10 - id as i64))]),
+                vectors: HashMap::new(),
+            };
+            let value = tagged(NODE_TAG, entity.encode().unwrap());
+            tx.put("graph", format!("n{}", id).as_bytes(), &value).unwrap();
+
+            let edge = Edge {
+                id: EdgeId::from(id),
+                source: EntityId::from(id),
+                target: EntityId::from(id % 4 + 1),
+                edge_type: EdgeType::new("NEXT"),
+                properties: HashMap::from([("weight".to_string(), Value::Float(0.5))]),
+            };
+            let value = tagged(EDGE_TAG, edge.encode().unwrap());
+            tx.put("graph", format!("e{}", id).as_bytes(), &value).unwrap();
+        }
+        tx.put("graph", b"x", &[0x07, 1, 2]).unwrap();
+        tx.commit().unwrap();
+
+        let options = EntityScanOptions {
+            mixed_table: Some(MixedTable {
+                table: "graph".to_string(),
+                kind: RecordKind::Node,
+            }),
+            schema_sample: SCHEMA_SAMPLE_SIZE,
+            ..EntityScanOptions::default()
+        };
+
+        // Only node properties are discovered
+        let ((columns, _), failures) =
+            discover_entity_schema_with_failures(&engine, &options).unwrap();
+        assert!(columns.iter().any(|col| col.name == "prop_rank"));
+        assert!(!columns.iter().any(|col| col.name == "prop_weight"));
+        assert_eq!(failures, 1);
+
+        let init_data = ManifoldEntitiesInitData::new();
+        let entities = init_data.next_batch("test", &engine, &options, BATCH_SIZE).unwrap();
+        let ids: Vec<u64> = entities
+            .iter()
+            .filter_map(|record| match record {
+                ScanRecord::Decoded { record, .. } => Some(record.id.as_u64()),
+                ScanRecord::Failed { .. } => None,
+            })
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(entities.len(), 4);
+        let stats = &init_data.stats;
+        assert_eq!(stats.rows_scanned.load(Ordering::Relaxed), 9);
+        assert_eq!(stats.rows_filtered.load(Ordering::Relaxed), 4);
+        assert_eq!(stats.decode_failures.load(Ordering::Relaxed), 1);
+
+        // Safe mode reports the unknown tag
+        let safe = EntityScanOptions { safe_mode: true, ..options.clone() };
+        let init_data = ManifoldEntitiesInitData::new();
+        let entities = init_data.next_batch("test", &engine, &safe, BATCH_SIZE).unwrap();
+        assert!(matches!(
+            entities.last(),
+            Some(ScanRecord::Failed { error, .. }) if error == "unknown record tag 0x07"
+        ));
+
+        // Sorting reads edges as nulls, to be dropped on decode; the label
+        // catalog skips them
+        let sorted = sort_entities(&engine, "rank", &options).unwrap();
+        let keys: Vec<Vec<u8>> =
+            sorted.map(|entry| storage_key(&entry.unwrap().0).to_vec()).collect();
+        let nodes: Vec<&[u8]> =
+            keys.iter().map(|key| &key[..]).filter(|key| key[0] == b'n').collect();
+        assert_eq!(nodes, vec![b"n4", b"n3", b"n2", b"n1"]);
+        let tx = engine.begin_read().unwrap();
+        let mixed = options.mixed_table.as_ref();
+        assert_eq!(label_catalog(&tx, mixed).unwrap(), vec!["Even", "Odd"]);
+    }
+
+    #[test]
+    fn test_empty_database() {
+        use crate::scanner::edges::{discover_edge_schema, EdgeScanOptions, ManifoldEdgesInitData};
+        use crate::scanner::engine::open_engine;
+        use redb::ReadOnlyDatabase;
+
+        // Created and closed without a write: the file has no tables at all
+        let db = TempDb::new("empty");
+        drop(db.open());
+        let db_path = db.db_path();
+
+        // Opened read-write, and read-only as on a read-only mount
+        for read_only in [false, true] {
+            let engine = &if read_only {
+                ManifoldEngine::ReadOnly(ReadOnlyDatabase::open(db.path()).unwrap())
+            } else {
+                open_engine(db_path).unwrap()
+            };
+            let options = EntityScanOptions {
+                include_labels: true,
+                schema_sample: SCHEMA_SAMPLE_SIZE,
+                ..EntityScanOptions::default()
+            };
+            let (columns, _) = discover_entity_schema(engine, &options).unwrap();
+            let names: Vec<&str> = columns.iter().map(|col| col.name.as_str()).collect();
+            assert_eq!(names, vec!["id", "labels"]);
+            let init_data = ManifoldEntitiesInitData::new();
+            let entities = init_data.next_batch(db_path, engine, &options, BATCH_SIZE).unwrap();
+            assert!(entities.is_empty());
+
+            let options = EdgeScanOptions {
+                schema_sample: SCHEMA_SAMPLE_SIZE,
+                ..EdgeScanOptions::default()
+            };
+            let (columns, _) = discover_edge_schema(engine, &options).unwrap();
+            assert!(columns.iter().all(|col| col.property.is_none()));
+            let init_data = ManifoldEdgesInitData::new();
+            assert!(init_data.next_batch(db_path, engine, false, BATCH_SIZE).unwrap().is_empty());
+        }
     }
 }
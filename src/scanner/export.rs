@@ -0,0 +1,638 @@
+//! Graph export for ManifoldDB
+//!
+//! Implements a table function returning a whole graph, or a subgraph, as a
+//! single DOT or GraphML document, for handing to Graphviz or Gephi.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT document FROM manifold_export('/path/to/database.redb', 'dot');
+//! COPY (SELECT document FROM manifold_export('/path/to/database.redb', 'graphml',
+//!     labels=['Person'], edge_types=['KNOWS'])) TO 'graph.graphml' (HEADER false, QUOTE '');
+//! ```
+//!
+//! ## Subgraphs
+//! - `labels` keeps entities with at least one of the labels, and only edges
+//!   with both endpoints kept
+//! - `edge_types` keeps edges of the listed types; entities are unaffected
+//! - `case_insensitive=true` matches both lists ignoring case, lowercasing
+//!   each label and type read before comparing (a little extra work per
+//!   record)
+//!
+//! An edge is only exported with both endpoints, so one whose endpoint is
+//! filtered out, doesn't decode or doesn't exist is left out rather than
+//! pointing at a node the document doesn't define.
+//!
+//! ## Document
+//! Graphs are directed. Each entity becomes a node named by its id with a
+//! `labels` attribute (labels joined with `:`), and each edge carries `id` and
+//! `edge_type` attributes; properties become attributes of the same name. In
+//! GraphML every property gets a `<key>`, typed `long`, `double` or `boolean`
+//! when all its values are, `string` otherwise. NULL properties are left out,
+//! and lists are written as JSON.
+//!
+//! ## Cost
+//! The document is built in memory and returned as one value, so memory grows
+//! with the exported graph. The function errors out once more than
+//! `max_elements` entities and edges (default 1000000) would be exported.
+//! Records that fail to decode are skipped and counted in `decode_failures`.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    ffi::CString,
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::values::{sorted_properties, value_to_duckdb_string};
+use super::{get_cached_engine, named_bool, named_i64, named_string_list, string_param};
+
+/// Default bound on the entities plus edges held in memory
+const DEFAULT_MAX_ELEMENTS: usize = 1_000_000;
+
+/// Document format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dot,
+    GraphMl,
+}
+
+impl ExportFormat {
+    /// Parse the `format` argument
+    fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        match text.to_ascii_lowercase().as_str() {
+            "dot" => Ok(ExportFormat::Dot),
+            "graphml" => Ok(ExportFormat::GraphMl),
+            _ => Err(ManifoldScannerError::InvalidParameter(format!(
+                "format must be 'dot' or 'graphml', got '{}'",
+                text
+            ))),
+        }
+    }
+}
+
+/// Which entities and edges to export
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Keep entities with any of these labels (None = all)
+    pub labels: Option<HashSet<String>>,
+    /// Keep edges of these types (None = all)
+    pub edge_types: Option<HashSet<String>>,
+    /// The sets are lowercased; compare lowercased labels and types
+    pub case_insensitive: bool,
+}
+
+impl ExportFilter {
+    /// A label or edge type as the sets hold it
+    fn folded<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.case_insensitive {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+
+    /// Whether the entity has one of the kept labels
+    fn keeps_entity(&self, entity: &Entity) -> bool {
+        self.labels.as_ref().is_none_or(|labels| {
+            entity.labels.iter().any(|label| labels.contains(self.folded(label.as_str()).as_ref()))
+        })
+    }
+
+    /// Whether the edge is of a kept type
+    fn keeps_type(&self, edge: &Edge) -> bool {
+        self.edge_types
+            .as_ref()
+            .is_none_or(|types| types.contains(self.folded(edge.edge_type.as_str()).as_ref()))
+    }
+}
+
+/// Bind data for the export
+#[repr(C)]
+pub struct ManifoldExportBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Document format
+    pub format: ExportFormat,
+    /// Subgraph selection
+    pub filter: ExportFilter,
+    /// Largest graph (entities plus edges) the export will build
+    pub max_elements: usize,
+}
+
+/// Init data for the export - holds the document
+#[repr(C)]
+pub struct ManifoldExportInitData {
+    /// The rendered graph
+    pub document: String,
+    /// Records skipped because they didn't decode
+    pub decode_failures: u64,
+    /// Flag indicating the row has been emitted
+    pub done: AtomicBool,
+}
+
+/// Graph export VTab implementation
+pub struct ManifoldExportVTab;
+
+impl VTab for ManifoldExportVTab {
+    type InitData = ManifoldExportInitData;
+    type BindData = ManifoldExportBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let format = ExportFormat::parse(&string_param(bind, 1, "format")?)?;
+
+        let case_insensitive = named_bool(bind, "case_insensitive")?.unwrap_or(false);
+        let as_set = |list: Option<Vec<String>>| {
+            list.map(|l| {
+                l.into_iter()
+                    .map(|name| if case_insensitive { name.to_lowercase() } else { name })
+                    .collect()
+            })
+        };
+        let filter = ExportFilter {
+            labels: as_set(named_string_list(bind, "labels")?),
+            edge_types: as_set(named_string_list(bind, "edge_types")?),
+            case_insensitive,
+        };
+
+        let max_elements = match named_i64(bind, "max_elements")? {
+            Some(max_elements) if max_elements > 0 => max_elements as usize,
+            Some(max_elements) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "max_elements must be positive, got {}",
+                    max_elements
+                ))
+                .into())
+            }
+            None => DEFAULT_MAX_ELEMENTS,
+        };
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        bind.add_result_column("document", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("decode_failures", LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        bind.set_cardinality(1, true);
+
+        Ok(ManifoldExportBindData {
+            db_path,
+            format,
+            filter,
+            max_elements,
+        })
+    }
+
+    /// Init phase: load the graph and render the document
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldExportBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        let graph = load_graph(&tx, &bind_data.filter, bind_data.max_elements)?;
+        let document = match bind_data.format {
+            ExportFormat::Dot => render_dot(&graph.entities, &graph.edges),
+            ExportFormat::GraphMl => render_graphml(&graph.entities, &graph.edges),
+        };
+
+        Ok(ManifoldExportInitData {
+            document,
+            decode_failures: graph.decode_failures,
+            done: AtomicBool::new(false),
+        })
+    }
+
+    /// Func phase: emit the single document row
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_export".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // format
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        let varchar_list = || LogicalTypeHandle::list(&LogicalTypeId::Varchar.into());
+        Some(vec![
+            ("labels".to_string(), varchar_list()),
+            ("edge_types".to_string(), varchar_list()),
+            (
+                "case_insensitive".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "max_elements".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+        ])
+    }
+}
+
+impl ManifoldExportVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        if init_data.done.swap(true, Ordering::Relaxed) {
+            output.set_len(0);
+            return Ok(());
+        }
+
+        let vector = output.flat_vector(0);
+        vector.insert(0, CString::new(init_data.document.as_str())?);
+        output.flat_vector(1).as_mut_slice::<u64>()[0] = init_data.decode_failures;
+
+        output.set_len(1);
+        Ok(())
+    }
+}
+
+/// The entities and edges to export
+struct Graph {
+    entities: Vec<Entity>,
+    edges: Vec<Edge>,
+    /// Records skipped because they didn't decode
+    decode_failures: u64,
+}
+
+/// Read the entities and edges the filter keeps, failing once there are more
+/// than `max_elements` of them
+fn load_graph(
+    tx: &RedbTransaction,
+    filter: &ExportFilter,
+    max_elements: usize,
+) -> Result<Graph, Box<dyn Error>> {
+    let too_large = || {
+        ManifoldScannerError::InvalidParameter(format!(
+            "graph has more than {} entities and edges; raise max_elements or export a \
+             subgraph with labels or edge_types",
+            max_elements
+        ))
+    };
+
+    // A missing table is an empty one
+    let mut entities = Vec::new();
+    let mut decode_failures = 0;
+    if let Ok(mut cursor) = tx.cursor("nodes") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            match Entity::decode(&value) {
+                Ok(entity) if filter.keeps_entity(&entity) => {
+                    if entities.len() >= max_elements {
+                        return Err(too_large().into());
+                    }
+                    entities.push(entity);
+                }
+                Ok(_) => {}
+                Err(_) => decode_failures += 1,
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    // Edges need both endpoints in the export
+    let kept_ids: HashSet<u64> = entities.iter().map(|entity| entity.id.as_u64()).collect();
+
+    let mut edges = Vec::new();
+    if let Ok(mut cursor) = tx.cursor("edges") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            match Edge::decode(&value) {
+                Ok(edge) => {
+                    let endpoints_kept = kept_ids.contains(&edge.source.as_u64())
+                        && kept_ids.contains(&edge.target.as_u64());
+                    if filter.keeps_type(&edge) && endpoints_kept {
+                        if entities.len() + edges.len() >= max_elements {
+                            return Err(too_large().into());
+                        }
+                        edges.push(edge);
+                    }
+                }
+                Err(_) => decode_failures += 1,
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    Ok(Graph {
+        entities,
+        edges,
+        decode_failures,
+    })
+}
+
+/// An entity's labels as one attribute value
+fn joined_labels(entity: &Entity) -> String {
+    let labels: Vec<&str> = entity.labels.iter().map(|label| label.as_str()).collect();
+    labels.join(":")
+}
+
+/// Render the graph as a DOT digraph
+fn render_dot(entities: &[Entity], edges: &[Edge]) -> String {
+    let mut dot = String::from("digraph manifold {\n");
+
+    for entity in entities {
+        let mut attributes = vec![("labels".to_string(), joined_labels(entity))];
+        attributes.extend(dot_properties(&entity.properties));
+        let _ = writeln!(
+            dot,
+            "  {} [{}];",
+            dot_quote(&entity.id.as_u64().to_string()),
+            dot_attributes(&attributes)
+        );
+    }
+
+    for edge in edges {
+        let mut attributes = vec![
+            ("id".to_string(), edge.id.as_u64().to_string()),
+            ("edge_type".to_string(), edge.edge_type.as_str().to_string()),
+        ];
+        attributes.extend(dot_properties(&edge.properties));
+        let _ = writeln!(
+            dot,
+            "  {} -> {} [{}];",
+            dot_quote(&edge.source.as_u64().to_string()),
+            dot_quote(&edge.target.as_u64().to_string()),
+            dot_attributes(&attributes)
+        );
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Non-NULL properties as attribute name/value pairs, sorted by name
+fn dot_properties(properties: &HashMap<String, Value>) -> Vec<(String, String)> {
+    sorted_properties(properties)
+        .into_iter()
+        .filter(|(_, value)| !matches!(value, Value::Null))
+        .map(|(name, value)| (name.to_string(), value_to_duckdb_string(value, None)))
+        .collect()
+}
+
+/// Render `name="value"` pairs for an attribute list
+fn dot_attributes(attributes: &[(String, String)]) -> String {
+    let pairs: Vec<String> = attributes
+        .iter()
+        .map(|(name, value)| format!("{}={}", dot_name(name), dot_quote(value)))
+        .collect();
+    pairs.join(", ")
+}
+
+/// An attribute name, bare when it is a plain DOT identifier
+fn dot_name(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        dot_quote(name)
+    }
+}
+
+/// Quote a DOT identifier or value
+fn dot_quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// GraphML `attr.type` able to hold every value of a property
+fn graphml_type(values: &[&Value]) -> &'static str {
+    if values.iter().all(|value| matches!(value, Value::Bool(_))) {
+        "boolean"
+    } else if values.iter().all(|value| matches!(value, Value::Int(_))) {
+        "long"
+    } else if values.iter().all(|value| matches!(value, Value::Int(_) | Value::Float(_))) {
+        "double"
+    } else {
+        "string"
+    }
+}
+
+/// Declare a `<key>` per property name, returning name -> key id
+fn graphml_keys<'a>(
+    xml: &mut String,
+    domain: &str,
+    prefix: char,
+    property_maps: impl Iterator<Item = &'a HashMap<String, Value>>,
+) -> HashMap<&'a str, String> {
+    let mut values_by_name: BTreeMap<&str, Vec<&Value>> = BTreeMap::new();
+    for properties in property_maps {
+        for (name, value) in properties {
+            if !matches!(value, Value::Null) {
+                values_by_name.entry(name).or_default().push(value);
+            }
+        }
+    }
+
+    let mut keys = HashMap::new();
+    for (i, (name, values)) in values_by_name.into_iter().enumerate() {
+        let id = format!("{}{}", prefix, i);
+        let _ = writeln!(
+            xml,
+            "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+            id,
+            domain,
+            xml_escape(name),
+            graphml_type(&values)
+        );
+        keys.insert(name, id);
+    }
+    keys
+}
+
+/// Write `<data>` elements for non-NULL properties, sorted by name
+fn graphml_data(
+    xml: &mut String,
+    properties: &HashMap<String, Value>,
+    keys: &HashMap<&str, String>,
+) {
+    for (name, value) in sorted_properties(properties) {
+        if let Some(key) = keys.get(name) {
+            let text = value_to_duckdb_string(value, None);
+            let _ = write!(xml, "<data key=\"{}\">{}</data>", key, xml_escape(&text));
+        }
+    }
+}
+
+/// Render the graph as a GraphML document
+fn render_graphml(entities: &[Entity], edges: &[Edge]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+         \x20 <key id=\"labels\" for=\"node\" attr.name=\"labels\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"edge_type\" for=\"edge\" attr.name=\"edge_type\" attr.type=\"string\"/>\n",
+    );
+    let node_keys = graphml_keys(&mut xml, "node", 'n', entities.iter().map(|e| &e.properties));
+    let edge_keys = graphml_keys(&mut xml, "edge", 'e', edges.iter().map(|e| &e.properties));
+    xml.push_str("  <graph id=\"manifold\" edgedefault=\"directed\">\n");
+
+    for entity in entities {
+        let _ = write!(
+            xml,
+            "    <node id=\"{}\"><data key=\"labels\">{}</data>",
+            entity.id.as_u64(),
+            xml_escape(&joined_labels(entity))
+        );
+        graphml_data(&mut xml, &entity.properties, &node_keys);
+        xml.push_str("</node>\n");
+    }
+
+    for edge in edges {
+        let _ = write!(
+            xml,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"><data key=\"edge_type\">{}</data>",
+            edge.id.as_u64(),
+            edge.source.as_u64(),
+            edge.target.as_u64(),
+            xml_escape(edge.edge_type.as_str())
+        );
+        graphml_data(&mut xml, &edge.properties, &edge_keys);
+        xml.push_str("</edge>\n");
+    }
+
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+/// Escape text for an XML attribute or element
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId, Label};
+
+    #[test]
+    fn test_export_subgraph() {
+        let db = TempDb::new("export");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        let entities = [(1u64, "Person", "Ann \"A\""), (2, "Person", "Bo"), (3, "City", "Oslo")];
+        for (id, label, name) in entities {
+            let entity = Entity::new(EntityId::from(id))
+                .with_label(Label::new(label))
+                .with_property("name", Value::String(name.to_string()));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        let edges = [(10u64, 1u64, 2u64, "KNOWS"), (11, 1, 3, "LIVES_IN")];
+        for (id, source, target, edge_type) in edges {
+            let edge = Edge::new(
+                EdgeId::new(id),
+                EntityId::from(source),
+                EntityId::from(target),
+                EdgeType::new(edge_type),
+            )
+            .with_property("since", Value::Int(2020));
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        // An edge to a missing entity, and a record of each kind that doesn't decode
+        let dangling = Edge::new(EdgeId::new(12), EntityId::from(1), EntityId::from(99), "KNOWS");
+        tx.put("edges", &12u64.to_be_bytes(), &dangling.encode().unwrap()).unwrap();
+        tx.put("nodes", &4u64.to_be_bytes(), b"not an entity").unwrap();
+        tx.put("edges", &13u64.to_be_bytes(), b"not an edge").unwrap();
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let graph = load_graph(&tx, &ExportFilter::default(), 100).unwrap();
+        assert_eq!((graph.entities.len(), graph.edges.len()), (3, 2));
+        assert_eq!(graph.decode_failures, 2);
+        assert!(load_graph(&tx, &ExportFilter::default(), 4).is_err());
+
+        // A label filter drops edges leaving the kept entities
+        let people = ExportFilter {
+            labels: Some(["Person".to_string()].into_iter().collect()),
+            edge_types: None,
+            case_insensitive: false,
+        };
+        let Graph { entities, edges, .. } = load_graph(&tx, &people, 100).unwrap();
+        assert_eq!((entities.len(), edges.len()), (2, 1));
+
+        // Case-insensitive filters hold lowercased names
+        let any_case = ExportFilter {
+            labels: None,
+            edge_types: Some(["lives_in".to_string()].into_iter().collect()),
+            case_insensitive: true,
+        };
+        assert_eq!(load_graph(&tx, &any_case, 100).unwrap().edges.len(), 1);
+        let exact = ExportFilter { case_insensitive: false, ..any_case };
+        assert!(load_graph(&tx, &exact, 100).unwrap().edges.is_empty());
+
+        let dot = render_dot(&entities, &edges);
+        assert!(dot.starts_with("digraph manifold {\n"), "{}", dot);
+        assert!(dot.contains(r#"  "1" [labels="Person", name="Ann \"A\""];"#), "{}", dot);
+        assert!(
+            dot.contains(r#"  "1" -> "2" [id="10", edge_type="KNOWS", since="2020"];"#),
+            "{}",
+            dot
+        );
+
+        let xml = render_graphml(&entities, &edges);
+        assert!(
+            xml.contains(r#"<key id="n0" for="node" attr.name="name" attr.type="string"/>"#),
+            "{}",
+            xml
+        );
+        assert!(xml.contains(r#"attr.name="since" attr.type="long"/>"#), "{}", xml);
+        assert!(xml.contains(r#"<data key="n0">Ann &quot;A&quot;</data>"#), "{}", xml);
+        assert!(xml.contains(r#"<edge id="e10" source="1" target="2">"#), "{}", xml);
+        assert!(xml.ends_with("</graphml>\n"), "{}", xml);
+    }
+}
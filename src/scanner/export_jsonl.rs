@@ -0,0 +1,280 @@
+//! JSON Lines export for ManifoldDB
+//!
+//! Implements a table function writing every entity or edge to a file as one
+//! JSON object per line, for loading into tools that read JSON Lines.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_export_jsonl('/path/to/database.redb', 'nodes', '/out/nodes.jsonl');
+//! SELECT * FROM manifold_export_jsonl('/path/to/database.redb', 'edges', '/out/edges.jsonl');
+//! ```
+//!
+//! ## Lines
+//! Entities are written as `{"id":1,"labels":["Person"],"properties":{...}}`
+//! and edges as `{"id":10,"source":1,"target":2,"edge_type":"KNOWS",
+//! "properties":{...}}`, in id order. Ids are JSON numbers, and properties
+//! keep their types - the same sorted-key objects `properties_as_json` gives.
+//!
+//! ## Result
+//! One row with `records` (BIGINT), the number of lines written, and
+//! `decode_failures` (UBIGINT), the records skipped because they failed to
+//! decode. The file is created or truncated. A file that can't be opened or
+//! written fails the query, and a failed export may leave a partial file
+//! behind.
+//!
+//! ## Streaming
+//! Records are read from one snapshot by a cursor and written through a
+//! buffered writer as they are decoded, so memory stays flat however large
+//! the table. The export runs at init time.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, Entity};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::values::properties_to_json;
+use super::{get_cached_engine, string_param};
+
+/// Bind data for the JSON Lines export
+#[repr(C)]
+pub struct ManifoldExportJsonlBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Table to export ('nodes' or 'edges')
+    pub table: String,
+    /// File to write
+    pub out_path: String,
+}
+
+/// Init data for the JSON Lines export - holds the line count
+#[repr(C)]
+pub struct ManifoldExportJsonlInitData {
+    /// Number of lines written
+    pub records: u64,
+    /// Records skipped because they didn't decode
+    pub decode_failures: u64,
+    /// Flag indicating the row has been emitted
+    pub done: AtomicBool,
+}
+
+/// JSON Lines export VTab implementation
+pub struct ManifoldExportJsonlVTab;
+
+impl VTab for ManifoldExportJsonlVTab {
+    type InitData = ManifoldExportJsonlInitData;
+    type BindData = ManifoldExportJsonlBindData;
+
+    /// Bind phase: validate the table, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let table = string_param(bind, 1, "table")?;
+        let out_path = string_param(bind, 2, "out_path")?;
+
+        if table != "nodes" && table != "edges" {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "table must be 'nodes' or 'edges', got '{}'",
+                table
+            ))
+            .into());
+        }
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        bind.add_result_column("records", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("decode_failures", LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        bind.set_cardinality(1, true);
+
+        Ok(ManifoldExportJsonlBindData {
+            db_path,
+            table,
+            out_path,
+        })
+    }
+
+    /// Init phase: write the file
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldExportJsonlBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        let (records, decode_failures) =
+            export_jsonl(&tx, &bind_data.table, &bind_data.out_path)?;
+        Ok(ManifoldExportJsonlInitData {
+            records,
+            decode_failures,
+            done: AtomicBool::new(false),
+        })
+    }
+
+    /// Func phase: emit the single count row
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_export_jsonl".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // table
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // out_path
+        ])
+    }
+}
+
+impl ManifoldExportJsonlVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        if init_data.done.swap(true, Ordering::Relaxed) {
+            output.set_len(0);
+            return Ok(());
+        }
+
+        let mut vector = output.flat_vector(0);
+        vector.as_mut_slice::<i64>()[0] = init_data.records as i64;
+        output.flat_vector(1).as_mut_slice::<u64>()[0] = init_data.decode_failures;
+
+        output.set_len(1);
+        Ok(())
+    }
+}
+
+/// The JSON Lines line for an entity, without the newline
+fn entity_line(entity: &Entity) -> String {
+    let labels: Vec<&str> = entity.labels.iter().map(|label| label.as_str()).collect();
+    format!(
+        "{{\"id\":{},\"labels\":{},\"properties\":{}}}",
+        entity.id.as_u64(),
+        serde_json::to_string(&labels).unwrap_or_else(|_| "[]".to_string()),
+        properties_to_json(&entity.properties)
+    )
+}
+
+/// The JSON Lines line for an edge, without the newline
+fn edge_line(edge: &Edge) -> String {
+    format!(
+        "{{\"id\":{},\"source\":{},\"target\":{},\"edge_type\":{},\"properties\":{}}}",
+        edge.id.as_u64(),
+        edge.source.as_u64(),
+        edge.target.as_u64(),
+        serde_json::to_string(edge.edge_type.as_str()).unwrap_or_else(|_| "\"\"".to_string()),
+        properties_to_json(&edge.properties)
+    )
+}
+
+/// Write every record of the table to `out_path`, one line each, returning
+/// the number of lines written and of records that didn't decode
+fn export_jsonl(
+    tx: &RedbTransaction,
+    table: &str,
+    out_path: &str,
+) -> Result<(u64, u64), Box<dyn Error>> {
+    let write_error = |e: std::io::Error| ManifoldScannerError::ExportWriteError {
+        path: out_path.to_string(),
+        reason: e.to_string(),
+    };
+    let mut writer = BufWriter::new(File::create(out_path).map_err(write_error)?);
+
+    let mut records = 0;
+    let mut decode_failures = 0;
+    if let Ok(mut cursor) = tx.cursor(table) {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            let line = match table {
+                "nodes" => Entity::decode(&value).ok().map(|entity| entity_line(&entity)),
+                _ => Edge::decode(&value).ok().map(|edge| edge_line(&edge)),
+            };
+            match line {
+                Some(line) => {
+                    writeln!(writer, "{}", line).map_err(write_error)?;
+                    records += 1;
+                }
+                None => decode_failures += 1,
+            }
+            entry = cursor.next()?;
+        }
+    }
+    writer.flush().map_err(write_error)?;
+    Ok((records, decode_failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EntityId, Label, Value};
+
+    #[test]
+    fn test_export_jsonl() {
+        let db = TempDb::new("export_jsonl");
+        let out = db.path().with_extension("jsonl");
+        let out_path = out.to_str().unwrap();
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        let ada = Entity::new(EntityId::from(1))
+            .with_label(Label::new("Person"))
+            .with_property("name", Value::String("Ada \"A\"".to_string()))
+            .with_property("age", Value::Int(36));
+        tx.put("nodes", &1u64.to_be_bytes(), &ada.encode().unwrap()).unwrap();
+        tx.put("nodes", &2u64.to_be_bytes(), b"not an entity").unwrap();
+        let bob = Entity::new(EntityId::from(3));
+        tx.put("nodes", &3u64.to_be_bytes(), &bob.encode().unwrap()).unwrap();
+        let knows = Edge::new(EdgeId::from(10), EntityId::from(1), EntityId::from(3), "KNOWS")
+            .with_property("since", Value::Float(2.5));
+        tx.put("edges", &10u64.to_be_bytes(), &knows.encode().unwrap()).unwrap();
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        assert_eq!(export_jsonl(&tx, "nodes", out_path).unwrap(), (2, 1));
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "{\"id\":1,\"labels\":[\"Person\"],\
+             \"properties\":{\"age\":36,\"name\":\"Ada \\\"A\\\"\"}}\n\
+             {\"id\":3,\"labels\":[],\"properties\":{}}\n"
+        );
+
+        // Exporting again truncates the file
+        assert_eq!(export_jsonl(&tx, "edges", out_path).unwrap(), (1, 0));
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "{\"id\":10,\"source\":1,\"target\":3,\"edge_type\":\"KNOWS\",\
+             \"properties\":{\"since\":2.5}}\n"
+        );
+
+        // An unwritable path is an export error naming the file
+        let missing = std::env::temp_dir().join("manifold_no_such_dir").join("out.jsonl");
+        let error = export_jsonl(&tx, "nodes", missing.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().starts_with("Cannot write export file"), "{}", error);
+
+        let _ = std::fs::remove_file(&out);
+    }
+}
@@ -0,0 +1,328 @@
+//! Feature matrices for ManifoldDB
+//!
+//! Implements a table function returning chosen properties of a known list of
+//! entities as one typed row per entity - a feature table for a model,
+//! without scanning every entity and pivoting.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_feature_matrix('/path/to/database.redb', ['1', '42', '7'],
+//!     ['age', 'score']);
+//! SELECT * FROM manifold_feature_matrix('/path/to/database.redb', ids, props,
+//!     skip_missing=true);
+//! ```
+//!
+//! ## Columns
+//! - `id` - Entity ID (VARCHAR)
+//! - `prop_<name>` - One column per requested property, in the order given
+//!
+//! Rows come back in the order of the ids, each id once. An id with no
+//! entity (or one that fails to decode) gets a row of NULLs, or no row with
+//! `skip_missing=true`.
+//!
+//! ## Types
+//! A property column is BOOLEAN if every non-null value of it among the
+//! requested entities is a boolean, BIGINT if every one is an integer, DOUBLE
+//! if they are all numbers, and VARCHAR otherwise (rendered like
+//! `manifold_entities` property columns, and also when there are no values).
+//! The types come from the rows returned, so every value fits its column.
+//!
+//! ## Cost
+//! Each id is one point lookup on the nodes table; only the requested
+//! properties are kept. The lookups happen at bind time, within one read
+//! transaction, since the rows decide the column types, and the rows are
+//! held in memory until emitted.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{StorageEngine, Transaction};
+
+use crate::schema::{manifold_value_to_column_type, ColumnType, DEFAULT_PROP_PREFIX};
+use super::reach_count::parse_seeds;
+use super::values::{value_to_duckdb_string, value_to_f64};
+use super::{
+    chunk_batch_size, get_cached_engine, insert_str, named_bool, string_list_param, string_param,
+};
+
+/// One requested entity: its id and the requested property values, or None
+/// if it doesn't exist
+type FeatureRow = (u64, Option<Vec<Option<Value>>>);
+
+/// The rows and the type of each property column
+#[derive(Debug, Default)]
+pub struct FeatureMatrix {
+    /// Rows in id order
+    pub rows: Vec<FeatureRow>,
+    /// Column type of each requested property
+    pub types: Vec<ColumnType>,
+}
+
+/// Bind data for feature matrices - the rows, read at bind
+#[repr(C)]
+pub struct ManifoldFeatureMatrixBindData {
+    pub matrix: FeatureMatrix,
+}
+
+/// Init data for feature matrices - tracks emitted rows
+#[repr(C)]
+pub struct ManifoldFeatureMatrixInitData {
+    /// Number of rows already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Feature matrix VTab implementation
+pub struct ManifoldFeatureMatrixVTab;
+
+impl VTab for ManifoldFeatureMatrixVTab {
+    type InitData = ManifoldFeatureMatrixInitData;
+    type BindData = ManifoldFeatureMatrixBindData;
+
+    /// Bind phase: look up the entities, set up typed columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let ids = parse_seeds(&string_list_param(bind, 1, "ids")?)?;
+        let mut props = string_list_param(bind, 2, "props")?;
+        let skip_missing = named_bool(bind, "skip_missing")?.unwrap_or(false);
+
+        let mut seen = HashSet::new();
+        props.retain(|prop| seen.insert(prop.clone()));
+
+        let engine = get_cached_engine(&db_path)?;
+        let tx = engine.begin_read()?;
+        let matrix = read_features(&tx, &ids, &props, skip_missing)?;
+
+        bind.add_result_column("id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        for (prop, column_type) in props.iter().zip(&matrix.types) {
+            let name = format!("{}{}", DEFAULT_PROP_PREFIX, prop);
+            bind.add_result_column(&name, column_type.to_logical_type_handle());
+        }
+        bind.set_cardinality(matrix.rows.len() as u64, true);
+
+        Ok(ManifoldFeatureMatrixBindData { matrix })
+    }
+
+    /// Init phase: start at the first row
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldFeatureMatrixInitData {
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the rows
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_feature_matrix".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        let varchar_list = || LogicalTypeHandle::list(&LogicalTypeId::Varchar.into());
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            varchar_list(),                                  // ids
+            varchar_list(),                                  // props
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![(
+            "skip_missing".to_string(),
+            LogicalTypeHandle::from(LogicalTypeId::Boolean),
+        )])
+    }
+}
+
+impl ManifoldFeatureMatrixVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let matrix = &func.get_bind_data().matrix;
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(matrix.rows.len());
+        let batch = &matrix.rows[start..end];
+
+        let ids = output.flat_vector(0);
+        for (row_idx, (id, _)) in batch.iter().enumerate() {
+            insert_str(&ids, row_idx, &id.to_string());
+        }
+
+        for (prop_idx, column_type) in matrix.types.iter().enumerate() {
+            let mut vector = output.flat_vector(1 + prop_idx);
+            for (row_idx, (_, values)) in batch.iter().enumerate() {
+                let value = values.as_ref().and_then(|values| values[prop_idx].as_ref());
+                let Some(value) = value else {
+                    vector.set_null(row_idx);
+                    continue;
+                };
+                match (column_type, value) {
+                    (ColumnType::Boolean, Value::Bool(b)) => {
+                        vector.as_mut_slice::<bool>()[row_idx] = *b;
+                    }
+                    (ColumnType::Bigint, Value::Int(i)) => {
+                        vector.as_mut_slice::<i64>()[row_idx] = *i;
+                    }
+                    (ColumnType::Double, value) => match value_to_f64(value) {
+                        Some(f) => vector.as_mut_slice::<f64>()[row_idx] = f,
+                        None => vector.set_null(row_idx),
+                    },
+                    (ColumnType::Varchar, value) => {
+                        let text = value_to_duckdb_string(value, None).replace('\0', "");
+                        insert_str(&vector, row_idx, &text);
+                    }
+                    // Column types come from these values, so nothing else occurs
+                    _ => vector.set_null(row_idx),
+                }
+            }
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Look up each id once and keep the requested properties, typing each
+/// column from the values found
+fn read_features(
+    tx: &RedbTransaction,
+    ids: &[u64],
+    props: &[String],
+    skip_missing: bool,
+) -> Result<FeatureMatrix, Box<dyn Error>> {
+    let mut observed: Vec<Vec<ColumnType>> = vec![Vec::new(); props.len()];
+    let mut rows = Vec::with_capacity(ids.len());
+
+    for &id in ids {
+        // Missing table or entity, or a record that won't decode: no values
+        let entity = match tx.get("nodes", &id.to_be_bytes()) {
+            Ok(Some(value)) => Entity::decode(&value).ok(),
+            Ok(None) | Err(_) => None,
+        };
+        let Some(mut entity) = entity else {
+            if !skip_missing {
+                rows.push((id, None));
+            }
+            continue;
+        };
+
+        let values = props
+            .iter()
+            .zip(&mut observed)
+            .map(|(prop, types)| match entity.properties.remove(prop) {
+                None | Some(Value::Null) => None,
+                Some(value) => {
+                    let column_type = manifold_value_to_column_type(&value);
+                    if !types.contains(&column_type) {
+                        types.push(column_type);
+                    }
+                    Some(value)
+                }
+            })
+            .collect();
+        rows.push((id, Some(values)));
+    }
+
+    Ok(FeatureMatrix {
+        rows,
+        types: observed.iter().map(|types| feature_type(types)).collect(),
+    })
+}
+
+/// Column type for a property with these observed value types
+fn feature_type(types: &[ColumnType]) -> ColumnType {
+    let all = |allowed: &[ColumnType]| {
+        !types.is_empty() && types.iter().all(|t| allowed.contains(t))
+    };
+    if all(&[ColumnType::Boolean]) {
+        ColumnType::Boolean
+    } else if all(&[ColumnType::Bigint]) {
+        ColumnType::Bigint
+    } else if all(&[ColumnType::Bigint, ColumnType::Double]) {
+        ColumnType::Double
+    } else {
+        ColumnType::Varchar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::EntityId;
+
+    #[test]
+    fn test_read_features() {
+        let db = TempDb::new("feature_matrix");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        let entities = [
+            Entity::new(EntityId::from(1))
+                .with_property("age", Value::Int(30))
+                .with_property("score", Value::Float(0.5))
+                .with_property("active", Value::Bool(true)),
+            Entity::new(EntityId::from(2))
+                .with_property("age", Value::Int(41))
+                .with_property("score", Value::Int(2))
+                .with_property("name", Value::String("Bo".to_string())),
+        ];
+        for entity in &entities {
+            let key = entity.id.as_u64().to_be_bytes();
+            tx.put("nodes", &key, &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let props: Vec<String> =
+            ["age", "score", "active", "name", "none"].iter().map(|p| p.to_string()).collect();
+        let tx = engine.begin_read().unwrap();
+
+        let matrix = read_features(&tx, &[2, 9, 1], &props, false).unwrap();
+        assert_eq!(
+            matrix.types,
+            vec![
+                ColumnType::Bigint,
+                ColumnType::Double,
+                ColumnType::Boolean,
+                ColumnType::Varchar,
+                ColumnType::Varchar,
+            ]
+        );
+        let ids: Vec<u64> = matrix.rows.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 9, 1]);
+        assert!(matrix.rows[1].1.is_none());
+        let first = matrix.rows[0].1.as_ref().unwrap();
+        assert_eq!(first[0], Some(Value::Int(41)));
+        assert_eq!(first[2], None);
+
+        let matrix = read_features(&tx, &[2, 9, 1], &props, true).unwrap();
+        let ids: Vec<u64> = matrix.rows.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 1]);
+    }
+}
@@ -0,0 +1,368 @@
+//! Property equality lookup for ManifoldDB
+//!
+//! Implements a table function returning the entities whose property equals
+//! a value - the "find the node with this business key" counterpart to
+//! reading entities by id.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_find_by_prop('/path/to/database.redb', 'nodes', 'email',
+//!     'ada@example.com');
+//! ```
+//!
+//! ## Columns
+//! - `id` - Entity ID (VARCHAR)
+//! - `labels` - Entity labels (VARCHAR[])
+//! - `properties` - All properties as a JSON object (VARCHAR)
+//!
+//! Only `'nodes'` can be searched: property indexes hold entity ids. Matches
+//! are returned in id order.
+//!
+//! ## Matching
+//! The value is given as text and matches a property stored as that string,
+//! or as the number or boolean it spells: `'42'` finds `Int(42)` and
+//! `Float(42.0)` as well as `"42"`.
+//!
+//! ## Cost
+//! When the database has a single-property index on the property (for any
+//! label), each candidate value is a range seek into `property_index`, then a
+//! point lookup per match, so the cost depends on the matches, not the
+//! database. Every entity found is checked against the value, so a stale
+//! index entry is never returned. Without an index every entity is read.
+//! Results are computed at init time, within one read
+//! transaction, and held in memory until emitted.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::index::{IndexDef, IndexId, IndexType, PropertyIndexEntry, PropertyIndexScan};
+use manifoldb_core::types::{Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::values::properties_to_json;
+use super::{chunk_batch_size, get_cached_engine, insert_str, string_param};
+
+/// Table holding property index entries
+const PROPERTY_INDEX: &str = "property_index";
+/// Table holding index definitions
+const INDEX_CATALOG: &str = "index_catalog";
+
+/// Bind data for a property lookup
+#[repr(C)]
+pub struct ManifoldFindByPropBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Property to match
+    pub prop: String,
+    /// Values the given text may be stored as
+    pub values: Vec<Value>,
+}
+
+/// Init data for a property lookup - holds the matches
+#[repr(C)]
+pub struct ManifoldFindByPropInitData {
+    /// Matching entities in id order, computed at init
+    pub entities: Vec<Entity>,
+    /// Number of rows already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Property lookup VTab implementation
+pub struct ManifoldFindByPropVTab;
+
+impl VTab for ManifoldFindByPropVTab {
+    type InitData = ManifoldFindByPropInitData;
+    type BindData = ManifoldFindByPropBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let table = string_param(bind, 1, "table")?;
+        let prop = string_param(bind, 2, "prop")?;
+        let value = string_param(bind, 3, "value")?;
+
+        if table != "nodes" {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "table must be 'nodes' (property indexes cover entities), got '{}'",
+                table
+            ))
+            .into());
+        }
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        let varchar = || LogicalTypeHandle::from(LogicalTypeId::Varchar);
+        bind.add_result_column("id", varchar());
+        bind.add_result_column("labels", LogicalTypeHandle::list(&LogicalTypeId::Varchar.into()));
+        bind.add_result_column("properties", varchar());
+
+        Ok(ManifoldFindByPropBindData {
+            db_path,
+            prop,
+            values: lookup_values(&value),
+        })
+    }
+
+    /// Init phase: find the matching entities
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldFindByPropBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        let entities = match find_indexed(&tx, &bind_data.prop, &bind_data.values)? {
+            Some(entities) => entities,
+            None => find_by_scan(&tx, &bind_data.prop, &bind_data.values)?,
+        };
+
+        Ok(ManifoldFindByPropInitData {
+            entities,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the matches
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_find_by_prop".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // table
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // prop
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // value
+        ])
+    }
+}
+
+impl ManifoldFindByPropVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.entities.len());
+        let batch = &init_data.entities[start..end];
+
+        let ids = output.flat_vector(0);
+        let properties = output.flat_vector(2);
+        let mut labels = output.list_vector(1);
+        for (row_idx, entity) in batch.iter().enumerate() {
+            insert_str(&ids, row_idx, &entity.id.as_u64().to_string());
+            insert_str(&properties, row_idx, &properties_to_json(&entity.properties));
+
+            let offset = labels.len();
+            let count = entity.labels.len();
+            let child = labels.child(offset + count);
+            for (i, label) in entity.labels.iter().enumerate() {
+                insert_str(&child, offset + i, label.as_str());
+            }
+            labels.set_entry(row_idx, offset, count);
+            labels.set_len(offset + count);
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// The values a lookup text may be stored as: the string itself, and the
+/// integer, float or boolean it spells
+fn lookup_values(text: &str) -> Vec<Value> {
+    let mut values = vec![Value::String(text.to_string())];
+    if let Ok(int) = text.parse::<i64>() {
+        values.push(Value::Int(int));
+    }
+    if let Ok(float) = text.parse::<f64>() {
+        if float.is_finite() {
+            values.push(Value::Float(float));
+        }
+    }
+    match text {
+        "true" => values.push(Value::Bool(true)),
+        "false" => values.push(Value::Bool(false)),
+        _ => {}
+    }
+    values
+}
+
+/// Whether an entity's property equals one of the values
+fn matches(entity: &Entity, prop: &str, values: &[Value]) -> bool {
+    entity.properties.get(prop).is_some_and(|found| values.contains(found))
+}
+
+/// Labels with a single-property equality index on `prop`
+fn indexed_labels(tx: &RedbTransaction, prop: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    // No catalog table: the database has no indexes
+    let Ok(mut cursor) = tx.cursor(INDEX_CATALOG) else {
+        return Ok(Vec::new());
+    };
+    let mut labels = Vec::new();
+    let mut entry = cursor.seek_first()?;
+    while let Some((_key, value)) = entry {
+        if let Some(def) = IndexDef::decode(&value) {
+            let equality = matches!(def.index_type, IndexType::BTree | IndexType::Hash);
+            if equality && def.columns == [prop] {
+                labels.push(def.table_name);
+            }
+        }
+        entry = cursor.next()?;
+    }
+    Ok(labels)
+}
+
+/// Find matches through the property index, or `None` when no index covers
+/// the property
+fn find_indexed(
+    tx: &RedbTransaction,
+    prop: &str,
+    values: &[Value],
+) -> Result<Option<Vec<Entity>>, Box<dyn Error>> {
+    let labels = indexed_labels(tx, prop)?;
+    if labels.is_empty() {
+        return Ok(None);
+    }
+    let Ok(mut cursor) = tx.cursor(PROPERTY_INDEX) else {
+        return Ok(None);
+    };
+
+    let mut ids = BTreeSet::new();
+    for label in &labels {
+        let index_id = IndexId::from_label_property(label, prop);
+        for value in values {
+            let Some((start, end)) = PropertyIndexScan::exact_value_range(index_id, value) else {
+                continue;
+            };
+            let mut entry = cursor.seek(&start)?;
+            while let Some((key, _value)) = entry {
+                if key.as_slice() >= end.as_slice() {
+                    break;
+                }
+                if let Some(found) = PropertyIndexEntry::decode_key(&key) {
+                    ids.insert(found.entity_id.as_u64());
+                }
+                entry = cursor.next()?;
+            }
+        }
+    }
+
+    let mut entities = Vec::new();
+    for id in ids {
+        // Missing or undecodable entities, and stale entries, are skipped
+        let entity = match tx.get("nodes", &id.to_be_bytes()) {
+            Ok(Some(value)) => Entity::decode(&value).ok(),
+            Ok(None) | Err(_) => None,
+        };
+        if let Some(entity) = entity.filter(|entity| matches(entity, prop, values)) {
+            entities.push(entity);
+        }
+    }
+    Ok(Some(entities))
+}
+
+/// Find matches by reading every entity
+fn find_by_scan(
+    tx: &RedbTransaction,
+    prop: &str,
+    values: &[Value],
+) -> Result<Vec<Entity>, Box<dyn Error>> {
+    let mut entities = Vec::new();
+    let Ok(mut cursor) = tx.cursor("nodes") else {
+        return Ok(entities);
+    };
+    let mut entry = cursor.seek_first()?;
+    while let Some((_key, value)) = entry {
+        if let Ok(entity) = Entity::decode(&value) {
+            if matches(&entity, prop, values) {
+                entities.push(entity);
+            }
+        }
+        entry = cursor.next()?;
+    }
+    Ok(entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::EntityId;
+
+    #[test]
+    fn test_find_by_prop() {
+        let db = TempDb::new("find_by_prop");
+        let engine = db.open();
+
+        let people = [(1u64, "ada", 36), (2, "grace", 85), (3, "alan", 41), (4, "ada", 12)];
+        let mut tx = engine.begin_write().unwrap();
+        for (id, name, age) in people {
+            let entity = Entity::new(EntityId::from(id))
+                .with_label("Person")
+                .with_property("name", Value::String(name.to_string()))
+                .with_property("age", Value::Int(age));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let ids = |entities: Vec<Entity>| -> Vec<u64> {
+            entities.iter().map(|entity| entity.id.as_u64()).collect()
+        };
+
+        // No catalog: not indexed, and the scan finds both matches
+        let tx = engine.begin_read().unwrap();
+        assert!(find_indexed(&tx, "name", &lookup_values("ada")).unwrap().is_none());
+        assert_eq!(ids(find_by_scan(&tx, "name", &lookup_values("ada")).unwrap()), [1, 4]);
+        assert_eq!(ids(find_by_scan(&tx, "age", &lookup_values("41")).unwrap()), [3]);
+        drop(tx);
+
+        // Index name for Person; entity 4's entry is missing and entity 2's
+        // is stale, so only the index decides which entities are read
+        let def = IndexDef::builder("idx_person_name", "Person").column("name").build();
+        let index_id = IndexId::from_label_property("Person", "name");
+        let entry = |name: &str, id: u64| {
+            let value = Value::String(name.to_string());
+            PropertyIndexEntry::new(index_id, value, EntityId::from(id)).encode_key().unwrap()
+        };
+        let mut tx = engine.begin_write().unwrap();
+        tx.put(INDEX_CATALOG, b"idx_person_name", &def.encode()).unwrap();
+        for key in [entry("ada", 1), entry("ada", 2), entry("alan", 3)] {
+            tx.put(PROPERTY_INDEX, &key, &[]).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let found = find_indexed(&tx, "name", &lookup_values("ada")).unwrap().unwrap();
+        assert_eq!(ids(found), [1]);
+        let none = find_indexed(&tx, "name", &lookup_values("bob")).unwrap().unwrap();
+        assert!(none.is_empty());
+        assert!(find_indexed(&tx, "age", &lookup_values("41")).unwrap().is_none());
+    }
+}
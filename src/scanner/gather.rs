@@ -0,0 +1,348 @@
+//! Neighbour feature gathering for ManifoldDB
+//!
+//! Implements a table function returning, for a set of seed entities, each
+//! seed's one-hop neighbours with chosen properties of theirs - the message
+//! passing step of a graph neural network, in one call instead of a join of
+//! an edge scan with an entity scan.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_gather('/path/to/database.redb', ['1', '42'], 'out', 'KNOWS',
+//!     ['age', 'score']);
+//! ```
+//!
+//! ## Columns
+//! - `seed_id` - Seed entity ID (VARCHAR)
+//! - `neighbor_id` - Neighbour entity ID (VARCHAR)
+//! - `prop_<name>` - One VARCHAR column per requested property of the
+//!   neighbour, rendered like `manifold_entities` property columns; NULL when
+//!   the neighbour lacks it or doesn't exist
+//!
+//! Neighbours are targets of the seed's outgoing edges of the type for
+//! `'out'`, sources of its incoming ones for `'in'`, or both for `'both'`.
+//! Each (seed, neighbour) pair is returned once however many edges join
+//! them, in seed order and then neighbour id order. Seeds without
+//! neighbours have no rows.
+//!
+//! ## Cost
+//! One pass over the edges table finds every seed's neighbours, then each
+//! distinct neighbour is read once by a point lookup on the nodes table, so
+//! a neighbour shared by many seeds is decoded once. Only the requested
+//! properties are kept. Results are computed at init time, within one read
+//! transaction, and held in memory until emitted.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use crate::schema::DEFAULT_PROP_PREFIX;
+use super::neighbors::NeighborDirection;
+use super::reach_count::parse_seeds;
+use super::values::value_to_duckdb_string;
+use super::{chunk_batch_size, get_cached_engine, string_list_param, string_param};
+
+/// What to gather
+#[derive(Debug, Clone)]
+pub struct GatherOptions {
+    /// Seeds, in output order
+    pub seeds: Vec<u64>,
+    /// Which edges lead to neighbours
+    pub direction: NeighborDirection,
+    /// Edge type to follow
+    pub edge_type: String,
+    /// Neighbour properties to return, in column order
+    pub props: Vec<String>,
+}
+
+/// The gathered pairs and neighbour properties
+#[derive(Debug, Default)]
+pub struct Gathered {
+    /// (seed, neighbour) in output order
+    pub pairs: Vec<(u64, u64)>,
+    /// Rendered values of the requested properties, by neighbour id; absent
+    /// for neighbours that don't exist or don't decode
+    pub values: HashMap<u64, Vec<Option<String>>>,
+}
+
+/// Bind data for gathering
+#[repr(C)]
+pub struct ManifoldGatherBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Gathering parameters
+    pub options: GatherOptions,
+}
+
+/// Init data for gathering - holds the results
+#[repr(C)]
+pub struct ManifoldGatherInitData {
+    /// Pairs and neighbour values, computed at init
+    pub gathered: Gathered,
+    /// Number of rows already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Gather VTab implementation
+pub struct ManifoldGatherVTab;
+
+impl VTab for ManifoldGatherVTab {
+    type InitData = ManifoldGatherInitData;
+    type BindData = ManifoldGatherBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let seeds = parse_seeds(&string_list_param(bind, 1, "seeds")?)?;
+        let direction = string_param(bind, 2, "direction")?;
+        let edge_type = string_param(bind, 3, "edge_type")?;
+        let mut props = string_list_param(bind, 4, "props")?;
+
+        let direction = NeighborDirection::parse(&direction).map_err(|_| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "direction must be 'out', 'in' or 'both', got '{}'",
+                direction
+            ))
+        })?;
+        let mut seen = HashSet::new();
+        props.retain(|prop| seen.insert(prop.clone()));
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        let varchar = || LogicalTypeHandle::from(LogicalTypeId::Varchar);
+        bind.add_result_column("seed_id", varchar());
+        bind.add_result_column("neighbor_id", varchar());
+        for prop in &props {
+            bind.add_result_column(&format!("{}{}", DEFAULT_PROP_PREFIX, prop), varchar());
+        }
+
+        Ok(ManifoldGatherBindData {
+            db_path,
+            options: GatherOptions {
+                seeds,
+                direction,
+                edge_type,
+                props,
+            },
+        })
+    }
+
+    /// Init phase: find the neighbours and read their properties
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldGatherBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        Ok(ManifoldGatherInitData {
+            gathered: gather(&tx, &bind_data.options)?,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the pairs
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_gather".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        let varchar_list = || LogicalTypeHandle::list(&LogicalTypeId::Varchar.into());
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            varchar_list(),                                  // seeds
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // direction
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // edge_type
+            varchar_list(),                                  // props
+        ])
+    }
+}
+
+impl ManifoldGatherVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+        let gathered = &init_data.gathered;
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(gathered.pairs.len());
+        let batch = &gathered.pairs[start..end];
+
+        let seeds = output.flat_vector(0);
+        let neighbors = output.flat_vector(1);
+        for (row_idx, &(seed, neighbor)) in batch.iter().enumerate() {
+            seeds.insert(row_idx, CString::new(seed.to_string())?);
+            neighbors.insert(row_idx, CString::new(neighbor.to_string())?);
+        }
+
+        for prop_idx in 0..bind_data.options.props.len() {
+            let mut vector = output.flat_vector(2 + prop_idx);
+            for (row_idx, (_, neighbor)) in batch.iter().enumerate() {
+                let values = gathered.values.get(neighbor);
+                match values.and_then(|values| values[prop_idx].as_ref()) {
+                    Some(value) => vector.insert(row_idx, CString::new(value.as_str())?),
+                    None => vector.set_null(row_idx),
+                }
+            }
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Find each seed's distinct neighbours in one pass over the edges
+///
+/// Every seed has an entry, empty when it has no neighbours.
+pub(crate) fn seed_neighbours(
+    tx: &RedbTransaction,
+    seeds: &[u64],
+    direction: NeighborDirection,
+    edge_type: &str,
+) -> Result<HashMap<u64, BTreeSet<u64>>, Box<dyn Error>> {
+    let mut neighbours: HashMap<u64, BTreeSet<u64>> =
+        seeds.iter().map(|&seed| (seed, BTreeSet::new())).collect();
+
+    if let Ok(mut cursor) = tx.cursor("edges") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            if let Ok(edge) = Edge::decode(&value) {
+                if edge.edge_type.as_str() == edge_type {
+                    let (source, target) = (edge.source.as_u64(), edge.target.as_u64());
+                    if direction != NeighborDirection::In {
+                        if let Some(set) = neighbours.get_mut(&source) {
+                            set.insert(target);
+                        }
+                    }
+                    if direction != NeighborDirection::Out {
+                        if let Some(set) = neighbours.get_mut(&target) {
+                            set.insert(source);
+                        }
+                    }
+                }
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    Ok(neighbours)
+}
+
+/// Find each seed's neighbours in one pass over the edges, then read the
+/// requested properties of every distinct neighbour once
+fn gather(tx: &RedbTransaction, options: &GatherOptions) -> Result<Gathered, Box<dyn Error>> {
+    let neighbours =
+        seed_neighbours(tx, &options.seeds, options.direction, &options.edge_type)?;
+
+    let mut gathered = Gathered::default();
+    for seed in &options.seeds {
+        for &neighbour in &neighbours[seed] {
+            gathered.pairs.push((*seed, neighbour));
+            if gathered.values.contains_key(&neighbour) {
+                continue;
+            }
+            // Missing table or entity, or a record that won't decode: no values
+            let entity = match tx.get("nodes", &neighbour.to_be_bytes()) {
+                Ok(Some(value)) => Entity::decode(&value).ok(),
+                Ok(None) | Err(_) => None,
+            };
+            if let Some(entity) = entity {
+                let values = options
+                    .props
+                    .iter()
+                    .map(|prop| match entity.properties.get(prop) {
+                        None | Some(Value::Null) => None,
+                        Some(value) => Some(value_to_duckdb_string(value, None).replace('\0', "")),
+                    })
+                    .collect();
+                gathered.values.insert(neighbour, values);
+            }
+        }
+    }
+
+    Ok(gathered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EntityId};
+
+    #[test]
+    fn test_gather() {
+        let db = TempDb::new("gather");
+        let engine = db.open();
+
+        // 1 -> 2, 1 -> 3 (twice), 4 -> 1, 2 -> 3; 1 -> 5 is another type and
+        // 3 -> 9 leads to a missing entity
+        let mut tx = engine.begin_write().unwrap();
+        for (id, age) in [(1u64, 30), (2, 41), (3, 25), (4, 52), (5, 60)] {
+            let entity = Entity::new(EntityId::from(id)).with_property("age", Value::Int(age));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        let edges = [
+            (10u64, 1u64, 2u64, "KNOWS"),
+            (11, 1, 3, "KNOWS"),
+            (12, 1, 3, "KNOWS"),
+            (13, 4, 1, "KNOWS"),
+            (14, 2, 3, "KNOWS"),
+            (15, 1, 5, "BLOCKS"),
+            (16, 3, 9, "KNOWS"),
+        ];
+        for (id, source, target, edge_type) in edges {
+            let (source, target) = (EntityId::from(source), EntityId::from(target));
+            let edge = Edge::new(EdgeId::from(id), source, target, edge_type);
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let options = |seeds: &[u64], direction| GatherOptions {
+            seeds: seeds.to_vec(),
+            direction,
+            edge_type: "KNOWS".to_string(),
+            props: vec!["age".to_string(), "name".to_string()],
+        };
+        let tx = engine.begin_read().unwrap();
+
+        // Pairs in seed order, each neighbour once
+        let out = gather(&tx, &options(&[3, 1], NeighborDirection::Out)).unwrap();
+        assert_eq!(out.pairs, vec![(3, 9), (1, 2), (1, 3)]);
+        assert_eq!(out.values[&2], vec![Some("41".to_string()), None]);
+        assert!(!out.values.contains_key(&9));
+
+        let both = gather(&tx, &options(&[1], NeighborDirection::Both)).unwrap();
+        assert_eq!(both.pairs, vec![(1, 2), (1, 3), (1, 4)]);
+        let incoming = gather(&tx, &options(&[3, 7], NeighborDirection::In)).unwrap();
+        assert_eq!(incoming.pairs, vec![(3, 1), (3, 2)]);
+    }
+}
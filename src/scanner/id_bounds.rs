@@ -0,0 +1,193 @@
+//! Id bounds lookup for ManifoldDB
+//!
+//! Implements a table function returning the smallest and largest id in the
+//! nodes or edges table without scanning it.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_id_bounds('/path/to/database.redb', 'nodes');
+//! SELECT min_id, max_id FROM manifold_id_bounds('/path/to/database.redb', 'edges');
+//! ```
+//!
+//! ## Strategy
+//! Ids are stored as big-endian u64 keys, so key order is id order: the bounds
+//! are the first and last keys of the table, read with two cursor seeks. This
+//! is O(1) where `MIN(id), MAX(id)` over a scanner is a full scan.
+//!
+//! DuckDB's table function API gives no view of the aggregates above a scan,
+//! so `SELECT MIN(id) FROM manifold_entities(...)` can't be rewritten to this
+//! automatically. Scanner ids are VARCHAR, so those aggregates also compare as
+//! text ('10' < '9'); the UBIGINT columns here compare numerically.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::{get_cached_engine, string_param};
+
+/// Tables whose keys are entity or edge ids
+const ID_TABLES: [&str; 2] = ["nodes", "edges"];
+
+/// Bind data for id bounds - holds the target table
+#[repr(C)]
+pub struct ManifoldIdBoundsBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Table to read bounds from ('nodes' or 'edges')
+    pub table: String,
+}
+
+/// Init data for id bounds - tracks whether the single row was emitted
+#[repr(C)]
+pub struct ManifoldIdBoundsInitData {
+    /// Flag indicating the row has been emitted
+    pub done: AtomicBool,
+}
+
+/// Id bounds VTab implementation
+pub struct ManifoldIdBoundsVTab;
+
+impl VTab for ManifoldIdBoundsVTab {
+    type InitData = ManifoldIdBoundsInitData;
+    type BindData = ManifoldIdBoundsBindData;
+
+    /// Bind phase: validate the table, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let table = string_param(bind, 1, "table")?;
+
+        if !ID_TABLES.contains(&table.as_str()) {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "table must be 'nodes' or 'edges', got '{}'",
+                table
+            ))
+            .into());
+        }
+
+        bind.add_result_column("min_id", LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        bind.add_result_column("max_id", LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        bind.set_cardinality(1, true);
+
+        Ok(ManifoldIdBoundsBindData { db_path, table })
+    }
+
+    /// Init phase: nothing to prepare
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldIdBoundsInitData {
+            done: AtomicBool::new(false),
+        })
+    }
+
+    /// Func phase: emit the single bounds row
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_id_bounds".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // table
+        ])
+    }
+}
+
+impl ManifoldIdBoundsVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+
+        if init_data.done.swap(true, Ordering::Relaxed) {
+            output.set_len(0);
+            return Ok(());
+        }
+
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+        let bounds = id_bounds(&tx, &bind_data.table)?;
+
+        let (min_id, max_id) = bounds.unzip();
+        for (col_idx, bound) in [min_id, max_id].into_iter().enumerate() {
+            let mut vector = output.flat_vector(col_idx);
+            match bound {
+                Some(id) => vector.as_mut_slice::<u64>()[0] = id,
+                None => vector.set_null(0),
+            }
+        }
+
+        output.set_len(1);
+        Ok(())
+    }
+}
+
+/// Smallest and largest id in the table, or None if it is empty or missing
+fn id_bounds(tx: &RedbTransaction, table: &str) -> Result<Option<(u64, u64)>, Box<dyn Error>> {
+    let Ok(mut cursor) = tx.cursor(table) else {
+        // Table doesn't exist yet - no ids
+        return Ok(None);
+    };
+
+    let first = cursor.seek_first()?;
+    let last = cursor.seek_last()?;
+    match (first, last) {
+        (Some((min_key, _)), Some((max_key, _))) => {
+            Ok(Some((key_to_id(&min_key)?, key_to_id(&max_key)?)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Decode a big-endian u64 id key
+fn key_to_id(key: &[u8]) -> Result<u64, ManifoldScannerError> {
+    <[u8; 8]>::try_from(key).map(u64::from_be_bytes).map_err(|_| {
+        ManifoldScannerError::StorageError(format!(
+            "expected an 8-byte id key, got {} bytes",
+            key.len()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+
+    #[test]
+    fn test_id_bounds() {
+        let db = TempDb::new("id_bounds");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in [300u64, 5, 42, 1 << 40] {
+            tx.put("nodes", &id.to_be_bytes(), b"").unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        assert_eq!(id_bounds(&tx, "nodes").unwrap(), Some((5, 1 << 40)));
+        assert_eq!(id_bounds(&tx, "edges").unwrap(), None);
+    }
+}
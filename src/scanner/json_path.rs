@@ -0,0 +1,338 @@
+//! JSON paths over entity properties
+//!
+//! Parses and evaluates the `where_json` filter of `manifold_entities`, a
+//! minimal JSON-path comparison such as `$.address.city = "NYC"`, and the
+//! `json_extract` paths, such as `$.region`, that pick one value out of a
+//! property.
+//!
+//! ## Syntax
+//! `<path> <operator> <literal>`, where:
+//! - the path starts with `$` followed by segments: `.name` for a field
+//!   (letters, digits and `_`), `["any name"]` for a field with other
+//!   characters, or `[n]` for an array element. The first segment names the
+//!   property, the rest walk into its value
+//! - the operator is `=` (or `==`) or `!=`
+//! - the literal is JSON: a double-quoted string, a number, `true`, `false`
+//!   or `null` (arrays and objects compare structurally)
+//!
+//! A `json_extract` path has the same segments, but `$` is the property's
+//! value rather than the entity, and there is no operator.
+//!
+//! ## Nested Values
+//! A property can hold a nested object in two forms, and the path walks into
+//! either: an array of `[key, value]` pairs (see `value_as_object`), or a
+//! string containing a JSON object or array. Numbers compare by value, so
+//! `1` matches a stored `1.0`.
+//!
+//! ## Missing Paths
+//! When the path doesn't resolve (no such property, field or index), the
+//! predicate is false for both operators, as a comparison with NULL is in SQL.
+
+use std::collections::HashMap;
+
+use manifoldb_core::types::Value;
+use serde_json::Value as Json;
+
+use super::values::base64_encode;
+use crate::error::ManifoldScannerError;
+use crate::schema::value_as_object;
+
+/// One step of a path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+/// Comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+}
+
+/// A parsed `json_extract` path, relative to one property's value
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    path: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// Parse a path such as `$.region`
+    pub fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        let invalid = invalid_path("json_extract", text);
+        let (path, rest) = parse_segments(text, &invalid)?;
+        if !rest.trim().is_empty() {
+            return Err(invalid("unexpected text after the path"));
+        }
+        Ok(Self { path })
+    }
+
+    /// The value at the path, or None if it doesn't resolve
+    pub fn extract(&self, value: &Value) -> Option<Json> {
+        resolve(value_to_json(value), &self.path)
+    }
+}
+
+/// A parsed `where_json` predicate
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPathFilter {
+    /// Property the path starts from
+    property: String,
+    /// Segments after the property
+    path: Vec<Segment>,
+    operator: Operator,
+    literal: Json,
+}
+
+impl JsonPathFilter {
+    /// Parse a predicate such as `$.address.city = "NYC"`
+    pub fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        let invalid = invalid_path("where_json", text);
+        let (mut segments, rest) = parse_segments(text, &invalid)?;
+
+        let property = match segments.first() {
+            Some(Segment::Field(name)) => name.clone(),
+            Some(Segment::Index(_)) => {
+                return Err(invalid("the path must start with a property name"))
+            }
+            None => return Err(invalid("the path must name a property")),
+        };
+
+        let rest = rest.trim_start();
+        let (operator, rest) = if let Some(rest) = rest.strip_prefix("!=") {
+            (Operator::Ne, rest)
+        } else if let Some(rest) = rest.strip_prefix("==") {
+            (Operator::Eq, rest)
+        } else if let Some(rest) = rest.strip_prefix('=') {
+            (Operator::Eq, rest)
+        } else {
+            return Err(invalid("expected '=' or '!=' after the path"));
+        };
+
+        let literal = serde_json::from_str(rest.trim())
+            .map_err(|_| invalid("the value must be JSON, e.g. \"text\", 42 or true"))?;
+
+        Ok(Self {
+            property,
+            path: segments.split_off(1),
+            operator,
+            literal,
+        })
+    }
+
+    /// The predicate's path, operator and JSON literal, for diagnostics
+    pub fn describe(&self) -> (String, &'static str, String) {
+        let bare = |name: &str| {
+            !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        };
+        let mut path = String::from("$");
+        let segments = std::iter::once(Segment::Field(self.property.clone()));
+        for segment in segments.chain(self.path.iter().cloned()) {
+            match segment {
+                Segment::Field(name) if bare(&name) => {
+                    path.push('.');
+                    path.push_str(&name);
+                }
+                Segment::Field(name) => path.push_str(&format!("[{}]", Json::String(name))),
+                Segment::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        let operator = match self.operator {
+            Operator::Eq => "=",
+            Operator::Ne => "!=",
+        };
+        (path, operator, self.literal.to_string())
+    }
+
+    /// Whether an entity's properties satisfy the predicate
+    pub fn matches(&self, properties: &HashMap<String, Value>) -> bool {
+        let Some(value) = properties.get(&self.property) else {
+            return false;
+        };
+        let Some(found) = resolve(value_to_json(value), &self.path) else {
+            return false;
+        };
+
+        let equal = json_equal(&found, &self.literal);
+        match self.operator {
+            Operator::Eq => equal,
+            Operator::Ne => !equal,
+        }
+    }
+}
+
+/// Error builder for a parameter holding a malformed path
+fn invalid_path<'a>(param: &'a str, text: &'a str) -> impl Fn(&str) -> ManifoldScannerError + 'a {
+    move |reason| {
+        ManifoldScannerError::InvalidParameter(format!(
+            "invalid {} '{}': {}",
+            param, text, reason
+        ))
+    }
+}
+
+/// Parse `$` and the segments after it, returning them and the rest of the text
+fn parse_segments<'t>(
+    text: &'t str,
+    invalid: &impl Fn(&str) -> ManifoldScannerError,
+) -> Result<(Vec<Segment>, &'t str), ManifoldScannerError> {
+    let mut rest = text
+        .trim_start()
+        .strip_prefix('$')
+        .ok_or_else(|| invalid("the path must start with '$'"))?;
+
+    let mut segments = Vec::new();
+    loop {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if end == 0 {
+                return Err(invalid("expected a field name after '.'"));
+            }
+            segments.push(Segment::Field(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let after = after.trim_start();
+            let (segment, consumed) = if after.starts_with('"') {
+                // Quoted names may contain ']', so read exactly one JSON string
+                let mut strings = serde_json::Deserializer::from_str(after).into_iter::<String>();
+                match strings.next() {
+                    Some(Ok(name)) => (Segment::Field(name), strings.byte_offset()),
+                    _ => return Err(invalid("unterminated field name in '[...]'")),
+                }
+            } else {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed '['"))?;
+                let index = after[..end].trim().parse::<usize>().map_err(|_| {
+                    invalid("'[...]' must hold an array index or a quoted field name")
+                })?;
+                (Segment::Index(index), end)
+            };
+            rest = after[consumed..]
+                .trim_start()
+                .strip_prefix(']')
+                .ok_or_else(|| invalid("unclosed '['"))?;
+            segments.push(segment);
+        } else {
+            break;
+        }
+    }
+
+    Ok((segments, rest))
+}
+
+/// Walk the segments into a value, parsing JSON strings on the way
+fn resolve(mut current: Json, path: &[Segment]) -> Option<Json> {
+    for segment in path {
+        if let Json::String(text) = &current {
+            current = serde_json::from_str(text).ok()?;
+        }
+        current = match (segment, current) {
+            (Segment::Field(name), Json::Object(mut fields)) => fields.remove(name)?,
+            (Segment::Index(index), Json::Array(mut items)) if *index < items.len() => {
+                items.swap_remove(*index)
+            }
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Compare two JSON values, treating numbers by value
+fn json_equal(a: &Json, b: &Json) -> bool {
+    match (a, b) {
+        (Json::Number(x), Json::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+/// Convert a property value to JSON, reading pair arrays as objects
+fn value_to_json(value: &Value) -> Json {
+    if let Some(fields) = value_as_object(value) {
+        return Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value_to_json(value)))
+                .collect(),
+        );
+    }
+
+    match value {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Int(i) => Json::from(*i),
+        Value::Float(f) => serde_json::Number::from_f64(*f).map_or(Json::Null, Json::Number),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Bytes(b) => Json::String(base64_encode(b)),
+        Value::Array(items) => Json::Array(items.iter().map(value_to_json).collect()),
+        Value::Vector(v) => Json::from(v.clone()),
+        Value::SparseVector(sv) => Json::Array(
+            sv.iter()
+                .map(|&(i, v)| Json::Array(vec![Json::from(i), Json::from(v)]))
+                .collect(),
+        ),
+        Value::MultiVector(mv) => Json::from(mv.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(key: &str, value: Value) -> Value {
+        Value::Array(vec![Value::String(key.to_string()), value])
+    }
+
+    #[test]
+    fn test_json_path_filter() {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "address".to_string(),
+            Value::Array(vec![
+                pair("city", Value::String("NYC".to_string())),
+                pair("zip", Value::Int(10001)),
+            ]),
+        );
+        properties.insert(
+            "meta".to_string(),
+            Value::String(r#"{"tags": ["a", "b"], "odd key]": 1.0}"#.to_string()),
+        );
+
+        let matches = |text: &str| JsonPathFilter::parse(text).unwrap().matches(&properties);
+        assert!(matches(r#"$.address.city = "NYC""#));
+        assert!(matches(r#"$.address.city == "NYC""#));
+        assert!(!matches(r#"$.address.city = "LA""#));
+        assert!(matches(r#"$.address.city != "LA""#));
+        assert!(matches("$.address.zip = 10001.0"));
+        assert!(matches(r#"$.meta.tags[1] = "b""#));
+        assert!(matches(r#"$["meta"]["odd key]"] = 1"#));
+
+        // Missing paths match neither operator
+        assert!(!matches(r#"$.address.state = "NY""#));
+        assert!(!matches(r#"$.address.state != "NY""#));
+        assert!(!matches("$.meta.tags[5] != 0"));
+        assert!(!matches("$.missing = null"));
+
+        let invalid = ["address.city = 1", "$ = 1", "$[0] = 1", "$.a.", "$.a ~ 1", "$.a = NYC"];
+        for invalid in invalid {
+            assert!(JsonPathFilter::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_json_path_extract() {
+        let meta = Value::String(r#"{"region": "eu", "zones": [1, 2]}"#.to_string());
+        let extract = |text: &str| JsonPath::parse(text).unwrap().extract(&meta);
+        assert_eq!(extract("$.region"), Some(Json::from("eu")));
+        assert_eq!(extract("$.zones[1]"), Some(Json::from(2)));
+        assert_eq!(extract("$.missing"), None);
+
+        let address = Value::Array(vec![pair("city", Value::String("NYC".to_string()))]);
+        let city = JsonPath::parse("$.city").unwrap().extract(&address);
+        assert_eq!(city, Some(Json::from("NYC")));
+
+        assert!(JsonPath::parse("region").is_err());
+        assert!(JsonPath::parse("$.region = 1").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Label bit mapping for ManifoldDB
+//!
+//! Implements a table function returning which label each bit of a
+//! `manifold_entities(..., label_mode='mask')` `labels_mask` column stands
+//! for.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_label_bits('/path/to/database.redb');
+//! SELECT b.label, count(*)
+//! FROM manifold_entities('/path/to/database.redb', label_mode='mask') e
+//! JOIN manifold_label_bits('/path/to/database.redb') b ON e.labels_mask & b.mask != 0
+//! GROUP BY b.label;
+//! ```
+//!
+//! ## Columns
+//! - `bit` - Bit position, from 0
+//! - `label` - The label
+//! - `mask` - `1 << bit`, to test a `labels_mask` with `&`
+//!
+//! ## Stability
+//! Bits are assigned to the database's distinct labels in sorted order, the
+//! same catalog the entity scan builds, so the two agree as long as no label
+//! is added or removed between them. A new label shifts the bits of every
+//! label sorting after it: masks are only comparable with a mapping read from
+//! the same state of the database, and shouldn't be stored on their own.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use manifoldb_storage::StorageEngine;
+
+use super::entities::label_catalog;
+use super::{get_cached_engine, insert_str, string_param};
+
+/// Bind data for the label bit mapping
+#[repr(C)]
+pub struct ManifoldLabelBitsBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+}
+
+/// Init data for the label bit mapping - holds the catalog
+#[repr(C)]
+pub struct ManifoldLabelBitsInitData {
+    /// Labels in bit order
+    pub labels: Vec<String>,
+    /// Flag indicating the rows have been emitted
+    pub done: AtomicBool,
+}
+
+/// Label bit mapping VTab implementation
+pub struct ManifoldLabelBitsVTab;
+
+impl VTab for ManifoldLabelBitsVTab {
+    type InitData = ManifoldLabelBitsInitData;
+    type BindData = ManifoldLabelBitsBindData;
+
+    /// Bind phase: set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+
+        bind.add_result_column("bit", LogicalTypeHandle::from(LogicalTypeId::Integer));
+        bind.add_result_column("label", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("mask", LogicalTypeHandle::from(LogicalTypeId::UInteger));
+
+        Ok(ManifoldLabelBitsBindData { db_path })
+    }
+
+    /// Init phase: build the catalog
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldLabelBitsBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        Ok(ManifoldLabelBitsInitData {
+            labels: label_catalog(&tx, None)?,
+            done: AtomicBool::new(false),
+        })
+    }
+
+    /// Func phase: emit one row per bit
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_label_bits".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+        ])
+    }
+}
+
+impl ManifoldLabelBitsVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        // At most MAX_MASK_LABELS rows, so one chunk holds them all
+        if init_data.done.swap(true, Ordering::Relaxed) {
+            output.set_len(0);
+            return Ok(());
+        }
+
+        let mut bits = output.flat_vector(0);
+        let labels = output.flat_vector(1);
+        let mut masks = output.flat_vector(2);
+        for (bit, label) in init_data.labels.iter().enumerate() {
+            bits.as_mut_slice::<i32>()[bit] = bit as i32;
+            insert_str(&labels, bit, label);
+            masks.as_mut_slice::<u32>()[bit] = 1 << bit;
+        }
+
+        output.set_len(init_data.labels.len());
+        Ok(())
+    }
+}
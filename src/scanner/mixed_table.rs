@@ -0,0 +1,216 @@
+//! Mixed node and edge tables
+//!
+//! ManifoldDB keeps nodes and edges in tables of their own, but some
+//! databases store both in a single table, each value led by a tag byte
+//! saying which kind of record follows:
+//! - `0x01` - a node, encoded as in `nodes`
+//! - `0x02` - an edge, encoded as in `edges`
+//!
+//! (the same bytes ManifoldDB uses to prefix entity and edge keys). The
+//! entity and edge scanners read such a table when given its name and the
+//! kind of record to return:
+//! ```sql
+//! SELECT * FROM manifold_entities('/path/to/database.redb',
+//!     table => 'graph', record_filter => 'node');
+//! SELECT * FROM manifold_edges('/path/to/database.redb',
+//!     table => 'graph', record_filter => 'edge');
+//! ```
+//!
+//! Records of the other kind are skipped, counted in `rows_filtered` of
+//! `manifold_last_scan_stats`, and schema discovery samples only records of
+//! the requested kind. A value with any other tag, or none, is undecodable:
+//! skipped, or an error row in safe mode.
+//!
+//! Keys in a mixed table can't be plain ids, as a node and an edge may share
+//! one, and ManifoldDB's index tables don't cover it. So options that look
+//! records up by id or read an index are refused with `table`
+//! (`manifold_entities_ordered`, `neighbor_agg`, `source_props`,
+//! `target_props`), and `manifold_edges_of_type` filters a full scan.
+
+use duckdb::vtab::BindInfo;
+
+use crate::error::ManifoldScannerError;
+
+/// Tag of a node record
+pub const NODE_TAG: u8 = 0x01;
+/// Tag of an edge record
+pub const EDGE_TAG: u8 = 0x02;
+
+/// The kind of record a scan returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Node,
+    Edge,
+}
+
+impl RecordKind {
+    /// Parse a `record_filter` value
+    fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        match text.to_lowercase().as_str() {
+            "node" => Ok(RecordKind::Node),
+            "edge" => Ok(RecordKind::Edge),
+            _ => Err(ManifoldScannerError::InvalidParameter(format!(
+                "record_filter must be 'node' or 'edge', got '{}'",
+                text
+            ))),
+        }
+    }
+
+    /// The tag byte leading this kind's values
+    fn tag(self) -> u8 {
+        match self {
+            RecordKind::Node => NODE_TAG,
+            RecordKind::Edge => EDGE_TAG,
+        }
+    }
+
+    /// Name as given to `record_filter`
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordKind::Node => "node",
+            RecordKind::Edge => "edge",
+        }
+    }
+
+    /// The scanner that returns this kind
+    fn scanner(self) -> &'static str {
+        match self {
+            RecordKind::Node => "manifold_entities",
+            RecordKind::Edge => "manifold_edges",
+        }
+    }
+}
+
+/// A stored value of a mixed table, as one scan sees it
+#[derive(Debug, PartialEq)]
+pub enum Tagged<'a> {
+    /// A record of the scanned kind, without its tag
+    Record(&'a [u8]),
+    /// A record of the other kind
+    OtherKind,
+    /// A value with an unknown tag, or empty
+    Unknown(Option<u8>),
+}
+
+/// A table holding both kinds of record, and the kind a scan returns
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedTable {
+    pub table: String,
+    pub kind: RecordKind,
+}
+
+impl MixedTable {
+    /// Read `table` and `record_filter` for a scanner returning `kind`; None
+    /// when neither is given
+    pub fn from_bind(
+        bind: &BindInfo,
+        kind: RecordKind,
+    ) -> Result<Option<Self>, ManifoldScannerError> {
+        Self::parse(
+            bind.get_named_parameter("table").map(|v| v.to_string()),
+            bind.get_named_parameter("record_filter").map(|v| v.to_string()),
+            kind,
+        )
+    }
+
+    fn parse(
+        table: Option<String>,
+        record_filter: Option<String>,
+        kind: RecordKind,
+    ) -> Result<Option<Self>, ManifoldScannerError> {
+        let (table, record_filter) = match (table, record_filter) {
+            (None, None) => return Ok(None),
+            (Some(table), Some(record_filter)) => (table, record_filter),
+            _ => {
+                return Err(ManifoldScannerError::InvalidParameter(
+                    "table and record_filter must be given together".to_string(),
+                ))
+            }
+        };
+        if table.is_empty() {
+            return Err(ManifoldScannerError::InvalidParameter(
+                "table must not be empty".to_string(),
+            ));
+        }
+        let requested = RecordKind::parse(&record_filter)?;
+        if requested != kind {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "{} returns {} records; use {} for record_filter => '{}'",
+                kind.scanner(),
+                kind.name(),
+                requested.scanner(),
+                requested.name()
+            )));
+        }
+        Ok(Some(Self { table, kind }))
+    }
+
+    /// Read a stored value's tag
+    pub fn untag<'a>(&self, value: &'a [u8]) -> Tagged<'a> {
+        match value.split_first() {
+            Some((&tag, record)) if tag == self.kind.tag() => Tagged::Record(record),
+            Some((&tag, _)) if tag == NODE_TAG || tag == EDGE_TAG => Tagged::OtherKind,
+            Some((&tag, _)) => Tagged::Unknown(Some(tag)),
+            None => Tagged::Unknown(None),
+        }
+    }
+
+    /// Refuse an option that looks records up by id or reads an index
+    pub fn refuse(&self, option: &str) -> ManifoldScannerError {
+        ManifoldScannerError::InvalidParameter(format!(
+            "{} can't be used with table => '{}': it needs records keyed by id or an index",
+            option, self.table
+        ))
+    }
+}
+
+/// The table a scan reads: the mixed table if given, else `default`
+pub fn scan_table<'a>(mixed: Option<&'a MixedTable>, default: &'a str) -> &'a str {
+    mixed.map_or(default, |mixed| &mixed.table)
+}
+
+/// The record in a value read from `mixed` if given, None when the value
+/// holds no record of the scanned kind
+pub fn record_bytes<'a>(mixed: Option<&MixedTable>, value: &'a [u8]) -> Option<&'a [u8]> {
+    match mixed.map(|mixed| mixed.untag(value)) {
+        None => Some(value),
+        Some(Tagged::Record(record)) => Some(record),
+        Some(_) => None,
+    }
+}
+
+/// Error for a value whose tag is neither kind's
+pub fn unknown_tag_error(tag: Option<u8>) -> String {
+    match tag {
+        Some(tag) => format!("unknown record tag 0x{:02x}", tag),
+        None => "empty record, no tag".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_table() {
+        let parse = |table: Option<&str>, filter: Option<&str>, kind| {
+            MixedTable::parse(table.map(String::from), filter.map(String::from), kind)
+        };
+        assert_eq!(parse(None, None, RecordKind::Node).unwrap(), None);
+        let nodes = parse(Some("graph"), Some("Node"), RecordKind::Node).unwrap().unwrap();
+        assert_eq!(nodes, MixedTable { table: "graph".to_string(), kind: RecordKind::Node });
+
+        let unpaired = [(Some("graph"), None), (None, Some("node")), (Some(""), Some("node"))];
+        for (table, filter) in unpaired {
+            assert!(parse(table, filter, RecordKind::Node).is_err());
+        }
+        assert!(parse(Some("graph"), Some("vertex"), RecordKind::Node).is_err());
+        let error = parse(Some("graph"), Some("edge"), RecordKind::Node).unwrap_err().to_string();
+        assert!(error.contains("use manifold_edges"), "{}", error);
+
+        assert_eq!(nodes.untag(&[NODE_TAG, 7, 8]), Tagged::Record(&[7, 8]));
+        assert_eq!(nodes.untag(&[EDGE_TAG, 7]), Tagged::OtherKind);
+        assert_eq!(nodes.untag(&[0x03, 7]), Tagged::Unknown(Some(0x03)));
+        assert_eq!(nodes.untag(&[]), Tagged::Unknown(None));
+    }
+}
@@ -3,40 +3,1581 @@
 //! Each scanner implements the DuckDB VTab trait to expose
 //! Manifold data as queryable tables.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::sync::{Arc, Mutex, OnceLock};
-use manifoldb_storage::backends::RedbEngine;
-use manifoldb_storage::StorageEngine;
+use std::ffi::CString;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use duckdb::core::{DataChunkHandle, FlatVector, Inserter};
+use duckdb::vtab::BindInfo;
+use manifoldb_core::encoding::Decoder;
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, CursorResult, StorageEngine, Transaction};
 
+use manifoldb_core::types::Value;
+
+use crate::error::ManifoldScannerError;
+use crate::schema::{value_as_object, ColumnType, DiscoveredColumn, EXTRA_PROPS_COLUMN};
+use decimal::{populate_decimal, populate_decimal_text, CoercionPolicy};
+use engine::{open_engine, ManifoldEngine};
+use json_path::JsonPath;
+use mixed_table::{MixedTable, Tagged};
+use timestamp::timestamp_cell;
+use values::{
+    pairs_to_json, value_to_bool, value_to_duckdb_string, value_to_json_string, value_type_name,
+};
+
+pub mod changes;
+pub mod collections;
+pub mod config;
+pub mod dangling_edges;
+pub mod decimal;
 pub mod entities;
 pub mod edges;
+pub mod encode_vector;
+pub mod engine;
+pub mod export;
+pub mod export_jsonl;
+pub mod feature_matrix;
+pub mod find_by_prop;
+pub mod gather;
+pub mod edge_weights;
+pub mod id_bounds;
+pub mod json_path;
+pub mod label_bits;
+pub mod mixed_table;
+pub mod neighbors;
+pub mod neighbors_aggregate;
+pub mod pushdown;
+pub mod new_entities;
+pub mod reach_count;
+pub mod refresh;
+pub mod sort;
+pub mod stats;
+pub mod storage_stats;
+#[cfg(test)]
+pub mod test_db;
+pub mod timestamp;
+pub mod traverse;
+pub mod triangles;
+pub mod values;
+pub mod vector_search;
+pub mod verify;
+pub mod warmup;
 
-/// Batch size for reading from Manifold
+/// Batch size for reading from Manifold, unless `MANIFOLD_BATCH_SIZE` is set
 /// Chosen to balance memory usage and throughput
 pub const BATCH_SIZE: usize = 1024;
 
-/// Maximum entities to sample for schema discovery
-/// Balance between accuracy and startup time
+/// Largest batch a DuckDB output chunk can hold (its vector size)
+const MAX_BATCH_SIZE: usize = 2048;
+
+/// Default records sampled from each of the start, middle and end of a table
+/// for schema discovery - balance between accuracy and startup time. Overridden
+/// by `MANIFOLD_SCHEMA_SAMPLE`, and per query by `schema_sample`
 pub const SCHEMA_SAMPLE_SIZE: usize = 100;
 
-/// Global engine cache - maps db_path to Arc<RedbEngine>
+/// Most decimals `float_precision` accepts - a DOUBLE holds no more than 17
+/// significant digits, so further decimals only pad the string
+const MAX_FLOAT_PRECISION: usize = 17;
+
+static BATCH_SIZE_OVERRIDE: OnceLock<usize> = OnceLock::new();
+static SCHEMA_SAMPLE_OVERRIDE: OnceLock<usize> = OnceLock::new();
+
+/// Rows per batch: `MANIFOLD_BATCH_SIZE` if set and valid, else `BATCH_SIZE`
+///
+/// Read from the environment on first use, so it is fixed for the process.
+pub fn batch_size() -> usize {
+    *BATCH_SIZE_OVERRIDE.get_or_init(|| {
+        let raw = std::env::var("MANIFOLD_BATCH_SIZE").ok();
+        env_usize(raw.as_deref(), BATCH_SIZE, MAX_BATCH_SIZE)
+    })
+}
+
+/// Rows to produce for one output chunk: `batch_size()`, clamped to what the
+/// chunk can hold
+///
+/// Writing a row past a vector's capacity is out of bounds, so every scanner
+/// sizes its batches from the chunk it was handed rather than from a setting.
+pub fn chunk_batch_size(output: &DataChunkHandle) -> usize {
+    if output.num_columns() == 0 {
+        return batch_size();
+    }
+    // Every vector in a chunk has the chunk's capacity (DuckDB's vector size)
+    batch_size().min(output.flat_vector(0).capacity())
+}
+
+/// Whether a filtered scan should stop reading and return its batch
+///
+/// A scan that drops records (`where_json`, a type filter, the decode audit)
+/// would otherwise read until it had `batch_size` rows, which for a selective
+/// filter can mean most of the table in one call, unresponsive to
+/// cancellation. Reads are capped at `batch_size` records per call instead,
+/// returning a short batch; the continuation key picks up where it stopped.
+/// An empty output ends a DuckDB scan, so a call with no rows yet keeps going.
+pub fn batch_full(kept: usize, read: usize, batch_size: usize) -> bool {
+    kept >= batch_size || (kept > 0 && read >= batch_size)
+}
+
+/// Default schema sample: `MANIFOLD_SCHEMA_SAMPLE` if set and valid, else
+/// `SCHEMA_SAMPLE_SIZE`; read once, like `batch_size`
+pub fn schema_sample_size() -> usize {
+    *SCHEMA_SAMPLE_OVERRIDE.get_or_init(|| {
+        let raw = std::env::var("MANIFOLD_SCHEMA_SAMPLE").ok();
+        env_usize(raw.as_deref(), SCHEMA_SAMPLE_SIZE, usize::MAX)
+    })
+}
+
+/// Parse a positive integer environment override, falling back to the
+/// default when it is malformed or out of range
+fn env_usize(raw: Option<&str>, default: usize, max: usize) -> usize {
+    match raw.map(|raw| raw.trim().parse::<usize>()) {
+        Some(Ok(value)) if (1..=max).contains(&value) => value,
+        _ => default,
+    }
+}
+
+/// Discovered columns plus a name -> index lookup, as produced at bind time
+pub type DiscoveredSchema = (Vec<DiscoveredColumn>, HashMap<String, usize>);
+
+/// A scanned batch plus the continuation key for the next batch
+pub type ScanBatch<T> = (Vec<T>, Option<Vec<u8>>);
+
+/// A record read from storage during a scan
+///
+/// In safe mode, records that fail to decode are kept as `Failed` so they can
+/// be emitted as diagnostic rows; otherwise they are skipped. Both keep the
+/// storage key the record was read from, for the `expose_keys` column.
+pub enum ScanRecord<T> {
+    Decoded { key: Vec<u8>, record: T },
+    Failed { key: Vec<u8>, error: String },
+}
+
+impl<T> ScanRecord<T> {
+    /// The storage key the record was read from
+    pub fn key(&self) -> &[u8] {
+        match self {
+            ScanRecord::Decoded { key, .. } | ScanRecord::Failed { key, .. } => key,
+        }
+    }
+}
+
+/// Decode one stored record
+///
+/// Returns None for records that should be skipped (decode failures outside
+/// safe mode). In safe mode, a panic inside the decoder is caught and reported
+/// like any other decode error.
+pub fn decode_record<T: Decoder>(key: &[u8], value: &[u8], safe_mode: bool) -> Option<ScanRecord<T>> {
+    if !safe_mode {
+        return T::decode(value).ok().map(|record| ScanRecord::Decoded {
+            key: key.to_vec(),
+            record,
+        });
+    }
+
+    let error = match std::panic::catch_unwind(|| T::decode(value)) {
+        Ok(Ok(record)) => {
+            return Some(ScanRecord::Decoded {
+                key: key.to_vec(),
+                record,
+            })
+        }
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "panic while decoding record".to_string(),
+    };
+
+    Some(ScanRecord::Failed {
+        key: key.to_vec(),
+        error,
+    })
+}
+
+/// Number of scan snapshots currently holding a read transaction
+static OPEN_SNAPSHOTS: AtomicUsize = AtomicUsize::new(0);
+
+/// A read transaction held across all batches of one scan
+///
+/// Every batch reads the same redb version, so a scan never mixes data from
+/// before and after a concurrent write. The pinned version can't be reclaimed
+/// while the snapshot lives, so scanners drop it as soon as the scan is done;
+/// an abandoned scan (LIMIT, cancellation) releases it when its init data drops.
+pub struct ScanSnapshot {
+    tx: RedbTransaction,
+}
+
+impl ScanSnapshot {
+    /// Begin a read transaction for a scan
+    pub fn begin(engine: &ManifoldEngine) -> Result<Self, Box<dyn Error>> {
+        let tx = engine.begin_read()?;
+        OPEN_SNAPSHOTS.fetch_add(1, Ordering::Relaxed);
+        Ok(Self { tx })
+    }
+
+    /// The held transaction
+    pub fn tx(&self) -> &RedbTransaction {
+        &self.tx
+    }
+}
+
+impl Drop for ScanSnapshot {
+    fn drop(&mut self) {
+        OPEN_SNAPSHOTS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Read one batch of a cursor scan, ending the scan if the read fails
+///
+/// Storage failures mid-scan (file removed or corrupted) are reported with the
+/// database path and where the scan had got to, and `done` is set so DuckDB
+/// doesn't call back into the same failure.
+pub fn read_scan_batch<T>(
+    done: &AtomicBool,
+    db_path: &str,
+    start_after_key: Option<&[u8]>,
+    read: impl FnOnce() -> Result<T, Box<dyn Error>>,
+) -> Result<T, ManifoldScannerError> {
+    read().map_err(|e| {
+        done.store(true, Ordering::Relaxed);
+        let position = match start_after_key {
+            Some(key) => format!("batch after key {}", key_to_id_string(key)),
+            None => "first batch".to_string(),
+        };
+        ManifoldScannerError::StorageError(format!(
+            "failed to read {} of '{}': {}",
+            position, db_path, e
+        ))
+    })
+}
+
+/// Number of live scan snapshots, for leak checks and `manifold_config`
+pub fn open_snapshots() -> usize {
+    OPEN_SNAPSHOTS.load(Ordering::Relaxed)
+}
+
+/// Render a storage key as an id: big-endian u64 keys as decimal, others as hex
+pub fn key_to_id_string(key: &[u8]) -> String {
+    match <[u8; 8]>::try_from(key) {
+        Ok(bytes) => u64::from_be_bytes(bytes).to_string(),
+        Err(_) => key.iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+/// Write a string into a VARCHAR vector by length
+///
+/// The `CString` and `&str` inserters copy up to the first NUL (or fail on
+/// one), so names stored with an embedded NUL would come back cut short or
+/// fail the query. DuckDB copies strings longer than its 12-byte inline form
+/// into the vector's own heap, so values of any size are stored whole.
+pub fn insert_str(vector: &FlatVector, row_idx: usize, text: &str) {
+    vector.insert(row_idx, text.as_bytes());
+}
+
+/// Leave a VARCHAR value NULL, or write the `null_string` token in its place
+pub fn null_varchar(vector: &mut FlatVector, row_idx: usize, null_string: Option<&str>) {
+    match null_string {
+        Some(token) => insert_str(vector, row_idx, token),
+        None => vector.set_null(row_idx),
+    }
+}
+
+/// Number a batch's rows into the BIGINT columns at `col_indices`, reserving
+/// the batch's range of `rows_emitted` so numbering continues across batches
+pub fn populate_row_numbers(
+    rows_emitted: &AtomicU64,
+    col_indices: &[usize],
+    batch_size: usize,
+    output: &mut DataChunkHandle,
+) {
+    if col_indices.is_empty() {
+        return;
+    }
+    let first = rows_emitted.fetch_add(batch_size as u64, Ordering::Relaxed);
+    for &col_idx in col_indices {
+        let mut vector = output.flat_vector(col_idx);
+        let numbers = &mut vector.as_mut_slice::<i64>()[..batch_size];
+        for (row_idx, number) in numbers.iter_mut().enumerate() {
+            *number = (first + row_idx as u64) as i64;
+        }
+    }
+}
+
+/// Populate a safe-mode diagnostic row: id from the key, the error, NULL elsewhere
+pub fn populate_error_row(
+    row_idx: usize,
+    key: &[u8],
+    error: &str,
+    column_index: &HashMap<String, usize>,
+    output: &mut DataChunkHandle,
+) -> Result<(), Box<dyn Error>> {
+    for (name, &col_idx) in column_index {
+        let mut vector = output.flat_vector(col_idx);
+        match name.as_str() {
+            "id" => vector.insert(row_idx, CString::new(key_to_id_string(key))?),
+            "_error" => vector.insert(row_idx, CString::new(error.replace('\0', ""))?),
+            "_key" => vector.insert(row_idx, key),
+            // Numbered with the rest of the batch
+            "rowid" | "row_number" => {}
+            _ => vector.set_null(row_idx),
+        }
+    }
+
+    Ok(())
+}
+
+/// Name of the `debug_columns` JSON twin of a property column
+pub fn json_column_name(prop_column: &str) -> String {
+    format!("{}__json", prop_column)
+}
+
+/// Add a `prop_x__json` VARCHAR after each property column (`debug_columns`)
+///
+/// The twin holds the value as rendered by `value_to_json_string`, next to the
+/// `value_to_duckdb_string` form in `prop_x`, so the two conversions can be
+/// compared row by row.
+pub fn add_json_debug_columns(columns: &mut Vec<DiscoveredColumn>) {
+    let mut with_debug = Vec::with_capacity(columns.len() * 2);
+    for column in columns.drain(..) {
+        let twin = column.property.is_some().then(|| DiscoveredColumn {
+            name: json_column_name(&column.name),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        });
+        with_debug.push(column);
+        with_debug.extend(twin);
+    }
+    *columns = with_debug;
+}
+
+/// Name of the `include_types` companion of a property column
+pub fn type_column_name(prop_column: &str) -> String {
+    format!("{}_type", prop_column)
+}
+
+/// Add a `prop_x_type` VARCHAR after each property column (`include_types`)
+///
+/// The companion records which `Value` variant each cell came from, so values
+/// of a column that collapsed to VARCHAR can be told apart. Fails if a
+/// companion's name is already a property column (properties `x` and
+/// `x_type`), since the two couldn't be distinguished.
+pub fn add_type_columns(columns: &mut Vec<DiscoveredColumn>) -> Result<(), ManifoldScannerError> {
+    let names: HashSet<String> = columns.iter().map(|column| column.name.clone()).collect();
+    let mut with_types = Vec::with_capacity(columns.len() * 2);
+    for column in columns.drain(..) {
+        let companion = if column.property.is_some() {
+            let name = type_column_name(&column.name);
+            if names.contains(&name) {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "include_types can't add '{}': a property column already has that name",
+                    name
+                )));
+            }
+            Some(DiscoveredColumn {
+                name,
+                column_type: ColumnType::Varchar,
+                nullable: true,
+                fields: Vec::new(),
+                property: None,
+            })
+        } else {
+            None
+        };
+        with_types.push(column);
+        with_types.extend(companion);
+    }
+    *columns = with_types;
+    Ok(())
+}
+
+/// Index of each property's column, by property name
+///
+/// Built once per batch for `populate_properties`; column names can't be used
+/// for the lookup, since `prop_prefix` and collisions decide them.
+pub fn property_index(columns: &[DiscoveredColumn]) -> HashMap<&str, usize> {
+    columns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, column)| Some((column.property.as_deref()?, i)))
+        .collect()
+}
+
+/// Output position of each column DuckDB asked for, by name
+///
+/// With projection pushdown the output chunk holds only the projected
+/// columns, in the order of `column_ids` (indexes into `columns`). Ids past
+/// the schema - the placeholder DuckDB projects when a query needs no column,
+/// as `COUNT(*)` does - get no entry, so such a projection maps to nothing.
+pub fn projected_index(
+    columns: &[DiscoveredColumn],
+    column_ids: &[u64],
+) -> HashMap<String, usize> {
+    column_ids
+        .iter()
+        .enumerate()
+        .filter_map(|(position, &id)| Some((columns.get(id as usize)?.name.clone(), position)))
+        .collect()
+}
+
+/// Fail if two columns share a name
+///
+/// Property columns are kept clear of the fixed columns when named, but with a
+/// short or empty `prop_prefix` one can still match a column an option adds
+/// later (`rowid`, `weight`, `is_<label>`, ...).
+pub fn check_unique_columns(columns: &[DiscoveredColumn]) -> Result<(), ManifoldScannerError> {
+    let mut names = HashSet::new();
+    match columns.iter().find(|column| !names.insert(column.name.as_str())) {
+        Some(column) => Err(ManifoldScannerError::InvalidParameter(format!(
+            "two columns are named '{}'; set prop_prefix so property columns don't collide \
+             with the columns other options add",
+            column.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Populate the property columns of one row
+///
+/// VARCHAR columns get the DuckDB string form of the value. STRUCT columns get
+/// one typed child per field, and are NULL when the record lacks the property
+/// or the value isn't an object of the discovered shape. BOOLEAN columns (from
+/// `coerce_bool`) get the value's boolean reading, NULL if it has none, and
+/// TIMESTAMP columns (from `timestamp_props`) the parsed time, NULL if it
+/// doesn't parse. Every typed column is SQL NULL, never a zero, when the record lacks the
+/// property or holds null, so aggregates skip it. If the schema has an
+/// `extra_props` column, properties without a column of their own go there
+/// as a JSON object (NULL if there are none). With `include_types`, each
+/// `prop_x_type` column gets the value's variant name, or NULL when the record
+/// lacks the property. A column with a `json_extract` path gets the value at
+/// that path instead of the whole property (see `extracted_string`).
+///
+/// `property_index` points into the full `columns`, while cells are written at
+/// their `column_index` positions, so a projected scan fills only the columns
+/// DuckDB asked for.
+///
+/// Returns how many values didn't fit their `decimal_props` column, or fails
+/// on the first one under `CoercionPolicy::Error`.
+pub fn populate_properties(
+    row_idx: usize,
+    properties: &HashMap<String, Value>,
+    columns: &[DiscoveredColumn],
+    column_index: &HashMap<String, usize>,
+    property_index: &HashMap<&str, usize>,
+    format: PropertyFormat,
+    output: &mut DataChunkHandle,
+) -> Result<u64, Box<dyn Error>> {
+    let float_precision = format.float_precision;
+    let include_types = format.include_types;
+    let extra_idx = column_index.get(EXTRA_PROPS_COLUMN).copied();
+    let mut extra = Vec::new();
+    let mut coerced = 0;
+
+    for (prop_name, prop_value) in properties {
+        let Some(&schema_idx) = property_index.get(prop_name.as_str()) else {
+            if extra_idx.is_some() {
+                extra.push((prop_name.as_str(), prop_value));
+            }
+            continue;
+        };
+        let column = &columns[schema_idx];
+        let col_name = &column.name;
+        if let Some(&json_idx) = column_index.get(&json_column_name(col_name)) {
+            let value_str = value_to_json_string(prop_value);
+            output.flat_vector(json_idx).insert(row_idx, CString::new(value_str)?);
+        }
+        if include_types {
+            if let Some(&type_idx) = column_index.get(&type_column_name(col_name)) {
+                output.flat_vector(type_idx).insert(row_idx, value_type_name(prop_value));
+            }
+        }
+        let Some(&col_idx) = column_index.get(col_name) else {
+            continue;
+        };
+        if let Some(path) = format.json_extract.and_then(|paths| paths.get(col_name)) {
+            let mut vector = output.flat_vector(col_idx);
+            match path.extract(prop_value).and_then(extracted_string) {
+                Some(value_str) => {
+                    let value_str = truncate_value(value_str, format.max_value_bytes);
+                    vector.insert(row_idx, CString::new(value_str)?)
+                }
+                None => null_varchar(&mut vector, row_idx, format.null_string),
+            }
+        } else {
+            match column.column_type {
+                ColumnType::Struct => {
+                    populate_struct(column, col_idx, row_idx, prop_value, &format, output)?
+                }
+                ColumnType::Boolean => {
+                    let mut vector = output.flat_vector(col_idx);
+                    match value_to_bool(prop_value) {
+                        Some(b) => vector.as_mut_slice::<bool>()[row_idx] = b,
+                        None => vector.set_null(row_idx),
+                    }
+                }
+                ColumnType::Decimal { width, scale } => {
+                    if !populate_decimal(col_idx, row_idx, prop_value, width, scale, output) {
+                        let shape = (width, scale);
+                        coerced += coercion_failure(col_name, shape, prop_value, &format)?;
+                    }
+                }
+                ColumnType::DecimalText { .. } if matches!(prop_value, Value::Null) => {
+                    null_varchar(&mut output.flat_vector(col_idx), row_idx, format.null_string)
+                }
+                ColumnType::DecimalText { width, scale } => {
+                    let shape = (width, scale);
+                    let vector = output.flat_vector(col_idx);
+                    let fits =
+                        populate_decimal_text(&vector, row_idx, prop_value, shape, float_precision);
+                    if !fits {
+                        coerced += coercion_failure(col_name, shape, prop_value, &format)?;
+                    }
+                }
+                ColumnType::Timestamp => {
+                    let mut vector = output.flat_vector(col_idx);
+                    match timestamp_cell(prop_value) {
+                        Some(micros) => vector.as_mut_slice::<i64>()[row_idx] = micros,
+                        None => vector.set_null(row_idx),
+                    }
+                }
+                _ if matches!(prop_value, Value::Null) => {
+                    null_varchar(&mut output.flat_vector(col_idx), row_idx, format.null_string)
+                }
+                _ => {
+                    let value_str = value_to_duckdb_string(prop_value, float_precision);
+                    let value_str = truncate_value(value_str, format.max_value_bytes);
+                    output.flat_vector(col_idx).insert(row_idx, CString::new(value_str)?);
+                }
+            }
+        }
+    }
+
+    if let Some(col_idx) = extra_idx {
+        let mut vector = output.flat_vector(col_idx);
+        if extra.is_empty() {
+            vector.set_null(row_idx);
+        } else {
+            // Sorted by key so scans are repeatable
+            extra.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            vector.insert(row_idx, CString::new(pairs_to_json(&extra))?);
+        }
+    }
+
+    // Columns for properties the record doesn't have
+    for column in columns {
+        let Some(prop_name) = &column.property else {
+            continue;
+        };
+        if properties.contains_key(prop_name) {
+            continue;
+        }
+        if let Some(&col_idx) = column_index.get(&column.name) {
+            match column.column_type {
+                ColumnType::Struct => null_struct_row(column, col_idx, row_idx, output),
+                ColumnType::Varchar | ColumnType::DecimalText { .. } => {
+                    null_varchar(&mut output.flat_vector(col_idx), row_idx, format.null_string)
+                }
+                // Typed cells have no "absent" value of their own; leaving
+                // one unset would show whatever the vector last held
+                _ => output.flat_vector(col_idx).set_null(row_idx),
+            }
+        }
+        if include_types {
+            if let Some(&type_idx) = column_index.get(&type_column_name(&column.name)) {
+                output.flat_vector(type_idx).set_null(row_idx);
+            }
+        }
+    }
+
+    Ok(coerced)
+}
+
+/// How `populate_properties` renders values
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PropertyFormat<'a> {
+    /// Fixed decimals for floats rendered as VARCHAR (None = round-trip)
+    pub float_precision: Option<usize>,
+    /// Fill the `prop_x_type` columns
+    pub include_types: bool,
+    /// Paths to extract instead of the whole value, by column name
+    pub json_extract: Option<&'a HashMap<String, JsonPath>>,
+    /// Token written for null and absent VARCHAR values (None = SQL NULL)
+    pub null_string: Option<&'a str>,
+    /// What to do with values that don't fit a `decimal_props` column
+    pub coercion_policy: CoercionPolicy,
+    /// Id of the record being written, for coercion errors
+    pub record_id: u64,
+    /// Read boolean-like strings into BOOLEAN struct fields
+    pub coerce_bool: bool,
+    /// Longest VARCHAR property value written in full (None = no limit)
+    pub max_value_bytes: Option<usize>,
+}
+
+/// Appended to VARCHAR property values cut short by `max_value_bytes`
+pub const TRUNCATION_MARKER: &str = "...[truncated]";
+
+/// Cut a rendered value longer than `max_bytes` back to that many bytes (less
+/// if that would split a character) and append `TRUNCATION_MARKER`
+fn truncate_value(mut text: String, max_bytes: Option<usize>) -> String {
+    let Some(max_bytes) = max_bytes.filter(|&max| text.len() > max) else {
+        return text;
+    };
+    let mut cut = max_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    text.truncate(cut);
+    text.push_str(TRUNCATION_MARKER);
+    text
+}
+
+/// Handle a value that didn't fit its column: an error under
+/// `CoercionPolicy::Error`, otherwise one failure to count (none for nulls)
+fn coercion_failure(
+    column: &str,
+    (width, scale): (u8, u8),
+    value: &Value,
+    format: &PropertyFormat,
+) -> Result<u64, ManifoldScannerError> {
+    if matches!(value, Value::Null) {
+        return Ok(0);
+    }
+    if format.coercion_policy != CoercionPolicy::Error {
+        return Ok(1);
+    }
+
+    Err(ManifoldScannerError::CoercionError {
+        id: format.record_id,
+        column: column.to_string(),
+        column_type: format!("DECIMAL({},{})", width, scale),
+        value: value_to_json_string(value),
+    })
+}
+
+/// Render an extracted JSON value as DuckDB's `->>` would: strings without
+/// quotes, other values as JSON text, and JSON null as NULL
+fn extracted_string(value: serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Write a nested-object value into a STRUCT column
+fn populate_struct(
+    column: &DiscoveredColumn,
+    col_idx: usize,
+    row_idx: usize,
+    value: &Value,
+    format: &PropertyFormat,
+    output: &mut DataChunkHandle,
+) -> Result<(), Box<dyn Error>> {
+    let object = match value_as_object(value) {
+        Some(object) if object.len() == column.fields.len() => object,
+        _ => {
+            null_struct_row(column, col_idx, row_idx, output);
+            return Ok(());
+        }
+    };
+
+    let capacity = output.flat_vector(col_idx).capacity();
+    let structs = output.struct_vector(col_idx);
+    for (field_idx, (field_name, field_type)) in column.fields.iter().enumerate() {
+        let mut child = structs.child(field_idx, capacity);
+        let field_value = object.iter().find(|(key, _)| key == field_name).map(|(_, v)| *v);
+        match (field_type, field_value) {
+            (_, None | Some(Value::Null)) => child.set_null(row_idx),
+            (ColumnType::Boolean, Some(Value::Bool(b))) => child.as_mut_slice::<bool>()[row_idx] = *b,
+            (ColumnType::Boolean, Some(v @ Value::String(_))) if format.coerce_bool => {
+                match value_to_bool(v) {
+                    Some(b) => child.as_mut_slice::<bool>()[row_idx] = b,
+                    None => child.set_null(row_idx),
+                }
+            }
+            (ColumnType::Bigint, Some(Value::Int(i))) => child.as_mut_slice::<i64>()[row_idx] = *i,
+            (ColumnType::Double, Some(Value::Float(f))) => child.as_mut_slice::<f64>()[row_idx] = *f,
+            (ColumnType::Varchar, Some(v)) => {
+                let text = value_to_duckdb_string(v, format.float_precision);
+                child.insert(row_idx, CString::new(text)?)
+            }
+            // Value of a type the sample didn't see for this field
+            (_, Some(_)) => child.set_null(row_idx),
+        }
+    }
+
+    Ok(())
+}
+
+/// Mark a STRUCT row NULL, along with its fields
+fn null_struct_row(
+    column: &DiscoveredColumn,
+    col_idx: usize,
+    row_idx: usize,
+    output: &mut DataChunkHandle,
+) {
+    let capacity = output.flat_vector(col_idx).capacity();
+    let mut structs = output.struct_vector(col_idx);
+    for field_idx in 0..column.fields.len() {
+        structs.child(field_idx, capacity).set_null(row_idx);
+    }
+    structs.set_null(row_idx);
+}
+
+/// Error for a parameter value that doesn't have the declared type
+///
+/// DuckDB casts arguments to the declared types before bind, so this catches
+/// what gets through the cast: NULLs, which arrive as the text `NULL`.
+fn wrong_type(name: &str, expected: &str, text: &str) -> ManifoldScannerError {
+    if text == "NULL" {
+        return ManifoldScannerError::InvalidParameter(format!(
+            "{} must be a {}, not NULL",
+            name, expected
+        ));
+    }
+    ManifoldScannerError::InvalidParameter(format!(
+        "{} must be a {}, got '{}'",
+        name, expected, text
+    ))
+}
+
+/// Parse a BOOLEAN parameter from its text form
+fn parse_bool_param(name: &str, text: &str) -> Result<bool, ManifoldScannerError> {
+    match text {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(wrong_type(name, "BOOLEAN", text)),
+    }
+}
+
+/// Parse a BIGINT parameter from its text form
+fn parse_i64_param(name: &str, text: &str) -> Result<i64, ManifoldScannerError> {
+    text.parse().map_err(|_| wrong_type(name, "BIGINT", text))
+}
+
+/// Check a required VARCHAR parameter is present and non-empty
+///
+/// A prepared-statement parameter reaches bind as its value, already cast to
+/// VARCHAR, so `?` bound to a path works like a literal; bound to NULL it
+/// arrives as the text `NULL`, which is rejected rather than opened as a file
+/// of that name. The text form can't tell the two apart, so a literal
+/// `'NULL'` is rejected too.
+fn parse_string_param(name: &str, text: String) -> Result<String, ManifoldScannerError> {
+    if text == "NULL" {
+        return Err(wrong_type(name, "VARCHAR", &text));
+    }
+    if text.is_empty() {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "{} must be a non-empty VARCHAR",
+            name
+        )));
+    }
+    Ok(text)
+}
+
+/// Read a required VARCHAR positional parameter
+pub fn string_param(
+    bind: &BindInfo,
+    index: u64,
+    name: &str,
+) -> Result<String, ManifoldScannerError> {
+    parse_string_param(name, bind.get_parameter(index).to_string())
+}
+
+/// Read a required LIST(VARCHAR) positional parameter
+pub fn string_list_param(
+    bind: &BindInfo,
+    index: u64,
+    name: &str,
+) -> Result<Vec<String>, ManifoldScannerError> {
+    parse_string_list(name, &bind.get_parameter(index).to_string())
+}
+
+/// Read a required BIGINT positional parameter
+pub fn i64_param(bind: &BindInfo, index: u64, name: &str) -> Result<i64, ManifoldScannerError> {
+    parse_i64_param(name, &bind.get_parameter(index).to_string())
+}
+
+/// Read an optional BOOLEAN named parameter
+pub fn named_bool(bind: &BindInfo, name: &str) -> Result<Option<bool>, ManifoldScannerError> {
+    bind.get_named_parameter(name)
+        .map(|v| parse_bool_param(name, &v.to_string()))
+        .transpose()
+}
+
+/// Read an optional BIGINT named parameter
+pub fn named_i64(bind: &BindInfo, name: &str) -> Result<Option<i64>, ManifoldScannerError> {
+    bind.get_named_parameter(name)
+        .map(|v| parse_i64_param(name, &v.to_string()))
+        .transpose()
+}
+
+/// Read the optional `float_precision` named parameter (decimals for floats
+/// rendered as VARCHAR)
+pub fn named_float_precision(bind: &BindInfo) -> Result<Option<usize>, ManifoldScannerError> {
+    named_i64(bind, "float_precision")?.map(parse_float_precision).transpose()
+}
+
+/// Check a `float_precision` value is from 0 to MAX_FLOAT_PRECISION
+fn parse_float_precision(precision: i64) -> Result<usize, ManifoldScannerError> {
+    if precision < 0 {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "float_precision must not be negative, got {}",
+            precision
+        )));
+    }
+    if precision > MAX_FLOAT_PRECISION as i64 {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "float_precision must be at most {}, got {}",
+            MAX_FLOAT_PRECISION, precision
+        )));
+    }
+    Ok(precision as usize)
+}
+
+/// Read the optional `schema_sample` named parameter (records sampled per
+/// region of the table at bind time)
+pub fn named_schema_sample(bind: &BindInfo) -> Result<usize, ManifoldScannerError> {
+    match named_i64(bind, "schema_sample")? {
+        Some(sample) if sample > 0 => Ok(sample as usize),
+        Some(sample) => Err(ManifoldScannerError::InvalidParameter(format!(
+            "schema_sample must be positive, got {}",
+            sample
+        ))),
+        None => Ok(schema_sample_size()),
+    }
+}
+
+/// Read an optional LIST(VARCHAR) named parameter
+pub fn named_string_list(
+    bind: &BindInfo,
+    name: &str,
+) -> Result<Option<Vec<String>>, ManifoldScannerError> {
+    bind.get_named_parameter(name)
+        .map(|v| parse_string_list(name, &v.to_string()))
+        .transpose()
+}
+
+/// Parse a DuckDB list literal such as `[Person, 'Big, Co']` into strings
+///
+/// Items may be bare or single-quoted (with `''` as an escaped quote); quoting
+/// is needed for items containing commas or brackets.
+fn parse_string_list(name: &str, text: &str) -> Result<Vec<String>, ManifoldScannerError> {
+    let invalid = || {
+        ManifoldScannerError::InvalidParameter(format!("{} must be a list, got '{}'", name, text))
+    };
+    let inner = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+
+    let mut items = Vec::new();
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        items.push(read_list_item(&mut chars, &[',']).ok_or_else(invalid)?);
+
+        match chars.next() {
+            Some(',') => {}
+            None => break,
+            Some(_) => return Err(invalid()),
+        }
+    }
+
+    Ok(items)
+}
+
+/// Read one bare or single-quoted item of a list or map literal
+///
+/// A bare item runs up to the next of `stops` and is trimmed; a quoted one
+/// uses `''` for an escaped quote. Returns None for an unterminated quote.
+fn read_list_item(chars: &mut Peekable<Chars>, stops: &[char]) -> Option<String> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+    let mut item = String::new();
+    if chars.next_if_eq(&'\'').is_some() {
+        loop {
+            match chars.next()? {
+                '\'' if chars.next_if_eq(&'\'').is_some() => item.push('\''),
+                '\'' => break,
+                c => item.push(c),
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    } else {
+        while let Some(c) = chars.next_if(|c| !stops.contains(c)) {
+            item.push(c);
+        }
+        item.truncate(item.trim_end().len());
+    }
+    Some(item)
+}
+
+/// Read an optional named parameter mapping names to strings
+///
+/// Accepts a struct literal (`{'prop_meta': '$.region'}`) or a MAP
+/// (`MAP {'prop_meta': '$.region'}`), which reach bind as text.
+pub fn named_string_map(
+    bind: &BindInfo,
+    name: &str,
+) -> Result<Option<Vec<(String, String)>>, ManifoldScannerError> {
+    bind.get_named_parameter(name)
+        .map(|v| parse_string_map(name, &v.to_string()))
+        .transpose()
+}
+
+/// Parse the text form of a struct or map, `{key: value, ...}` or
+/// `{key=value, ...}`, into pairs
+fn parse_string_map(name: &str, text: &str) -> Result<Vec<(String, String)>, ManifoldScannerError> {
+    let invalid = || {
+        ManifoldScannerError::InvalidParameter(format!(
+            "{} must be a struct such as {{'key': 'value'}}, got '{}'",
+            name, text
+        ))
+    };
+    let inner = text
+        .trim()
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .ok_or_else(invalid)?;
+
+    let mut pairs = Vec::new();
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let key = read_list_item(&mut chars, &[':', '=']).ok_or_else(invalid)?;
+        if !matches!(chars.next(), Some(':' | '=')) {
+            return Err(invalid());
+        }
+        let value = read_list_item(&mut chars, &[',']).ok_or_else(invalid)?;
+        pairs.push((key, value));
+
+        match chars.next() {
+            Some(',') => {}
+            None => break,
+            Some(_) => return Err(invalid()),
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// Position a cursor on the first entry after a key, or the first entry
+///
+/// The key is normally the last one a scan read from the same snapshot, but
+/// it may not be present (a position from another snapshot, or a hand-made
+/// key in a gap left by deleted records). A seek lands on the key or the
+/// next present one, so the cursor only steps past an exact match.
+pub fn first_after(cursor: &mut impl Cursor, after_key: Option<&[u8]>) -> CursorResult {
+    let Some(after_key) = after_key else {
+        return cursor.seek_first();
+    };
+    match cursor.seek(after_key)? {
+        Some((key, _)) if key == after_key => cursor.next(),
+        entry => Ok(entry),
+    }
+}
+
+/// Sample up to `per_region` records from each of the start, middle and end of
+/// a table, returning their values
+///
+/// Records added later have higher ids, so sampling the end catches
+/// properties that newer records introduced, at the cost of three seeks
+/// rather than a scan. Keys are big-endian ids, so the middle is found by
+/// seeking to the id halfway between the first and last keys; tables with
+/// other keys are sampled at the ends only. Overlapping regions in small
+/// tables return each record once. Scanners use `sample_records`, which
+/// samples the same way but skips records that don't decode.
+#[cfg(test)]
+pub fn sample_table(
+    tx: &RedbTransaction,
+    table: &str,
+    per_region: usize,
+) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    Ok(sample_with(tx, table, per_region, Sampled::Kept)?.records)
+}
+
+/// Records decoded by `sample_records`, and how many sampled records didn't
+/// decode
+#[derive(Debug)]
+pub struct RecordSample<T> {
+    pub records: Vec<T>,
+    pub failed: usize,
+}
+
+/// Sample like `sample_table`, counting only records that decode
+///
+/// A record that fails to decode doesn't use up its region's quota, so a
+/// few bad records don't shrink the sample; to keep bind quick on a badly
+/// damaged table, each region gives up after `per_region` failures.
+///
+/// In a mixed table only records of the scanned kind are sampled: the other
+/// kind's are passed over without counting toward either limit, so each
+/// region reads on until it has its quota of the kind.
+pub fn sample_records<T: Decoder>(
+    tx: &RedbTransaction,
+    table: &str,
+    mixed: Option<&MixedTable>,
+    per_region: usize,
+) -> Result<RecordSample<T>, Box<dyn Error>> {
+    let decode = |bytes: &[u8]| match T::decode(bytes) {
+        Ok(record) => Sampled::Kept(record),
+        Err(_) => Sampled::Failed,
+    };
+    sample_with(tx, table, per_region, |value| match mixed.map(|mixed| mixed.untag(&value)) {
+        None => decode(&value),
+        Some(Tagged::Record(record)) => decode(record),
+        Some(Tagged::OtherKind) => Sampled::Skipped,
+        Some(Tagged::Unknown(_)) => Sampled::Failed,
+    })
+}
+
+/// What sampling makes of one stored value
+enum Sampled<T> {
+    /// Taken into the sample
+    Kept(T),
+    /// Counted as failing to decode
+    Failed,
+    /// Not part of the sample at all
+    Skipped,
+}
+
+/// Shared sampling: `decode` keeps a record (returning what to keep), rejects
+/// it or skips it
+fn sample_with<T>(
+    tx: &RedbTransaction,
+    table: &str,
+    per_region: usize,
+    decode: impl FnMut(Vec<u8>) -> Sampled<T>,
+) -> Result<RecordSample<T>, Box<dyn Error>> {
+    let mut sample = TableSample {
+        per_region,
+        seen: HashSet::new(),
+        decode,
+        sample: RecordSample {
+            records: Vec::new(),
+            failed: 0,
+        },
+    };
+
+    let Ok(mut cursor) = tx.cursor(table) else {
+        // Table doesn't exist yet - nothing to sample
+        return Ok(sample.sample);
+    };
+
+    let first = cursor.seek_first()?;
+    let first_id = first.as_ref().and_then(|(key, _)| key_to_id(key));
+    sample.take_run(&mut cursor, first, false)?;
+
+    let last = cursor.seek_last()?;
+    let last_id = last.as_ref().and_then(|(key, _)| key_to_id(key));
+    sample.take_run(&mut cursor, last, true)?;
+
+    if let (Some(low), Some(high)) = (first_id, last_id) {
+        let middle = low + (high - low) / 2;
+        let entry = cursor.seek(&middle.to_be_bytes())?;
+        sample.take_run(&mut cursor, entry, false)?;
+    }
+
+    Ok(sample.sample)
+}
+
+/// Records collected by `sample_with`
+struct TableSample<T, F> {
+    per_region: usize,
+    seen: HashSet<Vec<u8>>,
+    decode: F,
+    sample: RecordSample<T>,
+}
+
+impl<T, F: FnMut(Vec<u8>) -> Sampled<T>> TableSample<T, F> {
+    /// Take up to `per_region` records starting at `entry`, moving backwards
+    /// if `reverse`; records `decode` rejects are counted, not taken, and
+    /// records it skips are neither
+    fn take_run(
+        &mut self,
+        cursor: &mut impl Cursor,
+        mut entry: Option<(Vec<u8>, Vec<u8>)>,
+        reverse: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let (mut taken, mut failed) = (0, 0);
+        while taken < self.per_region && failed < self.per_region {
+            let Some((key, value)) = entry else {
+                break;
+            };
+            if self.seen.insert(key) {
+                match (self.decode)(value) {
+                    Sampled::Kept(record) => {
+                        self.sample.records.push(record);
+                        taken += 1;
+                    }
+                    Sampled::Failed => {
+                        self.sample.failed += 1;
+                        failed += 1;
+                    }
+                    Sampled::Skipped => {}
+                }
+            } else {
+                // Already sampled by an overlapping region: counts toward the quota
+                taken += 1;
+            }
+            entry = if reverse { cursor.prev()? } else { cursor.next()? };
+        }
+        Ok(())
+    }
+}
+
+/// Decode a big-endian u64 id key
+fn key_to_id(key: &[u8]) -> Option<u64> {
+    <[u8; 8]>::try_from(key).ok().map(u64::from_be_bytes)
+}
+
+/// Global engine cache - maps db_path to Arc<ManifoldEngine>
 /// Shared between all scanners to avoid multiple opens of the same database
-static ENGINE_CACHE: OnceLock<Mutex<HashMap<String, Arc<RedbEngine>>>> = OnceLock::new();
+static ENGINE_CACHE: OnceLock<Mutex<HashMap<String, Arc<ManifoldEngine>>>> = OnceLock::new();
 
-fn get_engine_cache() -> &'static Mutex<HashMap<String, Arc<RedbEngine>>> {
+fn get_engine_cache() -> &'static Mutex<HashMap<String, Arc<ManifoldEngine>>> {
     ENGINE_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+/// Lock the engine cache, recovering it if a thread panicked while holding it
+///
+/// The map is only changed by single `insert`/`remove` calls, so a panic
+/// elsewhere under the lock can't leave it half-updated. Taking the guard back
+/// out of the `PoisonError` (and clearing the flag) keeps one panic from
+/// failing every later scan.
+fn lock_engine_cache() -> MutexGuard<'static, HashMap<String, Arc<ManifoldEngine>>> {
+    lock_recovered(get_engine_cache())
+}
+
+/// Lock a mutex, taking it back (and clearing the flag) if it is poisoned
+fn lock_recovered<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
 /// Get or create a cached engine for the given path
-pub fn get_cached_engine(db_path: &str) -> Result<Arc<RedbEngine>, Box<dyn Error>> {
-    let mut cache = get_engine_cache().lock().map_err(|e| format!("Lock error: {}", e))?;
+///
+/// There is no memory-mapped mode: redb removed its mmap backend (0.14) as
+/// unsound, and redb 3 reads through its own page cache only. Whether a file
+/// is opened read-write or read-only depends on the file (see [`engine`]), so
+/// the path alone is the cache key.
+pub fn get_cached_engine(db_path: &str) -> Result<Arc<ManifoldEngine>, Box<dyn Error>> {
+    let mut cache = lock_engine_cache();
 
     if let Some(engine) = cache.get(db_path) {
         return Ok(Arc::clone(engine));
     }
 
-    let engine = Arc::new(RedbEngine::open(db_path)?);
+    let engine = Arc::new(open_engine(db_path)?);
     cache.insert(db_path.to_string(), Arc::clone(&engine));
     Ok(engine)
 }
+
+/// Number of databases open in the engine cache
+pub fn cached_engine_count() -> usize {
+    lock_engine_cache().len()
+}
+
+/// The engine a scan reads through
+///
+/// Normally the shared cached engine. With `no_cache` the scan opens its own
+/// instead, so the file isn't left open for the rest of the session: the scan
+/// releases it once exhausted and the file closes when nothing else holds it.
+/// A re-executed prepared statement opens it again.
+pub struct QueryEngine {
+    db_path: String,
+    /// The scan's own engine, when bypassing the cache
+    private: Option<Mutex<Option<Arc<ManifoldEngine>>>>,
+}
+
+impl QueryEngine {
+    /// Open the database for a scan, through the cache unless `no_cache`
+    pub fn open(db_path: &str, no_cache: bool) -> Result<Self, Box<dyn Error>> {
+        let private = if no_cache {
+            Some(Mutex::new(Some(open_uncached(db_path)?)))
+        } else {
+            get_cached_engine(db_path)?;
+            None
+        };
+        Ok(Self {
+            db_path: db_path.to_string(),
+            private,
+        })
+    }
+
+    /// The engine to read through, reopening a released private engine
+    pub fn get(&self) -> Result<Arc<ManifoldEngine>, Box<dyn Error>> {
+        let Some(private) = &self.private else {
+            return get_cached_engine(&self.db_path);
+        };
+        let mut private = lock_recovered(private);
+        match &*private {
+            Some(engine) => Ok(Arc::clone(engine)),
+            None => Ok(Arc::clone(private.insert(open_uncached(&self.db_path)?))),
+        }
+    }
+
+    /// Drop a private engine once the scan is done; cached engines stay open
+    pub fn release(&self) {
+        if let Some(private) = &self.private {
+            *lock_recovered(private) = None;
+        }
+    }
+}
+
+/// Open an engine without adding it to the cache
+///
+/// redb allows one open handle per file in a process, so a file the cache
+/// already has open is read through that handle.
+fn open_uncached(db_path: &str) -> Result<Arc<ManifoldEngine>, Box<dyn Error>> {
+    let cache = lock_engine_cache();
+    match cache.get(db_path) {
+        Some(engine) => Ok(Arc::clone(engine)),
+        None => Ok(Arc::new(open_engine(db_path)?)),
+    }
+}
+
+/// Remove the cached engine for a path, returning whether there was one
+///
+/// The file is closed once scans still holding the engine finish.
+pub fn drop_cached_engine(db_path: &str) -> Result<bool, Box<dyn Error>> {
+    let mut cache = lock_engine_cache();
+    Ok(cache.remove(db_path).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{Entity, EntityId};
+
+    #[test]
+    fn test_add_json_debug_columns() {
+        let column = |name: &str| DiscoveredColumn {
+            name: name.to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: name.strip_prefix("prop_").map(str::to_string),
+        };
+        let mut columns = vec![column("id"), column("prop_a"), column("prop_b"), column("_error")];
+        add_json_debug_columns(&mut columns);
+
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "prop_a", "prop_a__json", "prop_b", "prop_b__json", "_error"]);
+    }
+
+    #[test]
+    fn test_projected_index() {
+        let column = |name: &str| DiscoveredColumn {
+            name: name.to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        };
+        let columns = vec![column("id"), column("labels"), column("prop_a"), column("prop_b")];
+
+        // Chunk positions follow the projection order, not the schema's
+        let index = projected_index(&columns, &[3, 0]);
+        assert_eq!(index.len(), 2);
+        assert_eq!((index["prop_b"], index["id"]), (0, 1));
+
+        // COUNT(*) projects only a placeholder past the schema: nothing to fill
+        assert!(projected_index(&columns, &[u64::MAX]).is_empty());
+        assert!(projected_index(&columns, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_add_type_columns() {
+        let column = |name: &str| DiscoveredColumn {
+            name: name.to_string(),
+            column_type: ColumnType::Varchar,
+            nullable: true,
+            fields: Vec::new(),
+            property: name
+                .strip_prefix("prop_")
+                .filter(|property| !property.ends_with("__json"))
+                .map(str::to_string),
+        };
+        let mut columns = vec![column("id"), column("prop_a"), column("prop_a__json")];
+        add_type_columns(&mut columns).unwrap();
+
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "prop_a", "prop_a_type", "prop_a__json"]);
+
+        // Properties `b` and `b_type` would share a column name
+        let mut columns = vec![column("prop_b"), column("prop_b_type")];
+        assert!(add_type_columns(&mut columns).is_err());
+    }
+
+    #[test]
+    fn test_sample_table() {
+        let db = TempDb::new("sample");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in 10..1010u64 {
+            tx.put("nodes", &id.to_be_bytes(), &id.to_be_bytes()).unwrap();
+        }
+        for id in [1u64, 2, 3, 4] {
+            tx.put("edges", &id.to_be_bytes(), &id.to_be_bytes()).unwrap();
+        }
+        for key in ["a", "b", "c", "d", "e"] {
+            tx.put("metadata", key.as_bytes(), key.as_bytes()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let ids = |values: Vec<Vec<u8>>| -> Vec<u64> {
+            values.iter().map(|v| u64::from_be_bytes(v[..].try_into().unwrap())).collect()
+        };
+
+        // Start, end (walking back) and the id halfway between
+        let sampled = ids(sample_table(&tx, "nodes", 3).unwrap());
+        assert_eq!(sampled, vec![10, 11, 12, 1009, 1008, 1007, 509, 510, 511]);
+
+        // Overlapping regions return each record once
+        assert_eq!(ids(sample_table(&tx, "edges", 3).unwrap()), vec![1, 2, 3, 4]);
+
+        // Keys that aren't ids are sampled at the ends only
+        assert_eq!(sample_table(&tx, "metadata", 1).unwrap(), vec![b"a".to_vec(), b"e".to_vec()]);
+        assert!(sample_table(&tx, "missing", 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sample_records_skips_undecodable() {
+        use manifoldb_core::encoding::Encoder;
+        use manifoldb_core::types::{Entity, EntityId};
+
+        let db = TempDb::new("sample_records");
+        let engine = db.open();
+
+        // Nodes 1, 2 and 20 are garbage; edges are all garbage
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1..=20u64 {
+            let value = match id {
+                1 | 2 | 20 => vec![0xff],
+                _ => Entity::new(EntityId::from(id)).encode().unwrap(),
+            };
+            tx.put("nodes", &id.to_be_bytes(), &value).unwrap();
+        }
+        for id in 1..=50u64 {
+            tx.put("edges", &id.to_be_bytes(), &[0xff]).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let sample = sample_records::<Entity>(&tx, "nodes", None, 3).unwrap();
+        let ids: Vec<u64> = sample.records.iter().map(|e| e.id.as_u64()).collect();
+        // Bad records don't use up a region's quota
+        assert_eq!(ids, vec![3, 4, 5, 19, 18, 17, 10, 11, 12]);
+        assert_eq!(sample.failed, 3);
+
+        // A region gives up after per_region failures
+        let sample = sample_records::<Entity>(&tx, "edges", None, 3).unwrap();
+        assert!(sample.records.is_empty());
+        assert_eq!(sample.failed, 9);
+    }
+
+    #[test]
+    fn test_key_to_id_string() {
+        assert_eq!(key_to_id_string(&42u64.to_be_bytes()), "42");
+        assert_eq!(key_to_id_string(&[0xde, 0xad]), "dead");
+    }
+
+    #[test]
+    fn test_read_scan_batch_error_ends_scan() {
+        let done = AtomicBool::new(false);
+        let result: Result<(), _> =
+            read_scan_batch(&done, "/tmp/gone.redb", Some(&7u64.to_be_bytes()), || {
+                Err("Storage error: file not found".into())
+            });
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("/tmp/gone.redb"));
+        assert!(message.contains("batch after key 7"));
+        assert!(message.contains("file not found"));
+        assert!(done.load(Ordering::Relaxed));
+
+        let done = AtomicBool::new(false);
+        assert_eq!(read_scan_batch(&done, "db", None, || Ok(3)).unwrap(), 3);
+        assert!(!done.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_wrong_typed_parameters() {
+        fn message<T: std::fmt::Debug>(result: Result<T, ManifoldScannerError>) -> String {
+            result.unwrap_err().to_string()
+        }
+
+        assert!(parse_bool_param("safe_mode", "true").unwrap());
+        assert!(!parse_bool_param("safe_mode", "false").unwrap());
+        assert_eq!(
+            message(parse_bool_param("safe_mode", "yes")),
+            "Invalid parameter: safe_mode must be a BOOLEAN, got 'yes'"
+        );
+        assert_eq!(
+            message(parse_bool_param("safe_mode", "NULL")),
+            "Invalid parameter: safe_mode must be a BOOLEAN, not NULL"
+        );
+
+        assert_eq!(parse_i64_param("depth", "-3").unwrap(), -3);
+        assert_eq!(
+            message(parse_i64_param("schema_sample", "100 rows")),
+            "Invalid parameter: schema_sample must be a BIGINT, got '100 rows'"
+        );
+        assert!(message(parse_i64_param("depth", "1.5")).contains("depth must be a BIGINT"));
+        assert!(message(parse_i64_param("k", "NULL")).contains("k must be a BIGINT, not NULL"));
+
+        assert_eq!(parse_string_param("db_path", "42".to_string()).unwrap(), "42");
+        assert_eq!(
+            message(parse_string_param("db_path", "NULL".to_string())),
+            "Invalid parameter: db_path must be a VARCHAR, not NULL"
+        );
+        assert_eq!(
+            message(parse_string_param("db_path", String::new())),
+            "Invalid parameter: db_path must be a non-empty VARCHAR"
+        );
+    }
+
+    #[test]
+    fn test_parse_float_precision() {
+        assert_eq!(parse_float_precision(0).unwrap(), 0);
+        assert_eq!(parse_float_precision(MAX_FLOAT_PRECISION as i64).unwrap(), MAX_FLOAT_PRECISION);
+        for precision in [-1, MAX_FLOAT_PRECISION as i64 + 1, i64::MAX] {
+            assert!(parse_float_precision(precision).is_err(), "{}", precision);
+        }
+    }
+
+    #[test]
+    fn test_parse_string_list() {
+        assert_eq!(parse_string_list("p", "[Person, Company]").unwrap(), vec!["Person", "Company"]);
+        assert_eq!(
+            parse_string_list("p", "['Big, Co', 'O''Neil']").unwrap(),
+            vec!["Big, Co", "O'Neil"]
+        );
+        assert_eq!(parse_string_list("p", "[]").unwrap(), Vec::<String>::new());
+        assert!(parse_string_list("p", "Person").is_err());
+        assert!(parse_string_list("p", "['open]").is_err());
+    }
+
+    #[test]
+    fn test_batch_full() {
+        // A full batch ends the call however much was read
+        assert!(batch_full(4, 4, 4));
+        // A short batch ends it once a batch's worth has been read
+        assert!(!batch_full(1, 3, 4));
+        assert!(batch_full(1, 4, 4));
+        // An empty batch never does: it would end the scan
+        assert!(!batch_full(0, 1000, 4));
+    }
+
+    #[test]
+    fn test_env_usize() {
+        assert_eq!(env_usize(None, 100, 2048), 100);
+        assert_eq!(env_usize(Some(" 512 "), 100, 2048), 512);
+        assert_eq!(env_usize(Some("2048"), 100, 2048), 2048);
+        for invalid in ["0", "4096", "-1", "lots", ""] {
+            assert_eq!(env_usize(Some(invalid), 100, 2048), 100, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_string_map() {
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            parse_string_map("p", "{'prop_meta': $.region, 'prop_b': '$[\"x, y\"]'}").unwrap(),
+            vec![pair("prop_meta", "$.region"), pair("prop_b", "$[\"x, y\"]")]
+        );
+        assert_eq!(
+            parse_string_map("p", "{prop_meta=$.region}").unwrap(),
+            vec![pair("prop_meta", "$.region")]
+        );
+        assert_eq!(parse_string_map("p", "{}").unwrap(), vec![]);
+        assert!(parse_string_map("p", "prop_meta").is_err());
+        assert!(parse_string_map("p", "{prop_meta}").is_err());
+    }
+
+    #[test]
+    fn test_query_engine_no_cache() {
+        use manifoldb_storage::backends::RedbEngine;
+
+        let db = TempDb::new("no_cache");
+        let db_path = db.db_path();
+        drop(db.open());
+
+        let query = QueryEngine::open(db_path, true).unwrap();
+        assert!(!lock_engine_cache().contains_key(db_path));
+        assert!(RedbEngine::open(db.path()).is_err(), "the scan holds the file open");
+
+        // Released, the file is closed; the next scan reopens it
+        query.release();
+        drop(db.open());
+        query.get().unwrap().begin_read().unwrap();
+        assert!(!lock_engine_cache().contains_key(db_path));
+    }
+
+    #[test]
+    fn test_truncate_value() {
+        assert_eq!(truncate_value("short".to_string(), Some(5)), "short");
+        assert_eq!(truncate_value("longer".to_string(), None), "longer");
+        assert_eq!(truncate_value("longer".to_string(), Some(4)), "long...[truncated]");
+        // "é" is two bytes; the cut backs off rather than splitting it
+        assert_eq!(truncate_value("caféine".to_string(), Some(4)), "caf...[truncated]");
+    }
+
+    #[test]
+    fn test_lock_survives_poisoning() {
+        // A local cache: poisoning the global one would fail other tests
+        let cache = Mutex::new(HashMap::from([("db".to_string(), 1)]));
+        let _ = std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let mut cache = cache.lock().unwrap();
+                    cache.insert("other".to_string(), 2);
+                    panic!("poisoning the cache");
+                })
+                .join()
+        });
+        assert!(cache.is_poisoned());
+
+        let guard = lock_recovered(&cache);
+        assert_eq!(guard.get("db"), Some(&1));
+        assert_eq!(guard.get("other"), Some(&2));
+        drop(guard);
+        assert!(!cache.is_poisoned());
+        assert_eq!(lock_recovered(&cache).len(), 2);
+    }
+
+    #[test]
+    fn test_decode_record_safe_mode() {
+        let garbage = [0xffu8, 0x01, 0x02];
+
+        assert!(decode_record::<Entity>(b"k", &garbage, false).is_none());
+        match decode_record::<Entity>(b"k", &garbage, true) {
+            Some(ScanRecord::Failed { key, error }) => {
+                assert_eq!(key, b"k");
+                assert!(!error.is_empty());
+            }
+            _ => panic!("expected a failed record in safe mode"),
+        }
+
+        // Decoded records keep their key too
+        let entity = Entity::new(EntityId::from(7));
+        let record = decode_record::<Entity>(&7u64.to_be_bytes(), &entity.encode().unwrap(), true);
+        assert_eq!(record.unwrap().key(), &7u64.to_be_bytes()[..]);
+    }
+}
@@ -0,0 +1,462 @@
+//! One-hop neighbour aggregates for entity scans
+//!
+//! Backs the `neighbor_agg` option of `manifold_entities`, which adds a column
+//! per aggregate over each entity's neighbours - the classic neighbourhood
+//! features of graph ML, without a self-join on the edges.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT id, neighbor_count, avg_neighbor_age
+//! FROM manifold_entities('/path/to/database.redb', neighbor_agg='count,avg:age',
+//!     neighbor_edge_type='KNOWS', neighbor_direction='both');
+//! ```
+//!
+//! ## Aggregates
+//! `neighbor_agg` is a comma-separated list of:
+//! - `count` - number of distinct neighbours, as `neighbor_count` (BIGINT)
+//! - `sum:p`, `avg:p`, `min:p`, `max:p` - over the neighbours' numeric values
+//!   of property `p`, as `sum_neighbor_p` etc. (DOUBLE). Neighbours without a
+//!   numeric value are left out; NULL when none has one
+//!
+//! Neighbours are the other ends of the entity's edges: targets of outgoing
+//! edges by default, sources of incoming edges with `neighbor_direction='in'`,
+//! or both with `'both'`. `neighbor_edge_type` restricts them to one edge type.
+//! Each neighbour counts once however many edges lead to it.
+//!
+//! ## Cost
+//! The option is off unless `neighbor_agg` is given. Init reads every edge
+//! once into an in-memory adjacency map (one entry per edge, two with
+//! `'both'`), then each scanned entity looks up its neighbours there. The
+//! neighbours' values are read by point lookups on the nodes table and cached,
+//! up to `NEIGHBOR_CACHE_CAPACITY` entities, so hub neighbours are decoded
+//! once. The adjacency and the neighbour values come from one read snapshot
+//! taken at init and held until the scan ends.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+
+use duckdb::core::DataChunkHandle;
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, Entity};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, Transaction};
+
+use crate::error::ManifoldScannerError;
+use crate::schema::{ColumnType, DiscoveredColumn};
+use super::engine::ManifoldEngine;
+use super::values::value_to_f64;
+use super::{ScanRecord, ScanSnapshot};
+
+/// Neighbours whose property values are kept between lookups
+pub const NEIGHBOR_CACHE_CAPACITY: usize = 100_000;
+
+/// Aggregate function over the neighbours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// One `neighbor_agg` entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NeighborAgg {
+    pub function: AggFunction,
+    /// Property aggregated (None for `count`)
+    pub property: Option<String>,
+}
+
+impl NeighborAgg {
+    /// Parse a list such as `count,avg:age`
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, ManifoldScannerError> {
+        let invalid = |reason: &str| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "invalid neighbor_agg '{}': {}",
+                text, reason
+            ))
+        };
+
+        let mut aggs: Vec<Self> = Vec::new();
+        for spec in text.split(',').map(str::trim) {
+            let agg = match spec.split_once(':') {
+                None if spec == "count" => Self {
+                    function: AggFunction::Count,
+                    property: None,
+                },
+                None => return Err(invalid("expected 'count' or '<function>:<property>'")),
+                Some((function, property)) => {
+                    let function = match function.trim() {
+                        "sum" => AggFunction::Sum,
+                        "avg" => AggFunction::Avg,
+                        "min" => AggFunction::Min,
+                        "max" => AggFunction::Max,
+                        _ => return Err(invalid("functions are sum, avg, min and max")),
+                    };
+                    let property = property.trim();
+                    if property.is_empty() {
+                        return Err(invalid("a property name is missing"));
+                    }
+                    Self {
+                        function,
+                        property: Some(property.to_string()),
+                    }
+                }
+            };
+            if aggs.contains(&agg) {
+                return Err(invalid("an aggregate is listed twice"));
+            }
+            aggs.push(agg);
+        }
+
+        Ok(aggs)
+    }
+
+    /// Output column, e.g. `neighbor_count` or `avg_neighbor_age`
+    pub fn column(&self) -> DiscoveredColumn {
+        let (name, column_type) = match (&self.function, &self.property) {
+            (AggFunction::Count, _) | (_, None) => {
+                ("neighbor_count".to_string(), ColumnType::Bigint)
+            }
+            (function, Some(property)) => {
+                let function = format!("{:?}", function).to_lowercase();
+                (format!("{}_neighbor_{}", function, property), ColumnType::Double)
+            }
+        };
+        DiscoveredColumn {
+            name,
+            column_type,
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        }
+    }
+}
+
+/// Which edges make two entities neighbours
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NeighborDirection {
+    /// Targets of outgoing edges
+    #[default]
+    Out,
+    /// Sources of incoming edges
+    In,
+    /// Either
+    Both,
+}
+
+impl NeighborDirection {
+    /// Parse the `neighbor_direction` parameter
+    pub fn parse(text: &str) -> Result<Self, ManifoldScannerError> {
+        match text {
+            "out" => Ok(Self::Out),
+            "in" => Ok(Self::In),
+            "both" => Ok(Self::Both),
+            _ => Err(ManifoldScannerError::InvalidParameter(format!(
+                "neighbor_direction must be 'out', 'in' or 'both', got '{}'",
+                text
+            ))),
+        }
+    }
+}
+
+/// The `neighbor_*` options of an entity scan
+#[derive(Debug, Clone, Default)]
+pub struct NeighborOptions {
+    /// Aggregates to compute; empty disables the feature
+    pub aggs: Vec<NeighborAgg>,
+    /// Only follow edges of this type
+    pub edge_type: Option<String>,
+    pub direction: NeighborDirection,
+}
+
+/// Entity id -> distinct neighbour ids
+pub type Adjacency = HashMap<u64, Vec<u64>>;
+
+/// Read every edge into an adjacency map for the options' type and direction
+fn build_adjacency(
+    tx: &RedbTransaction,
+    options: &NeighborOptions,
+) -> Result<Adjacency, Box<dyn Error>> {
+    let mut adjacency: Adjacency = HashMap::new();
+
+    if let Ok(mut cursor) = tx.cursor("edges") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            if let Ok(edge) = Edge::decode(&value) {
+                let type_matches = options
+                    .edge_type
+                    .as_deref()
+                    .is_none_or(|edge_type| edge.edge_type.as_str() == edge_type);
+                if type_matches {
+                    let (source, target) = (edge.source.as_u64(), edge.target.as_u64());
+                    if options.direction != NeighborDirection::In {
+                        adjacency.entry(source).or_default().push(target);
+                    }
+                    if options.direction != NeighborDirection::Out {
+                        adjacency.entry(target).or_default().push(source);
+                    }
+                }
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    for neighbours in adjacency.values_mut() {
+        neighbours.sort_unstable();
+        neighbours.dedup();
+    }
+    Ok(adjacency)
+}
+
+/// Numeric property values of neighbours, by entity id
+struct NeighborCache {
+    /// Properties read for each neighbour, in `values` order
+    properties: Vec<String>,
+    values: HashMap<u64, Vec<Option<f64>>>,
+}
+
+impl NeighborCache {
+    fn new(aggs: &[NeighborAgg]) -> Self {
+        let mut properties: Vec<String> = Vec::new();
+        for property in aggs.iter().filter_map(|agg| agg.property.clone()) {
+            if !properties.contains(&property) {
+                properties.push(property);
+            }
+        }
+        Self {
+            properties,
+            values: HashMap::new(),
+        }
+    }
+
+    /// The neighbour's value of each property, reading it on a miss
+    fn values(
+        &mut self,
+        tx: &RedbTransaction,
+        id: u64,
+    ) -> Result<&[Option<f64>], Box<dyn Error>> {
+        if !self.values.contains_key(&id) {
+            if self.values.len() >= NEIGHBOR_CACHE_CAPACITY {
+                self.values.clear();
+            }
+            // A missing or undecodable neighbour has no values
+            let entity = match tx.get("nodes", &id.to_be_bytes()) {
+                Ok(Some(value)) => Entity::decode(&value).ok(),
+                Ok(None) | Err(_) => None,
+            };
+            let values = self
+                .properties
+                .iter()
+                .map(|property| {
+                    entity
+                        .as_ref()
+                        .and_then(|entity| entity.properties.get(property))
+                        .and_then(value_to_f64)
+                })
+                .collect();
+            self.values.insert(id, values);
+        }
+        Ok(&self.values[&id])
+    }
+
+    /// Compute each aggregate over the neighbours
+    fn aggregate(
+        &mut self,
+        tx: &RedbTransaction,
+        aggs: &[NeighborAgg],
+        neighbours: &[u64],
+    ) -> Result<Vec<Option<f64>>, Box<dyn Error>> {
+        // Per property: (sum, count, min, max) of the numeric values
+        let empty = (0.0, 0usize, f64::INFINITY, f64::NEG_INFINITY);
+        let mut totals = vec![empty; self.properties.len()];
+        for &neighbour in neighbours {
+            for (total, value) in totals.iter_mut().zip(self.values(tx, neighbour)?) {
+                if let Some(value) = *value {
+                    total.0 += value;
+                    total.1 += 1;
+                    total.2 = total.2.min(value);
+                    total.3 = total.3.max(value);
+                }
+            }
+        }
+
+        Ok(aggs
+            .iter()
+            .map(|agg| {
+                let Some(property) = &agg.property else {
+                    return Some(neighbours.len() as f64);
+                };
+                let slot = self.properties.iter().position(|p| p == property)?;
+                let (sum, count, min, max) = totals[slot];
+                if count == 0 {
+                    return None;
+                }
+                Some(match agg.function {
+                    AggFunction::Count => count as f64,
+                    AggFunction::Sum => sum,
+                    AggFunction::Avg => sum / count as f64,
+                    AggFunction::Min => min,
+                    AggFunction::Max => max,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Adjacency, snapshot and cache for one scan's `neighbor_agg` columns
+pub struct NeighborAggregator {
+    aggs: Vec<NeighborAgg>,
+    state: Mutex<AggregatorState>,
+}
+
+struct AggregatorState {
+    snapshot: ScanSnapshot,
+    adjacency: Adjacency,
+    cache: NeighborCache,
+}
+
+impl NeighborAggregator {
+    /// Take a snapshot and read the adjacency from it
+    pub fn open(
+        engine: &ManifoldEngine,
+        options: &NeighborOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let snapshot = ScanSnapshot::begin(engine)?;
+        let adjacency = build_adjacency(snapshot.tx(), options)?;
+        Ok(Self {
+            aggs: options.aggs.clone(),
+            state: Mutex::new(AggregatorState {
+                snapshot,
+                adjacency,
+                cache: NeighborCache::new(&options.aggs),
+            }),
+        })
+    }
+
+    /// Fill the aggregate columns for the decoded entities of a batch; error
+    /// rows were already set to NULL. Nothing is aggregated when none of the
+    /// columns is projected.
+    pub fn populate(
+        &self,
+        entities: &[ScanRecord<Entity>],
+        column_index: &HashMap<String, usize>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let col_indices: Vec<Option<usize>> =
+            self.aggs.iter().map(|agg| column_index.get(&agg.column().name).copied()).collect();
+        if col_indices.iter().all(Option::is_none) {
+            return Ok(());
+        }
+        let state = &mut *self.state.lock().unwrap();
+
+        for (row_idx, record) in entities.iter().enumerate() {
+            let ScanRecord::Decoded { record: entity, .. } = record else {
+                continue;
+            };
+            let neighbours =
+                state.adjacency.get(&entity.id.as_u64()).map_or(&[][..], Vec::as_slice);
+            let results = state.cache.aggregate(state.snapshot.tx(), &self.aggs, neighbours)?;
+
+            for ((agg, &col_idx), result) in self.aggs.iter().zip(&col_indices).zip(results) {
+                let Some(col_idx) = col_idx else {
+                    continue;
+                };
+                let mut vector = output.flat_vector(col_idx);
+                match (agg.function, result) {
+                    (_, None) => vector.set_null(row_idx),
+                    (AggFunction::Count, Some(n)) => {
+                        vector.as_mut_slice::<i64>()[row_idx] = n as i64
+                    }
+                    (_, Some(x)) => vector.as_mut_slice::<f64>()[row_idx] = x,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId, Value};
+    use manifoldb_storage::StorageEngine;
+
+    #[test]
+    fn test_parse_neighbor_agg() {
+        let aggs = NeighborAgg::parse_list("count, avg:age,max:score").unwrap();
+        let names: Vec<String> = aggs.iter().map(|agg| agg.column().name).collect();
+        assert_eq!(names, vec!["neighbor_count", "avg_neighbor_age", "max_neighbor_score"]);
+        assert_eq!(aggs[0].column().column_type, ColumnType::Bigint);
+        assert_eq!(aggs[1].column().column_type, ColumnType::Double);
+
+        for invalid in ["", "total", "median:age", "avg:", "count,count"] {
+            assert!(NeighborAgg::parse_list(invalid).is_err(), "{}", invalid);
+        }
+        assert!(NeighborDirection::parse("sideways").is_err());
+    }
+
+    #[test]
+    fn test_neighbor_aggregates() {
+        let db = TempDb::new("neighbors");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for (id, age) in [(1u64, None), (2, Some(30)), (3, Some(40)), (4, None)] {
+            let mut entity = Entity::new(EntityId::from(id));
+            if let Some(age) = age {
+                entity.properties.insert("age".to_string(), Value::Int(age));
+            }
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        // 1 -> 2 twice, 1 -> 3, 1 -> 4 of another type, 3 -> 1
+        let edges = [
+            (1u64, 2u64, "KNOWS"),
+            (1, 2, "KNOWS"),
+            (1, 3, "KNOWS"),
+            (1, 4, "OWNS"),
+            (3, 1, "KNOWS"),
+        ];
+        for (i, &(source, target, edge_type)) in edges.iter().enumerate() {
+            let id = i as u64 + 1;
+            let edge = Edge::new(
+                EdgeId::from(id),
+                EntityId::from(source),
+                EntityId::from(target),
+                EdgeType::new(edge_type),
+            );
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let aggs = NeighborAgg::parse_list("count,avg:age,min:age,sum:missing").unwrap();
+        let options = |direction| NeighborOptions {
+            aggs: aggs.clone(),
+            edge_type: Some("KNOWS".to_string()),
+            direction,
+        };
+
+        let adjacency = build_adjacency(&tx, &options(NeighborDirection::Out)).unwrap();
+        assert_eq!(adjacency[&1], vec![2, 3]);
+        let mut cache = NeighborCache::new(&aggs);
+        let results = cache.aggregate(&tx, &aggs, &adjacency[&1]).unwrap();
+        assert_eq!(results, vec![Some(2.0), Some(35.0), Some(30.0), None]);
+
+        let adjacency = build_adjacency(&tx, &options(NeighborDirection::In)).unwrap();
+        assert_eq!(adjacency[&1], vec![3]);
+        let adjacency = build_adjacency(&tx, &options(NeighborDirection::Both)).unwrap();
+        assert_eq!(adjacency[&1], vec![2, 3]);
+        assert_eq!(adjacency[&2], vec![1]);
+        assert!(!adjacency.contains_key(&4));
+
+        // Neighbours without the property are left out, so avg is over 3 only
+        let results = cache.aggregate(&tx, &aggs, &[1, 3, 4]).unwrap();
+        assert_eq!(results, vec![Some(3.0), Some(40.0), Some(40.0), None]);
+    }
+}
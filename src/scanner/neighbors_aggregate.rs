@@ -0,0 +1,338 @@
+//! Neighbour property aggregates for ManifoldDB
+//!
+//! Implements a table function returning, for a set of seed entities, one
+//! aggregate of a numeric property over each seed's one-hop neighbours - the
+//! reduction step after `manifold_gather`, done in Rust so neighbour rows
+//! never reach DuckDB.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_neighbors_aggregate('/path/to/database.redb', ['1', '42'], 'out',
+//!     'KNOWS', 'score', 'mean');
+//! ```
+//!
+//! ## Columns
+//! - `seed_id` - Seed entity ID (VARCHAR)
+//! - `neighbor_count` - Number of distinct neighbours (BIGINT)
+//! - `value` - The aggregate (DOUBLE)
+//!
+//! ## Aggregates
+//! - `sum`, `mean` (or `avg`), `min`, `max` - over the neighbours' numeric
+//!   values of the property
+//! - `count` - how many neighbours have a numeric value
+//!
+//! Neighbours without a numeric value (absent, null, or a string that isn't a
+//! number) are left out, as with `neighbor_agg`. `value` is NULL when no
+//! neighbour has one - including seeds without neighbours - except for
+//! `count`, which is 0 then. Every seed has a row, in seed order; neighbours
+//! are found as in `manifold_gather`, each counted once however many edges
+//! join it to the seed.
+//!
+//! ## Cost
+//! One pass over the edges table finds every seed's neighbours, then each
+//! distinct neighbour is read once by a point lookup on the nodes table,
+//! however many seeds share it. Results are computed at init time, within one
+//! read transaction, and held in memory until emitted.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::Entity;
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::gather::seed_neighbours;
+use super::neighbors::{AggFunction, NeighborDirection};
+use super::reach_count::parse_seeds;
+use super::values::value_to_f64;
+use super::{chunk_batch_size, get_cached_engine, insert_str, string_list_param, string_param};
+
+/// What to aggregate
+#[derive(Debug, Clone)]
+pub struct NeighborsAggregateOptions {
+    /// Seeds, in output order
+    pub seeds: Vec<u64>,
+    /// Which edges lead to neighbours
+    pub direction: NeighborDirection,
+    /// Edge type to follow
+    pub edge_type: String,
+    /// Neighbour property aggregated
+    pub prop: String,
+    /// Aggregate function
+    pub function: AggFunction,
+}
+
+/// One seed's row
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedAggregate {
+    pub seed: u64,
+    /// Distinct neighbours
+    pub neighbors: usize,
+    /// The aggregate, None when no neighbour has a numeric value
+    pub value: Option<f64>,
+}
+
+/// Parse the aggregate function argument
+fn parse_function(text: &str) -> Result<AggFunction, ManifoldScannerError> {
+    match text {
+        "sum" => Ok(AggFunction::Sum),
+        "mean" | "avg" => Ok(AggFunction::Avg),
+        "min" => Ok(AggFunction::Min),
+        "max" => Ok(AggFunction::Max),
+        "count" => Ok(AggFunction::Count),
+        _ => Err(ManifoldScannerError::InvalidParameter(format!(
+            "function must be 'sum', 'mean', 'min', 'max' or 'count', got '{}'",
+            text
+        ))),
+    }
+}
+
+/// Bind data for neighbour aggregates
+#[repr(C)]
+pub struct ManifoldNeighborsAggregateBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Aggregation parameters
+    pub options: NeighborsAggregateOptions,
+}
+
+/// Init data for neighbour aggregates - holds the results
+#[repr(C)]
+pub struct ManifoldNeighborsAggregateInitData {
+    /// One row per seed, computed at init
+    pub rows: Vec<SeedAggregate>,
+    /// Number of rows already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Neighbour aggregate VTab implementation
+pub struct ManifoldNeighborsAggregateVTab;
+
+impl VTab for ManifoldNeighborsAggregateVTab {
+    type InitData = ManifoldNeighborsAggregateInitData;
+    type BindData = ManifoldNeighborsAggregateBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let seeds = parse_seeds(&string_list_param(bind, 1, "seeds")?)?;
+        let direction = string_param(bind, 2, "direction")?;
+        let edge_type = string_param(bind, 3, "edge_type")?;
+        let prop = string_param(bind, 4, "prop")?;
+        let function = parse_function(&string_param(bind, 5, "function")?)?;
+
+        let direction = NeighborDirection::parse(&direction).map_err(|_| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "direction must be 'out', 'in' or 'both', got '{}'",
+                direction
+            ))
+        })?;
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        bind.add_result_column("seed_id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("neighbor_count", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("value", LogicalTypeHandle::from(LogicalTypeId::Double));
+
+        Ok(ManifoldNeighborsAggregateBindData {
+            db_path,
+            options: NeighborsAggregateOptions {
+                seeds,
+                direction,
+                edge_type,
+                prop,
+                function,
+            },
+        })
+    }
+
+    /// Init phase: find the neighbours and aggregate their values
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldNeighborsAggregateBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        Ok(ManifoldNeighborsAggregateInitData {
+            rows: aggregate_neighbours(&tx, &bind_data.options)?,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the rows
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_neighbors_aggregate".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::list(&LogicalTypeId::Varchar.into()), // seeds
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // direction
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // edge_type
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // prop
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // function
+        ])
+    }
+}
+
+impl ManifoldNeighborsAggregateVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.rows.len());
+        let batch = &init_data.rows[start..end];
+
+        let seeds = output.flat_vector(0);
+        let mut counts = output.flat_vector(1);
+        let mut values = output.flat_vector(2);
+        for (row_idx, row) in batch.iter().enumerate() {
+            insert_str(&seeds, row_idx, &row.seed.to_string());
+            counts.as_mut_slice::<i64>()[row_idx] = row.neighbors as i64;
+            match row.value {
+                Some(value) => values.as_mut_slice::<f64>()[row_idx] = value,
+                None => values.set_null(row_idx),
+            }
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Aggregate numeric values with a function; None for no values, except
+/// `count`, which is 0
+fn aggregate(function: AggFunction, values: &[f64]) -> Option<f64> {
+    if function == AggFunction::Count {
+        return Some(values.len() as f64);
+    }
+    if values.is_empty() {
+        return None;
+    }
+    Some(match function {
+        AggFunction::Sum | AggFunction::Count => values.iter().sum(),
+        AggFunction::Avg => values.iter().sum::<f64>() / values.len() as f64,
+        AggFunction::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+        AggFunction::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    })
+}
+
+/// Find each seed's neighbours in one pass over the edges, read every
+/// distinct neighbour's value once, and aggregate per seed
+fn aggregate_neighbours(
+    tx: &RedbTransaction,
+    options: &NeighborsAggregateOptions,
+) -> Result<Vec<SeedAggregate>, Box<dyn Error>> {
+    let neighbours =
+        seed_neighbours(tx, &options.seeds, options.direction, &options.edge_type)?;
+
+    // Numeric value of each distinct neighbour; None for a missing entity, a
+    // record that won't decode, or no numeric value
+    let mut cache: HashMap<u64, Option<f64>> = HashMap::new();
+    let mut rows = Vec::with_capacity(options.seeds.len());
+    for seed in &options.seeds {
+        let mut values = Vec::new();
+        for &neighbour in &neighbours[seed] {
+            let value = cache.entry(neighbour).or_insert_with(|| {
+                let entity = match tx.get("nodes", &neighbour.to_be_bytes()) {
+                    Ok(Some(value)) => Entity::decode(&value).ok(),
+                    Ok(None) | Err(_) => None,
+                };
+                let entity = entity?;
+                entity.properties.get(&options.prop).and_then(value_to_f64)
+            });
+            values.extend(*value);
+        }
+        rows.push(SeedAggregate {
+            seed: *seed,
+            neighbors: neighbours[seed].len(),
+            value: aggregate(options.function, &values),
+        });
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{Edge, EdgeId, EntityId, Value};
+
+    #[test]
+    fn test_aggregate_neighbours() {
+        let db = TempDb::new("neighbors_aggregate");
+        let engine = db.open();
+
+        // 1 -> 2, 1 -> 3 (twice), 1 -> 4; 4 has no score and 5 has no
+        // neighbours
+        let mut tx = engine.begin_write().unwrap();
+        for (id, score) in [(1u64, Some(9.0)), (2, Some(1.0)), (3, Some(4.0)), (4, None), (5, None)]
+        {
+            let mut entity = Entity::new(EntityId::from(id));
+            if let Some(score) = score {
+                entity = entity.with_property("score", Value::Float(score));
+            }
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        for (id, source, target) in [(10u64, 1u64, 2u64), (11, 1, 3), (12, 1, 3), (13, 1, 4)] {
+            let (source, target) = (EntityId::from(source), EntityId::from(target));
+            let edge = Edge::new(EdgeId::from(id), source, target, "KNOWS");
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let options = |function| NeighborsAggregateOptions {
+            seeds: vec![1, 5],
+            direction: NeighborDirection::Out,
+            edge_type: "KNOWS".to_string(),
+            prop: "score".to_string(),
+            function,
+        };
+        let tx = engine.begin_read().unwrap();
+        let value = |function| {
+            let rows = aggregate_neighbours(&tx, &options(function)).unwrap();
+            assert_eq!(rows[0].neighbors, 3);
+            assert_eq!((rows[1].seed, rows[1].neighbors), (5, 0));
+            (rows[0].value, rows[1].value)
+        };
+
+        // Neighbour 4 has no score and is left out; seed 5 has no neighbours
+        assert_eq!(value(AggFunction::Avg), (Some(2.5), None));
+        assert_eq!(value(AggFunction::Sum), (Some(5.0), None));
+        assert_eq!(value(AggFunction::Min), (Some(1.0), None));
+        assert_eq!(value(AggFunction::Max), (Some(4.0), None));
+        assert_eq!(value(AggFunction::Count), (Some(2.0), Some(0.0)));
+
+        assert_eq!(parse_function("mean").unwrap(), AggFunction::Avg);
+        assert!(parse_function("median").is_err());
+    }
+}
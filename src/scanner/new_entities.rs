@@ -0,0 +1,332 @@
+//! Entities added between two snapshots of a database
+//!
+//! Implements a table function returning the entities present in one copy of
+//! a database but not in an earlier copy saved at another path, for computing
+//! additions without a full diff.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_new_entities('/backups/monday.redb', '/data/current.redb');
+//! ```
+//!
+//! ## Columns
+//! - `id` - Entity id
+//! - `labels` - Entity labels as LIST(VARCHAR)
+//! - `properties` - All properties as a JSON object with sorted keys
+//!
+//! ## Strategy
+//! Entities are matched by storage key only: an entity in both copies isn't
+//! returned even if its labels or properties changed, and entities removed
+//! since the base aren't reported. Both nodes tables are read in key order,
+//! one cursor each, and merged, so neither side is loaded into memory. Each
+//! side is read from its own snapshot held across batches. A missing nodes
+//! table is an empty one, and current records that fail to decode are skipped.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    ffi::CString,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::Entity;
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, Transaction};
+
+use super::engine::ManifoldEngine;
+use super::values::properties_to_json;
+use super::{
+    batch_full, chunk_batch_size, first_after, get_cached_engine, insert_str, read_scan_batch,
+    string_param, ScanBatch, ScanSnapshot,
+};
+
+/// Bind data for the new-entity scan
+#[repr(C)]
+pub struct ManifoldNewEntitiesBindData {
+    /// Path to the earlier copy
+    pub base_path: String,
+    /// Path to the later copy, whose entities are returned
+    pub current_path: String,
+}
+
+/// Snapshots of both copies, held across batches
+pub struct NewEntitiesSnapshots {
+    base: ScanSnapshot,
+    current: ScanSnapshot,
+}
+
+/// Init data for the new-entity scan - holds scan state
+#[repr(C)]
+pub struct ManifoldNewEntitiesInitData {
+    /// Flag indicating the scan is exhausted
+    pub done: AtomicBool,
+    /// Continuation marker - the last current key read
+    pub last_key: Mutex<Option<Vec<u8>>>,
+    /// Read transactions held across batches; None before the first batch and
+    /// after the scan completes
+    pub snapshots: Mutex<Option<NewEntitiesSnapshots>>,
+}
+
+impl ManifoldNewEntitiesInitData {
+    /// Read the next batch, releasing the snapshots once the scan is exhausted
+    fn next_batch(
+        &self,
+        bind_data: &ManifoldNewEntitiesBindData,
+        base: &ManifoldEngine,
+        current: &ManifoldEngine,
+        batch_size: usize,
+    ) -> Result<Vec<Entity>, Box<dyn Error>> {
+        if self.done.load(Ordering::Relaxed) {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let last_key = self.last_key.lock().unwrap().clone();
+
+        let current_path = &bind_data.current_path;
+        let batch = read_scan_batch(&self.done, current_path, last_key.as_deref(), || {
+            let snapshots = match &mut *snapshots {
+                Some(snapshots) => snapshots,
+                None => snapshots.insert(NewEntitiesSnapshots {
+                    base: ScanSnapshot::begin(base)?,
+                    current: ScanSnapshot::begin(current)?,
+                }),
+            };
+            scan_new_entity_batch(
+                snapshots.base.tx(),
+                snapshots.current.tx(),
+                last_key.as_deref(),
+                batch_size,
+            )
+        });
+        let (entities, next_key) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                // The scan is over; don't keep the snapshots pinned
+                *snapshots = None;
+                return Err(e.into());
+            }
+        };
+
+        if next_key.is_none() {
+            // The current copy is exhausted - stop pinning both snapshots
+            self.done.store(true, Ordering::Relaxed);
+            *snapshots = None;
+            return Ok(entities);
+        }
+
+        *self.last_key.lock().unwrap() = next_key;
+        Ok(entities)
+    }
+}
+
+/// New-entity scan VTab implementation
+pub struct ManifoldNewEntitiesVTab;
+
+impl VTab for ManifoldNewEntitiesVTab {
+    type InitData = ManifoldNewEntitiesInitData;
+    type BindData = ManifoldNewEntitiesBindData;
+
+    /// Bind phase: open both copies and set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let base_path = string_param(bind, 0, "base_path")?;
+        let current_path = string_param(bind, 1, "current_path")?;
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&base_path)?;
+        get_cached_engine(&current_path)?;
+
+        let varchar = || LogicalTypeHandle::from(LogicalTypeId::Varchar);
+        bind.add_result_column("id", varchar());
+        bind.add_result_column("labels", LogicalTypeHandle::list(&varchar()));
+        bind.add_result_column("properties", varchar());
+
+        Ok(ManifoldNewEntitiesBindData {
+            base_path,
+            current_path,
+        })
+    }
+
+    /// Init phase: the snapshots are taken with the first batch
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldNewEntitiesInitData {
+            done: AtomicBool::new(false),
+            last_key: Mutex::new(None),
+            snapshots: Mutex::new(None),
+        })
+    }
+
+    /// Func phase: emit the next batch of new entities
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_new_entities".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // base_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // current_path
+        ])
+    }
+}
+
+impl ManifoldNewEntitiesVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+
+        let base = get_cached_engine(&bind_data.base_path)?;
+        let current = get_cached_engine(&bind_data.current_path)?;
+        let batch_size = chunk_batch_size(output);
+        let entities = init_data.next_batch(bind_data, &base, &current, batch_size)?;
+        populate_new_entity_output(&entities, output)?;
+        output.set_len(entities.len());
+
+        Ok(())
+    }
+}
+
+/// Read current entities after `after_key` whose keys the base doesn't have
+///
+/// Returns up to `batch_size` entities and the current key to continue after,
+/// which is None once the current copy is exhausted. Reading stops early once
+/// a batch's worth of records has been read and at least one entity kept, so
+/// an empty batch only comes back at the end.
+fn scan_new_entity_batch(
+    base_tx: &RedbTransaction,
+    current_tx: &RedbTransaction,
+    after_key: Option<&[u8]>,
+    batch_size: usize,
+) -> Result<ScanBatch<Entity>, Box<dyn Error>> {
+    let mut entities = Vec::with_capacity(batch_size);
+    let Ok(mut current) = current_tx.cursor("nodes") else {
+        return Ok((entities, None));
+    };
+    let mut base = base_tx.cursor("nodes").ok();
+
+    let mut entry = first_after(&mut current, after_key)?;
+    let mut base_key = match &mut base {
+        Some(cursor) => first_after(cursor, after_key)?.map(|(key, _)| key),
+        None => None,
+    };
+
+    let mut read = 0;
+    while let Some((key, value)) = entry {
+        // Bring the base cursor level with this key
+        while base_key.as_deref().is_some_and(|base_key| base_key < key.as_slice()) {
+            base_key = base.as_mut().expect("base was read").next()?.map(|(key, _)| key);
+        }
+
+        if base_key.as_deref() != Some(key.as_slice()) {
+            if let Ok(entity) = Entity::decode(&value) {
+                entities.push(entity);
+            }
+        }
+        read += 1;
+
+        if batch_full(entities.len(), read, batch_size) {
+            return Ok((entities, Some(key)));
+        }
+        entry = current.next()?;
+    }
+
+    Ok((entities, None))
+}
+
+/// Populate DuckDB output chunk with new entity rows
+fn populate_new_entity_output(
+    entities: &[Entity],
+    output: &mut DataChunkHandle,
+) -> Result<(), Box<dyn Error>> {
+    let ids = output.flat_vector(0);
+    let properties = output.flat_vector(2);
+
+    let mut labels = output.list_vector(1);
+    let label_count = entities.iter().map(|entity| entity.labels.len()).sum();
+    let label_values = labels.child(label_count);
+    let mut label_offset = 0;
+
+    for (row_idx, entity) in entities.iter().enumerate() {
+        ids.insert(row_idx, CString::new(entity.id.as_u64().to_string())?);
+        for (i, label) in entity.labels.iter().enumerate() {
+            insert_str(&label_values, label_offset + i, label.as_str());
+        }
+        labels.set_entry(row_idx, label_offset, entity.labels.len());
+        label_offset += entity.labels.len();
+        properties.insert(row_idx, CString::new(properties_to_json(&entity.properties))?);
+    }
+    labels.set_len(label_offset);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::EntityId;
+    use manifoldb_storage::backends::RedbEngine;
+    use manifoldb_storage::StorageEngine;
+
+    /// Write a copy with entities at the given ids
+    fn write_copy(name: &str, ids: &[u64]) -> (TempDb, RedbEngine) {
+        let db = TempDb::new(&format!("new_entities_{}", name));
+        let engine = db.open();
+        let mut tx = engine.begin_write().unwrap();
+        for &id in ids {
+            let entity = Entity::new(EntityId::from(id));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+        (db, engine)
+    }
+
+    #[test]
+    fn test_scan_new_entity_batch() {
+        let (_base_db, base) = write_copy("base", &[1, 2, 4, 7, 9]);
+        let (_current_db, current) = write_copy("current", &[2, 3, 4, 5, 6, 8, 9, 10]);
+
+        // Batches of 2 continue after the last key read, across runs of shared keys
+        let base_tx = base.begin_read().unwrap();
+        let current_tx = current.begin_read().unwrap();
+        let mut after = None;
+        let mut ids = Vec::new();
+        loop {
+            let (entities, next) =
+                scan_new_entity_batch(&base_tx, &current_tx, after.as_deref(), 2).unwrap();
+            ids.extend(entities.iter().map(|entity| entity.id.as_u64()));
+            match next {
+                Some(key) => after = Some(key),
+                None => break,
+            }
+        }
+        assert_eq!(ids, vec![3, 5, 6, 8, 10]);
+
+        // A copy has nothing new over itself
+        let (entities, next) = scan_new_entity_batch(&current_tx, &current_tx, None, 3).unwrap();
+        assert!(entities.is_empty());
+        assert_eq!(next, None);
+    }
+}
@@ -0,0 +1,225 @@
+//! Pushdown diagnostics
+//!
+//! Implements `manifold_last_pushdown()`, a table function listing what the
+//! most recent entity or edge scan took over from DuckDB: the columns it was
+//! asked to fill and the filters it applied itself, so a pushdown option can
+//! be checked to have engaged rather than assumed to.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT id FROM manifold_entities('/path/to/database.redb', has_prop='email');
+//! SELECT * FROM manifold_last_pushdown();
+//! ```
+//!
+//! ## Columns
+//! One row per pushed projection or filter, none if no scan has run:
+//! - `function`, `db_path` - The scan, as in `manifold_last_scan_stats`
+//! - `kind` - `'projection'` or `'filter'`
+//! - `column_name` - The column filled, or what the filter tests: a
+//!   `where_json` path, a property, `labels`, `id` or `edge_type`
+//! - `operator` - The filter's comparison (NULL for projections)
+//! - `value` - The filter's operand, as text (NULL when it has none)
+//! - `applied` - Where the work happens: `'scan'` when the scanner does it
+//!   while reading, `'index'` when it seeks an index instead of reading
+//!   everything, `'duckdb'` when the scan fills the column regardless and
+//!   DuckDB drops what the query doesn't use
+//!
+//! `manifold_entities` and its variants fill only the columns the query
+//! uses, so their projection rows list just those (none for `count(*)`).
+//! Edge scans don't take projections, so every column is listed as
+//! `'duckdb'`.
+//!
+//! ## What Is Not Listed
+//! DuckDB doesn't hand SQL `WHERE` predicates to these scanners (see
+//! `filter_pushdown` in `manifold_config`): they are always evaluated by
+//! DuckDB after the scan, and never appear here. Only the filters given as
+//! scanner parameters do - `where_json`, `has_prop`, the label list of
+//! `manifold_entities_by_labels`, the id list of `manifold_entities_ordered`,
+//! the type of `manifold_edges_of_type`, `dedupe_reciprocal` and the
+//! `record_filter` of a mixed table (as `record tag`). Their effect
+//! shows in `rows_filtered` of `manifold_last_scan_stats`.
+//!
+//! The report is published when a scan starts. As with the scan stats,
+//! DuckDB gives functions no handle on the calling connection, so the last
+//! scan is the last one in the process, across connections.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use super::{chunk_batch_size, insert_str};
+
+/// Pushdown of the most recent scan
+static LAST_PUSHDOWN: Mutex<Option<PushdownReport>> = Mutex::new(None);
+
+/// The scanner does the work while reading
+pub const APPLIED_SCAN: &str = "scan";
+/// The scanner seeks an index rather than reading every record
+pub const APPLIED_INDEX: &str = "index";
+/// DuckDB does the work after the scan
+pub const APPLIED_DUCKDB: &str = "duckdb";
+
+/// One pushed projection or filter
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushdownEntry {
+    pub kind: &'static str,
+    pub column: String,
+    pub operator: Option<String>,
+    pub value: Option<String>,
+    pub applied: &'static str,
+}
+
+impl PushdownEntry {
+    /// A column the scan fills
+    pub fn projection(column: &str, applied: &'static str) -> Self {
+        Self {
+            kind: "projection",
+            column: column.to_string(),
+            operator: None,
+            value: None,
+            applied,
+        }
+    }
+
+    /// A filter the scan applies
+    pub fn filter(
+        column: impl Into<String>,
+        operator: &str,
+        value: Option<String>,
+        applied: &'static str,
+    ) -> Self {
+        Self {
+            kind: "filter",
+            column: column.into(),
+            operator: Some(operator.to_string()),
+            value,
+            applied,
+        }
+    }
+}
+
+/// A scan's pushed projections and filters
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushdownReport {
+    pub function: String,
+    pub db_path: String,
+    /// Filters first, then projections in column order
+    pub entries: Vec<PushdownEntry>,
+}
+
+impl PushdownReport {
+    /// Make this the last scan's report
+    pub fn publish(self) {
+        *LAST_PUSHDOWN.lock().unwrap() = Some(self);
+    }
+}
+
+/// Bind data for last pushdown - the report, read at bind
+#[repr(C)]
+pub struct ManifoldLastPushdownBindData {
+    /// None if no scan has run yet
+    pub report: Option<PushdownReport>,
+}
+
+/// Init data for last pushdown - tracks emitted rows
+#[repr(C)]
+pub struct ManifoldLastPushdownInitData {
+    /// Number of entries already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Last pushdown VTab implementation
+pub struct ManifoldLastPushdownVTab;
+
+impl VTab for ManifoldLastPushdownVTab {
+    type InitData = ManifoldLastPushdownInitData;
+    type BindData = ManifoldLastPushdownBindData;
+
+    /// Bind phase: take a copy of the report, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let columns =
+            ["function", "db_path", "kind", "column_name", "operator", "value", "applied"];
+        for name in columns {
+            bind.add_result_column(name, LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        }
+
+        let report = LAST_PUSHDOWN.lock().unwrap().clone();
+        let rows = report.as_ref().map_or(0, |report| report.entries.len());
+        bind.set_cardinality(rows as u64, true);
+
+        Ok(ManifoldLastPushdownBindData { report })
+    }
+
+    /// Init phase: nothing to prepare
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldLastPushdownInitData {
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the entries
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_last_pushdown".into()),
+        }
+    }
+
+    /// No parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        None
+    }
+}
+
+impl ManifoldLastPushdownVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+        let Some(report) = &bind_data.report else {
+            output.set_len(0);
+            return Ok(());
+        };
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(report.entries.len());
+        let batch = &report.entries[start..end];
+
+        let vectors: Vec<_> = (0..7).map(|idx| output.flat_vector(idx)).collect();
+        for (row_idx, entry) in batch.iter().enumerate() {
+            insert_str(&vectors[0], row_idx, &report.function);
+            insert_str(&vectors[1], row_idx, &report.db_path);
+            insert_str(&vectors[2], row_idx, entry.kind);
+            insert_str(&vectors[3], row_idx, &entry.column);
+            for (vector_idx, text) in [(4, &entry.operator), (5, &entry.value)] {
+                match text {
+                    Some(text) => insert_str(&vectors[vector_idx], row_idx, text),
+                    None => output.flat_vector(vector_idx).set_null(row_idx),
+                }
+            }
+            insert_str(&vectors[6], row_idx, entry.applied);
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+//! Reachable set sizes for ManifoldDB
+//!
+//! Implements a table function returning, for each node, how many distinct
+//! entities are reachable from it within a number of hops along edges of one
+//! type - a simple measure of influence.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_reach_count('/path/to/database.redb', 2, 'KNOWS');
+//! SELECT * FROM manifold_reach_count('/path/to/database.redb', 3, 'FOLLOWS',
+//!     seeds=['1', '42']);
+//! ```
+//!
+//! ## Columns
+//! - `node_id` - Entity ID the search started from (VARCHAR)
+//! - `reach` - Distinct entities reached within `depth` hops, not counting
+//!   the node itself (BIGINT)
+//!
+//! Edges are followed from source to target only, as in `manifold_traverse`.
+//!
+//! ## Cost
+//! Each node gets its own breadth-first search over the `manifold_traverse`
+//! core, keeping only the size of the reached set, so the work grows with
+//! the number of nodes times the size of their neighbourhoods. Nodes are
+//! every entity in the nodes table, or the `seeds` list when given; the
+//! function errors out before searching if there are more than `max_nodes`
+//! of them (default 10000). Results are computed at init time, within one
+//! read transaction.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::types::EdgeType;
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::traverse::{breadth_first, Neighbours};
+use super::{
+    chunk_batch_size, get_cached_engine, i64_param, named_i64, named_string_list, string_param,
+};
+
+/// Default bound on the number of nodes searched from
+const DEFAULT_MAX_NODES: usize = 10_000;
+
+/// What to count
+#[derive(Debug, Clone)]
+pub struct ReachOptions {
+    /// Maximum number of hops
+    pub max_depth: usize,
+    /// Edge type to follow
+    pub edge_type: String,
+    /// Nodes to count for (None = every entity)
+    pub seeds: Option<Vec<u64>>,
+    /// Most nodes the function will search from
+    pub max_nodes: usize,
+}
+
+/// Bind data for reach counts
+#[repr(C)]
+pub struct ManifoldReachCountBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Counting parameters
+    pub options: ReachOptions,
+}
+
+/// Init data for reach counts - holds the results
+#[repr(C)]
+pub struct ManifoldReachCountInitData {
+    /// (node, reach) in seed or id order
+    pub counts: Vec<(u64, usize)>,
+    /// Number of rows already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Reach count VTab implementation
+pub struct ManifoldReachCountVTab;
+
+impl VTab for ManifoldReachCountVTab {
+    type InitData = ManifoldReachCountInitData;
+    type BindData = ManifoldReachCountBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let depth = i64_param(bind, 1, "depth")?;
+        let edge_type = string_param(bind, 2, "edge_type")?;
+
+        if depth < 0 {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "depth must not be negative, got {}",
+                depth
+            ))
+            .into());
+        }
+
+        let max_nodes = match named_i64(bind, "max_nodes")? {
+            Some(max_nodes) if max_nodes > 0 => max_nodes as usize,
+            Some(max_nodes) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "max_nodes must be positive, got {}",
+                    max_nodes
+                ))
+                .into())
+            }
+            None => DEFAULT_MAX_NODES,
+        };
+
+        let seeds = named_string_list(bind, "seeds")?;
+        let seeds = seeds.map(|seeds| parse_seeds(&seeds)).transpose()?;
+
+        bind.add_result_column("node_id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("reach", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+
+        Ok(ManifoldReachCountBindData {
+            db_path,
+            options: ReachOptions {
+                max_depth: depth as usize,
+                edge_type,
+                seeds,
+                max_nodes,
+            },
+        })
+    }
+
+    /// Init phase: run a search from every node
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldReachCountBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        Ok(ManifoldReachCountInitData {
+            counts: reach_counts(&tx, &bind_data.options)?,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the counts
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_reach_count".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Bigint),  // depth
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // edge_type
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "seeds".to_string(),
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar)),
+            ),
+            (
+                "max_nodes".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Bigint),
+            ),
+        ])
+    }
+}
+
+impl ManifoldReachCountVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.counts.len());
+        let batch = &init_data.counts[start..end];
+
+        let ids = output.flat_vector(0);
+        let mut reaches = output.flat_vector(1);
+        for (row_idx, &(node, reach)) in batch.iter().enumerate() {
+            ids.insert(row_idx, CString::new(node.to_string())?);
+            reaches.as_mut_slice::<i64>()[row_idx] = reach as i64;
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Parse seed ids, dropping repeats
+pub(crate) fn parse_seeds(seeds: &[String]) -> Result<Vec<u64>, ManifoldScannerError> {
+    let mut seen = HashSet::new();
+    let mut ids = Vec::with_capacity(seeds.len());
+    for seed in seeds {
+        let id = seed.trim().parse::<u64>().map_err(|_| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "seeds must be entity ids, got '{}'",
+                seed
+            ))
+        })?;
+        if seen.insert(id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Every entity id in the nodes table, failing once there are more than
+/// `max_nodes`
+fn all_nodes(tx: &RedbTransaction, max_nodes: usize) -> Result<Vec<u64>, Box<dyn Error>> {
+    let mut ids = Vec::new();
+    let Ok(mut cursor) = tx.cursor("nodes") else {
+        // Table doesn't exist yet - no nodes
+        return Ok(ids);
+    };
+
+    let mut entry = cursor.seek_first()?;
+    while let Some((key, _value)) = entry {
+        if let Ok(bytes) = <[u8; 8]>::try_from(key.as_slice()) {
+            if ids.len() == max_nodes {
+                return Err(too_many_nodes(max_nodes).into());
+            }
+            ids.push(u64::from_be_bytes(bytes));
+        }
+        entry = cursor.next()?;
+    }
+    Ok(ids)
+}
+
+fn too_many_nodes(max_nodes: usize) -> ManifoldScannerError {
+    ManifoldScannerError::InvalidParameter(format!(
+        "manifold_reach_count would search from more than max_nodes ({}) nodes; \
+         pass seeds or raise max_nodes",
+        max_nodes
+    ))
+}
+
+/// Size of the set reachable within `max_depth` hops from each node
+fn reach_counts(
+    tx: &RedbTransaction,
+    options: &ReachOptions,
+) -> Result<Vec<(u64, usize)>, Box<dyn Error>> {
+    let nodes = match &options.seeds {
+        Some(seeds) if seeds.len() > options.max_nodes => {
+            return Err(too_many_nodes(options.max_nodes).into())
+        }
+        Some(seeds) => seeds.clone(),
+        None => all_nodes(tx, options.max_nodes)?,
+    };
+
+    let edge_type = EdgeType::new(options.edge_type.as_str());
+    let neighbours = Neighbours::open(tx, &edge_type)?;
+
+    let mut counts = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let mut reach = 0;
+        let admit = |_| Ok(true);
+        breadth_first(tx, &neighbours, &edge_type, node, options.max_depth, admit, |_| {
+            reach += 1
+        })?;
+        counts.push((node, reach));
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{Edge, EdgeId, Entity, EntityId};
+
+    #[test]
+    fn test_reach_counts() {
+        let db = TempDb::new("reach_count");
+        let engine = db.open();
+
+        // 1 -> 2 -> 3 -> 1 is a cycle, 3 -> 4, and 5 only has a LIKES edge
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1..=5u64 {
+            let entity = Entity::new(EntityId::from(id));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        let edges = [(10u64, 1u64, 2u64, "KNOWS"), (11, 2, 3, "KNOWS"), (12, 3, 1, "KNOWS")];
+        let edges = edges.into_iter().chain([(13, 3, 4, "KNOWS"), (14, 5, 1, "LIKES")]);
+        for (id, source, target, edge_type) in edges {
+            let edge = Edge::new(
+                EdgeId::from(id),
+                EntityId::from(source),
+                EntityId::from(target),
+                edge_type,
+            );
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let options = |max_depth, seeds: Option<Vec<u64>>, max_nodes| ReachOptions {
+            max_depth,
+            edge_type: "KNOWS".to_string(),
+            seeds,
+            max_nodes,
+        };
+        let tx = engine.begin_read().unwrap();
+
+        // The start isn't counted, even when a cycle leads back to it
+        let counts = reach_counts(&tx, &options(1, None, 10)).unwrap();
+        assert_eq!(counts, vec![(1, 1), (2, 1), (3, 2), (4, 0), (5, 0)]);
+        let counts = reach_counts(&tx, &options(5, None, 10)).unwrap();
+        assert_eq!(counts, vec![(1, 3), (2, 3), (3, 3), (4, 0), (5, 0)]);
+
+        let counts = reach_counts(&tx, &options(2, Some(vec![2, 1]), 2)).unwrap();
+        assert_eq!(counts, vec![(2, 3), (1, 2)]);
+
+        let error = reach_counts(&tx, &options(2, None, 4)).unwrap_err().to_string();
+        assert!(error.contains("more than max_nodes (4)"), "{}", error);
+        assert!(reach_counts(&tx, &options(2, Some(vec![1, 2, 3]), 2)).is_err());
+
+        assert_eq!(parse_seeds(&["3".to_string(), " 3".to_string()]).unwrap(), vec![3]);
+        assert!(parse_seeds(&["x".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+//! Reopening cached databases
+//!
+//! Implements `manifold_refresh(path)`, a scalar function that drops the
+//! cached handle for a database and opens it again.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT manifold_refresh('/path/to/database.redb');
+//! ```
+//!
+//! ## Concurrency Contract
+//! Each path is opened once and the handle cached for the life of the
+//! process (see `get_cached_engine`). Every query begins a new read
+//! transaction on that handle, so it sees everything committed through it.
+//!
+//! redb locks the file for as long as a handle is open: exclusively for a
+//! read-write handle, shared for a read-only one. While this extension has a
+//! file cached, any other handle that tries to open it for writing - another
+//! process, or ManifoldDB itself in this process - fails with "database
+//! already open", and if ManifoldDB has it open first, scans fail the same
+//! way. Two handles therefore never write and read the same file at once, and
+//! the cache can't serve data older than another handle's commits.
+//!
+//! The cached handle does go stale when the file is replaced rather than
+//! written in place (a new snapshot renamed over the old path leaves the
+//! handle reading the old file), or on filesystems without file locks, where
+//! redb can't detect a writer and a handle keeps the state it opened with.
+//! `manifold_refresh` drops the handle and reopens the path to pick up the
+//! current file. Scans already running keep the old handle until they finish.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeId},
+    ffi::duckdb_string_t,
+    types::DuckString,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+
+use super::{drop_cached_engine, get_cached_engine};
+
+/// Drop the cached handle for a path and open it again, returning whether a
+/// handle was cached
+pub fn refresh_engine(db_path: &str) -> Result<bool, Box<dyn Error>> {
+    let was_cached = drop_cached_engine(db_path)?;
+    get_cached_engine(db_path)?;
+    Ok(was_cached)
+}
+
+/// `manifold_refresh` scalar implementation
+pub struct ManifoldRefreshScalar;
+
+impl VScalar for ManifoldRefreshScalar {
+    type State = ();
+
+    /// Reopen each non-NULL path, returning whether it had been cached
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let paths = input.flat_vector(0);
+        let values = paths.as_slice_with_len::<duckdb_string_t>(input.len());
+        let mut output = output.flat_vector();
+
+        for (row_idx, path) in values.iter().enumerate() {
+            if paths.row_is_null(row_idx as u64) {
+                output.set_null(row_idx);
+                continue;
+            }
+
+            let path = DuckString::new(&mut { *path }).as_str().to_string();
+            output.as_mut_slice::<bool>()[row_idx] = refresh_engine(&path)?;
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::exact(
+            vec![LogicalTypeId::Varchar.into()],
+            LogicalTypeId::Boolean.into(),
+        )]
+    }
+
+    /// Reopening is a side effect; never constant-fold it
+    fn volatile() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_storage::backends::RedbEngine;
+    use manifoldb_storage::{StorageEngine, Transaction};
+
+    #[test]
+    fn test_refresh_engine() {
+        let db = TempDb::new("refresh");
+        let db_path = db.db_path();
+        let replacement = db.path().with_extension("next");
+
+        let write = |path: &std::path::Path, value: &[u8]| {
+            let engine = RedbEngine::open(path).unwrap();
+            let mut tx = engine.begin_write().unwrap();
+            tx.put("nodes", &1u64.to_be_bytes(), value).unwrap();
+            tx.commit().unwrap();
+        };
+        let read = || {
+            let tx = get_cached_engine(db_path).unwrap().begin_read().unwrap();
+            tx.get("nodes", &1u64.to_be_bytes()).unwrap()
+        };
+
+        write(db.path(), b"old");
+        assert_eq!(read().as_deref(), Some(&b"old"[..]));
+
+        // The cached handle locks the file against other writers
+        assert!(RedbEngine::open(db.path()).is_err());
+
+        // A snapshot renamed over the path is invisible until a refresh
+        write(&replacement, b"new");
+        std::fs::rename(&replacement, db.path()).unwrap();
+        assert_eq!(read().as_deref(), Some(&b"old"[..]));
+        assert!(refresh_engine(db_path).unwrap());
+        assert_eq!(read().as_deref(), Some(&b"new"[..]));
+
+        drop_cached_engine(db_path).unwrap();
+        assert!(!refresh_engine(db_path).unwrap());
+        drop_cached_engine(db_path).unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+//! External merge sort for ordered scans
+//!
+//! Buffers `(sort key, record)` pairs in memory and, once the buffer exceeds a
+//! memory threshold, sorts it and spills it to a temporary run file. When the
+//! input is complete the in-memory remainder and all runs are merged with a
+//! heap, so only one pending entry per run is held in memory during output.
+//! At most `MERGE_FAN_IN` runs are open at once: beyond that, runs are first
+//! merged in groups into longer runs, pass after pass, so a small memory limit
+//! can't exhaust file descriptors.
+//!
+//! ## Run File Format
+//! A sequence of entries, each `key_len: u32 LE`, key bytes, `record_len: u32 LE`,
+//! record bytes. Each sorter keeps its runs in a directory of its own in the
+//! system temp directory, created fresh (owner-only on Unix) so no other user
+//! can plant or read files there; run files are created new, never reused.
+//! Runs are removed when the sorter or stream that owns them is dropped, and
+//! the directory with the last of them.
+
+use std::{
+    cmp::{Ordering as CmpOrdering, Reverse},
+    collections::BinaryHeap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Default in-memory budget before spilling to disk (64 MiB)
+pub const DEFAULT_SORT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Approximate per-entry bookkeeping cost (two Vec headers)
+const ENTRY_OVERHEAD: usize = 48;
+
+/// Most runs merged at once
+const MERGE_FAN_IN: usize = 64;
+
+/// Names tried for a spill directory before giving up
+const SPILL_DIR_ATTEMPTS: usize = 100;
+
+/// Counter for unique spill directory names within this process
+static SPILL_DIR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A sort key and the record it orders
+pub type SortEntry = (Vec<u8>, Vec<u8>);
+
+/// A sorter's private directory for its runs, removed once they all are
+struct SpillDir {
+    path: PathBuf,
+}
+
+impl SpillDir {
+    /// Create a new directory, never one that already exists
+    fn create() -> io::Result<Self> {
+        for _ in 0..SPILL_DIR_ATTEMPTS {
+            let path = std::env::temp_dir().join(format!(
+                "manifold_sort_{}_{}",
+                std::process::id(),
+                SPILL_DIR_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let mut builder = fs::DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "no free name for a sort spill directory",
+        ))
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir(&self.path);
+    }
+}
+
+/// A spilled run on disk, deleted when dropped
+struct RunFile {
+    path: PathBuf,
+    /// Keeps the directory until its last run is gone
+    _dir: Arc<SpillDir>,
+}
+
+impl Drop for RunFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Sorts entries by key, spilling to disk past a memory limit
+pub struct ExternalSorter {
+    memory_limit: usize,
+    buffer: Vec<SortEntry>,
+    buffered_bytes: usize,
+    runs: Vec<RunFile>,
+    /// Created with the first run
+    dir: Option<Arc<SpillDir>>,
+    /// Runs written so far, naming the next
+    written_runs: usize,
+}
+
+impl ExternalSorter {
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            memory_limit,
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            runs: Vec::new(),
+            dir: None,
+            written_runs: 0,
+        }
+    }
+
+    /// Add an entry, spilling the buffer if it grows past the limit
+    pub fn push(&mut self, key: Vec<u8>, record: Vec<u8>) -> io::Result<()> {
+        self.buffered_bytes += key.len() + record.len() + ENTRY_OVERHEAD;
+        self.buffer.push((key, record));
+
+        if self.buffered_bytes > self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Number of runs written to disk so far
+    #[cfg(test)]
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Finish input and return the merged, sorted stream
+    pub fn finish(mut self) -> io::Result<SortedStream> {
+        self.buffer.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        // The final merge also reads the in-memory buffer
+        while self.runs.len() > MERGE_FAN_IN - 1 {
+            self.merge_pass()?;
+        }
+
+        let mut sources = Vec::with_capacity(self.runs.len() + 1);
+        sources.push(Source::Memory(std::mem::take(&mut self.buffer).into_iter()));
+        for run in self.runs.drain(..) {
+            sources.push(Source::open(run)?);
+        }
+
+        SortedStream::new(sources)
+    }
+
+    /// Sort the buffer and write it out as a run
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let entries = std::mem::take(&mut self.buffer).into_iter().map(Ok);
+        let run = self.write_run(entries)?;
+
+        self.runs.push(run);
+        self.buffered_bytes = 0;
+        Ok(())
+    }
+
+    /// Merge the runs in groups of `MERGE_FAN_IN`, each into one longer run
+    fn merge_pass(&mut self) -> io::Result<()> {
+        let mut runs = std::mem::take(&mut self.runs).into_iter().peekable();
+        while runs.peek().is_some() {
+            let group: Vec<RunFile> = runs.by_ref().take(MERGE_FAN_IN).collect();
+            if group.len() == 1 {
+                self.runs.extend(group);
+                continue;
+            }
+            let sources = group.into_iter().map(Source::open).collect::<io::Result<_>>()?;
+            let merged = self.write_run(SortedStream::new(sources)?)?;
+            self.runs.push(merged);
+        }
+        Ok(())
+    }
+
+    /// Write sorted entries out as a new run file
+    fn write_run(
+        &mut self,
+        entries: impl Iterator<Item = io::Result<SortEntry>>,
+    ) -> io::Result<RunFile> {
+        let dir = match &self.dir {
+            Some(dir) => Arc::clone(dir),
+            None => Arc::clone(self.dir.insert(Arc::new(SpillDir::create()?))),
+        };
+        let path = dir.path.join(format!("{}.run", self.written_runs));
+        self.written_runs += 1;
+
+        let file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        let run = RunFile { path, _dir: dir };
+        let mut writer = BufWriter::new(file);
+        for entry in entries {
+            let (key, record) = entry?;
+            write_chunk(&mut writer, &key)?;
+            write_chunk(&mut writer, &record)?;
+        }
+        writer.flush()?;
+        Ok(run)
+    }
+}
+
+/// One input to the merge
+enum Source {
+    Memory(std::vec::IntoIter<SortEntry>),
+    File {
+        reader: BufReader<File>,
+        _run: RunFile,
+    },
+}
+
+impl Source {
+    /// Read a run from its start
+    fn open(run: RunFile) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(&run.path)?);
+        Ok(Source::File { reader, _run: run })
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<SortEntry>> {
+        match self {
+            Source::Memory(entries) => Ok(entries.next()),
+            Source::File { reader, .. } => {
+                let Some(key) = read_chunk(reader)? else {
+                    return Ok(None);
+                };
+                let record = read_chunk(reader)?.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated sort run")
+                })?;
+                Ok(Some((key, record)))
+            }
+        }
+    }
+}
+
+/// Heap entry: the next pending entry of one source
+struct Pending {
+    key: Vec<u8>,
+    record: Vec<u8>,
+    source: usize,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        // Equal keys come out in source order so the merge is deterministic
+        self.key
+            .cmp(&other.key)
+            .then(self.source.cmp(&other.source))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sorted output of an `ExternalSorter`
+pub struct SortedStream {
+    sources: Vec<Source>,
+    heap: BinaryHeap<Reverse<Pending>>,
+}
+
+impl SortedStream {
+    fn new(mut sources: Vec<Source>) -> io::Result<Self> {
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (source, input) in sources.iter_mut().enumerate() {
+            if let Some((key, record)) = input.next_entry()? {
+                heap.push(Reverse(Pending { key, record, source }));
+            }
+        }
+        Ok(Self { sources, heap })
+    }
+}
+
+impl Iterator for SortedStream {
+    type Item = io::Result<SortEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(Pending { key, record, source }) = self.heap.pop()?;
+
+        // Refill from the source we just consumed
+        match self.sources[source].next_entry() {
+            Ok(Some((key, record))) => self.heap.push(Reverse(Pending { key, record, source })),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+
+        Some(Ok((key, record)))
+    }
+}
+
+fn write_chunk(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "sort entry too large"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_chunk(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len_bytes = [0u8; 4];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_in_memory() {
+        let mut sorter = ExternalSorter::new(DEFAULT_SORT_MEMORY_LIMIT);
+        for key in [3u8, 1, 2] {
+            sorter.push(vec![key], vec![key * 10]).unwrap();
+        }
+        assert_eq!(sorter.spilled_runs(), 0);
+
+        let sorted: Vec<SortEntry> = sorter.finish().unwrap().map(|e| e.unwrap()).collect();
+        assert_eq!(sorted, vec![(vec![1], vec![10]), (vec![2], vec![20]), (vec![3], vec![30])]);
+    }
+
+    #[test]
+    fn test_spill_and_merge() {
+        // Tiny limit: roughly every few entries become their own run
+        let mut sorter = ExternalSorter::new(200);
+        let mut expected = Vec::new();
+        for i in 0..500u32 {
+            let key = ((i * 7919) % 500).to_be_bytes().to_vec();
+            let record = format!("record-{}", i).into_bytes();
+            expected.push((key.clone(), record.clone()));
+            sorter.push(key, record).unwrap();
+        }
+        // More runs than are merged at once, so finishing takes a pass first
+        assert!(sorter.spilled_runs() > MERGE_FAN_IN);
+
+        let dir = sorter.dir.as_ref().unwrap().path.clone();
+        let run_paths: Vec<PathBuf> = sorter.runs.iter().map(|r| r.path.clone()).collect();
+        assert!(run_paths.iter().all(|p| p.starts_with(&dir)));
+
+        let stream = sorter.finish().unwrap();
+        assert!(stream.sources.len() <= MERGE_FAN_IN);
+        // Merged runs replaced the spilled ones
+        assert!(run_paths.iter().all(|p| !p.exists()));
+
+        let sorted: Vec<SortEntry> = stream.map(|e| e.unwrap()).collect();
+        expected.sort();
+        assert_eq!(sorted, expected);
+
+        // Dropping the stream removes every run file and the directory
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,304 @@
+//! Scan instrumentation
+//!
+//! Implements `manifold_last_scan_stats()`, a table function returning the
+//! work counters of the most recent entity or edge scan, so pushdown can be
+//! checked and slow queries diagnosed from the numbers rather than guessed.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT count(*) FROM manifold_entities('/path/to/database.redb',
+//!     where_json='$.address.city = "NYC"');
+//! SELECT rows_scanned, rows_filtered FROM manifold_last_scan_stats();
+//! ```
+//!
+//! ## Counters
+//! - `rows_scanned` - records read from storage, whether or not returned
+//! - `rows_filtered` - records read but dropped by a pushed-down filter
+//!   (`where_json`, `has_prop`, `manifold_entities_by_labels`, an unindexed
+//!   `manifold_edges_of_type`, `dedupe_reciprocal`, the other kind's records
+//!   in a mixed `table`)
+//! - `batches` - output batches produced
+//! - `decode_failures` - records that failed to decode, returned as safe-mode
+//!   rows or skipped
+//! - `index_seeks` - point lookups made through an index
+//!   (`manifold_edges_of_type` walking `edge_type_index`,
+//!   `manifold_entities_ordered` fetching each id)
+//! - `coercion_failures` - property values that didn't fit their
+//!   `decimal_props` column and were written as NULL or as text, per
+//!   `coercion_policy`
+//! - `sample_decode_failures` - records sampled for schema discovery at bind
+//!   that failed to decode; columns only come from the ones that did, so a
+//!   high count means some may be missing
+//!
+//! ## Scope
+//! `manifold_entities` (and its `_by_labels` and `_ordered` forms),
+//! `manifold_edges` and `manifold_edges_of_type` publish their counters after
+//! every batch, so a scan that was stopped early (by a `LIMIT`, say) reports
+//! the work it did. As with the default path, DuckDB gives functions no handle
+//! on the calling connection, so the last scan is the last one in the process,
+//! across connections.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use manifoldb_core::encoding::Decoder;
+use std::{
+    error::Error,
+    ffi::CString,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use super::mixed_table::{unknown_tag_error, MixedTable, Tagged};
+use super::{decode_record, ScanRecord};
+
+/// Counters of the most recent scan
+static LAST_SCAN: Mutex<Option<PublishedStats>> = Mutex::new(None);
+
+/// Work counters for one scan, shared by its batches
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    pub rows_scanned: AtomicU64,
+    pub rows_filtered: AtomicU64,
+    pub batches: AtomicU64,
+    pub decode_failures: AtomicU64,
+    pub index_seeks: AtomicU64,
+    pub coercion_failures: AtomicU64,
+    pub sample_decode_failures: AtomicU64,
+}
+
+impl ScanStats {
+    /// Decode a record read from storage, counting it and any failure
+    pub fn decode<T: Decoder>(
+        &self,
+        key: &[u8],
+        value: &[u8],
+        safe_mode: bool,
+    ) -> Option<ScanRecord<T>> {
+        self.rows_scanned.fetch_add(1, Ordering::Relaxed);
+        let record = decode_record(key, value, safe_mode);
+        if !matches!(record, Some(ScanRecord::Decoded { .. })) {
+            self.decode_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        record
+    }
+
+    /// `decode` for a record read from `mixed` if set: records of the other
+    /// kind are counted as filtered, values with no known tag as failures
+    pub fn decode_in<T: Decoder>(
+        &self,
+        mixed: Option<&MixedTable>,
+        key: &[u8],
+        value: &[u8],
+        safe_mode: bool,
+    ) -> Option<ScanRecord<T>> {
+        let Some(mixed) = mixed else {
+            return self.decode(key, value, safe_mode);
+        };
+        match mixed.untag(value) {
+            Tagged::Record(record) => self.decode(key, record, safe_mode),
+            Tagged::OtherKind => {
+                self.rows_scanned.fetch_add(1, Ordering::Relaxed);
+                self.filtered(1);
+                None
+            }
+            Tagged::Unknown(tag) => {
+                self.rows_scanned.fetch_add(1, Ordering::Relaxed);
+                self.decode_failures.fetch_add(1, Ordering::Relaxed);
+                safe_mode.then(|| ScanRecord::Failed {
+                    key: key.to_vec(),
+                    error: unknown_tag_error(tag),
+                })
+            }
+        }
+    }
+
+    /// Count records dropped by a pushed-down filter
+    pub fn filtered(&self, count: usize) {
+        self.rows_filtered.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Count one index lookup
+    pub fn index_seek(&self) {
+        self.index_seeks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count values that didn't fit their declared column type
+    pub fn coerced(&self, count: u64) {
+        self.coercion_failures.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Record how many schema sample records failed to decode at bind
+    pub fn sample_failures(&self, count: usize) {
+        self.sample_decode_failures.store(count as u64, Ordering::Relaxed);
+    }
+
+    /// Count an output batch and publish the totals so far
+    pub fn finish_batch(&self, function: &str, db_path: &str) {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        *LAST_SCAN.lock().unwrap() = Some(PublishedStats {
+            function: function.to_string(),
+            db_path: db_path.to_string(),
+            counters: self.counters(),
+        });
+    }
+
+    /// Current totals, in `COUNTER_COLUMNS` order
+    fn counters(&self) -> [u64; 7] {
+        [
+            &self.rows_scanned,
+            &self.rows_filtered,
+            &self.batches,
+            &self.decode_failures,
+            &self.index_seeks,
+            &self.coercion_failures,
+            &self.sample_decode_failures,
+        ]
+        .map(|counter| counter.load(Ordering::Relaxed))
+    }
+}
+
+/// Counter columns, in `PublishedStats::counters` order
+const COUNTER_COLUMNS: [&str; 7] = [
+    "rows_scanned",
+    "rows_filtered",
+    "batches",
+    "decode_failures",
+    "index_seeks",
+    "coercion_failures",
+    "sample_decode_failures",
+];
+
+/// A scan's counters as of its latest batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishedStats {
+    pub function: String,
+    pub db_path: String,
+    pub counters: [u64; 7],
+}
+
+/// Bind data for last scan stats - the counters, read at bind
+#[repr(C)]
+pub struct ManifoldLastScanStatsBindData {
+    /// None if no scan has run yet
+    pub stats: Option<PublishedStats>,
+}
+
+/// Init data for last scan stats - tracks whether the row was emitted
+#[repr(C)]
+pub struct ManifoldLastScanStatsInitData {
+    /// Flag indicating the row has been emitted
+    pub done: AtomicBool,
+}
+
+/// Last scan stats VTab implementation
+pub struct ManifoldLastScanStatsVTab;
+
+impl VTab for ManifoldLastScanStatsVTab {
+    type InitData = ManifoldLastScanStatsInitData;
+    type BindData = ManifoldLastScanStatsBindData;
+
+    /// Bind phase: take a copy of the counters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        bind.add_result_column("function", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("db_path", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        for name in COUNTER_COLUMNS {
+            bind.add_result_column(name, LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        }
+
+        let stats = LAST_SCAN.lock().unwrap().clone();
+        bind.set_cardinality(stats.is_some() as u64, true);
+
+        Ok(ManifoldLastScanStatsBindData { stats })
+    }
+
+    /// Init phase: nothing to prepare
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldLastScanStatsInitData {
+            done: AtomicBool::new(false),
+        })
+    }
+
+    /// Func phase: emit one row, or none if no scan has run
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_last_scan_stats".into()),
+        }
+    }
+
+    /// No parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        None
+    }
+}
+
+impl ManifoldLastScanStatsVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+
+        let stats = match &bind_data.stats {
+            Some(stats) if !init_data.done.swap(true, Ordering::Relaxed) => stats,
+            _ => {
+                output.set_len(0);
+                return Ok(());
+            }
+        };
+
+        output.flat_vector(0).insert(0, CString::new(stats.function.as_str())?);
+        output.flat_vector(1).insert(0, CString::new(stats.db_path.as_str())?);
+        for (offset, &counter) in stats.counters.iter().enumerate() {
+            output.flat_vector(2 + offset).as_mut_slice::<u64>()[0] = counter;
+        }
+
+        output.set_len(1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{Entity, EntityId};
+
+    #[test]
+    fn test_scan_stats() {
+        let stats = ScanStats::default();
+        let good = Entity::new(EntityId::from(1)).encode().unwrap();
+
+        assert!(stats.decode::<Entity>(b"k1", &good, false).is_some());
+        assert!(stats.decode::<Entity>(b"k2", &[0xff], false).is_none());
+        assert!(matches!(
+            stats.decode::<Entity>(b"k3", &[0xff], true),
+            Some(ScanRecord::Failed { .. })
+        ));
+        stats.filtered(1);
+        stats.index_seek();
+        stats.coerced(2);
+        stats.sample_failures(4);
+        stats.finish_batch("manifold_test_stats", "/db");
+        assert_eq!(stats.counters(), [3, 1, 1, 2, 1, 2, 4]);
+
+        // Scans in other tests publish too, so only check this row if it is still the last
+        let published = LAST_SCAN.lock().unwrap().clone().unwrap();
+        if published.function == "manifold_test_stats" {
+            assert_eq!(published.counters, [3, 1, 1, 2, 1, 2, 4]);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Storage statistics for ManifoldDB
+//!
+//! Implements `manifold_storage_stats(db_path)`, a table function reporting
+//! how much space each table of a database takes, for capacity planning.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_storage_stats('/path/to/database.redb')
+//! ORDER BY total_bytes DESC;
+//! ```
+//!
+//! ## Columns
+//! - `table_name` - A ManifoldDB table (`nodes`, `edges`, `label_index`,
+//!   `hnsw_*`, ...), or `manifold_data` for the redb table holding them all
+//! - `num_entries` - Keys in the table
+//! - `total_bytes` - Bytes the table takes
+//! - `page_count` - redb pages the table takes (NULL for ManifoldDB tables)
+//!
+//! ## Tables and Pages
+//! ManifoldDB keeps every table in a single redb table, `manifold_data`,
+//! prefixing each key with its table's name. redb keeps page statistics per
+//! redb table only, so they are reported once, on the `manifold_data` row:
+//! its `total_bytes` is what the B-tree occupies (stored keys and values,
+//! page metadata and fragmentation) and its `page_count` its leaf and branch
+//! pages.
+//!
+//! The rows above it split the stored bytes by table: a ManifoldDB table's
+//! `total_bytes` is the length of its stored keys (name prefix included) and
+//! values. These sum to less than `manifold_data`'s total, the rest being
+//! page overhead shared by all tables. Pages hold keys of neighbouring
+//! tables, so they can't be split by table and `page_count` is NULL.
+//!
+//! Entries are counted by reading the keys and values without decoding them.
+//! That is a pass over the whole database; the `manifold_data` row alone
+//! comes from redb's metadata.
+//!
+//! Tables are listed by name, `manifold_data` last. A database with nothing
+//! written yet has no rows.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_storage::backends::redb::tables::{decode_key, DATA_TABLE};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::StorageEngine;
+use redb::{ReadableTable, ReadableTableMetadata, TableError};
+
+use super::{chunk_batch_size, get_cached_engine, insert_str, string_param};
+
+/// Name reported for the redb table holding every ManifoldDB table
+const DATA_TABLE_NAME: &str = "manifold_data";
+
+/// Space taken by one table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStorage {
+    pub table_name: String,
+    pub num_entries: u64,
+    pub total_bytes: u64,
+    /// Known only for the redb table
+    pub page_count: Option<u64>,
+}
+
+/// Bind data for storage stats
+#[repr(C)]
+pub struct ManifoldStorageStatsBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+}
+
+/// Init data for storage stats - holds the rows
+#[repr(C)]
+pub struct ManifoldStorageStatsInitData {
+    /// Tables by name, then `manifold_data`
+    pub tables: Vec<TableStorage>,
+    /// Number of rows already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Storage stats VTab implementation
+pub struct ManifoldStorageStatsVTab;
+
+impl VTab for ManifoldStorageStatsVTab {
+    type InitData = ManifoldStorageStatsInitData;
+    type BindData = ManifoldStorageStatsBindData;
+
+    /// Bind phase: set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+
+        bind.add_result_column("table_name", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        for name in ["num_entries", "total_bytes", "page_count"] {
+            bind.add_result_column(name, LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        }
+
+        Ok(ManifoldStorageStatsBindData { db_path })
+    }
+
+    /// Init phase: measure the tables
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldStorageStatsBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+
+        Ok(ManifoldStorageStatsInitData {
+            tables: storage_stats(engine.begin_read()?)?,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the rows
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_storage_stats".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+        ])
+    }
+}
+
+impl ManifoldStorageStatsVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.tables.len());
+        let batch = &init_data.tables[start..end];
+
+        let names = output.flat_vector(0);
+        for (row_idx, table) in batch.iter().enumerate() {
+            insert_str(&names, row_idx, &table.table_name);
+        }
+
+        let mut entries = output.flat_vector(1);
+        for (row_idx, table) in batch.iter().enumerate() {
+            entries.as_mut_slice::<u64>()[row_idx] = table.num_entries;
+        }
+
+        let mut bytes = output.flat_vector(2);
+        for (row_idx, table) in batch.iter().enumerate() {
+            bytes.as_mut_slice::<u64>()[row_idx] = table.total_bytes;
+        }
+
+        let mut pages = output.flat_vector(3);
+        for (row_idx, table) in batch.iter().enumerate() {
+            match table.page_count {
+                Some(count) => pages.as_mut_slice::<u64>()[row_idx] = count,
+                None => pages.set_null(row_idx),
+            }
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Measure each ManifoldDB table, then the redb table holding them
+fn storage_stats(tx: RedbTransaction) -> Result<Vec<TableStorage>, Box<dyn Error>> {
+    let RedbTransaction::Read(tx) = tx else {
+        return Err("manifold_storage_stats needs a read transaction".into());
+    };
+    let data = match tx.open_table(DATA_TABLE) {
+        Ok(data) => data,
+        // Nothing written yet
+        Err(TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut tables: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for entry in data.iter()? {
+        let (key, value) = entry?;
+        let key = key.value();
+        // Keys without a table prefix count toward manifold_data only
+        let Some((table, _)) = decode_key(key) else {
+            continue;
+        };
+        let (entries, bytes) = tables.entry(table.to_string()).or_default();
+        *entries += 1;
+        *bytes += (key.len() + value.value().len()) as u64;
+    }
+
+    let stats = data.stats()?;
+    let mut rows: Vec<TableStorage> = tables
+        .into_iter()
+        .map(|(table_name, (num_entries, total_bytes))| TableStorage {
+            table_name,
+            num_entries,
+            total_bytes,
+            page_count: None,
+        })
+        .collect();
+    rows.push(TableStorage {
+        table_name: DATA_TABLE_NAME.to_string(),
+        num_entries: data.len()?,
+        total_bytes: stats.stored_bytes() + stats.metadata_bytes() + stats.fragmented_bytes(),
+        page_count: Some(stats.leaf_pages() + stats.branch_pages()),
+    });
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_storage::Transaction;
+
+    #[test]
+    fn test_storage_stats() {
+        let db = TempDb::new("storage_stats");
+        let engine = db.open();
+        assert_eq!(storage_stats(engine.begin_read().unwrap()).unwrap(), vec![]);
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in 0..100u64 {
+            tx.put("nodes", &id.to_be_bytes(), &[0; 50]).unwrap();
+        }
+        tx.put("edges", &1u64.to_be_bytes(), &[0; 20]).unwrap();
+        tx.put("nodes_meta", b"k", b"v").unwrap();
+        tx.commit().unwrap();
+
+        let rows = storage_stats(engine.begin_read().unwrap()).unwrap();
+        let names: Vec<&str> = rows.iter().map(|row| row.table_name.as_str()).collect();
+        assert_eq!(names, vec!["edges", "nodes", "nodes_meta", "manifold_data"]);
+
+        // Key bytes include the "<table>\0" prefix
+        assert_eq!((rows[0].num_entries, rows[0].total_bytes), (1, 6 + 8 + 20));
+        assert_eq!((rows[1].num_entries, rows[1].total_bytes), (100, 100 * (6 + 8 + 50)));
+        assert_eq!((rows[2].num_entries, rows[2].total_bytes), (1, 11 + 1 + 1));
+        assert!(rows[..3].iter().all(|row| row.page_count.is_none()));
+
+        // The redb table covers them all, plus page overhead
+        let data = &rows[3];
+        let stored: u64 = rows[..3].iter().map(|row| row.total_bytes).sum();
+        assert_eq!(data.num_entries, 102);
+        assert!(data.total_bytes >= stored);
+        assert!(data.page_count.unwrap() >= 1);
+    }
+}
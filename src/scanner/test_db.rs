@@ -0,0 +1,43 @@
+//! Temporary databases for unit tests
+
+use manifoldb_storage::backends::RedbEngine;
+use std::path::{Path, PathBuf};
+
+/// A database file in the temp directory, removed when dropped
+///
+/// Declare it before the engines and transactions that use the file, so they
+/// are dropped first.
+pub struct TempDb {
+    path: PathBuf,
+}
+
+impl TempDb {
+    /// Reserve `manifold_<name>_<pid>.redb`, removing anything a failed run
+    /// left there; the file is created by the first `open`
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("manifold_{}_{}.redb", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        TempDb { path }
+    }
+
+    /// Open the database read-write, creating it if needed
+    pub fn open(&self) -> RedbEngine {
+        RedbEngine::open(&self.path).unwrap()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path as the scanners take it
+    pub fn db_path(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,255 @@
+//! Timestamp property columns
+//!
+//! Manifold has no timestamp value type, so times are stored as ISO-8601
+//! strings. The `timestamp_props` parameter names properties to expose as
+//! DuckDB `TIMESTAMP` columns instead of VARCHAR, parsed while the rows are
+//! written, so queries can compare and truncate them without a `strptime`.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT date_trunc('day', prop_created) AS day, count(*)
+//! FROM manifold_entities('/path/to/database.redb', timestamp_props=['created', 'updated'])
+//! GROUP BY day;
+//! ```
+//!
+//! ## Accepted Formats
+//! RFC 3339 and the common ISO-8601 extended forms:
+//! - `2024-03-05` - midnight
+//! - `2024-03-05T10:20`, `2024-03-05T10:20:30`, `2024-03-05T10:20:30.123456`
+//!   - `t` or a space may stand for the `T`, and `,` for the decimal point
+//!   - fractions finer than a microsecond are truncated
+//! - any of the times followed by a UTC offset: `Z`, `+02:00`, `+0200` or
+//!   `+02`
+//!
+//! ## Time Zones
+//! `TIMESTAMP` holds no zone. A value with an offset is converted to UTC
+//! (`2024-03-05T12:00:00+02:00` becomes `2024-03-05 10:00:00`); a value
+//! without one is kept as written, so a column mixing both is only coherent
+//! if the naive values are UTC too.
+//!
+//! Anything else - text in another format, an impossible date or time such
+//! as `2023-02-29` or `24:00`, or a value that isn't a string - is NULL.
+
+use duckdb::vtab::BindInfo;
+use manifoldb_core::types::Value;
+use std::collections::HashSet;
+
+use crate::error::ManifoldScannerError;
+use crate::schema::{ColumnType, DiscoveredColumn};
+use super::named_string_list;
+
+/// Microseconds in a second
+const MICROS_PER_SECOND: i64 = 1_000_000;
+
+/// Read `timestamp_props`, dropping repeats
+pub fn named_timestamp_props(bind: &BindInfo) -> Result<Vec<String>, ManifoldScannerError> {
+    let mut props = named_string_list(bind, "timestamp_props")?.unwrap_or_default();
+    let mut seen = HashSet::new();
+    props.retain(|prop| seen.insert(prop.clone()));
+    Ok(props)
+}
+
+/// Retype the columns of the named properties as TIMESTAMP
+///
+/// Fails if a property has no column of its own (it wasn't sampled, or went
+/// to `extra_props`) or its column was already retyped by another option.
+pub fn apply_timestamp_props(
+    columns: &mut [DiscoveredColumn],
+    timestamp_props: &[String],
+) -> Result<(), ManifoldScannerError> {
+    for prop in timestamp_props {
+        let column = columns
+            .iter_mut()
+            .find(|col| col.property.as_ref() == Some(prop))
+            .ok_or_else(|| {
+                ManifoldScannerError::InvalidParameter(format!(
+                    "timestamp_props names '{}', which is not a discovered property",
+                    prop
+                ))
+            })?;
+        if matches!(column.column_type, ColumnType::Decimal { .. } | ColumnType::DecimalText { .. })
+        {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "'{}' can't be in both decimal_props and timestamp_props",
+                column.name
+            )));
+        }
+        column.column_type = ColumnType::Timestamp;
+        column.nullable = true;
+        column.fields.clear();
+    }
+    Ok(())
+}
+
+/// The TIMESTAMP cell of a property, in microseconds since the epoch: None
+/// (SQL NULL) unless it is a string in an accepted format
+pub fn timestamp_cell(value: &Value) -> Option<i64> {
+    match value {
+        Value::String(text) => parse_timestamp(text),
+        _ => None,
+    }
+}
+
+/// Parse an ISO-8601 date or date-time into microseconds since the epoch,
+/// in UTC when it has an offset
+fn parse_timestamp(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let date = text.get(..10)?.as_bytes();
+    if date[4] != b'-' || date[7] != b'-' {
+        return None;
+    }
+    let year = digits(&date[..4])?;
+    let month = digits(&date[5..7])?;
+    let day = digits(&date[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+
+    let rest = &text.as_bytes()[10..];
+    let (seconds, micros) = match rest.split_first() {
+        None => (0, 0),
+        Some((b'T' | b't' | b' ', time)) => parse_time(time)?,
+        Some(_) => return None,
+    };
+    Some((days * 86_400 + seconds) * MICROS_PER_SECOND + micros)
+}
+
+/// Parse `HH:MM[:SS[.fraction]][offset]` into (seconds into the UTC day,
+/// which the offset may take outside 0..86400, and microseconds)
+fn parse_time(time: &[u8]) -> Option<(i64, i64)> {
+    if time.len() < 5 || time[2] != b':' {
+        return None;
+    }
+    let hour = digits(&time[..2])?;
+    let minute = digits(&time[3..5])?;
+    let mut rest = &time[5..];
+
+    let mut second = 0;
+    let mut micros = 0;
+    if let Some(after) = rest.strip_prefix(b":") {
+        second = digits(after.get(..2)?)?;
+        rest = &after[2..];
+        if let Some((b'.' | b',', after)) = rest.split_first() {
+            let len = after.iter().take_while(|b| b.is_ascii_digit()).count();
+            if len == 0 {
+                return None;
+            }
+            // Pad or truncate to six digits
+            let kept = &after[..len.min(6)];
+            micros = digits(kept)? * 10_i64.pow(6 - kept.len() as u32);
+            rest = &after[len..];
+        }
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let offset = parse_offset(rest)?;
+    Some((hour * 3600 + minute * 60 + second - offset, micros))
+}
+
+/// Parse a UTC offset (`Z`, `+HH:MM`, `+HHMM`, `+HH` or none) into seconds
+/// east of UTC
+fn parse_offset(offset: &[u8]) -> Option<i64> {
+    let (sign, rest) = match offset.split_first() {
+        None => return Some(0),
+        Some((b'Z' | b'z', [])) => return Some(0),
+        Some((b'+', rest)) => (1, rest),
+        Some((b'-', rest)) => (-1, rest),
+        Some(_) => return None,
+    };
+    let (hours, minutes) = match rest {
+        [h1, h2] => (digits(&[*h1, *h2])?, 0),
+        [h1, h2, b':', m1, m2] | [h1, h2, m1, m2] => {
+            (digits(&[*h1, *h2])?, digits(&[*m1, *m2])?)
+        }
+        _ => return None,
+    };
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// An all-digit field's value
+fn digits(field: &[u8]) -> Option<i64> {
+    if field.is_empty() || !field.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    Some(field.iter().fold(0, |n, digit| n * 10 + i64::from(digit - b'0')))
+}
+
+/// Days in a month of the proleptic Gregorian calendar
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Count years from March, so the leap day ends the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let at = |text: &str| timestamp_cell(&Value::String(text.to_string()));
+        let noon = Some(1_709_640_000_000_000);
+
+        // Valid forms
+        assert_eq!(at("2024-03-05"), Some(1_709_596_800_000_000));
+        assert_eq!(at("2024-03-05T10:20:30"), Some(1_709_634_030_000_000));
+        assert_eq!(at("2024-03-05 12:00"), noon);
+        assert_eq!(at("2024-03-05t12:00:00Z"), noon);
+        assert_eq!(at("2024-03-05T12:00:00.25"), Some(1_709_640_000_250_000));
+        assert_eq!(at("2024-03-05T12:00:00,123456789Z"), Some(1_709_640_000_123_456));
+        assert_eq!(at("1969-12-31T23:59:59Z"), Some(-MICROS_PER_SECOND));
+        assert_eq!(at("2024-02-29"), Some(1_709_164_800_000_000));
+        assert_eq!(at("1970-01-01"), Some(0));
+
+        // Offsets are converted to UTC, across a day boundary too
+        assert_eq!(at("2024-03-05T14:00:00+02:00"), noon);
+        assert_eq!(at("2024-03-05T14:00+0200"), noon);
+        assert_eq!(at("2024-03-05T07:30:00-04:30"), noon);
+        assert_eq!(at("2024-03-06T01:00:00+13"), noon);
+
+        // Invalid text or values are NULL
+        let invalid = [
+            "",
+            "yesterday",
+            "2024-3-5",
+            "2024/03/05",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-04-31",
+            "2024-03-05T24:00",
+            "2024-03-05T10:60",
+            "2024-03-05T10:20:61",
+            "2024-03-05T10",
+            "2024-03-05T10:20:30.",
+            "2024-03-05T10:20:30+2",
+            "2024-03-05T10:20:30+24:00",
+            "2024-03-05T10:20:30 UTC",
+            "2024-03-05X10:20",
+        ];
+        for text in invalid {
+            assert_eq!(at(text), None, "{}", text);
+        }
+        assert_eq!(timestamp_cell(&Value::Int(1_709_640_000)), None);
+        assert_eq!(timestamp_cell(&Value::Null), None);
+    }
+}
@@ -0,0 +1,637 @@
+//! Graph traversal for ManifoldDB
+//!
+//! Implements a table function returning the entities reachable from a start
+//! entity by following edges of one type, breadth first, up to a depth.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_traverse('/path/to/database.redb', '1', 'KNOWS', 3);
+//! SELECT * FROM manifold_traverse('/path/to/database.redb', '1', 'WORKS_WITH', 4,
+//!     node_label_filter='Person');
+//! ```
+//!
+//! ## Columns
+//! - `id` - Reached entity ID (VARCHAR), each returned once
+//! - `depth` - Number of hops on the shortest path from the start (BIGINT);
+//!   the start itself is returned at depth 0
+//! - `parent_id` - Entity the search expanded to reach this one (VARCHAR,
+//!   NULL for the start)
+//! - `via_edge_id` - Edge followed from the parent (VARCHAR, NULL for the
+//!   start)
+//!
+//! Parents are recorded when an entity is first reached, so the parent links
+//! form a breadth-first tree rooted at the start; a self-join on
+//! `parent_id = id` walks it without any path parsing.
+//!
+//! Edges are followed from source to target only.
+//!
+//! ## Label Filter
+//! `node_label_filter='<label>'` restricts the search to entities carrying
+//! the label: other entities are neither returned nor expanded, so paths only
+//! run through matching nodes. The start is exempt unless `filter_seed=true`,
+//! in which case a start without the label yields no rows.
+//!
+//! ## Path Costs
+//! `accumulate_prop='<property>'` adds a `path_cost` (DOUBLE) column: the sum
+//! of that numeric edge property over the edges from the start to each entity,
+//! 0 for the start. The path is the one the search first reached the entity
+//! by, the `parent_id` chain, so it has the fewest hops but not necessarily
+//! the lowest cost. If an edge on it lacks the property or holds a value that
+//! isn't numeric, the cost is NULL, for that entity and everything reached
+//! through it. Each reached edge is looked up once, after the search.
+//!
+//! ## Strategy
+//! Neighbours are read from Manifold's outgoing edge index (`edges_out`), one
+//! prefix seek per expanded node. Databases without that index have the edges
+//! of the type read once into an in-memory adjacency map instead. The reached
+//! set is built at init time, within one read transaction.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::keys::encode_edge_by_source_type_prefix;
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, EdgeType, Entity, EntityId};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::values::value_to_f64;
+use super::{chunk_batch_size, get_cached_engine, i64_param, named_bool, string_param};
+
+/// Logical table indexing edges by source entity and type
+const EDGES_OUT: &str = "edges_out";
+
+/// What to traverse
+#[derive(Debug, Clone)]
+pub struct TraverseOptions {
+    /// Entity to start from
+    pub start: u64,
+    /// Edge type to follow
+    pub edge_type: String,
+    /// Maximum number of hops
+    pub max_depth: usize,
+    /// Only return and expand entities with this label
+    pub node_label_filter: Option<String>,
+    /// Apply the label filter to the start entity too
+    pub filter_seed: bool,
+    /// Edge property to sum along each path into `path_cost`
+    pub accumulate_prop: Option<String>,
+}
+
+/// An entity reached by the traversal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reached {
+    /// Entity id
+    pub id: u64,
+    /// Hops from the start
+    pub depth: usize,
+    /// Entity expanded to reach this one (None for the start)
+    pub parent: Option<u64>,
+    /// Edge followed from the parent (None for the start)
+    pub via_edge: Option<u64>,
+}
+
+/// Bind data for traversal
+#[repr(C)]
+pub struct ManifoldTraverseBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Traversal parameters
+    pub options: TraverseOptions,
+}
+
+/// Init data for traversal - holds the reached entities
+#[repr(C)]
+pub struct ManifoldTraverseInitData {
+    /// Reached entities in breadth-first order
+    pub reached: Vec<Reached>,
+    /// Path cost of each reached entity, with `accumulate_prop`
+    pub path_costs: Option<Vec<Option<f64>>>,
+    /// Number of entities already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Traversal VTab implementation
+pub struct ManifoldTraverseVTab;
+
+impl VTab for ManifoldTraverseVTab {
+    type InitData = ManifoldTraverseInitData;
+    type BindData = ManifoldTraverseBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let start_id = string_param(bind, 1, "start_id")?;
+        let edge_type = string_param(bind, 2, "edge_type")?;
+        let depth = i64_param(bind, 3, "depth")?;
+
+        let start = start_id.trim().parse::<u64>().map_err(|_| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "start_id must be an entity id, got '{}'",
+                start_id
+            ))
+        })?;
+        if depth < 0 {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "depth must not be negative, got {}",
+                depth
+            ))
+            .into());
+        }
+
+        bind.add_result_column("id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("depth", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("parent_id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("via_edge_id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        let accumulate_prop = bind.get_named_parameter("accumulate_prop").map(|v| v.to_string());
+        if accumulate_prop.is_some() {
+            bind.add_result_column("path_cost", LogicalTypeHandle::from(LogicalTypeId::Double));
+        }
+
+        Ok(ManifoldTraverseBindData {
+            db_path,
+            options: TraverseOptions {
+                start,
+                edge_type,
+                max_depth: depth as usize,
+                node_label_filter: bind
+                    .get_named_parameter("node_label_filter")
+                    .map(|v| v.to_string()),
+                filter_seed: named_bool(bind, "filter_seed")?.unwrap_or(false),
+                accumulate_prop,
+            },
+        })
+    }
+
+    /// Init phase: run the traversal
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldTraverseBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        let reached = traverse(&tx, &bind_data.options)?;
+        let path_costs = match &bind_data.options.accumulate_prop {
+            Some(prop) => Some(path_costs(&tx, &reached, prop)?),
+            None => None,
+        };
+
+        Ok(ManifoldTraverseInitData {
+            reached,
+            path_costs,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the reached entities
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_traverse".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // start_id
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // edge_type
+            LogicalTypeHandle::from(LogicalTypeId::Bigint),  // depth
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "node_label_filter".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "filter_seed".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "accumulate_prop".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+        ])
+    }
+}
+
+impl ManifoldTraverseVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.reached.len());
+        let batch = &init_data.reached[start..end];
+
+        let ids = output.flat_vector(0);
+        for (row_idx, reached) in batch.iter().enumerate() {
+            ids.insert(row_idx, CString::new(reached.id.to_string())?);
+        }
+
+        let mut depths = output.flat_vector(1);
+        for (row_idx, reached) in batch.iter().enumerate() {
+            depths.as_mut_slice::<i64>()[row_idx] = reached.depth as i64;
+        }
+
+        let mut parents = output.flat_vector(2);
+        let mut via_edges = output.flat_vector(3);
+        for (row_idx, reached) in batch.iter().enumerate() {
+            match (reached.parent, reached.via_edge) {
+                (Some(parent), Some(edge)) => {
+                    parents.insert(row_idx, CString::new(parent.to_string())?);
+                    via_edges.insert(row_idx, CString::new(edge.to_string())?);
+                }
+                _ => {
+                    parents.set_null(row_idx);
+                    via_edges.set_null(row_idx);
+                }
+            }
+        }
+
+        if let Some(path_costs) = &init_data.path_costs {
+            let mut costs = output.flat_vector(4);
+            for (row_idx, cost) in path_costs[start..end].iter().enumerate() {
+                match cost {
+                    Some(cost) => costs.as_mut_slice::<f64>()[row_idx] = *cost,
+                    None => costs.set_null(row_idx),
+                }
+            }
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Where a traversal reads the neighbours of an entity from
+pub(crate) enum Neighbours {
+    /// Prefix seeks on the outgoing edge index
+    Index,
+    /// Adjacency of the edge type, read from the edges table up front:
+    /// source -> (edge id, target)
+    Loaded(HashMap<u64, Vec<(u64, u64)>>),
+}
+
+impl Neighbours {
+    /// Use the outgoing edge index if the database has one
+    pub(crate) fn open(tx: &RedbTransaction, edge_type: &EdgeType) -> Result<Self, Box<dyn Error>> {
+        if let Ok(mut cursor) = tx.cursor(EDGES_OUT) {
+            if cursor.seek_first()?.is_some() {
+                return Ok(Neighbours::Index);
+            }
+        }
+
+        let mut adjacency: HashMap<u64, Vec<(u64, u64)>> = HashMap::new();
+        if let Ok(mut cursor) = tx.cursor("edges") {
+            let mut entry = cursor.seek_first()?;
+            while let Some((_key, value)) = entry {
+                if let Ok(edge) = Edge::decode(&value) {
+                    if edge.edge_type == *edge_type {
+                        adjacency
+                            .entry(edge.source.as_u64())
+                            .or_default()
+                            .push((edge.id.as_u64(), edge.target.as_u64()));
+                    }
+                }
+                entry = cursor.next()?;
+            }
+        }
+        Ok(Neighbours::Loaded(adjacency))
+    }
+
+    /// (edge id, target) of the entity's outgoing edges of the type
+    fn of(
+        &self,
+        tx: &RedbTransaction,
+        node: u64,
+        edge_type: &EdgeType,
+    ) -> Result<Vec<(u64, u64)>, Box<dyn Error>> {
+        match self {
+            Neighbours::Loaded(adjacency) => {
+                Ok(adjacency.get(&node).cloned().unwrap_or_default())
+            }
+            Neighbours::Index => {
+                let mut targets = Vec::new();
+                let mut cursor = tx.cursor(EDGES_OUT)?;
+                let prefix = encode_edge_by_source_type_prefix(EntityId::from(node), edge_type);
+
+                let mut entry = cursor.seek(&prefix)?;
+                while let Some((key, _value)) = entry {
+                    if !key.starts_with(&prefix) {
+                        break;
+                    }
+                    // The edge id is the trailing big-endian u64; resolving it
+                    // also weeds out edge type hash collisions
+                    let edge_key = &key[prefix.len()..];
+                    if let Some(value) = tx.get("edges", edge_key)? {
+                        if let Ok(edge) = Edge::decode(&value) {
+                            if edge.edge_type == *edge_type && edge.source.as_u64() == node {
+                                targets.push((edge.id.as_u64(), edge.target.as_u64()));
+                            }
+                        }
+                    }
+                    entry = cursor.next()?;
+                }
+                Ok(targets)
+            }
+        }
+    }
+}
+
+/// Whether the entity exists and carries the label
+fn has_label(tx: &RedbTransaction, id: u64, label: &str) -> Result<bool, Box<dyn Error>> {
+    let value = match tx.get("nodes", &id.to_be_bytes()) {
+        Ok(Some(value)) => value,
+        // Missing entity, or no nodes table at all
+        Ok(None) | Err(_) => return Ok(false),
+    };
+    Ok(Entity::decode(&value).is_ok_and(|entity| entity.labels.iter().any(|l| l.as_str() == label)))
+}
+
+/// Breadth-first search from the start entity, returning each reached entity
+/// with its depth and how it was first reached
+fn traverse(
+    tx: &RedbTransaction,
+    options: &TraverseOptions,
+) -> Result<Vec<Reached>, Box<dyn Error>> {
+    let edge_type = EdgeType::new(options.edge_type.as_str());
+    let label = options.node_label_filter.as_deref();
+    let matches = |id: u64| -> Result<bool, Box<dyn Error>> {
+        match label {
+            Some(label) => has_label(tx, id, label),
+            None => Ok(true),
+        }
+    };
+
+    if options.filter_seed && !matches(options.start)? {
+        return Ok(Vec::new());
+    }
+
+    let neighbours = Neighbours::open(tx, &edge_type)?;
+    let mut reached = vec![Reached {
+        id: options.start,
+        depth: 0,
+        parent: None,
+        via_edge: None,
+    }];
+    let start = options.start;
+    breadth_first(tx, &neighbours, &edge_type, start, options.max_depth, matches, |node| {
+        reached.push(node)
+    })?;
+
+    Ok(reached)
+}
+
+/// Sum an edge property along each reached entity's parent chain
+///
+/// `reached` is in breadth-first order, so every parent's cost is known
+/// before its children's. A missing edge, property or numeric value makes
+/// the cost NULL from there on.
+fn path_costs(
+    tx: &RedbTransaction,
+    reached: &[Reached],
+    prop: &str,
+) -> Result<Vec<Option<f64>>, Box<dyn Error>> {
+    let mut by_id: HashMap<u64, Option<f64>> = HashMap::with_capacity(reached.len());
+    let mut costs = Vec::with_capacity(reached.len());
+    for node in reached {
+        let cost = match (node.parent, node.via_edge) {
+            (Some(parent), Some(edge_id)) => {
+                let weight = match tx.get("edges", &edge_id.to_be_bytes())? {
+                    Some(value) => Edge::decode(&value)
+                        .ok()
+                        .and_then(|edge| edge.properties.get(prop).and_then(value_to_f64)),
+                    None => None,
+                };
+                by_id.get(&parent).copied().flatten().zip(weight).map(|(a, b)| a + b)
+            }
+            _ => Some(0.0),
+        };
+        by_id.insert(node.id, cost);
+        costs.push(cost);
+    }
+    Ok(costs)
+}
+
+/// The breadth-first search core: call `on_reach` for each entity first
+/// reached within `max_depth` hops of the start, the start itself excluded
+///
+/// Entities `admit` rejects are neither reported nor expanded.
+pub(crate) fn breadth_first(
+    tx: &RedbTransaction,
+    neighbours: &Neighbours,
+    edge_type: &EdgeType,
+    start: u64,
+    max_depth: usize,
+    mut admit: impl FnMut(u64) -> Result<bool, Box<dyn Error>>,
+    mut on_reach: impl FnMut(Reached),
+) -> Result<(), Box<dyn Error>> {
+    let mut visited = HashSet::from([start]);
+    let mut frontier = vec![start];
+
+    for depth in 1..=max_depth {
+        let mut next = Vec::new();
+        for &node in &frontier {
+            for (edge_id, target) in neighbours.of(tx, node, edge_type)? {
+                // Filtered-out entities are marked visited too, so their
+                // labels are read only once
+                if visited.insert(target) && admit(target)? {
+                    on_reach(Reached {
+                        id: target,
+                        depth,
+                        parent: Some(node),
+                        via_edge: Some(edge_id),
+                    });
+                    next.push(target);
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::keys::encode_edge_by_source_key;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, Label, Value};
+
+    #[test]
+    fn test_traverse() {
+        let db = TempDb::new("traverse");
+        let engine = db.open();
+
+        // 1 -> 2 -> 4 and 1 -> 3 -> 4 -> 5, where 3 is a Bot
+        let labels = [(1u64, "Person"), (2, "Person"), (3, "Bot"), (4, "Person"), (5, "Person")];
+        let edges = [
+            (10u64, 1u64, 2u64),
+            (11, 1, 3),
+            (12, 3, 4),
+            (13, 2, 4),
+            (14, 4, 5),
+            (15, 5, 1),
+        ];
+        let knows = EdgeType::new("KNOWS");
+
+        let write = |with_index: bool| {
+            let mut tx = engine.begin_write().unwrap();
+            for (id, label) in labels {
+                let entity = Entity {
+                    id: EntityId::from(id),
+                    labels: vec![Label::new(label)],
+                    properties: HashMap::new(),
+                    vectors: HashMap::new(),
+                };
+                tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+            }
+            for (id, source, target) in edges {
+                let edge = Edge {
+                    id: EdgeId::from(id),
+                    source: EntityId::from(source),
+                    target: EntityId::from(target),
+                    edge_type: knows.clone(),
+                    properties: HashMap::new(),
+                };
+                tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+                if with_index {
+                    let key = encode_edge_by_source_key(edge.source, &knows, edge.id);
+                    tx.put(EDGES_OUT, &key, b"").unwrap();
+                }
+            }
+            tx.commit().unwrap();
+        };
+
+        let options = |max_depth: usize, filter: Option<&str>, filter_seed: bool| TraverseOptions {
+            start: 1,
+            edge_type: "KNOWS".to_string(),
+            max_depth,
+            node_label_filter: filter.map(str::to_string),
+            filter_seed,
+            accumulate_prop: None,
+        };
+
+        let depths = |reached: Vec<Reached>| -> Vec<(u64, usize)> {
+            reached.iter().map(|r| (r.id, r.depth)).collect()
+        };
+
+        // Without the edges_out index first, then with it
+        for with_index in [false, true] {
+            write(with_index);
+            let tx = engine.begin_read().unwrap();
+
+            let reached = depths(traverse(&tx, &options(2, None, false)).unwrap());
+            assert_eq!(reached, vec![(1, 0), (2, 1), (3, 1), (4, 2)]);
+
+            let reached = depths(traverse(&tx, &options(5, Some("Person"), false)).unwrap());
+            assert_eq!(reached, vec![(1, 0), (2, 1), (4, 2), (5, 3)]);
+
+            // The seed is exempt from the filter unless filter_seed is set
+            let reached = depths(traverse(&tx, &options(5, Some("Bot"), false)).unwrap());
+            assert_eq!(reached, vec![(1, 0), (3, 1)]);
+            assert!(traverse(&tx, &options(5, Some("Bot"), true)).unwrap().is_empty());
+
+            // Parent links form a tree: every non-seed entity hangs off an
+            // entity reached one hop earlier, through a real edge between them
+            let reached = traverse(&tx, &options(5, None, false)).unwrap();
+            assert_eq!((reached[0].parent, reached[0].via_edge), (None, None));
+            for (i, node) in reached.iter().enumerate().skip(1) {
+                let parent = reached[..i]
+                    .iter()
+                    .find(|r| Some(r.id) == node.parent)
+                    .expect("parent reached first");
+                assert_eq!(parent.depth + 1, node.depth);
+                assert!(edges.contains(&(node.via_edge.unwrap(), parent.id, node.id)));
+            }
+            let links: Vec<(u64, Option<u64>, Option<u64>)> =
+                reached.iter().map(|r| (r.id, r.parent, r.via_edge)).collect();
+            assert_eq!(
+                links,
+                vec![
+                    (1, None, None),
+                    (2, Some(1), Some(10)),
+                    (3, Some(1), Some(11)),
+                    (4, Some(2), Some(13)),
+                    (5, Some(4), Some(14)),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_path_costs() {
+        let db = TempDb::new("path_cost");
+        let engine = db.open();
+
+        // 1 -> 2 -> 4 -> 5 and 1 -> 3 -> 4, 3 -> 6; 4 -> 5 has no distance
+        // and 3 -> 6 a non-numeric one
+        let edges = [
+            (10u64, 1u64, 2u64, Some(Value::Float(2.5))),
+            (11, 1, 3, Some(Value::Int(1))),
+            (12, 2, 4, Some(Value::String("4".to_string()))),
+            (13, 3, 4, Some(Value::Int(1))),
+            (14, 4, 5, None),
+            (15, 3, 6, Some(Value::Bool(true))),
+        ];
+        let mut tx = engine.begin_write().unwrap();
+        for (id, source, target, distance) in edges {
+            let (source, target) = (EntityId::from(source), EntityId::from(target));
+            let mut edge = Edge::new(EdgeId::from(id), source, target, "ROAD");
+            if let Some(distance) = distance {
+                edge = edge.with_property("distance", distance);
+            }
+            tx.put("edges", &id.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let options = TraverseOptions {
+            start: 1,
+            edge_type: "ROAD".to_string(),
+            max_depth: 5,
+            node_label_filter: None,
+            filter_seed: false,
+            accumulate_prop: Some("distance".to_string()),
+        };
+        let tx = engine.begin_read().unwrap();
+        let reached = traverse(&tx, &options).unwrap();
+        let ids: Vec<u64> = reached.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 6, 5]);
+
+        // 4 keeps the cost of its first path (via 2), not the cheaper one via
+        // 3; a missing or non-numeric distance makes the cost NULL onwards
+        let costs = path_costs(&tx, &reached, "distance").unwrap();
+        assert_eq!(costs, vec![Some(0.0), Some(2.5), Some(1.0), Some(6.5), None, None]);
+    }
+}
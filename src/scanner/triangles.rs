@@ -0,0 +1,271 @@
+//! Triangle count for ManifoldDB
+//!
+//! Implements a table function returning the number of triangles formed by
+//! edges of one type, the numerator of the global clustering coefficient.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT triangles FROM manifold_triangle_count('/path/to/database.redb', 'KNOWS');
+//! SELECT * FROM manifold_triangle_count('/path/to/database.redb', 'KNOWS', max_nodes=500000);
+//! ```
+//!
+//! ## Semantics
+//! Edges are treated as undirected: direction, parallel edges and self-loops
+//! are ignored, and each triangle is counted once.
+//!
+//! ## Cost
+//! The edges of the type are read once into an in-memory adjacency map, so
+//! memory is O(nodes + edges). Counting uses the node-iterator algorithm over
+//! id-ordered neighbours, O(sum of squared degrees) time - fine for modest
+//! graphs, slow when a few nodes have very high degree. To keep that bounded,
+//! the function errors out before counting once the graph has more than
+//! `max_nodes` nodes (default 100000).
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    ops::Bound::{Excluded, Unbounded},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, EdgeType};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::{get_cached_engine, named_i64, string_param};
+
+/// Default bound on the number of nodes loaded into memory
+const DEFAULT_MAX_NODES: usize = 100_000;
+
+/// Bind data for the triangle count
+#[repr(C)]
+pub struct ManifoldTriangleCountBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Edge type forming the graph
+    pub edge_type: String,
+    /// Largest graph (in nodes) the count will run on
+    pub max_nodes: usize,
+}
+
+/// Init data for the triangle count - holds the result
+#[repr(C)]
+pub struct ManifoldTriangleCountInitData {
+    /// Number of triangles
+    pub triangles: u64,
+    /// Flag indicating the row has been emitted
+    pub done: AtomicBool,
+}
+
+/// Triangle count VTab implementation
+pub struct ManifoldTriangleCountVTab;
+
+impl VTab for ManifoldTriangleCountVTab {
+    type InitData = ManifoldTriangleCountInitData;
+    type BindData = ManifoldTriangleCountBindData;
+
+    /// Bind phase: read parameters, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let edge_type = string_param(bind, 1, "edge_type")?;
+
+        let max_nodes = match named_i64(bind, "max_nodes")? {
+            Some(max_nodes) if max_nodes > 0 => max_nodes as usize,
+            Some(max_nodes) => {
+                return Err(ManifoldScannerError::InvalidParameter(format!(
+                    "max_nodes must be positive, got {}",
+                    max_nodes
+                ))
+                .into())
+            }
+            None => DEFAULT_MAX_NODES,
+        };
+
+        bind.add_result_column("triangles", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.set_cardinality(1, true);
+
+        Ok(ManifoldTriangleCountBindData {
+            db_path,
+            edge_type,
+            max_nodes,
+        })
+    }
+
+    /// Init phase: load the graph and count triangles
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldTriangleCountBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let tx = engine.begin_read()?;
+
+        let adjacency = load_adjacency(&tx, &bind_data.edge_type, bind_data.max_nodes)?;
+
+        Ok(ManifoldTriangleCountInitData {
+            triangles: count_triangles(&adjacency),
+            done: AtomicBool::new(false),
+        })
+    }
+
+    /// Func phase: emit the single count row
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_triangle_count".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // edge_type
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![(
+            "max_nodes".to_string(),
+            LogicalTypeHandle::from(LogicalTypeId::Bigint),
+        )])
+    }
+}
+
+impl ManifoldTriangleCountVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        if init_data.done.swap(true, Ordering::Relaxed) {
+            output.set_len(0);
+            return Ok(());
+        }
+
+        let mut vector = output.flat_vector(0);
+        vector.as_mut_slice::<i64>()[0] = init_data.triangles as i64;
+
+        output.set_len(1);
+        Ok(())
+    }
+}
+
+/// Undirected adjacency of the edges of one type, without self-loops
+type Adjacency = HashMap<u64, BTreeSet<u64>>;
+
+/// Read the edges of a type into an adjacency map, failing once it holds more
+/// than `max_nodes` nodes
+fn load_adjacency(
+    tx: &RedbTransaction,
+    edge_type: &str,
+    max_nodes: usize,
+) -> Result<Adjacency, Box<dyn Error>> {
+    let mut adjacency = Adjacency::new();
+
+    let Ok(mut cursor) = tx.cursor("edges") else {
+        // No edges table - an empty graph
+        return Ok(adjacency);
+    };
+
+    let edge_type = EdgeType::new(edge_type);
+    let mut entry = cursor.seek_first()?;
+    while let Some((_key, value)) = entry {
+        if let Ok(edge) = Edge::decode(&value) {
+            let (source, target) = (edge.source.as_u64(), edge.target.as_u64());
+            if edge.edge_type == edge_type && source != target {
+                adjacency.entry(source).or_default().insert(target);
+                adjacency.entry(target).or_default().insert(source);
+
+                if adjacency.len() > max_nodes {
+                    return Err(ManifoldScannerError::InvalidParameter(format!(
+                        "graph of '{}' edges has more than {} nodes; raise max_nodes to count \
+                         triangles on it",
+                        edge_type.as_str(),
+                        max_nodes
+                    ))
+                    .into());
+                }
+            }
+        }
+        entry = cursor.next()?;
+    }
+
+    Ok(adjacency)
+}
+
+/// Count triangles with the node-iterator algorithm, visiting each triangle
+/// only from its smallest node so it is counted once
+fn count_triangles(adjacency: &Adjacency) -> u64 {
+    let mut triangles = 0;
+    for (&node, neighbours) in adjacency {
+        let higher: Vec<u64> = neighbours.range((Excluded(node), Unbounded)).copied().collect();
+        for (i, first) in higher.iter().enumerate() {
+            let first_neighbours = &adjacency[first];
+            triangles += higher[i + 1..]
+                .iter()
+                .filter(|second| first_neighbours.contains(second))
+                .count() as u64;
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EntityId};
+
+    #[test]
+    fn test_triangle_count() {
+        let db = TempDb::new("triangles");
+        let engine = db.open();
+
+        // Two KNOWS triangles sharing 2-3 (one edge reversed, one duplicated),
+        // a self-loop, and a LIKES edge that would close a third triangle
+        let edges = [
+            (1u64, 2u64, "KNOWS"),
+            (2, 3, "KNOWS"),
+            (3, 1, "KNOWS"),
+            (1, 3, "KNOWS"),
+            (2, 4, "KNOWS"),
+            (4, 3, "KNOWS"),
+            (4, 4, "KNOWS"),
+            (4, 5, "KNOWS"),
+            (5, 3, "LIKES"),
+        ];
+        let mut tx = engine.begin_write().unwrap();
+        for (id, (source, target, edge_type)) in edges.into_iter().enumerate() {
+            let edge = Edge {
+                id: EdgeId::from(id as u64),
+                source: EntityId::from(source),
+                target: EntityId::from(target),
+                edge_type: EdgeType::new(edge_type),
+                properties: HashMap::new(),
+            };
+            tx.put("edges", &(id as u64).to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let adjacency = load_adjacency(&tx, "KNOWS", 5).unwrap();
+        assert_eq!(count_triangles(&adjacency), 2);
+        assert_eq!(count_triangles(&load_adjacency(&tx, "LIKES", 5).unwrap()), 0);
+        assert!(load_adjacency(&tx, "KNOWS", 4).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Conversion of Manifold values into DuckDB cell representations
+//!
+//! Shared by all scanners so the same property value renders identically
+//! whether it comes from an entity or an edge.
+//!
+//! ## Determinism
+//! Repeated scans of the same record must produce byte-identical output. Values
+//! only contain ordered collections (`Vec`), and property maps rendered as
+//! JSON are visited in sorted key order via [`sorted_properties`], so nothing
+//! here depends on `HashMap` iteration order.
+
+use std::collections::HashMap;
+
+use manifoldb_core::types::Value;
+
+/// Visit a property map in sorted key order
+pub fn sorted_properties(properties: &HashMap<String, Value>) -> Vec<(&str, &Value)> {
+    let mut sorted: Vec<(&str, &Value)> = properties
+        .iter()
+        .map(|(key, value)| (key.as_str(), value))
+        .collect();
+    sorted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    sorted
+}
+
+/// Convert a Manifold Value to a JSON string for DuckDB
+pub fn value_to_json_string(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) => serde_json::to_string(s).unwrap_or_else(|_| format!("\"{}\"", s)),
+        Value::Bytes(b) => serde_json::to_string(&base64_encode(b)).unwrap_or_else(|_| "\"\"".to_string()),
+        Value::Array(arr) => serde_json::to_string(arr).unwrap_or_else(|_| "[]".to_string()),
+        Value::Vector(v) => serde_json::to_string(v).unwrap_or_else(|_| "[]".to_string()),
+        Value::SparseVector(sv) => serde_json::to_string(sv).unwrap_or_else(|_| "{}".to_string()),
+        Value::MultiVector(mv) => serde_json::to_string(mv).unwrap_or_else(|_| "[]".to_string()),
+    }
+}
+
+/// Convert a whole property map to a JSON object string
+///
+/// Keys are written in sorted order, so the same properties always produce
+/// the same string regardless of `HashMap` iteration order - the output is
+/// safe to hash or diff.
+pub fn properties_to_json(properties: &HashMap<String, Value>) -> String {
+    pairs_to_json(&sorted_properties(properties))
+}
+
+/// Convert key-value pairs, in the given order, to a JSON object string
+pub fn pairs_to_json(pairs: &[(&str, &Value)]) -> String {
+    let fields: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| {
+            let key = serde_json::to_string(key).unwrap_or_else(|_| format!("\"{}\"", key));
+            format!("{}:{}", key, value_to_json_string(value))
+        })
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Simple base64 encoding for bytes
+pub fn base64_encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::new();
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0] as usize;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
+
+        let _ = write!(result, "{}", ALPHABET[(b0 >> 2) & 0x3F] as char);
+        let _ = write!(result, "{}", ALPHABET[((b0 << 4) | (b1 >> 4)) & 0x3F] as char);
+
+        if chunk.len() > 1 {
+            let _ = write!(result, "{}", ALPHABET[((b1 << 2) | (b2 >> 6)) & 0x3F] as char);
+        } else {
+            result.push('=');
+        }
+
+        if chunk.len() > 2 {
+            let _ = write!(result, "{}", ALPHABET[b2 & 0x3F] as char);
+        } else {
+            result.push('=');
+        }
+    }
+    result
+}
+
+/// Convert a Manifold Value to the appropriate DuckDB string representation
+///
+/// Floats use the shortest string that parses back to the same f64 unless
+/// `float_precision` is set, in which case they get exactly that many decimals
+/// (lossy, for display). Floats nested inside JSON-encoded values are always
+/// round-trip formatted.
+pub fn value_to_duckdb_string(value: &Value, float_precision: Option<usize>) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => match float_precision {
+            Some(precision) if f.is_finite() => format!("{:.*}", precision, f),
+            _ => f.to_string(),
+        },
+        Value::String(s) => s.clone(),
+        // For complex types, use JSON (bytes as a base64 string)
+        _ => value_to_json_string(value),
+    }
+}
+
+/// Name of a value's variant, for the `include_types` columns
+pub fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Int(_) => "int",
+        Value::Float(_) => "float",
+        Value::String(_) => "string",
+        Value::Bytes(_) => "bytes",
+        Value::Vector(_) => "vector",
+        Value::SparseVector(_) => "sparse_vector",
+        Value::MultiVector(_) => "multi_vector",
+        Value::Array(_) => "array",
+    }
+}
+
+/// Read a Manifold Value as a number, if it has a numeric interpretation
+pub fn value_to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Read a Manifold Value as a boolean for `coerce_bool`: booleans as they
+/// are, and strings holding one of the tokens `true`/`false`, `t`/`f`,
+/// `yes`/`no`, `y`/`n`, `on`/`off` or `1`/`0` (any case, surrounding
+/// whitespace ignored). Anything else has no boolean reading.
+pub fn value_to_bool(value: &Value) -> Option<bool> {
+    let text = match value {
+        Value::Bool(b) => return Some(*b),
+        Value::String(s) => s.trim().to_ascii_lowercase(),
+        _ => return None,
+    };
+    match text.as_str() {
+        "true" | "t" | "yes" | "y" | "on" | "1" => Some(true),
+        "false" | "f" | "no" | "n" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_to_bool() {
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(value_to_bool(&Value::Bool(false)), Some(false));
+        assert_eq!(value_to_bool(&string("TRUE")), Some(true));
+        assert_eq!(value_to_bool(&string(" 0 ")), Some(false));
+        assert_eq!(value_to_bool(&string("Yes")), Some(true));
+        assert_eq!(value_to_bool(&string("maybe")), None);
+        assert_eq!(value_to_bool(&string("")), None);
+        assert_eq!(value_to_bool(&Value::Int(1)), None);
+        assert_eq!(value_to_bool(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_value_to_f64() {
+        assert_eq!(value_to_f64(&Value::Int(3)), Some(3.0));
+        assert_eq!(value_to_f64(&Value::Float(2.5)), Some(2.5));
+        assert_eq!(value_to_f64(&Value::String(" 1.25 ".to_string())), Some(1.25));
+        assert_eq!(value_to_f64(&Value::String("heavy".to_string())), None);
+        assert_eq!(value_to_f64(&Value::Bool(true)), None);
+        assert_eq!(value_to_f64(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_bigint_boundaries_render_exactly() {
+        assert_eq!(value_to_duckdb_string(&Value::Int(i64::MAX), None), "9223372036854775807");
+        assert_eq!(value_to_duckdb_string(&Value::Int(i64::MIN), None), "-9223372036854775808");
+    }
+
+    #[test]
+    fn test_sorted_properties_is_order_independent() {
+        let mut forward = HashMap::new();
+        let mut backward = HashMap::new();
+        let keys = ["zeta", "alpha", "mid", "beta"];
+        for (i, key) in keys.iter().enumerate() {
+            forward.insert(key.to_string(), Value::Int(i as i64));
+        }
+        for (i, key) in keys.iter().enumerate().rev() {
+            backward.insert(key.to_string(), Value::Int(i as i64));
+        }
+
+        let names: Vec<&str> = sorted_properties(&forward).iter().map(|(k, _)| *k).collect();
+        assert_eq!(names, vec!["alpha", "beta", "mid", "zeta"]);
+        assert_eq!(sorted_properties(&forward), sorted_properties(&backward));
+    }
+
+    #[test]
+    fn test_collection_json() {
+        // Elements keep their stored order, and bytes are a base64 string
+        let cases = [
+            (
+                Value::Array(vec![Value::Int(3), Value::String("b".to_string()), Value::Int(1)]),
+                r#"[{"Int":3},{"String":"b"},{"Int":1}]"#,
+            ),
+            (Value::SparseVector(vec![(7, 0.5), (2, 0.25)]), "[[7,0.5],[2,0.25]]"),
+            (Value::MultiVector(vec![vec![1.0, 2.0], vec![0.5]]), "[[1.0,2.0],[0.5]]"),
+            (Value::Bytes(vec![0, 1, 2, 255]), "\"AAEC/w==\""),
+            (Value::Bytes(Vec::new()), "\"\""),
+        ];
+        for (value, expected) in &cases {
+            assert_eq!(value_to_duckdb_string(value, None), *expected);
+            assert_eq!(value_to_json_string(value), *expected);
+        }
+    }
+
+    #[test]
+    fn test_properties_to_json_is_canonical() {
+        let entries = [
+            ("name", Value::String("Ada".to_string())),
+            ("age", Value::Int(36)),
+            ("score", Value::Float(0.5)),
+            ("active", Value::Bool(true)),
+            ("tags", Value::Array(vec![Value::String("x".to_string())])),
+        ];
+
+        // Same entity, maps built in different insertion orders
+        let forward: HashMap<String, Value> =
+            entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        let reverse: HashMap<String, Value> =
+            entries.iter().rev().map(|(k, v)| (k.to_string(), v.clone())).collect();
+
+        let first = properties_to_json(&forward);
+        for _ in 0..20 {
+            assert_eq!(properties_to_json(&forward), first);
+            assert_eq!(properties_to_json(&reverse), first);
+        }
+        assert!(first.starts_with(r#"{"active":true,"age":36,"name":"Ada","score":0.5,"tags":"#));
+        assert_eq!(properties_to_json(&HashMap::new()), "{}");
+    }
+
+    #[test]
+    fn test_float_precision() {
+        let value = Value::Float(2.0 / 3.0);
+        assert_eq!(value_to_duckdb_string(&value, None), "0.6666666666666666");
+        assert_eq!(value_to_duckdb_string(&value, Some(2)), "0.67");
+        assert_eq!(value_to_duckdb_string(&Value::Float(5.0), Some(0)), "5");
+
+        // Only floats are affected
+        assert_eq!(value_to_duckdb_string(&Value::Int(5), Some(2)), "5");
+        assert_eq!(value_to_duckdb_string(&Value::Float(f64::NAN), Some(2)), "NaN");
+    }
+}
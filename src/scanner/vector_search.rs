@@ -0,0 +1,943 @@
+//! Vector similarity search for ManifoldDB
+//!
+//! Implements a table function that returns the `k` entities whose vector
+//! property is closest to a query vector.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_vector_search('/path/to/database.redb', 'embedding', [0.1, 0.2, 0.3], 10);
+//! SELECT * FROM manifold_vector_search('/path/to/database.redb', 'embedding', [0.1, 0.2, 0.3], 10,
+//!     exact=true, metric='cosine');
+//!
+//! -- Batch mode: many queries in one scan, tagged with their position in the list
+//! SELECT * FROM manifold_vector_search_batch('/path/to/database.redb', 'embedding',
+//!     [[0.1, 0.2, 0.3], [0.3, 0.2, 0.1]], 10);
+//! ```
+//!
+//! ## Search Strategy
+//!
+//! The collection is the name of the entity property holding `Value::Vector`
+//! embeddings. Index-backed (HNSW) search needs the Manifold vector index crate,
+//! which this extension does not link, so searches run as an exact brute-force
+//! scan:
+//! - Every entity is read once through a cursor over the nodes table
+//! - A bounded max-heap keeps only the best `k` candidates in memory
+//! - Stored vectors whose dimension differs from the query are skipped
+//!
+//! `exact` chooses between the two (`brute_force` is an older spelling):
+//! - unset: approximate when the index can be read, otherwise the exact scan;
+//!   this build can't read Manifold's HNSW index, so that is the exact scan
+//! - `true`: always use the exact scan; its distances are ground truth for
+//!   measuring an index's recall
+//! - `false`: require the approximate index path, failing with an error that
+//!   says the HNSW index isn't readable
+//!
+//! The exact scan streams: only the current record and the `k` best hits per
+//! query are held, so collections of any size search in bounded memory.
+//!
+//! Batch mode keeps one top-k heap per query and scores every stored vector
+//! against all of them in the same pass, so the scan is shared across queries.
+//!
+//! ## Matched Vectors
+//! `include_vector=true` adds a `vector` LIST(DOUBLE) column after `distance`
+//! with each hit's stored vector, to inspect embeddings or recompute distances
+//! in SQL. The scan keeps only ids and distances, so the vectors are read
+//! afterwards with one point lookup per hit, from the same read transaction
+//! the hits were scored in. It is off by default: a row then carries the whole
+//! vector, which for large embeddings outweighs everything else returned.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::BinaryHeap,
+    error::Error,
+    ffi::CString,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Entity, Value};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, StorageEngine, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::engine::ManifoldEngine;
+use super::{chunk_batch_size, get_cached_engine, i64_param, named_bool, string_param};
+
+/// Table holding Manifold's HNSW index registry (index name -> config)
+pub const HNSW_REGISTRY_TABLE: &str = "hnsw_registry";
+
+/// Largest `k` accepted; each query holds up to `k` hits in memory
+pub const MAX_K: usize = 100_000;
+
+/// Hits a top-k heap reserves room for up front; larger heaps grow as needed
+const TOP_K_PREALLOC: usize = 1024;
+
+/// Distance metric used to rank candidates (smaller is closer)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// Euclidean distance
+    L2,
+    /// 1 - cosine similarity
+    Cosine,
+    /// Negated dot product
+    InnerProduct,
+}
+
+impl DistanceMetric {
+    fn parse(name: &str) -> Result<Self, ManifoldScannerError> {
+        match name.to_ascii_lowercase().as_str() {
+            "l2" | "euclidean" => Ok(DistanceMetric::L2),
+            "cosine" => Ok(DistanceMetric::Cosine),
+            "ip" | "dot" | "inner_product" => Ok(DistanceMetric::InnerProduct),
+            other => Err(ManifoldScannerError::InvalidParameter(format!(
+                "unknown metric '{}', expected 'l2', 'cosine' or 'inner_product'",
+                other
+            ))),
+        }
+    }
+
+    /// Distance between the query and a stored vector of the same dimension
+    fn distance(self, query: &[f64], candidate: &[f32]) -> f64 {
+        match self {
+            DistanceMetric::L2 => query
+                .iter()
+                .zip(candidate)
+                .map(|(q, c)| (q - *c as f64).powi(2))
+                .sum::<f64>()
+                .sqrt(),
+            DistanceMetric::Cosine => {
+                let mut dot = 0.0;
+                let mut query_norm = 0.0;
+                let mut candidate_norm = 0.0;
+                for (q, c) in query.iter().zip(candidate) {
+                    let c = *c as f64;
+                    dot += q * c;
+                    query_norm += q * q;
+                    candidate_norm += c * c;
+                }
+                if query_norm == 0.0 || candidate_norm == 0.0 {
+                    1.0
+                } else {
+                    1.0 - dot / (query_norm.sqrt() * candidate_norm.sqrt())
+                }
+            }
+            DistanceMetric::InnerProduct => -query
+                .iter()
+                .zip(candidate)
+                .map(|(q, c)| q * *c as f64)
+                .sum::<f64>(),
+        }
+    }
+}
+
+/// A search hit: entity id and its distance to the query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchHit {
+    pub id: u64,
+    pub distance: f64,
+}
+
+impl Eq for SearchHit {}
+
+impl Ord for SearchHit {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        // Break distance ties on id so results are deterministic
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for SearchHit {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keeps the `k` closest hits seen so far
+///
+/// A max-heap ordered by distance: the root is the worst of the current best
+/// `k`, so each candidate costs one comparison and at most one O(log k) swap.
+pub struct TopK {
+    k: usize,
+    heap: BinaryHeap<SearchHit>,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k.min(TOP_K_PREALLOC) + 1),
+        }
+    }
+
+    /// Offer a candidate; it is kept only if it beats the current worst hit
+    pub fn offer(&mut self, hit: SearchHit) {
+        if self.heap.len() < self.k {
+            self.heap.push(hit);
+        } else if let Some(worst) = self.heap.peek() {
+            if hit < *worst {
+                self.heap.pop();
+                self.heap.push(hit);
+            }
+        }
+    }
+
+    /// Hits sorted from closest to furthest
+    pub fn into_sorted_vec(self) -> Vec<SearchHit> {
+        self.heap.into_sorted_vec()
+    }
+}
+
+/// Bind data for vector search - holds the query
+#[repr(C)]
+pub struct ManifoldVectorSearchBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Property holding the vectors to search
+    pub collection: String,
+    /// Query vector
+    pub query: Vec<f64>,
+    /// Number of results to return
+    pub k: usize,
+    /// Distance metric
+    pub metric: DistanceMetric,
+    /// Explicit exact/approximate choice (None = fall back automatically)
+    pub brute_force: Option<bool>,
+    /// Add the `vector` column
+    pub include_vector: bool,
+}
+
+/// Init data for vector search - holds the ranked hits
+#[repr(C)]
+pub struct ManifoldVectorSearchInitData {
+    /// Hits sorted by distance
+    pub hits: Vec<SearchHit>,
+    /// Each hit's stored vector, when `include_vector` is set
+    pub vectors: Vec<Option<Vec<f32>>>,
+    /// Number of hits already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Vector search VTab implementation
+pub struct ManifoldVectorSearchVTab;
+
+impl VTab for ManifoldVectorSearchVTab {
+    type InitData = ManifoldVectorSearchInitData;
+    type BindData = ManifoldVectorSearchBindData;
+
+    /// Bind phase: parse the query, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let collection = string_param(bind, 1, "collection")?;
+        let query = parse_list_literal(&bind.get_parameter(2).to_string())?;
+        let k = parse_k(i64_param(bind, 3, "k")?)?;
+
+        if query.is_empty() {
+            return Err(ManifoldScannerError::InvalidParameter(
+                "query vector must not be empty".to_string(),
+            )
+            .into());
+        }
+
+        let metric = parse_metric(bind)?;
+        let brute_force = parse_search_mode(bind)?;
+        let include_vector = named_bool(bind, "include_vector")?.unwrap_or(false);
+
+        bind.add_result_column("id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("distance", LogicalTypeHandle::from(LogicalTypeId::Double));
+        if include_vector {
+            bind.add_result_column("vector", vector_column_type());
+        }
+
+        Ok(ManifoldVectorSearchBindData {
+            db_path,
+            collection,
+            query,
+            k,
+            metric,
+            brute_force,
+            include_vector,
+        })
+    }
+
+    /// Init phase: run the search
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldVectorSearchBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+
+        check_search_mode(&engine, &bind_data.collection, bind_data.brute_force)?;
+
+        let tx = engine.begin_read()?;
+        let hits = brute_force_search(
+            &tx,
+            &bind_data.collection,
+            std::slice::from_ref(&bind_data.query),
+            bind_data.k,
+            bind_data.metric,
+        )?
+        .pop()
+        .unwrap_or_default();
+
+        let vectors = if bind_data.include_vector {
+            hit_vectors(&tx, &bind_data.collection, &hits)
+        } else {
+            Vec::new()
+        };
+
+        Ok(ManifoldVectorSearchInitData {
+            hits,
+            vectors,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the ranked hits
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_vector_search".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // collection
+            LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Double)), // query
+            LogicalTypeHandle::from(LogicalTypeId::Bigint),  // k
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "metric".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "exact".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            // Older spelling of `exact`
+            (
+                "brute_force".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+            (
+                "include_vector".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+        ])
+    }
+}
+
+impl ManifoldVectorSearchVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.hits.len());
+        let batch = &init_data.hits[start..end];
+
+        let mut distances = output.flat_vector(1);
+        for (row_idx, hit) in batch.iter().enumerate() {
+            distances.as_mut_slice::<f64>()[row_idx] = hit.distance;
+        }
+
+        let ids = output.flat_vector(0);
+        for (row_idx, hit) in batch.iter().enumerate() {
+            ids.insert(row_idx, CString::new(hit.id.to_string())?);
+        }
+
+        if func.get_bind_data().include_vector {
+            populate_vectors(output, 2, &init_data.vectors[start..end]);
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Batch-mode bind data - holds all queries
+#[repr(C)]
+pub struct ManifoldVectorSearchBatchBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Property holding the vectors to search
+    pub collection: String,
+    /// Query vectors, searched independently
+    pub queries: Vec<Vec<f64>>,
+    /// Number of results to return per query
+    pub k: usize,
+    /// Distance metric
+    pub metric: DistanceMetric,
+    /// Explicit exact/approximate choice (None = fall back automatically)
+    pub brute_force: Option<bool>,
+    /// Add the `vector` column
+    pub include_vector: bool,
+}
+
+/// Batch-mode init data - holds the ranked hits of every query
+#[repr(C)]
+pub struct ManifoldVectorSearchBatchInitData {
+    /// (query index, hit) pairs, grouped by query and sorted by distance
+    pub hits: Vec<(usize, SearchHit)>,
+    /// Each hit's stored vector, when `include_vector` is set
+    pub vectors: Vec<Option<Vec<f32>>>,
+    /// Number of hits already emitted
+    pub offset: AtomicUsize,
+}
+
+/// Batch vector search VTab implementation
+pub struct ManifoldVectorSearchBatchVTab;
+
+impl VTab for ManifoldVectorSearchBatchVTab {
+    type InitData = ManifoldVectorSearchBatchInitData;
+    type BindData = ManifoldVectorSearchBatchBindData;
+
+    /// Bind phase: parse the queries, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let collection = string_param(bind, 1, "collection")?;
+        let queries = parse_nested_list_literal(&bind.get_parameter(2).to_string())?;
+        let k = parse_k(i64_param(bind, 3, "k")?)?;
+
+        if queries.is_empty() {
+            return Err(ManifoldScannerError::InvalidParameter(
+                "query list must not be empty".to_string(),
+            )
+            .into());
+        }
+        if let Some(index) = queries.iter().position(|q| q.is_empty()) {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "query vector {} must not be empty",
+                index
+            ))
+            .into());
+        }
+
+        let metric = parse_metric(bind)?;
+        let brute_force = parse_search_mode(bind)?;
+        let include_vector = named_bool(bind, "include_vector")?.unwrap_or(false);
+
+        bind.add_result_column("query_index", LogicalTypeHandle::from(LogicalTypeId::Bigint));
+        bind.add_result_column("id", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("distance", LogicalTypeHandle::from(LogicalTypeId::Double));
+        if include_vector {
+            bind.add_result_column("vector", vector_column_type());
+        }
+
+        Ok(ManifoldVectorSearchBatchBindData {
+            db_path,
+            collection,
+            queries,
+            k,
+            metric,
+            brute_force,
+            include_vector,
+        })
+    }
+
+    /// Init phase: run every search in a single pass
+    fn init(init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        let bind_data = unsafe { &*init.get_bind_data::<ManifoldVectorSearchBatchBindData>() };
+        let engine = get_cached_engine(&bind_data.db_path)?;
+
+        check_search_mode(&engine, &bind_data.collection, bind_data.brute_force)?;
+
+        let tx = engine.begin_read()?;
+        let results = brute_force_search(
+            &tx,
+            &bind_data.collection,
+            &bind_data.queries,
+            bind_data.k,
+            bind_data.metric,
+        )?;
+
+        let hits: Vec<(usize, SearchHit)> = results
+            .into_iter()
+            .enumerate()
+            .flat_map(|(query_index, hits)| hits.into_iter().map(move |hit| (query_index, hit)))
+            .collect();
+
+        let vectors = if bind_data.include_vector {
+            let flat: Vec<SearchHit> = hits.iter().map(|&(_, hit)| hit).collect();
+            hit_vectors(&tx, &bind_data.collection, &flat)
+        } else {
+            Vec::new()
+        };
+
+        Ok(ManifoldVectorSearchBatchInitData {
+            hits,
+            vectors,
+            offset: AtomicUsize::new(0),
+        })
+    }
+
+    /// Func phase: emit the ranked hits
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_vector_search_batch".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        let query = LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Double));
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // collection
+            LogicalTypeHandle::list(&query),                 // queries
+            LogicalTypeHandle::from(LogicalTypeId::Bigint),  // k
+        ])
+    }
+
+    /// Define named parameters
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        ManifoldVectorSearchVTab::named_parameters()
+    }
+}
+
+impl ManifoldVectorSearchBatchVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+
+        let start = init_data.offset.load(Ordering::Relaxed);
+        let end = (start + chunk_batch_size(output)).min(init_data.hits.len());
+        let batch = &init_data.hits[start..end];
+
+        let mut query_indices = output.flat_vector(0);
+        for (row_idx, (query_index, _)) in batch.iter().enumerate() {
+            query_indices.as_mut_slice::<i64>()[row_idx] = *query_index as i64;
+        }
+
+        let mut distances = output.flat_vector(2);
+        for (row_idx, (_, hit)) in batch.iter().enumerate() {
+            distances.as_mut_slice::<f64>()[row_idx] = hit.distance;
+        }
+
+        let ids = output.flat_vector(1);
+        for (row_idx, (_, hit)) in batch.iter().enumerate() {
+            ids.insert(row_idx, CString::new(hit.id.to_string())?);
+        }
+
+        if func.get_bind_data().include_vector {
+            populate_vectors(output, 3, &init_data.vectors[start..end]);
+        }
+
+        init_data.offset.store(end, Ordering::Relaxed);
+        output.set_len(batch.len());
+
+        Ok(())
+    }
+}
+
+/// Validate the `k` parameter
+fn parse_k(k: i64) -> Result<usize, ManifoldScannerError> {
+    if k <= 0 {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "k must be positive, got {}",
+            k
+        )));
+    }
+    if k > MAX_K as i64 {
+        return Err(ManifoldScannerError::InvalidParameter(format!(
+            "k must be at most {}, got {}",
+            MAX_K, k
+        )));
+    }
+    Ok(k as usize)
+}
+
+/// Read the `metric` named parameter (L2 when unset)
+fn parse_metric(bind: &BindInfo) -> Result<DistanceMetric, ManifoldScannerError> {
+    match bind.get_named_parameter("metric") {
+        Some(v) => DistanceMetric::parse(&v.to_string()),
+        None => Ok(DistanceMetric::L2),
+    }
+}
+
+/// Read `exact` and `brute_force` as one brute-force choice
+fn parse_search_mode(bind: &BindInfo) -> Result<Option<bool>, ManifoldScannerError> {
+    search_mode(named_bool(bind, "exact")?, named_bool(bind, "brute_force")?)
+}
+
+/// Combine `exact` with its older spelling `brute_force`, which must agree
+///
+/// `exact=true` is the exact scan and `exact=false` the index (approximate)
+/// path; unset prefers the index and falls back to the scan.
+fn search_mode(
+    exact: Option<bool>,
+    brute_force: Option<bool>,
+) -> Result<Option<bool>, ManifoldScannerError> {
+    match (exact, brute_force) {
+        (Some(exact), Some(brute_force)) if exact != brute_force => {
+            Err(ManifoldScannerError::InvalidParameter(format!(
+                "exact={} contradicts brute_force={}; pass only exact",
+                exact, brute_force
+            )))
+        }
+        (exact, brute_force) => Ok(exact.or(brute_force)),
+    }
+}
+
+/// Apply the `exact` choice: fail when the approximate path is required
+fn check_search_mode(
+    engine: &ManifoldEngine,
+    collection: &str,
+    brute_force: Option<bool>,
+) -> Result<(), Box<dyn Error>> {
+    if brute_force != Some(false) {
+        return Ok(());
+    }
+    let reason = if has_vector_index(engine, collection)? {
+        "its HNSW index is not readable by this build"
+    } else {
+        "it has no HNSW index"
+    };
+    Err(ManifoldScannerError::InvalidParameter(format!(
+        "cannot search collection '{}' approximately (exact=false): {}; pass exact=true for \
+         the exact scan",
+        collection, reason
+    ))
+    .into())
+}
+
+/// Check whether Manifold has registered an HNSW index for the collection
+fn has_vector_index(engine: &ManifoldEngine, collection: &str) -> Result<bool, Box<dyn Error>> {
+    let tx = engine.begin_read()?;
+    match tx.get(HNSW_REGISTRY_TABLE, collection.as_bytes()) {
+        Ok(entry) => Ok(entry.is_some()),
+        // Registry table doesn't exist - no indexes at all
+        Err(_) => Ok(false),
+    }
+}
+
+/// Exact search: score every stored vector, keeping the best `k` per query
+///
+/// All queries share one pass over the nodes table. Returns one sorted hit list
+/// per query, in query order.
+fn brute_force_search(
+    tx: &RedbTransaction,
+    collection: &str,
+    queries: &[Vec<f64>],
+    k: usize,
+    metric: DistanceMetric,
+) -> Result<Vec<Vec<SearchHit>>, Box<dyn Error>> {
+    let mut top_ks: Vec<TopK> = queries.iter().map(|_| TopK::new(k)).collect();
+
+    if let Ok(mut cursor) = tx.cursor("nodes") {
+        let mut entry = cursor.seek_first()?;
+        while let Some((_key, value)) = entry {
+            if let Ok(entity) = Entity::decode(&value) {
+                if let Some(Value::Vector(vector)) = entity.properties.get(collection) {
+                    for (top_k, query) in top_ks.iter_mut().zip(queries) {
+                        if vector.len() == query.len() {
+                            top_k.offer(SearchHit {
+                                id: entity.id.as_u64(),
+                                distance: metric.distance(query, vector),
+                            });
+                        }
+                    }
+                }
+            }
+            entry = cursor.next()?;
+        }
+    }
+
+    Ok(top_ks.into_iter().map(TopK::into_sorted_vec).collect())
+}
+
+/// Type of the `vector` column
+fn vector_column_type() -> LogicalTypeHandle {
+    LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Double))
+}
+
+/// The stored vector of each hit, in hit order; None if it has since become
+/// unreadable, which can't happen within the transaction that found it
+fn hit_vectors(
+    tx: &RedbTransaction,
+    collection: &str,
+    hits: &[SearchHit],
+) -> Vec<Option<Vec<f32>>> {
+    hits.iter()
+        .map(|hit| {
+            let value = tx.get("nodes", &hit.id.to_be_bytes()).ok().flatten()?;
+            match Entity::decode(&value).ok()?.properties.remove(collection) {
+                Some(Value::Vector(vector)) => Some(vector),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Write one batch's vectors into a LIST(DOUBLE) column, NULL where missing
+fn populate_vectors(output: &mut DataChunkHandle, col_idx: usize, vectors: &[Option<Vec<f32>>]) {
+    let mut list = output.list_vector(col_idx);
+    let mut values = Vec::new();
+    for (row_idx, vector) in vectors.iter().enumerate() {
+        match vector {
+            Some(vector) => {
+                list.set_entry(row_idx, values.len(), vector.len());
+                values.extend(vector.iter().map(|&x| x as f64));
+            }
+            None => list.set_null(row_idx),
+        }
+    }
+    list.set_child(&values);
+}
+
+/// Parse a DuckDB list literal such as `[0.1, 0.2, 0.3]` into numbers
+fn parse_list_literal(text: &str) -> Result<Vec<f64>, ManifoldScannerError> {
+    let inner = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(|| {
+            ManifoldScannerError::InvalidParameter(format!(
+                "query vector must be a list, got '{}'",
+                text
+            ))
+        })?;
+
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    inner
+        .split(',')
+        .map(|item| {
+            let item = item.trim();
+            item.parse::<f64>().map_err(|_| {
+                ManifoldScannerError::InvalidParameter(format!(
+                    "query vector must contain only numbers, got '{}'",
+                    item
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Parse a nested DuckDB list literal such as `[[0.1, 0.2], [0.3, 0.4]]`
+fn parse_nested_list_literal(text: &str) -> Result<Vec<Vec<f64>>, ManifoldScannerError> {
+    let invalid = || {
+        ManifoldScannerError::InvalidParameter(format!(
+            "queries must be a list of lists, got '{}'",
+            text
+        ))
+    };
+    let inner = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+
+    let mut queries = Vec::new();
+    let mut rest = inner.trim();
+    while !rest.is_empty() {
+        if !rest.starts_with('[') {
+            return Err(invalid());
+        }
+        let end = rest.find(']').ok_or_else(invalid)?;
+        queries.push(parse_list_literal(&rest[..=end])?);
+
+        rest = rest[end + 1..].trim_start();
+        if let Some(after_comma) = rest.strip_prefix(',') {
+            rest = after_comma.trim_start();
+        } else if !rest.is_empty() {
+            return Err(invalid());
+        }
+    }
+
+    Ok(queries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+
+    #[test]
+    fn test_parse_list_literal() {
+        assert_eq!(parse_list_literal("[1.0, 2.5, -3]").unwrap(), vec![1.0, 2.5, -3.0]);
+        assert_eq!(parse_list_literal("[]").unwrap(), Vec::<f64>::new());
+        assert!(parse_list_literal("[1.0, NULL]").is_err());
+        assert!(parse_list_literal("1.0, 2.0").is_err());
+    }
+
+    #[test]
+    fn test_parse_nested_list_literal() {
+        assert_eq!(
+            parse_nested_list_literal("[[1.0, 2.0], [3, -4.5]]").unwrap(),
+            vec![vec![1.0, 2.0], vec![3.0, -4.5]]
+        );
+        assert_eq!(parse_nested_list_literal("[]").unwrap(), Vec::<Vec<f64>>::new());
+        assert!(parse_nested_list_literal("[1.0, 2.0]").is_err());
+        assert!(parse_nested_list_literal("[[1.0] [2.0]]").is_err());
+    }
+
+    #[test]
+    fn test_distance_metrics() {
+        let query = [1.0, 0.0];
+        assert_eq!(DistanceMetric::L2.distance(&query, &[4.0, 4.0]), 5.0);
+        assert_eq!(DistanceMetric::Cosine.distance(&query, &[2.0, 0.0]), 0.0);
+        assert_eq!(DistanceMetric::Cosine.distance(&query, &[0.0, 0.0]), 1.0);
+        assert_eq!(DistanceMetric::InnerProduct.distance(&query, &[3.0, 1.0]), -3.0);
+    }
+
+    #[test]
+    fn test_check_search_mode() {
+        let db = TempDb::new("vector_search_mode");
+        let engine = ManifoldEngine::from(db.open());
+        engine.begin_write().unwrap().commit().unwrap();
+
+        assert!(check_search_mode(&engine, "embedding", None).is_ok());
+        assert!(check_search_mode(&engine, "embedding", Some(true)).is_ok());
+        let err = check_search_mode(&engine, "embedding", Some(false)).unwrap_err().to_string();
+        assert!(err.contains("exact=false") && err.contains("no HNSW index"), "{}", err);
+    }
+
+    #[test]
+    fn test_search_mode() {
+        assert_eq!(search_mode(None, None).unwrap(), None);
+        assert_eq!(search_mode(Some(true), None).unwrap(), Some(true));
+        assert_eq!(search_mode(Some(false), None).unwrap(), Some(false));
+        assert_eq!(search_mode(None, Some(true)).unwrap(), Some(true));
+        assert_eq!(search_mode(Some(true), Some(true)).unwrap(), Some(true));
+        assert!(search_mode(Some(false), Some(true)).is_err());
+    }
+
+    #[test]
+    fn test_top_k_keeps_closest() {
+        let distances = [0.9, 0.1, 0.5, 0.3, 0.7, 0.1];
+        let mut top_k = TopK::new(3);
+        for (id, &distance) in distances.iter().enumerate() {
+            top_k.offer(SearchHit { id: id as u64, distance });
+        }
+
+        let ids: Vec<u64> = top_k.into_sorted_vec().iter().map(|h| h.id).collect();
+        assert_eq!(ids, vec![1, 5, 3]);
+    }
+
+    #[test]
+    fn test_parse_k() {
+        assert_eq!(parse_k(10).unwrap(), 10);
+        assert_eq!(parse_k(MAX_K as i64).unwrap(), MAX_K);
+        for k in [0, -1, MAX_K as i64 + 1, i64::MAX] {
+            assert!(parse_k(k).is_err(), "{}", k);
+        }
+
+        // A large k doesn't reserve its whole heap up front
+        let top_k = TopK::new(MAX_K);
+        assert!(top_k.heap.capacity() <= 2 * (TOP_K_PREALLOC + 1));
+    }
+
+    #[test]
+    fn test_exact_search_matches_full_sort() {
+        use manifoldb_core::encoding::Encoder;
+        use manifoldb_core::types::EntityId;
+
+        let db = TempDb::new("exact_topk");
+        let engine = db.open();
+
+        // 300 pseudo-random 8-d vectors (a fixed LCG, so runs repeat), every
+        // 50th one 4-d to be skipped
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        };
+        let mut stored = Vec::new();
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1..=300u64 {
+            let dim = if id % 50 == 0 { 4 } else { 8 };
+            let vector: Vec<f32> = (0..dim).map(|_| next()).collect();
+            let entity = Entity::new(EntityId::from(id))
+                .with_property("embedding", Value::Vector(vector.clone()));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+            stored.push((id, vector));
+        }
+        tx.commit().unwrap();
+
+        let queries: Vec<Vec<f64>> =
+            (0..3).map(|_| (0..8).map(|_| f64::from(next())).collect()).collect();
+        let tx = engine.begin_read().unwrap();
+        for metric in [DistanceMetric::L2, DistanceMetric::Cosine, DistanceMetric::InnerProduct] {
+            for k in [1, 10, 294, 500] {
+                let found = brute_force_search(&tx, "embedding", &queries, k, metric);
+                for (query, hits) in queries.iter().zip(found.unwrap()) {
+                    // Score everything, sort, cut - what the heap must match
+                    let mut expected: Vec<SearchHit> = stored
+                        .iter()
+                        .filter(|(_, vector)| vector.len() == query.len())
+                        .map(|(id, vector)| SearchHit {
+                            id: *id,
+                            distance: metric.distance(query, vector),
+                        })
+                        .collect();
+                    expected.sort();
+                    expected.truncate(k);
+                    assert_eq!(hits, expected, "{:?} k={}", metric, k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hit_vectors() {
+        use manifoldb_core::encoding::Encoder;
+        use manifoldb_core::types::EntityId;
+
+        let db = TempDb::new("hit_vectors");
+        let engine = db.open();
+        let mut tx = engine.begin_write().unwrap();
+        for (id, vector) in [(1u64, vec![1.0f32, 0.0]), (2, vec![0.5, 0.5]), (3, vec![0.0, 1.0])] {
+            let entity =
+                Entity::new(EntityId::from(id)).with_property("embedding", Value::Vector(vector));
+            tx.put("nodes", &id.to_be_bytes(), &entity.encode().unwrap()).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+        let query = vec![0.0, 1.0];
+        let hits = brute_force_search(&tx, "embedding", &[query], 2, DistanceMetric::L2)
+            .unwrap()
+            .pop()
+            .unwrap();
+        let missing = SearchHit { id: 9, distance: 0.0 };
+        let vectors = hit_vectors(&tx, "embedding", &[hits[0], hits[1], missing]);
+        assert_eq!(vectors, vec![Some(vec![0.0, 1.0]), Some(vec![0.5, 0.5]), None]);
+    }
+}
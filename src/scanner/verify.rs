@@ -0,0 +1,323 @@
+//! Decode audit for ManifoldDB tables
+//!
+//! Implements a table function that tries to decode every record of the nodes
+//! or edges table and returns one row per record that fails, for finding
+//! corruption that the scanners would otherwise skip silently.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT * FROM manifold_verify('/path/to/database.redb', 'nodes');
+//! SELECT count(*) FROM manifold_verify('/path/to/database.redb', 'edges');
+//! ```
+//!
+//! ## Columns
+//! - `key` - The record's storage key in hex
+//! - `byte_len` - Length of the stored value in bytes
+//! - `error` - Why it failed to decode
+//!
+//! An empty result means every record decoded. Decoding is the same as a
+//! `safe_mode` scan, so a decoder panic is reported as a failure rather than
+//! aborting the audit.
+//!
+//! ## Strategy
+//! The table is read in key order from one read transaction, continuing after
+//! the last key read, so memory use is bounded by a batch of failures however
+//! large the table. A call returns once it has read a batch of records and
+//! found at least one failure (see `batch_full`); a clean table is still read
+//! in a single call, since an empty result ends the audit.
+
+use duckdb::{
+    core::{DataChunkHandle, Inserter, LogicalTypeHandle, LogicalTypeId},
+    vtab::{BindInfo, InitInfo, TableFunctionInfo, VTab},
+};
+use std::{
+    error::Error,
+    ffi::CString,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use manifoldb_core::encoding::Decoder;
+use manifoldb_core::types::{Edge, Entity};
+use manifoldb_storage::backends::RedbTransaction;
+use manifoldb_storage::{Cursor, Transaction};
+
+use crate::error::ManifoldScannerError;
+use super::engine::ManifoldEngine;
+use super::{
+    batch_full, chunk_batch_size, decode_record, first_after, get_cached_engine, read_scan_batch,
+    string_param, ScanBatch, ScanRecord, ScanSnapshot,
+};
+
+/// Tables whose records can be verified
+const VERIFY_TABLES: [&str; 2] = ["nodes", "edges"];
+
+/// A record that failed to decode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeFailure {
+    pub key: Vec<u8>,
+    pub byte_len: usize,
+    pub error: String,
+}
+
+/// Bind data for the audit - holds the target table
+#[repr(C)]
+pub struct ManifoldVerifyBindData {
+    /// Path to the ManifoldDB database
+    pub db_path: String,
+    /// Table to verify ('nodes' or 'edges')
+    pub table: String,
+}
+
+/// Init data for the audit - holds scan state
+#[repr(C)]
+pub struct ManifoldVerifyInitData {
+    /// Flag indicating the table has been read to the end
+    pub done: AtomicBool,
+    /// Last key read - the audit continues after it
+    pub last_key: Mutex<Option<Vec<u8>>>,
+    /// Read transaction held across batches; None before the first batch and
+    /// after the audit completes
+    pub snapshot: Mutex<Option<ScanSnapshot>>,
+}
+
+impl ManifoldVerifyInitData {
+    /// Read the next batch of failures, releasing the snapshot at the end of the table
+    fn next_batch(
+        &self,
+        db_path: &str,
+        table: &str,
+        engine: &ManifoldEngine,
+        batch_size: usize,
+    ) -> Result<Vec<DecodeFailure>, Box<dyn Error>> {
+        if self.done.load(Ordering::Relaxed) {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let start_after_key = self.last_key.lock().unwrap().clone();
+
+        let batch = read_scan_batch(&self.done, db_path, start_after_key.as_deref(), || {
+            let tx = match &mut *snapshot {
+                Some(snapshot) => snapshot.tx(),
+                None => snapshot.insert(ScanSnapshot::begin(engine)?).tx(),
+            };
+            scan_failures(tx, table, start_after_key.as_deref(), batch_size)
+        });
+        let (failures, next_key) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                // The audit is over; don't keep the snapshot pinned
+                *snapshot = None;
+                return Err(e.into());
+            }
+        };
+
+        match next_key {
+            Some(key) => *self.last_key.lock().unwrap() = Some(key),
+            None => {
+                // End of the table - stop pinning the snapshot
+                self.done.store(true, Ordering::Relaxed);
+                *snapshot = None;
+            }
+        }
+
+        Ok(failures)
+    }
+}
+
+/// Decode audit VTab implementation
+pub struct ManifoldVerifyVTab;
+
+impl VTab for ManifoldVerifyVTab {
+    type InitData = ManifoldVerifyInitData;
+    type BindData = ManifoldVerifyBindData;
+
+    /// Bind phase: validate the table, set up columns
+    fn bind(bind: &BindInfo) -> Result<Self::BindData, Box<dyn Error>> {
+        let db_path = string_param(bind, 0, "db_path")?;
+        let table = string_param(bind, 1, "table")?;
+
+        if !VERIFY_TABLES.contains(&table.as_str()) {
+            return Err(ManifoldScannerError::InvalidParameter(format!(
+                "table must be 'nodes' or 'edges', got '{}'",
+                table
+            ))
+            .into());
+        }
+
+        // Open now so a bad path fails at bind
+        get_cached_engine(&db_path)?;
+
+        bind.add_result_column("key", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+        bind.add_result_column("byte_len", LogicalTypeHandle::from(LogicalTypeId::UBigint));
+        bind.add_result_column("error", LogicalTypeHandle::from(LogicalTypeId::Varchar));
+
+        Ok(ManifoldVerifyBindData { db_path, table })
+    }
+
+    /// Init phase: the snapshot is taken with the first batch
+    fn init(_init: &InitInfo) -> Result<Self::InitData, Box<dyn Error>> {
+        Ok(ManifoldVerifyInitData {
+            done: AtomicBool::new(false),
+            last_key: Mutex::new(None),
+            snapshot: Mutex::new(None),
+        })
+    }
+
+    /// Func phase: emit the next batch of failures
+    fn func(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        // Wrap in catch_unwind to prevent panics from crossing FFI boundary
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Self::func_inner(func, output)
+        }));
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err("Internal panic in manifold_verify".into()),
+        }
+    }
+
+    /// Define input parameters
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // db_path
+            LogicalTypeHandle::from(LogicalTypeId::Varchar), // table
+        ])
+    }
+}
+
+impl ManifoldVerifyVTab {
+    fn func_inner(
+        func: &TableFunctionInfo<Self>,
+        output: &mut DataChunkHandle,
+    ) -> Result<(), Box<dyn Error>> {
+        let init_data = func.get_init_data();
+        let bind_data = func.get_bind_data();
+
+        let engine = get_cached_engine(&bind_data.db_path)?;
+        let (db_path, table) = (&bind_data.db_path, &bind_data.table);
+        let failures = init_data.next_batch(db_path, table, &engine, chunk_batch_size(output))?;
+
+        let keys = output.flat_vector(0);
+        let mut byte_lens = output.flat_vector(1);
+        let errors = output.flat_vector(2);
+        for (row_idx, failure) in failures.iter().enumerate() {
+            keys.insert(row_idx, CString::new(hex(&failure.key))?);
+            byte_lens.as_mut_slice::<u64>()[row_idx] = failure.byte_len as u64;
+            errors.insert(row_idx, CString::new(failure.error.as_str())?);
+        }
+
+        output.set_len(failures.len());
+        Ok(())
+    }
+}
+
+/// Read records after `start_after_key` until the batch is full (see
+/// `batch_full`) or the table ends
+///
+/// Returns the failures and the last key read, or None for the key once the
+/// table is exhausted (or missing).
+fn scan_failures(
+    tx: &RedbTransaction,
+    table: &str,
+    start_after_key: Option<&[u8]>,
+    batch_size: usize,
+) -> Result<ScanBatch<DecodeFailure>, Box<dyn Error>> {
+    let mut failures = Vec::new();
+
+    let Ok(mut cursor) = tx.cursor(table) else {
+        // Table doesn't exist - nothing to verify
+        return Ok((failures, None));
+    };
+
+    let mut entry = first_after(&mut cursor, start_after_key)?;
+
+    let mut read = 0;
+    while let Some((key, value)) = entry {
+        read += 1;
+        if let Some(error) = decode_error(table, &key, &value) {
+            failures.push(DecodeFailure {
+                key: key.clone(),
+                byte_len: value.len(),
+                error,
+            });
+        }
+        if batch_full(failures.len(), read, batch_size) {
+            return Ok((failures, Some(key)));
+        }
+        entry = cursor.next()?;
+    }
+
+    Ok((failures, None))
+}
+
+/// Why a record of the table fails to decode, or None if it decodes
+fn decode_error(table: &str, key: &[u8], value: &[u8]) -> Option<String> {
+    fn error<T: Decoder>(key: &[u8], value: &[u8]) -> Option<String> {
+        match decode_record::<T>(key, value, true) {
+            Some(ScanRecord::Failed { error, .. }) => Some(error),
+            _ => None,
+        }
+    }
+
+    match table {
+        "nodes" => error::<Entity>(key, value),
+        _ => error::<Edge>(key, value),
+    }
+}
+
+/// Lowercase hex of a key
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use manifoldb_core::encoding::Encoder;
+    use manifoldb_core::types::{EdgeId, EdgeType, EntityId};
+    use manifoldb_storage::StorageEngine;
+
+    #[test]
+    fn test_scan_failures() {
+        let db = TempDb::new("verify");
+        let engine = db.open();
+
+        let mut tx = engine.begin_write().unwrap();
+        for id in 1u64..=5 {
+            let value = match id {
+                2 | 4 => vec![0xff, 0x00, 0x01],
+                _ => Entity::new(EntityId::from(id)).encode().unwrap(),
+            };
+            tx.put("nodes", &id.to_be_bytes(), &value).unwrap();
+        }
+        let edge =
+            Edge::new(EdgeId::from(1), EntityId::from(1), EntityId::from(3), EdgeType::new("E"));
+        tx.put("edges", &1u64.to_be_bytes(), &edge.encode().unwrap()).unwrap();
+        tx.commit().unwrap();
+
+        let tx = engine.begin_read().unwrap();
+
+        // One failure per batch, continuing after the last
+        let (first, next_key) = scan_failures(&tx, "nodes", None, 1).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(hex(&first[0].key), "0000000000000002");
+        assert_eq!(first[0].byte_len, 3);
+        assert!(!first[0].error.is_empty());
+        let (rest, next_key) = scan_failures(&tx, "nodes", next_key.as_deref(), 1).unwrap();
+        assert_eq!(hex(&rest[0].key), "0000000000000004");
+        let (end, next_key) = scan_failures(&tx, "nodes", next_key.as_deref(), 1).unwrap();
+        assert!(end.is_empty() && next_key.is_none());
+
+        // Clean and missing tables have no failures
+        assert_eq!(scan_failures(&tx, "edges", None, 10).unwrap(), (Vec::new(), None));
+        assert!(scan_failures(&tx, "missing", None, 10).unwrap().0.is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Pre-opening databases
+//!
+//! Implements `manifold_warmup(path)`, a scalar function that does the work
+//! of a first scan ahead of time, for calling at service startup.
+//!
+//! ## Usage
+//! ```sql
+//! SELECT manifold_warmup('/path/to/database.redb');
+//! ```
+//!
+//! ## What It Warms
+//! - The engine cache: the file is opened (and repaired by redb if it was not
+//!   closed cleanly) and cached, so later queries skip the open
+//! - redb's page cache: entity and edge schema discovery is run with the
+//!   default sample size, reading the pages a first scan's bind would
+//!
+//! Discovered schemas are not cached - each scan still samples at bind, from
+//! pages that are now in memory. Returns the milliseconds taken.
+
+use duckdb::{
+    core::{DataChunkHandle, LogicalTypeId},
+    ffi::duckdb_string_t,
+    types::DuckString,
+    vscalar::{ScalarFunctionSignature, VScalar},
+    vtab::arrow::WritableVector,
+};
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use super::edges::{discover_edge_schema, EdgeScanOptions};
+use super::entities::{discover_entity_schema, EntityScanOptions};
+use super::{get_cached_engine, schema_sample_size};
+
+/// Open and cache a database and run schema discovery, returning the time taken
+pub fn warm_up(db_path: &str) -> Result<Duration, Box<dyn Error>> {
+    let started = Instant::now();
+    let engine = get_cached_engine(db_path)?;
+
+    let entity_options = EntityScanOptions {
+        include_labels: true,
+        schema_sample: schema_sample_size(),
+        ..Default::default()
+    };
+    discover_entity_schema(&engine, &entity_options)?;
+
+    let edge_options = EdgeScanOptions {
+        schema_sample: schema_sample_size(),
+        ..Default::default()
+    };
+    discover_edge_schema(&engine, &edge_options)?;
+
+    Ok(started.elapsed())
+}
+
+/// `manifold_warmup` scalar implementation
+pub struct ManifoldWarmupScalar;
+
+impl VScalar for ManifoldWarmupScalar {
+    type State = ();
+
+    /// Warm up each non-NULL path, returning the milliseconds taken
+    unsafe fn invoke(
+        _state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let paths = input.flat_vector(0);
+        let values = paths.as_slice_with_len::<duckdb_string_t>(input.len());
+        let mut output = output.flat_vector();
+
+        for (row_idx, path) in values.iter().enumerate() {
+            if paths.row_is_null(row_idx as u64) {
+                output.set_null(row_idx);
+                continue;
+            }
+
+            let path = DuckString::new(&mut { *path }).as_str().to_string();
+            output.as_mut_slice::<f64>()[row_idx] = warm_up(&path)?.as_secs_f64() * 1000.0;
+        }
+
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::exact(
+            vec![LogicalTypeId::Varchar.into()],
+            LogicalTypeId::Double.into(),
+        )]
+    }
+
+    /// Warming up is a side effect; never constant-fold it
+    fn volatile() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::test_db::TempDb;
+    use crate::scanner::drop_cached_engine;
+
+    #[test]
+    fn test_warm_up() {
+        let db = TempDb::new("warmup");
+        let db_path = db.db_path();
+
+        // A missing file fails rather than being created
+        assert!(warm_up(db_path).is_err());
+
+        drop(db.open());
+        warm_up(db_path).unwrap();
+        assert!(drop_cached_engine(db_path).unwrap());
+    }
+}
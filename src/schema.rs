@@ -9,7 +9,8 @@
 //! - Support schema evolution (new properties don't break queries)
 
 use duckdb::core::{LogicalTypeHandle, LogicalTypeId};
-use std::collections::HashMap;
+use manifoldb_core::types::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Our own type ID enum that implements Clone/Copy (LogicalTypeId from duckdb doesn't)
 /// This allows us to store type information in thread-safe structures
@@ -20,6 +21,21 @@ pub enum ColumnType {
     Double,
     Varchar,
     Blob,
+    /// INTEGER; only the `num_labels` column
+    Integer,
+    /// UINTEGER; only the `labels_mask` column
+    UInteger,
+    /// LIST(VARCHAR)
+    VarcharList,
+    /// STRUCT; the field list lives on the `DiscoveredColumn`
+    Struct,
+    /// DECIMAL(width, scale); never inferred, only set by `decimal_props`
+    Decimal { width: u8, scale: u8 },
+    /// VARCHAR holding DECIMAL(width, scale) text; `decimal_props` under
+    /// `coercion_policy='stringify'`
+    DecimalText { width: u8, scale: u8 },
+    /// TIMESTAMP; never inferred, only set by `timestamp_props`
+    Timestamp,
 }
 
 impl ColumnType {
@@ -31,12 +47,28 @@ impl ColumnType {
             ColumnType::Double => LogicalTypeId::Double,
             ColumnType::Varchar => LogicalTypeId::Varchar,
             ColumnType::Blob => LogicalTypeId::Blob,
+            ColumnType::Integer => LogicalTypeId::Integer,
+            ColumnType::UInteger => LogicalTypeId::UInteger,
+            ColumnType::VarcharList => LogicalTypeId::List,
+            ColumnType::Struct => LogicalTypeId::Struct,
+            ColumnType::Decimal { .. } => LogicalTypeId::Decimal,
+            ColumnType::DecimalText { .. } => LogicalTypeId::Varchar,
+            ColumnType::Timestamp => LogicalTypeId::Timestamp,
         }
     }
 
     /// Convert to DuckDB LogicalTypeHandle
     pub fn to_logical_type_handle(self) -> LogicalTypeHandle {
-        LogicalTypeHandle::from(self.to_logical_type_id())
+        match self {
+            ColumnType::VarcharList => {
+                LogicalTypeHandle::list(&LogicalTypeHandle::from(LogicalTypeId::Varchar))
+            }
+            // Without its fields a struct can only be empty; columns use
+            // `DiscoveredColumn::to_logical_type_handle`
+            ColumnType::Struct => LogicalTypeHandle::struct_type(&[]),
+            ColumnType::Decimal { width, scale } => LogicalTypeHandle::decimal(width, scale),
+            _ => LogicalTypeHandle::from(self.to_logical_type_id()),
+        }
     }
 
     /// Convert from DuckDB LogicalTypeId (defaults to Varchar for unknown types)
@@ -58,19 +90,67 @@ pub struct DiscoveredColumn {
     /// Column type (using our own Clone-able enum)
     pub column_type: ColumnType,
     pub nullable: bool,
+    /// Field names and types, for STRUCT columns; for the `labels_mask`
+    /// column, the label each bit stands for (empty otherwise)
+    pub fields: Vec<(String, ColumnType)>,
+    /// The property a property column holds (None for every other column)
+    pub property: Option<String>,
 }
 
 impl DiscoveredColumn {
     /// Convert the stored type to a LogicalTypeHandle for DuckDB registration
     pub fn to_logical_type_handle(&self) -> LogicalTypeHandle {
-        self.column_type.to_logical_type_handle()
+        match self.column_type {
+            ColumnType::Struct => {
+                let fields: Vec<(&str, LogicalTypeHandle)> = self
+                    .fields
+                    .iter()
+                    .map(|(name, field_type)| (name.as_str(), field_type.to_logical_type_handle()))
+                    .collect();
+                LogicalTypeHandle::struct_type(&fields)
+            }
+            _ => self.column_type.to_logical_type_handle(),
+        }
     }
 }
 
-/// Maps Manifold Value types to ColumnType
-pub fn manifold_value_to_column_type(value: &manifoldb_core::types::Value) -> ColumnType {
-    use manifoldb_core::types::Value;
+/// Read an array of `[key, value]` pairs as a nested object
+///
+/// Manifold has no object value type, so nested objects are stored as a
+/// `Value::Array` whose elements are all two-element arrays with a string key.
+/// Returns the fields sorted by key, or None if the value isn't shaped like
+/// that (including empty arrays and duplicate keys).
+pub fn value_as_object(value: &Value) -> Option<Vec<(&str, &Value)>> {
+    let Value::Array(items) = value else {
+        return None;
+    };
+    if items.is_empty() {
+        return None;
+    }
+
+    let mut fields = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            Value::Array(pair) => match pair.as_slice() {
+                [Value::String(key), value] => fields.push((key.as_str(), value)),
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+
+    fields.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    if fields.windows(2).any(|w| w[0].0 == w[1].0) {
+        return None;
+    }
+    Some(fields)
+}
 
+/// Maps Manifold Value types to ColumnType
+///
+/// `Value::Int` is stored as 8 bytes (big-endian i64), so BIGINT always holds it
+/// without truncation - there is no wider integer type that would need HUGEINT.
+pub fn manifold_value_to_column_type(value: &Value) -> ColumnType {
     match value {
         Value::Null => ColumnType::Varchar, // Nullable, default to varchar
         Value::Bool(_) => ColumnType::Boolean,
@@ -78,13 +158,224 @@ pub fn manifold_value_to_column_type(value: &manifoldb_core::types::Value) -> Co
         Value::Float(_) => ColumnType::Double,
         Value::String(_) => ColumnType::Varchar,
         Value::Bytes(_) => ColumnType::Blob,
+        Value::Array(_) if value_as_object(value).is_some() => ColumnType::Struct,
         Value::Array(_) => ColumnType::Varchar, // JSON-encode arrays for now
-        Value::Vector(_) => ColumnType::Varchar, // JSON-encode vectors
+        // JSON-encode vectors: each cell keeps its own length, so vectors of
+        // different dimensions can share a column without a dimension policy
+        Value::Vector(_) => ColumnType::Varchar,
         Value::SparseVector(_) => ColumnType::Varchar,
         Value::MultiVector(_) => ColumnType::Varchar,
     }
 }
 
+/// Shape of a property's nested-object values across the sample
+#[derive(Debug, Clone, PartialEq)]
+enum ObjectShape {
+    /// Every non-null value is an object with these keys (and observed field types)
+    Consistent(BTreeMap<String, Vec<ColumnType>>),
+    /// Some value isn't an object, or keys differ between values
+    Mixed,
+}
+
+impl ObjectShape {
+    /// Fold one property value into the shape seen so far
+    fn observe(shape: Option<Self>, value: &manifoldb_core::types::Value) -> Option<Self> {
+        if matches!(value, manifoldb_core::types::Value::Null) {
+            return shape;
+        }
+        let Some(fields) = value_as_object(value) else {
+            return Some(ObjectShape::Mixed);
+        };
+
+        match shape {
+            None => Some(ObjectShape::Consistent(
+                fields
+                    .iter()
+                    .map(|(key, field)| (key.to_string(), observed_field_type(field)))
+                    .collect(),
+            )),
+            Some(ObjectShape::Consistent(mut known))
+                if known.len() == fields.len()
+                    && fields.iter().all(|(key, _)| known.contains_key(*key)) =>
+            {
+                for (key, field) in fields {
+                    let types = known.get_mut(key).expect("key set matches");
+                    for field_type in observed_field_type(field) {
+                        if !types.contains(&field_type) {
+                            types.push(field_type);
+                        }
+                    }
+                }
+                Some(ObjectShape::Consistent(known))
+            }
+            Some(_) => Some(ObjectShape::Mixed),
+        }
+    }
+
+    /// STRUCT fields for a consistent shape
+    ///
+    /// A field keeps its scalar type if every sampled value agreed on one;
+    /// otherwise (mixed, nested, or binary) it is VARCHAR.
+    fn struct_fields(&self) -> Option<Vec<(String, ColumnType)>> {
+        let ObjectShape::Consistent(fields) = self else {
+            return None;
+        };
+
+        Some(
+            fields
+                .iter()
+                .map(|(name, types)| {
+                    let field_type = match types.as_slice() {
+                        [t @ (ColumnType::Boolean | ColumnType::Bigint | ColumnType::Double)] => *t,
+                        _ => ColumnType::Varchar,
+                    };
+                    (name.clone(), field_type)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Field type observed for one value; NULL fields don't constrain the type
+fn observed_field_type(value: &manifoldb_core::types::Value) -> Vec<ColumnType> {
+    match value {
+        manifoldb_core::types::Value::Null => Vec::new(),
+        _ => vec![manifold_value_to_column_type(value)],
+    }
+}
+
+/// Prefix of property column names unless `prop_prefix` says otherwise
+pub const DEFAULT_PROP_PREFIX: &str = "prop_";
+
+/// Build a property column: STRUCT when every sampled value is a same-shaped
+/// object, VARCHAR otherwise
+fn property_column(
+    column_name: String,
+    name: &str,
+    shape: Option<&ObjectShape>,
+) -> DiscoveredColumn {
+    match shape.and_then(ObjectShape::struct_fields) {
+        Some(fields) => DiscoveredColumn {
+            name: column_name,
+            column_type: ColumnType::Struct,
+            nullable: true,
+            fields,
+            property: Some(name.to_string()),
+        },
+        None => DiscoveredColumn {
+            name: column_name,
+            column_type: ColumnType::Varchar, // VARCHAR - simpler and DuckDB can cast
+            nullable: true,                 // Properties may not exist on all entities
+            fields: Vec::new(),
+            property: Some(name.to_string()),
+        },
+    }
+}
+
+/// Longest property column name, in bytes
+///
+/// A longer name (from a very long property key) is cut at a character
+/// boundary and ends in `_` plus 16 hex digits hashing the full name, so it
+/// stays unique and comes out the same on every scan rather than being cut
+/// differently by each client. 240 keeps the `__json` and `_type` companion
+/// columns under the 255-byte identifier limit common to DuckDB clients and
+/// export formats.
+pub const MAX_COLUMN_NAME_LEN: usize = 240;
+
+/// A column name within `MAX_COLUMN_NAME_LEN`, shortened with a hash suffix
+/// if needed
+fn fit_column_name(name: &str) -> String {
+    if name.len() <= MAX_COLUMN_NAME_LEN {
+        return name.to_string();
+    }
+    // FNV-1a: fixed across builds and platforms, unlike std's hasher
+    let hash = name
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    let mut cut = MAX_COLUMN_NAME_LEN - 17;
+    while !name.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}_{:016x}", &name[..cut], hash)
+}
+
+/// Name the column of each property: the prefix plus the property name
+///
+/// A name already taken - by a fixed column (`id`, `labels`, ...) or an
+/// earlier property, which only happens with a short or empty prefix - gets
+/// `prop_` in front until it is free. Names longer than
+/// `MAX_COLUMN_NAME_LEN` are shortened; the column's `property` still holds
+/// the full property name. Names are given in sorted property order, so the
+/// result is stable for a given set of properties.
+fn property_column_names<'a>(
+    property_names: &[&'a String],
+    prefix: &str,
+    taken: &mut HashSet<String>,
+) -> Vec<(String, &'a String)> {
+    property_names
+        .iter()
+        .map(|name| {
+            let mut full_name = format!("{}{}", prefix, name);
+            let mut column_name = fit_column_name(&full_name);
+            while taken.contains(&column_name) {
+                full_name = format!("{}{}", DEFAULT_PROP_PREFIX, full_name);
+                column_name = fit_column_name(&full_name);
+            }
+            taken.insert(column_name.clone());
+            (column_name, *name)
+        })
+        .collect()
+}
+
+/// Most `prop_*` columns a scan registers
+///
+/// Pathologically wide data can have thousands of property keys; past this
+/// many, the remaining properties share one `extra_props` JSON column instead
+/// of each getting a column, keeping bind well inside DuckDB's limits.
+pub const MAX_PROPERTY_COLUMNS: usize = 1000;
+
+/// Name of the catch-all column for properties without a column of their own
+pub const EXTRA_PROPS_COLUMN: &str = "extra_props";
+
+/// The property columns in name order, capped at `MAX_PROPERTY_COLUMNS`,
+/// plus `extra_props` if any property didn't fit, after the fixed columns
+fn property_columns(
+    fixed: &[DiscoveredColumn],
+    property_types: &HashMap<String, Vec<ColumnType>>,
+    object_shapes: &HashMap<String, ObjectShape>,
+    prefix: &str,
+) -> Vec<DiscoveredColumn> {
+    let mut property_names: Vec<_> = property_types.keys().collect();
+    property_names.sort(); // Consistent column ordering
+    let overflow = property_names.len() > MAX_PROPERTY_COLUMNS;
+    property_names.truncate(MAX_PROPERTY_COLUMNS);
+
+    let mut taken: HashSet<String> = fixed.iter().map(|column| column.name.clone()).collect();
+    if overflow {
+        taken.insert(EXTRA_PROPS_COLUMN.to_string());
+    }
+
+    let mut columns: Vec<DiscoveredColumn> =
+        property_column_names(&property_names, prefix, &mut taken)
+            .into_iter()
+            .map(|(column_name, name)| property_column(column_name, name, object_shapes.get(name)))
+            .collect();
+
+    if overflow {
+        columns.push(DiscoveredColumn {
+            name: EXTRA_PROPS_COLUMN.to_string(),
+            column_type: ColumnType::Varchar, // JSON object
+            nullable: true,
+            fields: Vec::new(),
+            property: None,
+        });
+    }
+
+    columns
+}
+
 /// Discovers schema from a collection of entities
 ///
 /// Scans entities to find all unique property keys and infer their types.
@@ -92,6 +383,8 @@ pub fn manifold_value_to_column_type(value: &manifoldb_core::types::Value) -> Co
 pub struct SchemaDiscovery {
     /// Property name -> observed types
     property_types: HashMap<String, Vec<ColumnType>>,
+    /// Property name -> nested-object shape
+    object_shapes: HashMap<String, ObjectShape>,
     /// Number of entities scanned
     sample_count: usize,
 }
@@ -100,6 +393,7 @@ impl SchemaDiscovery {
     pub fn new() -> Self {
         Self {
             property_types: HashMap::new(),
+            object_shapes: HashMap::new(),
             sample_count: 0,
         }
     }
@@ -114,12 +408,17 @@ impl SchemaDiscovery {
             let types = self
                 .property_types
                 .entry(key.clone())
-                .or_insert_with(Vec::new);
+                .or_default();
 
             // Only add if not already present
             if !types.contains(&col_type) {
                 types.push(col_type);
             }
+
+            let shape = ObjectShape::observe(self.object_shapes.remove(key), value);
+            if let Some(shape) = shape {
+                self.object_shapes.insert(key.clone(), shape);
+            }
         }
     }
 
@@ -127,41 +426,38 @@ impl SchemaDiscovery {
     ///
     /// Returns columns for:
     /// - id (always VARCHAR)
-    /// - labels (always VARCHAR, JSON array)
-    /// - All discovered property columns
-    pub fn finalize(self) -> Vec<DiscoveredColumn> {
-        let mut columns = Vec::new();
-
+    /// - labels (VARCHAR, JSON array; the entity scanner reshapes it per `label_mode`)
+    /// - All discovered property columns, named `prop_prefix` + property
+    pub fn finalize(self, prop_prefix: &str) -> Vec<DiscoveredColumn> {
         // Fixed columns that always exist
-        columns.push(DiscoveredColumn {
-            name: "id".to_string(),
-            column_type: ColumnType::Varchar,
-            nullable: false,
-        });
-
-        columns.push(DiscoveredColumn {
-            name: "labels".to_string(),
-            column_type: ColumnType::Varchar, // JSON array
-            nullable: false,
-        });
-
-        // Dynamic property columns - always use VARCHAR for simplicity
-        // DuckDB can cast to other types as needed in queries
-        let mut property_names: Vec<_> = self.property_types.keys().cloned().collect();
-        property_names.sort(); // Consistent column ordering
-
-        for name in property_names {
-            columns.push(DiscoveredColumn {
-                name: format!("prop_{}", name), // Prefix to avoid conflicts with fixed columns
-                column_type: ColumnType::Varchar, // Always VARCHAR - simpler and DuckDB can cast
-                nullable: true,                 // Properties may not exist on all entities
-            });
-        }
+        let mut columns = vec![
+            DiscoveredColumn {
+                name: "id".to_string(),
+                column_type: ColumnType::Varchar,
+                nullable: false,
+                fields: Vec::new(),
+                property: None,
+            },
+            DiscoveredColumn {
+                name: "labels".to_string(),
+                column_type: ColumnType::Varchar, // JSON array
+                nullable: false,
+                fields: Vec::new(),
+                property: None,
+            },
+        ];
+
+        // Dynamic property columns - VARCHAR for simplicity (DuckDB can cast as
+        // needed in queries), except consistently-shaped nested objects
+        let properties =
+            property_columns(&columns, &self.property_types, &self.object_shapes, prop_prefix);
+        columns.extend(properties);
 
         columns
     }
 
     /// Get sample count for diagnostics
+    #[allow(dead_code)]
     pub fn sample_count(&self) -> usize {
         self.sample_count
     }
@@ -170,6 +466,7 @@ impl SchemaDiscovery {
 /// Edge schema discovery (similar but for edges)
 pub struct EdgeSchemaDiscovery {
     property_types: HashMap<String, Vec<ColumnType>>,
+    object_shapes: HashMap<String, ObjectShape>,
     sample_count: usize,
 }
 
@@ -177,6 +474,7 @@ impl EdgeSchemaDiscovery {
     pub fn new() -> Self {
         Self {
             property_types: HashMap::new(),
+            object_shapes: HashMap::new(),
             sample_count: 0,
         }
     }
@@ -190,12 +488,17 @@ impl EdgeSchemaDiscovery {
             let types = self
                 .property_types
                 .entry(key.clone())
-                .or_insert_with(Vec::new);
+                .or_default();
 
             // Only add if not already present
             if !types.contains(&col_type) {
                 types.push(col_type);
             }
+
+            let shape = ObjectShape::observe(self.object_shapes.remove(key), value);
+            if let Some(shape) = shape {
+                self.object_shapes.insert(key.clone(), shape);
+            }
         }
     }
 
@@ -206,46 +509,44 @@ impl EdgeSchemaDiscovery {
     /// - source (VARCHAR) - source entity ID
     /// - target (VARCHAR) - target entity ID
     /// - edge_type (VARCHAR)
-    /// - All discovered property columns
-    pub fn finalize(self) -> Vec<DiscoveredColumn> {
-        let mut columns = Vec::new();
-
+    /// - All discovered property columns, named `prop_prefix` + property
+    pub fn finalize(self, prop_prefix: &str) -> Vec<DiscoveredColumn> {
         // Fixed columns for edges
-        columns.push(DiscoveredColumn {
-            name: "id".to_string(),
-            column_type: ColumnType::Varchar,
-            nullable: false,
-        });
-
-        columns.push(DiscoveredColumn {
-            name: "source".to_string(),
-            column_type: ColumnType::Varchar,
-            nullable: false,
-        });
-
-        columns.push(DiscoveredColumn {
-            name: "target".to_string(),
-            column_type: ColumnType::Varchar,
-            nullable: false,
-        });
-
-        columns.push(DiscoveredColumn {
-            name: "edge_type".to_string(),
-            column_type: ColumnType::Varchar,
-            nullable: false,
-        });
-
-        // Dynamic property columns - always use VARCHAR for simplicity
-        let mut property_names: Vec<_> = self.property_types.keys().cloned().collect();
-        property_names.sort();
-
-        for name in property_names {
-            columns.push(DiscoveredColumn {
-                name: format!("prop_{}", name),
-                column_type: ColumnType::Varchar, // Always VARCHAR - DuckDB can cast
-                nullable: true,
-            });
-        }
+        let mut columns = vec![
+            DiscoveredColumn {
+                name: "id".to_string(),
+                column_type: ColumnType::Varchar,
+                nullable: false,
+                fields: Vec::new(),
+                property: None,
+            },
+            DiscoveredColumn {
+                name: "source".to_string(),
+                column_type: ColumnType::Varchar,
+                nullable: false,
+                fields: Vec::new(),
+                property: None,
+            },
+            DiscoveredColumn {
+                name: "target".to_string(),
+                column_type: ColumnType::Varchar,
+                nullable: false,
+                fields: Vec::new(),
+                property: None,
+            },
+            DiscoveredColumn {
+                name: "edge_type".to_string(),
+                column_type: ColumnType::Varchar,
+                nullable: false,
+                fields: Vec::new(),
+                property: None,
+            },
+        ];
+
+        // Dynamic property columns - VARCHAR, or STRUCT for nested objects
+        let properties =
+            property_columns(&columns, &self.property_types, &self.object_shapes, prop_prefix);
+        columns.extend(properties);
 
         columns
     }
@@ -255,6 +556,23 @@ impl EdgeSchemaDiscovery {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_value_as_object() {
+        let pair = |k: &str, v: Value| Value::Array(vec![Value::String(k.to_string()), v]);
+
+        let value = Value::Array(vec![pair("b", Value::Int(2)), pair("a", Value::Bool(true))]);
+        assert_eq!(
+            value_as_object(&value),
+            Some(vec![("a", &Value::Bool(true)), ("b", &Value::Int(2))])
+        );
+
+        // Plain lists, empty arrays and duplicate keys aren't objects
+        assert_eq!(value_as_object(&Value::Array(vec![Value::Int(1), Value::Int(2)])), None);
+        assert_eq!(value_as_object(&Value::Array(Vec::new())), None);
+        let dup = Value::Array(vec![pair("a", Value::Int(1)), pair("a", Value::Int(2))]);
+        assert_eq!(value_as_object(&dup), None);
+    }
+
     #[test]
     fn test_schema_discovery_basic() {
         let mut discovery = SchemaDiscovery::new();
@@ -268,11 +586,156 @@ mod tests {
 
         discovery.observe_entity(&props1);
 
-        let schema = discovery.finalize();
+        let schema = discovery.finalize(DEFAULT_PROP_PREFIX);
 
         // Should have id, labels, prop_age, prop_name
         assert_eq!(schema.len(), 4);
         assert_eq!(schema[0].name, "id");
         assert_eq!(schema[1].name, "labels");
     }
+
+    #[test]
+    fn test_prop_prefix_avoids_fixed_columns() {
+        use manifoldb_core::types::Value;
+
+        let props: HashMap<String, Value> = ["id", "labels", "name", "prop_id"]
+            .iter()
+            .map(|name| (name.to_string(), Value::Int(1)))
+            .collect();
+        let discovery = || {
+            let mut discovery = SchemaDiscovery::new();
+            discovery.observe_entity(&props);
+            discovery
+        };
+
+        // An empty prefix keeps names, moving clashes behind `prop_` until free
+        let schema = discovery().finalize("");
+        let names: Vec<_> = schema.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "labels", "prop_id", "prop_labels", "name", "prop_prop_id"]);
+        assert_eq!(schema[2].property.as_deref(), Some("id"));
+        assert_eq!(schema[5].property.as_deref(), Some("prop_id"));
+
+        let schema = discovery().finalize("p.");
+        let names: Vec<_> = schema.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "labels", "p.id", "p.labels", "p.name", "p.prop_id"]);
+    }
+
+    #[test]
+    fn test_long_property_names() {
+        use manifoldb_core::types::Value;
+
+        // Two 300-character names that only differ past the cut, and a non-ASCII one
+        let long = "a".repeat(300);
+        let long_twin = format!("{}b", "a".repeat(299));
+        let wide = "é".repeat(150);
+        let props: HashMap<String, Value> = [long.clone(), long_twin.clone(), wide.clone()]
+            .into_iter()
+            .map(|name| (name, Value::Int(1)))
+            .collect();
+        let schema = || {
+            let mut discovery = SchemaDiscovery::new();
+            discovery.observe_entity(&props);
+            discovery.finalize(DEFAULT_PROP_PREFIX)
+        };
+
+        let columns = schema();
+        let names: Vec<&str> = columns[2..].iter().map(|c| c.name.as_str()).collect();
+        assert!(names.iter().all(|name| name.len() <= MAX_COLUMN_NAME_LEN));
+        let prefixed = |name: &&str| name.starts_with("prop_aaaa") || name.starts_with("prop_é");
+        assert!(names.iter().all(prefixed));
+        assert_ne!(names[0], names[1]);
+        // Same names on every scan, each still leading back to its property
+        let again = schema();
+        assert!(columns.iter().zip(&again).all(|(a, b)| a.name == b.name));
+        let properties: Vec<&str> =
+            columns[2..].iter().map(|c| c.property.as_deref().unwrap()).collect();
+        assert_eq!(properties, [long.as_str(), long_twin.as_str(), wide.as_str()]);
+
+        // Short names are untouched
+        assert_eq!(fit_column_name("prop_name"), "prop_name");
+    }
+
+    #[test]
+    fn test_int_width_fits_bigint() {
+        use manifoldb_core::encoding::{Decoder, Encoder};
+        use manifoldb_core::types::Value;
+
+        // Manifold integers are i64, exactly BIGINT's range
+        for i in [i64::MAX, i64::MAX - 1, i64::MIN] {
+            let value = Value::Int(i);
+            assert_eq!(manifold_value_to_column_type(&value), ColumnType::Bigint);
+
+            let decoded = Value::decode(&value.encode().unwrap()).unwrap();
+            assert_eq!(decoded, Value::Int(i));
+        }
+
+        // i64::MAX + 1 can only be stored as a string or float, never as a wrapped Int
+        let beyond = (i64::MAX as i128 + 1).to_string();
+        let value = Value::String(beyond.clone());
+        assert_eq!(manifold_value_to_column_type(&value), ColumnType::Varchar);
+        assert_eq!(Value::decode(&value.encode().unwrap()).unwrap(), Value::String(beyond));
+    }
+
+    #[test]
+    fn test_nested_object_struct_column() {
+        use manifoldb_core::types::Value;
+
+        fn object(fields: &[(&str, Value)]) -> Value {
+            Value::Array(
+                fields
+                    .iter()
+                    .map(|(k, v)| Value::Array(vec![Value::String(k.to_string()), v.clone()]))
+                    .collect(),
+            )
+        }
+
+        let mut discovery = SchemaDiscovery::new();
+        for (city, zip) in [("Oslo", Value::Int(150)), ("Bergen", Value::Null)] {
+            let mut props = HashMap::new();
+            props.insert(
+                "address".to_string(),
+                object(&[("zip", zip), ("city", Value::String(city.to_string()))]),
+            );
+            props.insert("meta".to_string(), object(&[("a", Value::Int(1))]));
+            discovery.observe_entity(&props);
+        }
+        let mut props = HashMap::new();
+        props.insert("meta".to_string(), object(&[("b", Value::Int(2))]));
+        discovery.observe_entity(&props);
+
+        let schema = discovery.finalize(DEFAULT_PROP_PREFIX);
+
+        // Same keys everywhere: STRUCT with per-field types, sorted by name
+        let address = schema.iter().find(|c| c.name == "prop_address").unwrap();
+        assert_eq!(address.column_type, ColumnType::Struct);
+        assert_eq!(
+            address.fields,
+            vec![
+                ("city".to_string(), ColumnType::Varchar),
+                ("zip".to_string(), ColumnType::Bigint),
+            ]
+        );
+
+        // Keys differ between entities: JSON VARCHAR
+        let meta = schema.iter().find(|c| c.name == "prop_meta").unwrap();
+        assert_eq!(meta.column_type, ColumnType::Varchar);
+        assert!(meta.fields.is_empty());
+    }
+    #[test]
+    fn test_wide_schema_overflows_to_extra_props() {
+        let mut discovery = SchemaDiscovery::new();
+        let props: HashMap<String, manifoldb_core::types::Value> = (0..MAX_PROPERTY_COLUMNS + 5)
+            .map(|i| (format!("p{:05}", i), manifoldb_core::types::Value::Int(i as i64)))
+            .collect();
+        discovery.observe_entity(&props);
+
+        let schema = discovery.finalize(DEFAULT_PROP_PREFIX);
+        let prop_columns = schema.iter().filter(|c| c.name.starts_with("prop_")).count();
+        assert_eq!(prop_columns, MAX_PROPERTY_COLUMNS);
+        assert_eq!(schema.last().unwrap().name, EXTRA_PROPS_COLUMN);
+
+        // The first properties by name keep their columns
+        assert!(schema.iter().any(|c| c.name == "prop_p00000"));
+        assert!(!schema.iter().any(|c| c.name == format!("prop_p{:05}", MAX_PROPERTY_COLUMNS)));
+    }
 }
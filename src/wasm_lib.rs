@@ -1,6 +1,10 @@
 #![allow(special_module_name)]
 
-mod lib;
+#[path = "lib.rs"]
+pub mod lib;
+
+// lib.rs refers to its modules through `crate::`, so make them visible at this root
+use lib::*;
 
 // To build the Wasm target, a `staticlib` crate-type is required
 //
@@ -25,8 +25,11 @@ fn create_test_database(path: &str) -> Result<(), Box<dyn std::error::Error>> {
             let mut props = HashMap::new();
             props.insert("name".to_string(), Value::String("Alice".to_string()));
             props.insert("age".to_string(), Value::Int(30));
+            props.insert("nickname".to_string(), Value::String("Al".to_string()));
+            props.insert("embedding".to_string(), Value::Vector(vec![1.0, 0.0, 0.0]));
             props
         },
+        vectors: HashMap::new(),
     };
     let key1 = 1u64.to_be_bytes();
     tx.put("nodes", &key1, &entity1.encode()?)?;
@@ -39,8 +42,10 @@ fn create_test_database(path: &str) -> Result<(), Box<dyn std::error::Error>> {
             let mut props = HashMap::new();
             props.insert("name".to_string(), Value::String("Bob".to_string()));
             props.insert("age".to_string(), Value::Int(25));
+            props.insert("embedding".to_string(), Value::Vector(vec![0.0, 1.0, 0.0]));
             props
         },
+        vectors: HashMap::new(),
     };
     let key2 = 2u64.to_be_bytes();
     tx.put("nodes", &key2, &entity2.encode()?)?;
@@ -53,8 +58,10 @@ fn create_test_database(path: &str) -> Result<(), Box<dyn std::error::Error>> {
             let mut props = HashMap::new();
             props.insert("name".to_string(), Value::String("Acme Corp".to_string()));
             props.insert("founded".to_string(), Value::Int(1990));
+            props.insert("nickname".to_string(), Value::Null);
             props
         },
+        vectors: HashMap::new(),
     };
     let key3 = 3u64.to_be_bytes();
     tx.put("nodes", &key3, &entity3.encode()?)?;
@@ -106,14 +113,24 @@ fn create_test_database(path: &str) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Create a database file with nothing written to it: it has no tables at all
+fn create_empty_database(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    drop(RedbEngine::open(path)?);
+    println!("Created empty database at {}", path);
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let test_db_path = "/tmp/manifold_test.redb";
+    let empty_db_path = "/tmp/manifold_empty_test.redb";
 
-    // Remove old test database if it exists
+    // Remove old test databases if they exist
     let _ = std::fs::remove_file(test_db_path);
+    let _ = std::fs::remove_file(empty_db_path);
 
-    // Create test database
+    // Create test databases
     create_test_database(test_db_path)?;
+    create_empty_database(empty_db_path)?;
 
     // Run Python test
     let python_script = format!(r#"
@@ -144,8 +161,43 @@ result = conn.execute("SELECT source, target, prop_since FROM manifold_edges('{}
 for row in result.fetchall():
     print(row)
 
+print("\\n=== Query: Vector search (brute force) ===")
+result = conn.execute("SELECT id, distance FROM manifold_vector_search('{}', 'embedding', [0.9, 0.1, 0.0], 1, brute_force=true)")
+rows = result.fetchall()
+print(rows)
+assert rows[0][0] == '1', rows
+
+print("\\n=== Typed columns: an absent property is NULL, not zero ===")
+result = conn.execute("SELECT id, prop_age FROM manifold_entities('{db}', decimal_props={{'prop_age': '18,0'}}) ORDER BY id")
+rows = result.fetchall()
+print(rows)
+assert rows[2] == ('3', None), rows
+rows = conn.execute("SELECT avg(prop_age) FROM manifold_entities('{db}', decimal_props={{'prop_age': '18,0'}})").fetchall()
+assert rows == [(27.5,)], rows
+
+print("\\n=== A null property value is NULL, or the null_string token ===")
+rows = conn.execute("SELECT id, prop_nickname FROM manifold_entities('{db}') ORDER BY id").fetchall()
+print(rows)
+assert rows[0] == ('1', 'Al') and rows[2] == ('3', None), rows
+rows = conn.execute("SELECT prop_nickname FROM manifold_entities('{db}', null_string='\\N') WHERE id = '3'").fetchall()
+assert rows == [('\\N',)], rows
+
+print("\\n=== Empty database: base columns, no rows ===")
+result = conn.execute("SELECT * FROM manifold_entities('{empty}')")
+columns = [desc[0] for desc in result.description]
+print("Columns:", columns)
+assert columns == ['id', 'labels'], columns
+assert result.fetchall() == []
+result = conn.execute("SELECT * FROM manifold_edges('{empty}')")
+columns = [desc[0] for desc in result.description]
+print("Columns:", columns)
+assert not any(name.startswith('prop_') for name in columns), columns
+assert result.fetchall() == []
+rows = conn.execute("SELECT count(*) FROM manifold_entities('{empty}')").fetchall()
+assert rows == [(0,)], rows
+
 print("\\nAll tests passed!")
-"#, test_db_path, test_db_path, test_db_path, test_db_path);
+"#, test_db_path, test_db_path, test_db_path, test_db_path, test_db_path, db = test_db_path, empty = empty_db_path);
 
     let manifest_dir = env!("CARGO_MANIFEST_DIR");
     let output = Command::new(format!("{}/configure/venv/bin/python3", manifest_dir))